@@ -0,0 +1,203 @@
+//! Fills the configured database with a demo user so the portfolio and alerts pages
+//! have something to show: `cargo run --bin seed`.
+//!
+//! SEED_EMAIL / SEED_USERNAME / SEED_PASSWORD override the demo credentials.
+//! Positions and orders use fixed prices, so no Finnhub key is needed.
+
+use std::env;
+
+use chrono::Utc;
+use mongodb::Client;
+use mongodb::bson::oid::ObjectId;
+
+use rustmarket::models::{user::normalize_and_validate_email, Order, Position, ORDER_FILLED};
+use rustmarket::services::{alerts_service, auth_service, user_service, watchlist_service};
+use rustmarket::{config, services, templates, AppState};
+
+const STARTING_CASH: f64 = 100_000.0;
+
+// (symbol, qty, price)
+const DEMO_BUYS: &[(&str, i64, f64)] = &[("AAPL", 25, 189.50), ("MSFT", 10, 415.20), ("NVDA", 8, 118.75)];
+
+// (symbol, qty, price)
+const DEMO_SELLS: &[(&str, i64, f64)] = &[("NVDA", 3, 124.10)];
+
+// (symbol, condition, target)
+const DEMO_ALERTS: &[(&str, &str, f64)] = &[("AAPL", "above", 210.0), ("MSFT", "below", 380.0), ("TSLA", "above", 300.0)];
+
+const DEMO_WATCHLIST: &[&str] = &["AAPL", "MSFT", "NVDA", "TSLA"];
+
+fn form_error(errs: &auth_service::FieldErrors) -> String {
+    errs.iter()
+        .map(|(k, v)| format!("{k}: {v}"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[tokio::main]
+async fn main() {
+    dotenvy::dotenv().ok();
+    tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::new("info"))
+        .init();
+
+    let settings = config::load();
+
+    let email = env::var("SEED_EMAIL").unwrap_or_else(|_| "demo@rustmarket.local".to_string());
+    let email = normalize_and_validate_email(&email).expect("SEED_EMAIL is not a valid email");
+    let username = env::var("SEED_USERNAME").unwrap_or_else(|_| "demo".to_string());
+    let password = env::var("SEED_PASSWORD").unwrap_or_else(|_| "demo1234".to_string());
+
+    let client = Client::with_uri_str(&settings.mongodb_uri)
+        .await
+        .expect("Failed to connect to MongoDB");
+    let db = client.database(&settings.mongodb_db);
+
+    services::db_init::ensure_indexes(&db, &settings)
+        .await
+        .expect("Failed to ensure MongoDB indexes");
+    services::migrations::run(&db)
+        .await
+        .expect("Failed to run MongoDB migrations");
+
+    let (events_tx, _events_rx) = tokio::sync::broadcast::channel::<String>(16);
+    let state = AppState {
+        hbs: templates::build_handlebars(),
+        db,
+        settings: settings.clone(),
+        finnhub: services::finnhub::FinnhubClient::new(settings.finnhub_api_key.clone()),
+        events_tx,
+        trades: services::trade_relay::TradeRelay::spawn(settings.finnhub_api_key.clone()),
+        ws_limiter: services::ws_limiter::WsLimiter::new(settings.ws_max_per_user),
+        clock: services::clock::real(),
+        started_at: std::time::Instant::now(),
+    };
+
+    // Re-running is a no-op: an existing demo user is left as it is.
+    if let Ok(user) = auth_service::login_user(&state, &email, &password).await {
+        tracing::info!("Demo user {} already exists ({}); nothing to do", email, user.id);
+        return;
+    }
+
+    let user_id = auth_service::register_user(&state, &username, &email, &password)
+        .await
+        .unwrap_or_else(|e| panic!("Failed to create demo user: {}", form_error(&e)));
+    tracing::info!("Created demo user {} ({})", email, user_id);
+
+    if let Err(e) = seed_portfolio(&state, user_id).await {
+        panic!("Failed to seed portfolio: {e}");
+    }
+
+    for (symbol, condition, target) in DEMO_ALERTS {
+        alerts_service::create_alert(&state, user_id, symbol, condition, *target, None)
+            .await
+            .expect("Failed to create demo alert");
+    }
+
+    for symbol in DEMO_WATCHLIST {
+        watchlist_service::add(&state, user_id, symbol)
+            .await
+            .expect("Failed to add demo watchlist symbol");
+    }
+
+    tracing::info!(
+        "Seeded {} positions, {} orders and {} alerts; log in as {} / {}",
+        DEMO_BUYS.len(),
+        DEMO_BUYS.len() + DEMO_SELLS.len(),
+        DEMO_ALERTS.len(),
+        email,
+        password
+    );
+}
+
+// Mirrors what market_buy/market_sell store, at fixed prices instead of live quotes.
+async fn seed_portfolio(state: &AppState, user_id: ObjectId) -> Result<(), String> {
+    let account = user_service::credit_funds(state, user_id, STARTING_CASH)
+        .await
+        .map_err(|e| form_error(&e))?;
+
+    let positions = state.db.collection::<Position>("positions");
+    let orders = state.db.collection::<Order>("orders");
+
+    // Spread the history over the last few days so "Held since" and the order log look real.
+    let now = Utc::now().timestamp();
+    let mut cash = account.cash;
+
+    for (i, (symbol, qty, price)) in DEMO_BUYS.iter().enumerate() {
+        let at = now - 86_400 * (DEMO_BUYS.len() - i) as i64;
+        let total = price * (*qty as f64);
+        cash -= total;
+
+        orders
+            .insert_one(
+                Order {
+                    id: ObjectId::new(),
+                    user_id,
+                    symbol: symbol.to_string(),
+                    side: "buy".to_string(),
+                    qty: *qty,
+                    price: *price,
+                    total,
+                    created_at: at,
+                    realized_pnl: None,
+                    quoted_price: None,
+                    status: ORDER_FILLED.to_string(),
+                    filled_qty: None,
+                },
+                None,
+            )
+            .await
+            .map_err(|e| e.to_string())?;
+
+        let sold: i64 = DEMO_SELLS
+            .iter()
+            .filter(|(s, _, _)| s == symbol)
+            .map(|(_, q, _)| q)
+            .sum();
+
+        positions
+            .insert_one(
+                Position {
+                    id: ObjectId::new(),
+                    user_id,
+                    symbol: symbol.to_string(),
+                    qty: qty - sold,
+                    avg_price: *price,
+                    created_at: at,
+                    updated_at: at,
+                    lots: Vec::new(),
+                },
+                None,
+            )
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+
+    for (symbol, qty, price) in DEMO_SELLS {
+        let total = price * (*qty as f64);
+        cash += total;
+
+        orders
+            .insert_one(
+                Order {
+                    id: ObjectId::new(),
+                    user_id,
+                    symbol: symbol.to_string(),
+                    side: "sell".to_string(),
+                    qty: *qty,
+                    price: *price,
+                    total,
+                    created_at: now - 3_600,
+                    realized_pnl: None,
+                    quoted_price: None,
+                    status: ORDER_FILLED.to_string(),
+                    filled_qty: None,
+                },
+                None,
+            )
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+
+    services::account_service::set_cash(state, user_id, cash, now).await
+}
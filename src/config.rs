@@ -4,13 +4,128 @@ use std::env;
 pub struct Settings {
     pub mongodb_uri: String,
     pub mongodb_db: String,
+    // APP_ENV=production: refuse to start with unsafe defaults such as the dev JWT secret.
+    pub is_production: bool,
     pub host: String,
     pub port: u16,
     pub cookie_secure: bool,
     pub jwt_ttl_days: i64,
+    // Signs new tokens; with JWT_SECRETS this is the first entry.
     pub jwt_secret: String,
+    // Earlier secrets still accepted when decoding, so a rotation doesn't log everyone out.
+    pub jwt_previous_secrets: Vec<String>,
+    // HMAC algorithm tokens are signed with (and the only one accepted).
+    pub jwt_algorithm: jsonwebtoken::Algorithm,
     pub jwt_cookie_name: String,
+    // Domain attribute on the auth cookie, e.g. ".example.com" to share it across
+    // subdomains; None scopes it to the exact host.
+    pub cookie_domain: Option<String>,
+    // SameSite attribute on the auth cookie; None forces Secure (see auth_cookie_secure).
+    pub cookie_same_site: axum_extra::extract::cookie::SameSite,
+    // New passwords (register, change) must be at least this long.
+    pub password_min_len: usize,
+    // Also require lower case, upper case and a digit in new passwords; login is unaffected.
+    pub strong_passwords: bool,
     pub finnhub_api_key: String,
+    pub snapshot_interval_secs: u64,
+    pub trade_flush_ms: u64,
+    pub ws_max_per_user: usize,
+    // Buffered app events per subscriber; a slower /events client gets a resync instead.
+    pub events_channel_capacity: usize,
+    pub template_hot_reload: bool,
+    // Triggered alerts are deleted by a TTL index this long after they fire.
+    pub alert_retention_days: u64,
+    // Cash a brand-new account is opened with.
+    pub starting_balance: f64,
+    // Send HTMX error snippets with 422/500; false keeps the old always-200 behaviour.
+    pub htmx_error_status: bool,
+    // Cap on qty * price a user may hold in one symbol; None means unlimited.
+    pub max_position_notional: Option<f64>,
+    // Origins allowed to call /api cross-origin; empty means same-origin only.
+    pub cors_allowed_origins: Vec<String>,
+    // How sells price the shares they take off a position.
+    pub cost_basis_method: CostBasisMethod,
+    // Simulated annual dividend yield in percent; None turns dividends off.
+    pub dividend_yield_pct: Option<f64>,
+    // How often held positions are paid their share of the yearly dividend.
+    pub dividend_interval_secs: u64,
+    // The position panel shows the user's latest fill for the symbol instead of a
+    // live quote while that fill is younger than this; 0 always quotes.
+    pub fill_price_max_age_secs: i64,
+    // Most a user may deposit per UTC day; None turns the limit off.
+    pub daily_deposit_limit: Option<f64>,
+    // Simulated slippage in basis points, always against the user; 0 fills at the quote.
+    pub slippage_bps: f64,
+    // Check with Finnhub that a symbol exists before creating an alert on it.
+    pub validate_alert_symbols: bool,
+    // Most untriggered alerts one user may have at once; None means unlimited.
+    pub max_alerts_per_user: Option<u64>,
+    // How long a company profile in the `profiles` collection is trusted before refetching.
+    pub profile_max_age_hours: u64,
+    // Exchange search results are narrowed to unless the user picks another, e.g. "US";
+    // None shows every exchange Finnhub returns.
+    pub default_search_exchange: Option<String>,
+    // Bearer token a Prometheus scraper sends to read /metrics; without one only
+    // signed-in admins can.
+    pub metrics_token: Option<String>,
+}
+
+/// Signing secret used when neither JWT_SECRETS nor JWT_SECRET is set.
+pub const DEV_JWT_SECRET: &str = "change-me-dev-secret";
+
+/// What to do about a deployment still on `DEV_JWT_SECRET`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DevSecretRisk {
+    // production: anyone can forge sessions, so don't start
+    Fatal,
+    // served over HTTPS but not marked production; nag in the logs
+    Warn,
+}
+
+impl Settings {
+    /// Whether tokens signed with the dev secret would be issued or accepted.
+    pub fn uses_dev_jwt_secret(&self) -> bool {
+        self.jwt_secret == DEV_JWT_SECRET || self.jwt_previous_secrets.iter().any(|s| s == DEV_JWT_SECRET)
+    }
+
+    /// None while the secret is real or the app looks like a local dev setup.
+    pub fn dev_secret_risk(&self) -> Option<DevSecretRisk> {
+        if !self.uses_dev_jwt_secret() {
+            return None;
+        }
+        if self.is_production {
+            Some(DevSecretRisk::Fatal)
+        } else if self.cookie_secure {
+            Some(DevSecretRisk::Warn)
+        } else {
+            None
+        }
+    }
+
+    /// Browsers drop SameSite=None cookies that aren't Secure, so None implies Secure.
+    pub fn auth_cookie_secure(&self) -> bool {
+        self.cookie_secure || self.cookie_same_site == axum_extra::extract::cookie::SameSite::None
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CostBasisMethod {
+    // one blended avg_price per position
+    #[default]
+    Average,
+    // per-buy lots, consumed oldest first
+    Fifo,
+}
+
+impl CostBasisMethod {
+    /// "fifo" (any case) selects FIFO; anything else keeps the average method.
+    pub fn parse(raw: &str) -> Self {
+        if raw.trim().eq_ignore_ascii_case("fifo") {
+            CostBasisMethod::Fifo
+        } else {
+            CostBasisMethod::Average
+        }
+    }
 }
 
 
@@ -23,6 +138,10 @@ pub fn load() -> Settings {
     let mongodb_db = env::var("MONGODB_DB")
         .unwrap_or_else(|_| "gomarket".to_string());
 
+    let is_production = env::var("APP_ENV")
+        .map(|v| v.trim().eq_ignore_ascii_case("production"))
+        .unwrap_or(false);
+
     let host = env::var("HOST")
         .unwrap_or_else(|_| "127.0.0.1".to_string());
 
@@ -31,27 +150,253 @@ pub fn load() -> Settings {
         .and_then(|s| s.parse::<u16>().ok())
         .unwrap_or(3000);
 
-    let jwt_secret = env::var("JWT_SECRET").unwrap_or_else(|_| "change-me-dev-secret".to_string());
+    // JWT_SECRETS="current,previous,..." wins over the single JWT_SECRET
+    let mut jwt_secrets = env::var("JWT_SECRETS")
+        .map(|v| parse_jwt_secrets(&v))
+        .unwrap_or_default();
+    if jwt_secrets.is_empty() {
+        jwt_secrets.push(env::var("JWT_SECRET").unwrap_or_else(|_| DEV_JWT_SECRET.to_string()));
+    }
+    let jwt_secret = jwt_secrets.remove(0);
+    let jwt_previous_secrets = jwt_secrets;
+
+    let jwt_algorithm = env::var("JWT_ALGORITHM")
+        .ok()
+        .and_then(|v| parse_jwt_algorithm(&v))
+        .unwrap_or(jsonwebtoken::Algorithm::HS256);
     let jwt_cookie_name = env::var("JWT_COOKIE_NAME").unwrap_or_else(|_| "auth".to_string());
     let cookie_secure = env::var("COOKIE_SECURE")
         .ok()
         .map(|v| v == "true" || v == "1")
         .unwrap_or(false);
+    let cookie_domain = env::var("COOKIE_DOMAIN")
+        .ok()
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty());
+    let cookie_same_site = env::var("COOKIE_SAMESITE")
+        .ok()
+        .and_then(|v| parse_same_site(&v))
+        .unwrap_or(axum_extra::extract::cookie::SameSite::Lax);
+
+    let password_min_len = env::var("PASSWORD_MIN_LEN")
+        .ok()
+        .and_then(|v| v.parse::<usize>().ok())
+        .filter(|v| *v > 0)
+        .unwrap_or(6);
+    let strong_passwords = env::var("STRONG_PASSWORDS")
+        .ok()
+        .map(|v| v == "true" || v == "1")
+        .unwrap_or(false);
 
     let jwt_ttl_days = env::var("JWT_TTL_DAYS")
         .ok()
         .and_then(|v| v.parse::<i64>().ok())
         .unwrap_or(7);
     let finnhub_api_key = env::var("FINNHUB_API_KEY").unwrap_or_default();
+
+    let snapshot_interval_secs = env::var("SNAPSHOT_INTERVAL_SECS")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .filter(|v| *v > 0)
+        .unwrap_or(86_400);
+
+    // Aggregation window for trades pushed to browser WebSockets
+    let trade_flush_ms = env::var("TRADE_FLUSH_MS")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .filter(|v| *v > 0)
+        .unwrap_or(250);
+
+    let ws_max_per_user = env::var("WS_MAX_PER_USER")
+        .ok()
+        .and_then(|v| v.parse::<usize>().ok())
+        .filter(|v| *v > 0)
+        .unwrap_or(10);
+
+    let events_channel_capacity = env::var("EVENTS_CHANNEL_CAPACITY")
+        .ok()
+        .and_then(|v| v.parse::<usize>().ok())
+        .filter(|v| *v > 0)
+        .map(|v| v.min(1 << 16))
+        .unwrap_or(256);
+
+    // Re-read templates on every render; on by default only in debug builds
+    let template_hot_reload = env::var("TEMPLATE_HOT_RELOAD")
+        .ok()
+        .map(|v| v == "true" || v == "1")
+        .unwrap_or(cfg!(debug_assertions));
+
+    let alert_retention_days = env::var("ALERT_RETENTION_DAYS")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .filter(|v| *v > 0)
+        .unwrap_or(7);
+
+    let starting_balance = env::var("STARTING_BALANCE")
+        .ok()
+        .and_then(|v| v.parse::<f64>().ok())
+        .filter(|v| v.is_finite() && *v >= 0.0)
+        .unwrap_or(10_000.0);
+
+    let htmx_error_status = env::var("HTMX_ERROR_STATUS")
+        .ok()
+        .map(|v| v == "true" || v == "1")
+        .unwrap_or(true);
+
+    let max_position_notional = env::var("MAX_POSITION_NOTIONAL")
+        .ok()
+        .and_then(|v| v.parse::<f64>().ok())
+        .filter(|v| v.is_finite() && *v > 0.0);
+
+    let cors_allowed_origins = env::var("CORS_ALLOWED_ORIGINS")
+        .map(|v| parse_origins(&v))
+        .unwrap_or_default();
+
+    let cost_basis_method = env::var("COST_BASIS_METHOD")
+        .map(|v| CostBasisMethod::parse(&v))
+        .unwrap_or_default();
+
+    let dividend_yield_pct = env::var("DIVIDEND_YIELD_PCT")
+        .ok()
+        .and_then(|v| v.parse::<f64>().ok())
+        .filter(|v| v.is_finite() && *v > 0.0);
+
+    let dividend_interval_secs = env::var("DIVIDEND_INTERVAL_SECS")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .filter(|v| *v > 0)
+        .unwrap_or(86_400);
+
+    let fill_price_max_age_secs = env::var("FILL_PRICE_MAX_AGE_SECS")
+        .ok()
+        .and_then(|v| v.parse::<i64>().ok())
+        .filter(|v| *v >= 0)
+        .unwrap_or(60);
+
+    // unset keeps a generous default; 0 turns the limit off
+    let daily_deposit_limit = match env::var("DAILY_DEPOSIT_LIMIT").ok().and_then(|v| v.parse::<f64>().ok()) {
+        Some(v) if v.is_finite() && v > 0.0 => Some(v),
+        Some(_) => None,
+        None => Some(1_000_000.0),
+    };
+
+    // capped at 10% so a typo can't make every fill absurd
+    let slippage_bps = env::var("SLIPPAGE_BPS")
+        .ok()
+        .and_then(|v| v.parse::<f64>().ok())
+        .filter(|v| v.is_finite() && *v >= 0.0)
+        .map(|v| v.min(1_000.0))
+        .unwrap_or(0.0);
+
+    let validate_alert_symbols = env::var("VALIDATE_ALERT_SYMBOLS")
+        .map(|v| v == "true" || v == "1")
+        .unwrap_or(true);
+
+    // unset keeps a generous default; 0 turns the limit off
+    let max_alerts_per_user = match env::var("MAX_ALERTS_PER_USER").ok().and_then(|v| v.parse::<u64>().ok()) {
+        Some(0) => None,
+        Some(v) => Some(v),
+        None => Some(100),
+    };
+
+    // profiles barely change; a week keeps Finnhub calls for popular tickers rare
+    let profile_max_age_hours = env::var("PROFILE_MAX_AGE_HOURS")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .filter(|v| *v > 0)
+        .unwrap_or(7 * 24);
+
+    let default_search_exchange = env::var("DEFAULT_SEARCH_EXCHANGE")
+        .ok()
+        .map(|v| v.trim().to_uppercase())
+        .filter(|v| !v.is_empty());
+
+    let metrics_token = env::var("METRICS_TOKEN")
+        .ok()
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty());
+
     Settings {
         mongodb_uri,
         mongodb_db,
+        is_production,
         host,
         port,
         jwt_secret,
+        jwt_previous_secrets,
+        jwt_algorithm,
         jwt_cookie_name,
+        cookie_domain,
+        cookie_same_site,
+        password_min_len,
+        strong_passwords,
         cookie_secure,
         jwt_ttl_days,
-        finnhub_api_key
+        finnhub_api_key,
+        snapshot_interval_secs,
+        trade_flush_ms,
+        ws_max_per_user,
+        events_channel_capacity,
+        template_hot_reload,
+        alert_retention_days,
+        starting_balance,
+        htmx_error_status,
+        max_position_notional,
+        cors_allowed_origins,
+        cost_basis_method,
+        dividend_yield_pct,
+        dividend_interval_secs,
+        fill_price_max_age_secs,
+        daily_deposit_limit,
+        slippage_bps,
+        validate_alert_symbols,
+        max_alerts_per_user,
+        profile_max_age_hours,
+        default_search_exchange,
+        metrics_token,
+    }
+}
+
+/// "https://a.example, https://b.example/" -> ["https://a.example", "https://b.example"].
+/// "*" is dropped: the API allows credentials, which browsers refuse with a wildcard.
+pub fn parse_origins(raw: &str) -> Vec<String> {
+    raw.split(',')
+        .map(|o| o.trim().trim_end_matches('/'))
+        .filter(|o| !o.is_empty() && *o != "*")
+        .map(str::to_string)
+        .collect()
+}
+
+/// "new, old ," -> ["new", "old"]; the first entry is the signing secret.
+pub fn parse_jwt_secrets(raw: &str) -> Vec<String> {
+    raw.split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Lax / Strict / None (any case).
+pub fn parse_same_site(raw: &str) -> Option<axum_extra::extract::cookie::SameSite> {
+    use axum_extra::extract::cookie::SameSite;
+
+    match raw.trim().to_ascii_lowercase().as_str() {
+        "lax" => Some(SameSite::Lax),
+        "strict" => Some(SameSite::Strict),
+        "none" => Some(SameSite::None),
+        _ => None,
+    }
+}
+
+/// HS256 / HS384 / HS512 (any case). The keys are shared secrets, so other
+/// algorithms are not accepted.
+pub fn parse_jwt_algorithm(raw: &str) -> Option<jsonwebtoken::Algorithm> {
+    use jsonwebtoken::Algorithm;
+
+    match raw.trim().to_ascii_uppercase().as_str() {
+        "HS256" => Some(Algorithm::HS256),
+        "HS384" => Some(Algorithm::HS384),
+        "HS512" => Some(Algorithm::HS512),
+        _ => None,
     }
 }
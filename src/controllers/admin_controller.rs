@@ -0,0 +1,181 @@
+//! `/admin`: site stats, the user list, account credit/reset and company profile
+//! refreshes. Every route sits
+//! behind `auth::require_admin`; admins are flagged with `users.is_admin` in the database.
+
+use axum::{
+    extract::{Extension, Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{Html, IntoResponse, Response},
+    Form,
+};
+use mongodb::bson::oid::ObjectId;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{
+    models::CurrentUser,
+    render::{self, ToastKind},
+    services::{admin_service, stocks_service},
+    templates, AppState,
+};
+
+use super::{symbol::Symbol, user_controller::parse_amount};
+
+// Refreshes the stats and the current user page after a change.
+const ADMIN_EVENTS: &[&str] = &["adminUpdated"];
+
+fn is_htmx(headers: &HeaderMap) -> bool {
+    headers
+        .get("HX-Request")
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.eq_ignore_ascii_case("true"))
+        .unwrap_or(false)
+}
+
+fn render_partial(state: &AppState, tpl: &str, ctx: serde_json::Value) -> Response {
+    match state.hbs.render(tpl, &ctx) {
+        Ok(html) => (StatusCode::OK, Html(html)).into_response(),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Html(format!("template error: {}", render::escape_html(&e.to_string()))),
+        )
+            .into_response(),
+    }
+}
+
+// GET /admin
+pub async fn get_dashboard(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    user: Option<Extension<CurrentUser>>,
+) -> Response {
+    let body = match state.hbs.render("pages/admin", &json!({})) {
+        Ok(s) => s,
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Html(format!("template error: {}", render::escape_html(&e.to_string()))),
+            )
+                .into_response()
+        }
+    };
+
+    if is_htmx(&headers) {
+        return (StatusCode::OK, Html(body)).into_response();
+    }
+
+    let user_ref = user.as_ref().map(|Extension(u)| u);
+    match render::render_full(&state, "Admin", body, user_ref) {
+        Ok(page) => (StatusCode::OK, Html(page)).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, Html(e)).into_response(),
+    }
+}
+
+// GET /admin/stats (HTMX partial)
+pub async fn get_stats(State(state): State<AppState>) -> Response {
+    match admin_service::stats(&state).await {
+        Ok(stats) => render_partial(&state, "partials/admin_stats", json!(stats)),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct UsersQuery {
+    pub page: Option<u64>,
+}
+
+// GET /admin/users?page=N (HTMX partial)
+pub async fn get_users(State(state): State<AppState>, Query(q): Query<UsersQuery>) -> Response {
+    let page = match admin_service::list_users(&state, q.page.unwrap_or(1)).await {
+        Ok(p) => p,
+        Err(e) => return e.into_response(),
+    };
+
+    let users: Vec<serde_json::Value> = page
+        .users
+        .iter()
+        .map(|u| {
+            json!({
+                "id": u.id,
+                "username": u.username,
+                "email": u.email,
+                "is_admin": u.is_admin,
+                "cash": templates::format_currency(u.cash),
+                "member_since": u.member_since,
+            })
+        })
+        .collect();
+
+    render_partial(
+        &state,
+        "partials/admin_users",
+        json!({
+            "users": users,
+            "page": page.page,
+            "pages": page.pages,
+            "total": page.total,
+            "prev_page": (page.page > 1).then(|| page.page - 1),
+            "next_page": (page.page < page.pages).then(|| page.page + 1),
+        }),
+    )
+}
+
+#[derive(Deserialize)]
+pub struct CreditForm {
+    pub amount: String,
+}
+
+// POST /admin/users/:id/credit
+pub async fn post_credit(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Form(form): Form<CreditForm>,
+) -> Response {
+    let Ok(user_id) = ObjectId::parse_str(id.trim()) else {
+        return render::error_toast(&state, StatusCode::NOT_FOUND, "Unknown user.");
+    };
+    let amount = match parse_amount(&form.amount) {
+        Ok(v) => v,
+        Err(msg) => return render::error_toast(&state, StatusCode::UNPROCESSABLE_ENTITY, msg),
+    };
+
+    match admin_service::credit_account(&state, user_id, amount).await {
+        Ok(acc) => {
+            let msg = format!(
+                "Credited {}; new balance {}.",
+                templates::format_currency(amount),
+                templates::format_currency(acc.cash)
+            );
+            render::toast(&state, ToastKind::Success, &msg, ADMIN_EVENTS)
+        }
+        Err(e) => render::app_error_toast(&state, &e),
+    }
+}
+
+// POST /admin/users/:id/reset
+pub async fn post_reset(State(state): State<AppState>, Path(id): Path<String>) -> Response {
+    let Ok(user_id) = ObjectId::parse_str(id.trim()) else {
+        return render::error_toast(&state, StatusCode::NOT_FOUND, "Unknown user.");
+    };
+
+    match admin_service::reset_account(&state, user_id).await {
+        Ok(()) => render::toast(&state, ToastKind::Success, "Account reset.", ADMIN_EVENTS),
+        Err(e) => render::app_error_toast(&state, &e),
+    }
+}
+
+// POST /admin/profiles/:symbol/refresh
+// Refetches a stored company profile now instead of waiting out PROFILE_MAX_AGE_HOURS.
+pub async fn post_refresh_profile(State(state): State<AppState>, Symbol(symbol): Symbol) -> Response {
+    match stocks_service::refresh_profile(&state, &symbol).await {
+        Ok(Some(p)) => {
+            let msg = format!("Refreshed the profile for {symbol} ({}).", p.name);
+            render::toast(&state, ToastKind::Success, &msg, &[])
+        }
+        Ok(None) => {
+            let msg = format!("Finnhub has no profile for {symbol}.");
+            render::toast(&state, ToastKind::Warning, &msg, &[])
+        }
+        Err(e) => render::app_error_toast(&state, &e),
+    }
+}
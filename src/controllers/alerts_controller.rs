@@ -1,6 +1,6 @@
 use axum::{
     extract::{Extension, Form, Path, State},
-    http::{HeaderMap, HeaderValue, StatusCode},
+    http::{HeaderMap, StatusCode},
     response::{Html, IntoResponse, Response},
 };
 use mongodb::bson::oid::ObjectId;
@@ -8,9 +8,10 @@ use serde::Deserialize;
 use serde_json::json;
 
 use crate::{
+    controllers::symbol::Symbol,
     models::CurrentUser,
-    render,
-    services::alerts_service,
+    render::{self, RenderFailed, ToastKind},
+    services::{alerts_service, user_service},
     AppState,
 };
 
@@ -22,20 +23,6 @@ fn is_htmx(headers: &HeaderMap) -> bool {
         .unwrap_or(false)
 }
 
-fn hx_trigger_value(events: &[&str]) -> HeaderValue {
-    if events.len() == 1 {
-        return HeaderValue::from_str(events[0]).unwrap_or_else(|_| HeaderValue::from_static(""));
-    }
-
-    let mut map = serde_json::Map::new();
-    for &e in events {
-        map.insert(e.to_string(), serde_json::Value::Bool(true));
-    }
-
-    let json = serde_json::Value::Object(map).to_string();
-    HeaderValue::from_str(&json).unwrap_or_else(|_| HeaderValue::from_static(""))
-}
-
 fn unauthorized_snippet() -> Response {
     (
         StatusCode::UNAUTHORIZED,
@@ -44,15 +31,14 @@ fn unauthorized_snippet() -> Response {
         .into_response()
 }
 
-fn render_page(state: &AppState, tpl: &str, ctx: serde_json::Value) -> String {
-    state
-        .hbs
-        .render(tpl, &ctx)
-        .unwrap_or_else(|e| format!("template error: {e}"))
+fn render_page(state: &AppState, tpl: &str, ctx: serde_json::Value) -> Result<String, RenderFailed> {
+    render::render_or_500(state, tpl, &ctx)
 }
 
-fn fmt2(x: f64) -> String {
-    format!("{:.2}", x)
+// Deletes are soft, so the toast can offer an undo for a few minutes.
+fn deleted_toast(state: &AppState, id: ObjectId) -> Response {
+    let undo_url = format!("/alerts/by-id/{}/restore", id.to_hex());
+    render::undo_toast(state, ToastKind::Success, "Alert deleted.", &undo_url, &["alertsUpdated"])
 }
 
 // ---------------- Pages ----------------
@@ -63,8 +49,10 @@ pub async fn get_alerts_page(
     user: Option<Extension<CurrentUser>>,
 ) -> Response {
     if is_htmx(&headers) {
-        let html = render_page(&state, "pages/alerts", json!({}));
-        return (StatusCode::OK, Html(html)).into_response();
+        return match render_page(&state, "pages/alerts", json!({})) {
+            Ok(html) => (StatusCode::OK, Html(html)).into_response(),
+            Err(e) => e.into_response(),
+        };
     }
 
     let user_ref = user.as_ref().map(|Extension(u)| u);
@@ -80,6 +68,9 @@ pub async fn get_alerts_page(
 pub struct CreateAlertForm {
     #[serde(rename = "targetPrice")]
     pub target_price: String,
+    // only read for "between"
+    #[serde(default, rename = "targetPriceHigh")]
+    pub target_price_high: String,
     pub condition: String,
 }
 
@@ -87,7 +78,7 @@ pub struct CreateAlertForm {
 pub async fn get_alerts_list(
     State(state): State<AppState>,
     headers: HeaderMap,
-    Path(symbol): Path<String>,
+    Symbol(sym): Symbol,
     user: Option<Extension<CurrentUser>>,
 ) -> Response {
     let Some(Extension(u)) = user else {
@@ -98,13 +89,12 @@ pub async fn get_alerts_list(
             .into_response();
     };
 
-    let sym = symbol.to_uppercase();
     let alerts = match alerts_service::list_user_symbol_alerts(&state, u.id, &sym).await {
         Ok(v) => v,
         Err(e) => {
             return (
                 StatusCode::INTERNAL_SERVER_ERROR,
-                Html(format!("db error: {e}")),
+                Html(format!("db error: {}", render::escape_html(&e.to_string()))),
             )
                 .into_response();
         }
@@ -117,19 +107,26 @@ pub async fn get_alerts_list(
               "id": a.id.to_hex(),
               "symbol": a.symbol,
               "condition": a.condition,
-              "target_price": fmt2(a.target_price),
-              "target_price_raw": a.target_price,
+              "target_price": a.target_price,
+              "target_price_high": a.target_price_high,
               "triggered": a.triggered,
             })
         })
         .collect();
 
-    let ctx = json!({ "symbol": sym, "alerts": items, "has_alerts": !items.is_empty() });
+    let prefs = user_service::get_preferences(&state, u.id).await.unwrap_or_default();
+
+    let ctx = json!({
+        "symbol": sym,
+        "alerts": items,
+        "has_alerts": !items.is_empty(),
+        "display_currency": prefs.display_currency,
+    });
 
-    let html = state
-        .hbs
-        .render("partials/alerts_list", &ctx)
-        .unwrap_or_else(|e| format!("template error: {e}"));
+    let html = match render_page(&state, "partials/alerts_list", ctx) {
+        Ok(html) => html,
+        Err(e) => return e.into_response(),
+    };
 
     if is_htmx(&headers) {
         return (StatusCode::OK, Html(html)).into_response();
@@ -140,7 +137,7 @@ pub async fn get_alerts_list(
 // POST /alerts/:symbol
 pub async fn post_create_alert(
     State(state): State<AppState>,
-    Path(symbol): Path<String>,
+    Symbol(sym): Symbol,
     user: Option<Extension<CurrentUser>>,
     Form(form): Form<CreateAlertForm>,
 ) -> Response {
@@ -148,65 +145,56 @@ pub async fn post_create_alert(
         return unauthorized_snippet();
     };
 
-    let sym = symbol.to_uppercase();
-
     let cond = form.condition.to_lowercase();
-    if cond != "above" && cond != "below" {
-        return (
-            StatusCode::OK,
-            Html(r#"<div class=\"text-danger\">Please choose a valid condition.</div>"#.to_string()),
-        )
-            .into_response();
+    if !matches!(cond.as_str(), "above" | "below" | "between") {
+        return render::toast(&state, ToastKind::Danger, "Please choose a valid condition.", &[]);
     }
 
     let target_str = form.target_price.trim();
     let target: f64 = match target_str.parse() {
         Ok(v) => v,
         Err(_) => {
-            return (
-                StatusCode::OK,
-                Html(
-                    r#"<div class=\"text-danger\">Please enter a valid target price.</div>"#
-                        .to_string(),
-                ),
-            )
-                .into_response();
+            return render::toast(&state, ToastKind::Danger, "Please enter a valid target price.", &[]);
         }
     };
 
     if !target.is_finite() || target <= 0.0 {
-        return (
-            StatusCode::OK,
-            Html(
-                r#"<div class=\"text-danger\">Please enter a valid target price.</div>"#.to_string(),
-            ),
-        )
-            .into_response();
+        return render::toast(&state, ToastKind::Danger, "Please enter a valid target price.", &[]);
     }
 
-    if let Err(e) = alerts_service::create_alert(&state, u.id, &sym, &cond, target).await {
-        return (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Html(format!("db error: {e}")),
-        )
-            .into_response();
+    let target_high = if cond == "between" {
+        match form.target_price_high.trim().parse::<f64>() {
+            Ok(v) => Some(v),
+            Err(_) => {
+                return render::toast(&state, ToastKind::Danger, "Please enter a valid upper price.", &[]);
+            }
+        }
+    } else {
+        None
+    };
+
+    // an alert on a typo would never fire; only a definite "no" blocks it, so a
+    // Finnhub outage or a missing key doesn't stop alerts from being created
+    if state.settings.validate_alert_symbols {
+        match state.finnhub.symbol_exists(&sym).await {
+            Ok(true) => {}
+            Ok(false) => return render::toast(&state, ToastKind::Danger, "Unknown symbol", &[]),
+            Err(e) => tracing::warn!("could not check alert symbol {sym}: {e}"),
+        }
     }
 
-    let mut headers = HeaderMap::new();
-    headers.insert("HX-Trigger", hx_trigger_value(&["alertsUpdated"]));
+    if let Err(e) = alerts_service::create_alert(&state, u.id, &sym, &cond, target, target_high).await {
+        return render::app_error_toast(&state, &e);
+    }
 
-    (
-        StatusCode::OK,
-        headers,
-        Html(r#"<div class=\"text-success\">Alert created.</div>"#.to_string()),
-    )
-        .into_response()
+    render::toast(&state, ToastKind::Success, "Alert created.", &["alertsUpdated"])
 }
 
 // POST /alerts/:symbol/:id/delete
 pub async fn post_delete_alert(
     State(state): State<AppState>,
-    Path((symbol, id)): Path<(String, String)>,
+    Symbol(symbol): Symbol,
+    Path((_, id)): Path<(String, String)>,
     user: Option<Extension<CurrentUser>>,
 ) -> Response {
     let Some(Extension(u)) = user else {
@@ -225,15 +213,12 @@ pub async fn post_delete_alert(
     if let Err(e) = alerts_service::delete_alert_for_symbol(&state, u.id, &symbol, oid).await {
         return (
             StatusCode::INTERNAL_SERVER_ERROR,
-            Html(format!("db error: {e}")),
+            Html(format!("db error: {}", render::escape_html(&e.to_string()))),
         )
             .into_response();
     }
 
-    let mut headers = HeaderMap::new();
-    headers.insert("HX-Trigger", hx_trigger_value(&["alertsUpdated"]));
-
-    (StatusCode::OK, headers, Html("".to_string())).into_response()
+    deleted_toast(&state, oid)
 }
 
 // POST /alerts/:id/delete
@@ -254,15 +239,84 @@ pub async fn post_delete_alert_global(
     if let Err(e) = alerts_service::delete_alert_global(&state, u.id, oid).await {
         return (
             StatusCode::INTERNAL_SERVER_ERROR,
-            Html(format!("db error: {e}")),
+            Html(format!("db error: {}", render::escape_html(&e.to_string()))),
         )
             .into_response();
     }
 
+    deleted_toast(&state, oid)
+}
+
+// POST /alerts/:symbol/delete_all
+pub async fn post_delete_all_alerts(
+    State(state): State<AppState>,
+    Symbol(sym): Symbol,
+    user: Option<Extension<CurrentUser>>,
+) -> Response {
+    let Some(Extension(u)) = user else {
+        return unauthorized_snippet();
+    };
+
+    let deleted = match alerts_service::delete_symbol_alerts(&state, u.id, &sym).await {
+        Ok(n) => n,
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Html(format!("db error: {}", render::escape_html(&e.to_string()))),
+            )
+                .into_response();
+        }
+    };
+
+    let prefs = user_service::get_preferences(&state, u.id).await.unwrap_or_default();
+    let html = match render_page(
+        &state,
+        "partials/alerts_list",
+        json!({
+            "symbol": sym,
+            "alerts": [],
+            "has_alerts": false,
+            "display_currency": prefs.display_currency,
+        }),
+    ) {
+        Ok(html) => html,
+        Err(e) => return e.into_response(),
+    };
+
+    let message = match deleted {
+        1 => "Deleted 1 alert.".to_string(),
+        n => format!("Deleted {n} alerts."),
+    };
     let mut headers = HeaderMap::new();
-    headers.insert("HX-Trigger", hx_trigger_value(&["alertsUpdated"]));
+    headers.insert("HX-Trigger", render::toast_trigger(ToastKind::Success, &message, &["alertsUpdated"]));
 
-    (StatusCode::OK, headers, Html("".to_string())).into_response()
+    (StatusCode::OK, headers, Html(html)).into_response()
+}
+
+// POST /alerts/by-id/:id/restore
+pub async fn post_restore_alert(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    user: Option<Extension<CurrentUser>>,
+) -> Response {
+    let Some(Extension(u)) = user else {
+        return unauthorized_snippet();
+    };
+
+    let oid = match ObjectId::parse_str(&id) {
+        Ok(x) => x,
+        Err(_) => return (StatusCode::BAD_REQUEST, Html("bad id".to_string())).into_response(),
+    };
+
+    match alerts_service::restore_alert(&state, u.id, oid).await {
+        Ok(true) => render::toast(&state, ToastKind::Success, "Alert restored.", &["alertsUpdated"]),
+        Ok(false) => render::toast(&state, ToastKind::Warning, "This alert can no longer be restored.", &[]),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Html(format!("db error: {}", render::escape_html(&e.to_string()))),
+        )
+            .into_response(),
+    }
 }
 
 // POST /alerts/:id/trigger
@@ -285,22 +339,19 @@ pub async fn post_trigger_alert(
         Err(e) => {
             return (
                 StatusCode::INTERNAL_SERVER_ERROR,
-                Html(format!("db error: {e}")),
+                Html(format!("db error: {}", render::escape_html(&e.to_string()))),
             )
                 .into_response();
         }
     };
 
-    let mut headers = HeaderMap::new();
-    headers.insert("HX-Trigger", hx_trigger_value(&["alertsUpdated"]));
-
     let msg = if !triggered_now {
-        r#"<div class=\"text-muted\">Alert already triggered.</div>"#
+        "Alert already triggered."
     } else {
-        r#"<div class=\"text-warning\">⚠️ Alert triggered!</div>"#
+        "⚠️ Alert triggered!"
     };
 
-    (StatusCode::OK, headers, Html(msg.to_string())).into_response()
+    render::toast(&state, ToastKind::Warning, msg, &["alertsUpdated"])
 }
 
 // GET /watchlist/alerts
@@ -310,49 +361,73 @@ pub async fn get_watchlist_alerts(
     user: Option<Extension<CurrentUser>>,
 ) -> Response {
     let mut groups: Vec<serde_json::Value> = vec![];
+    let mut currency = None;
 
     if let Some(Extension(u)) = user {
+        currency = user_service::get_preferences(&state, u.id)
+            .await
+            .ok()
+            .map(|p| p.display_currency);
+
         let map = match alerts_service::list_user_alerts_grouped(&state, u.id).await {
             Ok(m) => m,
             Err(e) => {
                 return (
                     StatusCode::INTERNAL_SERVER_ERROR,
-                    Html(format!("db error: {e}")),
+                    Html(format!("db error: {}", render::escape_html(&e.to_string()))),
                 )
                     .into_response()
             }
         };
 
+        // one concurrent round of quotes for every symbol on the page
+        let symbols: Vec<String> = map.keys().cloned().collect();
+        let quotes = state.finnhub.quotes(&symbols).await;
+
         for (symbol, alerts) in map {
+            let price = quotes
+                .get(&symbol)
+                .map(|q| q.c)
+                .filter(|c| c.is_finite() && *c > 0.0);
+
             let alerts_json: Vec<serde_json::Value> = alerts
                 .into_iter()
                 .map(|a| {
+                    let in_the_money = price
+                        .map(|p| alerts_service::alert_hit(&a, p))
+                        .unwrap_or(false);
+
                     json!({
                         "id": a.id.to_hex(),
                         "condition": a.condition,
-                        "target_price": fmt2(a.target_price),
+                        "target_price": a.target_price,
+                        "target_price_high": a.target_price_high,
                         "created_at": a.created_at,
                         "triggered": a.triggered,
                         "triggered_at": a.triggered_at,
+                        "triggered_date": a.triggered_at.and_then(alerts_service::triggered_date),
+                        "triggered_price": a.triggered_price,
+                        "in_the_money": in_the_money,
                     })
                 })
                 .collect();
 
             groups.push(json!({
                 "symbol": symbol,
+                "current_price": price,
+                "has_price": price.is_some(),
                 "alerts": alerts_json
             }));
         }
     }
 
     let ctx = json!({
-        "groups": if groups.is_empty() { serde_json::Value::Null } else { serde_json::Value::Array(groups) }
+        "groups": if groups.is_empty() { serde_json::Value::Null } else { serde_json::Value::Array(groups) },
+        "display_currency": currency,
     });
 
-    let body = state
-        .hbs
-        .render("partials/watchlist_alerts", &ctx)
-        .unwrap_or_else(|e| format!("template error: {e}"));
-
-    (StatusCode::OK, Html(body)).into_response()
+    match render_page(&state, "partials/watchlist_alerts", ctx) {
+        Ok(body) => (StatusCode::OK, Html(body)).into_response(),
+        Err(e) => e.into_response(),
+    }
 }
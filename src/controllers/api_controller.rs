@@ -0,0 +1,348 @@
+//! JSON API under `/api/v1` for non-browser clients (mobile app, smoke tests).
+//!
+//! Same services and auth as the HTML routes; clients without a cookie jar send
+//! `Authorization: Bearer <jwt>` instead of the auth cookie.
+
+use axum::{
+    extract::{rejection::JsonRejection, Extension, Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{
+    controllers::{realtime_controller::MAX_WS_SYMBOLS, user_controller::check_amount},
+    error::AppError,
+    models::{Account, CurrentUser, Order, Position},
+    services::{auth_service::FieldErrors, portfolio_service, stocks_service, trading_service, user_service},
+    AppState,
+};
+
+const DEFAULT_ORDERS_LIMIT: i64 = 50;
+const MAX_ORDERS_LIMIT: i64 = 500;
+
+fn error(status: StatusCode, message: &str) -> Response {
+    (status, Json(json!({ "error": message }))).into_response()
+}
+
+fn unauthorized() -> Response {
+    error(StatusCode::UNAUTHORIZED, "unauthorized")
+}
+
+// The API's validation contract: 422 with `{"errors": {field: message}}`, the same
+// FieldErrors map the HTML controllers render as snippets.
+fn validation_errors(errs: FieldErrors) -> Response {
+    (StatusCode::UNPROCESSABLE_ENTITY, Json(json!({ "errors": errs }))).into_response()
+}
+
+fn field_error(field: &str, message: &str) -> Response {
+    validation_errors(FieldErrors::from([(field.to_string(), message.to_string())]))
+}
+
+// JSON counterpart of AppError's HTML response; field errors keep their keys.
+fn app_error(e: AppError) -> Response {
+    e.report();
+    match e {
+        AppError::Validation(errs) => validation_errors(errs),
+        e => error(e.status(), &e.message()),
+    }
+}
+
+// Services that report through FieldErrors put failures that aren't the caller's
+// fault under "_form"; those are a 500, not a validation error.
+fn service_errors(errs: FieldErrors) -> Response {
+    match errs.get("_form") {
+        Some(msg) if errs.len() == 1 => {
+            tracing::error!("api: {msg}");
+            error(StatusCode::INTERNAL_SERVER_ERROR, "internal error")
+        }
+        _ => validation_errors(errs),
+    }
+}
+
+// Malformed or mistyped bodies get the JSON error shape too instead of axum's plain text.
+fn json_rejection(rejection: JsonRejection) -> Response {
+    error(rejection.status(), &rejection.body_text())
+}
+
+fn position_json(p: &Position) -> serde_json::Value {
+    json!({
+        "symbol": p.symbol,
+        "qty": p.qty,
+        "avg_price": p.avg_price,
+    })
+}
+
+fn order_json(o: &Order) -> serde_json::Value {
+    json!({
+        "id": o.id.to_hex(),
+        "symbol": o.symbol,
+        "side": o.side,
+        "qty": o.qty,
+        "price": o.price,
+        "quoted_price": o.quoted_price,
+        "total": o.total,
+        "status": o.status,
+        "filled_qty": o.filled_qty(),
+        "created_at": o.created_at,
+    })
+}
+
+// GET /api/v1/quote/:symbol
+pub async fn get_quote(
+    State(state): State<AppState>,
+    Path(symbol): Path<String>,
+    user: Option<Extension<CurrentUser>>,
+) -> Response {
+    if user.is_none() {
+        return unauthorized();
+    }
+
+    let symbol = symbol.trim().to_uppercase();
+    match state.finnhub.quote(&symbol).await {
+        Ok(q) => Json(json!({ "symbol": symbol, "quote": q })).into_response(),
+        Err(e) => app_error(e.into()),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct QuotesQuery {
+    #[serde(default)]
+    pub symbols: String,
+}
+
+// GET /api/v1/quotes?symbols=AAPL,MSFT
+// Polling fallback for pages that can't hold a trades socket; same symbol cap as
+// /ws/trades_multi. Symbols whose quote failed are missing from the map.
+pub async fn get_quotes(
+    State(state): State<AppState>,
+    Query(q): Query<QuotesQuery>,
+    user: Option<Extension<CurrentUser>>,
+) -> Response {
+    if user.is_none() {
+        return unauthorized();
+    }
+
+    let symbols = stocks_service::parse_symbol_list(&q.symbols, MAX_WS_SYMBOLS);
+    if symbols.is_empty() {
+        return error(StatusCode::BAD_REQUEST, "missing symbols");
+    }
+
+    let quotes = state.finnhub.quotes(&symbols).await;
+    Json(json!({ "quotes": quotes })).into_response()
+}
+
+// GET /api/v1/portfolio
+pub async fn get_portfolio(
+    State(state): State<AppState>,
+    user: Option<Extension<CurrentUser>>,
+) -> Response {
+    let Some(Extension(u)) = user else {
+        return unauthorized();
+    };
+
+    let views = match portfolio_service::list_portfolio_position_views(&state, u.id).await {
+        Ok(v) => v,
+        Err(e) => return app_error(e),
+    };
+    let summary = match portfolio_service::portfolio_summary(&state, u.id).await {
+        Ok(s) => s,
+        Err(e) => return app_error(e),
+    };
+    let dividends = match portfolio_service::dividend_income(&state, u.id).await {
+        Ok(d) => d,
+        Err(e) => return app_error(e),
+    };
+
+    let positions: Vec<serde_json::Value> = views
+        .iter()
+        .map(|v| {
+            json!({
+                "symbol": v.symbol,
+                "qty": v.qty,
+                "avg_price": v.avg_price,
+                "last_price": v.last_price,
+                "pnl": v.pnl,
+                "pnl_pct": v.pnl_pct,
+                "day_change": v.day_change,
+                "day_change_pct": v.day_change_pct,
+            })
+        })
+        .collect();
+
+    Json(json!({
+        "cash": summary.cash,
+        "market_value": summary.market_value,
+        "cost_basis": summary.cost_basis,
+        "total_value": summary.total_value,
+        "unrealized_pnl": summary.unrealized_pnl,
+        "unrealized_pnl_pct": summary.unrealized_pnl_pct,
+        "dividend_income": dividends,
+        "positions": positions,
+    }))
+    .into_response()
+}
+
+#[derive(Deserialize)]
+pub struct OrdersQuery {
+    pub symbol: Option<String>,
+    // unix seconds, both inclusive
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+// GET /api/v1/orders?symbol=AAPL&from=..&to=..&limit=..
+pub async fn get_orders(
+    State(state): State<AppState>,
+    Query(q): Query<OrdersQuery>,
+    user: Option<Extension<CurrentUser>>,
+) -> Response {
+    let Some(Extension(u)) = user else {
+        return unauthorized();
+    };
+
+    if let (Some(f), Some(t)) = (q.from, q.to)
+        && f > t
+    {
+        return error(StatusCode::UNPROCESSABLE_ENTITY, "\"from\" must be on or before \"to\"");
+    }
+
+    let filter = portfolio_service::OrderFilter {
+        symbol: q.symbol,
+        from: q.from,
+        to: q.to,
+    };
+    let limit = q.limit.unwrap_or(DEFAULT_ORDERS_LIMIT).clamp(1, MAX_ORDERS_LIMIT);
+
+    match portfolio_service::list_orders_filtered(&state, u.id, &filter, limit).await {
+        Ok(orders) => {
+            let items: Vec<serde_json::Value> = orders.iter().map(order_json).collect();
+            Json(json!({ "orders": items })).into_response()
+        }
+        Err(e) => app_error(e),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct TradeRequest {
+    pub symbol: String,
+    // "buy" | "sell"
+    pub side: String,
+    pub qty: i64,
+}
+
+// POST /api/v1/trade  {"symbol":"AAPL","side":"buy","qty":1}
+pub async fn post_trade(
+    State(state): State<AppState>,
+    user: Option<Extension<CurrentUser>>,
+    body: Result<Json<TradeRequest>, JsonRejection>,
+) -> Response {
+    let Some(Extension(u)) = user else {
+        return unauthorized();
+    };
+    let body = match body {
+        Ok(Json(b)) => b,
+        Err(rejection) => return json_rejection(rejection),
+    };
+
+    let result = match body.side.trim().to_lowercase().as_str() {
+        "buy" => trading_service::market_buy(&state, u.id, &body.symbol, body.qty)
+            .await
+            .map(|r| {
+                json!({
+                    "symbol": r.symbol,
+                    "side": "buy",
+                    "qty": r.qty,
+                    "fill_price": r.fill_price,
+                    "total": r.cost,
+                    "cash": r.new_cash,
+                    "position": position_json(&r.position),
+                })
+            }),
+        "sell" => trading_service::market_sell(&state, u.id, &body.symbol, body.qty)
+            .await
+            .map(|r| {
+                json!({
+                    "symbol": r.symbol,
+                    "side": "sell",
+                    "qty": r.qty,
+                    "fill_price": r.fill_price,
+                    "total": r.proceeds,
+                    "realized_pnl": r.realized_pnl,
+                    "cash": r.new_cash,
+                    "position": r.remaining.as_ref().map(position_json),
+                })
+            }),
+        _ => return error(StatusCode::UNPROCESSABLE_ENTITY, "side must be \"buy\" or \"sell\""),
+    };
+
+    match result {
+        Ok(v) => Json(v).into_response(),
+        Err(e) => app_error(e),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct FundsRequest {
+    pub amount: f64,
+    // same as the HTML form's: a retried request with the same key deposits once
+    #[serde(default)]
+    pub idempotency_key: Option<String>,
+}
+
+fn cash_json(acc: &Account) -> Response {
+    Json(json!({ "cash": acc.cash })).into_response()
+}
+
+// POST /api/v1/funds  {"amount":100,"idempotency_key":"..."}
+pub async fn post_funds(
+    State(state): State<AppState>,
+    user: Option<Extension<CurrentUser>>,
+    body: Result<Json<FundsRequest>, JsonRejection>,
+) -> Response {
+    let Some(Extension(u)) = user else {
+        return unauthorized();
+    };
+    let body = match body {
+        Ok(Json(b)) => b,
+        Err(rejection) => return json_rejection(rejection),
+    };
+
+    let amount = match check_amount(body.amount) {
+        Ok(v) => v,
+        Err(msg) => return field_error("amount", msg),
+    };
+
+    match user_service::deposit_funds(&state, u.id, amount, body.idempotency_key.as_deref()).await {
+        Ok(acc) => cash_json(&acc),
+        Err(errs) => service_errors(errs),
+    }
+}
+
+// POST /api/v1/funds/withdraw  {"amount":100}
+pub async fn post_withdraw(
+    State(state): State<AppState>,
+    user: Option<Extension<CurrentUser>>,
+    body: Result<Json<FundsRequest>, JsonRejection>,
+) -> Response {
+    let Some(Extension(u)) = user else {
+        return unauthorized();
+    };
+    let body = match body {
+        Ok(Json(b)) => b,
+        Err(rejection) => return json_rejection(rejection),
+    };
+
+    let amount = match check_amount(body.amount) {
+        Ok(v) => v,
+        Err(msg) => return field_error("amount", msg),
+    };
+
+    match user_service::withdraw_funds(&state, u.id, amount).await {
+        Ok(acc) => cash_json(&acc),
+        Err(errs) => service_errors(errs),
+    }
+}
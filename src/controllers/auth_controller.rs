@@ -1,15 +1,23 @@
+use std::net::SocketAddr;
+
 use axum::{
-    extract::{State},
+    extract::{ConnectInfo, State},
     http::{HeaderMap, StatusCode},
     response::{Html, IntoResponse, Response},
     Form,
 };
 use axum_extra::extract::cookie::CookieJar;
-use regex::Regex;
 use serde::Deserialize;
 use serde_json::json;
 
-use crate::{render, services::auth_service, AppState};
+use mongodb::bson::oid::ObjectId;
+
+use crate::{
+    models::user::normalize_and_validate_email,
+    render,
+    services::{auth_service, session_service},
+    AppState,
+};
 
 fn is_htmx(headers: &HeaderMap) -> bool {
     headers
@@ -25,9 +33,17 @@ fn htmx_redirect(path: &str) -> Response {
     (StatusCode::OK, headers, Html("".to_string())).into_response()
 }
 
-fn is_valid_email(email: &str) -> bool {
-    let re = Regex::new(r"^[^\s@]+@[^\s@]+\.[^\s@]+$").unwrap();
-    re.is_match(email)
+fn token_ttl_days(state: &AppState, remember_me: bool) -> i64 {
+    if remember_me { 30 } else { state.settings.jwt_ttl_days }
+}
+
+// Login history is best effort; a failed write never blocks the sign-in.
+async fn record_session(state: &AppState, user_id: ObjectId, headers: &HeaderMap, peer: Option<SocketAddr>) {
+    let ip = session_service::client_ip(headers, peer);
+    let agent = session_service::user_agent(headers);
+    if let Err(e) = session_service::record_login(state, user_id, &ip, &agent).await {
+        tracing::warn!("failed to record login session for {}: {}", user_id, e);
+    }
 }
 
 // ---------------- LOGIN ----------------
@@ -41,7 +57,7 @@ pub async fn get_login(
         Err(e) => {
             return (
                 StatusCode::INTERNAL_SERVER_ERROR,
-                Html(format!("template error: {e}")),
+                Html(format!("template error: {}", render::escape_html(&e.to_string()))),
             )
                 .into_response()
         }
@@ -61,24 +77,30 @@ pub async fn get_login(
 pub struct LoginForm {
     pub email: String,
     pub password: String,
+
+    #[serde(default, rename = "rememberMe")]
+    pub remember_me: Option<String>,
 }
 
 pub async fn post_login(
     State(state): State<AppState>,
     headers: HeaderMap,
     jar: CookieJar,
+    peer: Option<ConnectInfo<SocketAddr>>,
     Form(form): Form<LoginForm>,
 ) -> Response {
-    let email = form.email.trim().to_string();
     let password = form.password.trim().to_string();
+    let remember_me = form.remember_me.is_some();
 
     let mut errors = serde_json::Map::new();
 
-    if email.is_empty() {
-        errors.insert("email".into(), json!("Email is required."));
-    } else if !is_valid_email(&email) {
-        errors.insert("email".into(), json!("Invalid email."));
-    }
+    let email = match normalize_and_validate_email(&form.email) {
+        Ok(e) => e,
+        Err(msg) => {
+            errors.insert("email".into(), json!(msg));
+            form.email.trim().to_string()
+        }
+    };
 
     if password.is_empty() {
         errors.insert("password".into(), json!("Password is required."));
@@ -90,7 +112,7 @@ pub async fn post_login(
             .render(
                 "pages/login",
                 &json!({
-                    "values": {"email": email, "password": password},
+                    "values": {"email": email, "password": password, "rememberMe": remember_me},
                     "errors": errors
                 }),
             )
@@ -110,7 +132,7 @@ pub async fn post_login(
                 .render(
                     "pages/login",
                     &json!({
-                        "values": {"email": email, "password": password},
+                        "values": {"email": email, "password": password, "rememberMe": remember_me},
                         "errors": errors
                     }),
                 )
@@ -119,7 +141,8 @@ pub async fn post_login(
         }
     };
 
-    let token = match auth_service::make_jwt_with_days(&state, &user.id, 7) {
+    let ttl_days = token_ttl_days(&state, remember_me);
+    let token = match auth_service::make_jwt_with_days(&state, &user.id, user.token_version, ttl_days) {
         Ok(t) => t,
         Err(e) => {
             errors.insert("_form".into(), json!(format!("Auth error: {e}")));
@@ -128,7 +151,7 @@ pub async fn post_login(
                 .render(
                     "pages/login",
                     &json!({
-                        "values": {"email": email, "password": password},
+                        "values": {"email": email, "password": password, "rememberMe": remember_me},
                         "errors": errors
                     }),
                 )
@@ -137,7 +160,8 @@ pub async fn post_login(
         }
     };
 
-    let jar = jar.add(auth_service::auth_cookie(&state, token));
+    let jar = jar.add(auth_service::auth_cookie(&state, token, ttl_days));
+    record_session(&state, user.id, &headers, peer.map(|ConnectInfo(addr)| addr)).await;
 
     if is_htmx(&headers) {
         return (jar, htmx_redirect("/")).into_response();
@@ -161,7 +185,7 @@ pub async fn get_register(
         Err(e) => {
             return (
                 StatusCode::INTERNAL_SERVER_ERROR,
-                Html(format!("template error: {e}")),
+                Html(format!("template error: {}", render::escape_html(&e.to_string()))),
             )
                 .into_response()
         }
@@ -185,18 +209,22 @@ pub struct RegisterForm {
 
     #[serde(default, rename = "rePassword")]
     pub re_password: Option<String>,
+
+    #[serde(default, rename = "rememberMe")]
+    pub remember_me: Option<String>,
 }
 
 pub async fn post_register(
     State(state): State<AppState>,
     headers: HeaderMap,
     jar: CookieJar,
+    peer: Option<ConnectInfo<SocketAddr>>,
     Form(form): Form<RegisterForm>,
 ) -> Response {
     let username = form.username.trim().to_string();
-    let email = form.email.trim().to_string();
     let password = form.password.trim().to_string();
     let re_password = form.re_password.as_deref().unwrap_or("").trim().to_string();
+    let remember_me = form.remember_me.is_some();
 
     let mut errors = serde_json::Map::new();
 
@@ -206,16 +234,18 @@ pub async fn post_register(
         errors.insert("username".into(), json!("Username must be at least 2 characters."));
     }
 
-    if email.is_empty() {
-        errors.insert("email".into(), json!("Email is required."));
-    } else if !is_valid_email(&email) {
-        errors.insert("email".into(), json!("Invalid email."));
-    }
+    let email = match normalize_and_validate_email(&form.email) {
+        Ok(e) => e,
+        Err(msg) => {
+            errors.insert("email".into(), json!(msg));
+            form.email.trim().to_string()
+        }
+    };
 
     if password.is_empty() {
         errors.insert("password".into(), json!("Password is required."));
-    } else if password.len() < 6 {
-        errors.insert("password".into(), json!("Password must be at least 6 characters."));
+    } else if let Err(msg) = auth_service::validate_password(&state, &password) {
+        errors.insert("password".into(), json!(msg));
     }
 
     if re_password.is_empty() {
@@ -230,7 +260,7 @@ pub async fn post_register(
             .render(
                 "pages/register",
                 &json!({
-                    "values": {"username": username, "email": email, "password": password, "rePassword": re_password},
+                    "values": {"username": username, "email": email, "password": password, "rePassword": re_password, "rememberMe": remember_me},
                     "errors": errors
                 }),
             )
@@ -250,7 +280,7 @@ pub async fn post_register(
                 .render(
                     "pages/register",
                     &json!({
-                        "values": {"username": username, "email": email, "password": password, "rePassword": re_password},
+                        "values": {"username": username, "email": email, "password": password, "rePassword": re_password, "rememberMe": remember_me},
                         "errors": errors
                     }),
                 )
@@ -259,7 +289,8 @@ pub async fn post_register(
         }
     };
 
-    let token = match auth_service::make_jwt_with_days(&state, &user_id, 7) {
+    let ttl_days = token_ttl_days(&state, remember_me);
+    let token = match auth_service::make_jwt_with_days(&state, &user_id, 0, ttl_days) {
         Ok(t) => t,
         Err(e) => {
             errors.insert("_form".into(), json!(format!("Auth error: {e}")));
@@ -268,7 +299,7 @@ pub async fn post_register(
                 .render(
                     "pages/register",
                     &json!({
-                        "values": {"username": username, "email": email, "password": password, "rePassword": re_password},
+                        "values": {"username": username, "email": email, "password": password, "rePassword": re_password, "rememberMe": remember_me},
                         "errors": errors
                     }),
                 )
@@ -277,7 +308,8 @@ pub async fn post_register(
         }
     };
 
-    let jar = jar.add(auth_service::auth_cookie(&state, token));
+    let jar = jar.add(auth_service::auth_cookie(&state, token, ttl_days));
+    record_session(&state, user_id, &headers, peer.map(|ConnectInfo(addr)| addr)).await;
 
     if is_htmx(&headers) {
         return (jar, htmx_redirect("/")).into_response();
@@ -1,12 +1,13 @@
 use axum::{
     extract::{Extension, State},
-    http::{HeaderMap, StatusCode},
-    response::{Html, IntoResponse},
+    http::{header, HeaderMap, StatusCode},
+    response::{Html, IntoResponse, Response},
+    Json,
 };
 use mongodb::bson::doc;
 use serde_json::json;
 
-use crate::{models::CurrentUser, render, AppState};
+use crate::{models::CurrentUser, render, services::{self, finnhub::FinnhubError}, AppState};
 
 fn is_htmx(headers: &HeaderMap) -> bool {
     headers
@@ -54,6 +55,58 @@ pub async fn not_found(
     }
 }
 
+// GET /metrics
+// For a scraper with METRICS_TOKEN as its bearer token, or a signed-in admin.
+pub async fn metrics(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    user: Option<Extension<CurrentUser>>,
+) -> Response {
+    let is_admin = user.is_some_and(|Extension(u)| u.is_admin);
+    let bearer = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+    let token_ok = match (state.settings.metrics_token.as_deref(), bearer) {
+        (Some(expected), Some(given)) => tokens_match(expected, given.trim()),
+        _ => false,
+    };
+    if !is_admin && !token_ok {
+        return (StatusCode::UNAUTHORIZED, [(header::WWW_AUTHENTICATE, "Bearer")], "unauthorized").into_response();
+    }
+
+    match services::metrics::render() {
+        Some(body) => (
+            StatusCode::OK,
+            [("Content-Type", "text/plain; version=0.0.4")],
+            body,
+        )
+            .into_response(),
+        None => (StatusCode::SERVICE_UNAVAILABLE, "metrics recorder not installed").into_response(),
+    }
+}
+
+// Same length and bytes, without an early exit that would time the comparison.
+fn tokens_match(a: &str, b: &str) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.bytes().zip(b.bytes()).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+const FAVICON_SVG: &str = include_str!("../../static/favicon.svg");
+
+// GET /favicon.ico; browsers ask for it regardless of the <link rel="icon">
+pub async fn favicon() -> impl IntoResponse {
+    (
+        [
+            (header::CONTENT_TYPE, "image/svg+xml"),
+            (header::CACHE_CONTROL, crate::static_cache::DEFAULT_CACHE_CONTROL),
+        ],
+        FAVICON_SVG,
+    )
+}
+
 pub async fn health() -> impl IntoResponse {
     (StatusCode::OK, Html("ok".to_string()))
 }
@@ -63,8 +116,62 @@ pub async fn health_db(State(state): State<AppState>) -> impl IntoResponse {
         Ok(_) => (StatusCode::OK, Html("mongo: ok".to_string())).into_response(),
         Err(e) => (
             StatusCode::INTERNAL_SERVER_ERROR,
-            Html(format!("mongo error: {}", e)),
+            Html(format!("mongo error: {}", render::escape_html(&e.to_string()))),
         )
             .into_response(),
     }
 }
+
+// GET /health/version: which build is running and for how long. Only the db
+// name goes out, never the Mongo URI (it can carry credentials).
+pub async fn health_version(State(state): State<AppState>) -> impl IntoResponse {
+    Json(json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        // set GIT_COMMIT when building, e.g. GIT_COMMIT=$(git rev-parse --short HEAD) cargo build
+        "commit": option_env!("GIT_COMMIT").unwrap_or("unknown"),
+        "uptime_secs": state.started_at.elapsed().as_secs(),
+        "db": state.settings.mongodb_db,
+    }))
+}
+
+// Finnhub's last upstream check (at most one real quote per UPSTREAM_CHECK_TTL);
+// Ok carries the status line.
+async fn check_finnhub(state: &AppState) -> Result<String, String> {
+    match state.finnhub.check_upstream().await {
+        Ok(_) => Ok("finnhub: reachable".to_string()),
+        Err(FinnhubError::MissingKey) => Err("finnhub: missing key".to_string()),
+        Err(FinnhubError::RateLimited) => Err("finnhub: rate limited".to_string()),
+        Err(e) => Err(format!("finnhub error: {}", render::escape_html(&e.to_string()))),
+    }
+}
+
+pub async fn health_finnhub(State(state): State<AppState>) -> impl IntoResponse {
+    match check_finnhub(&state).await {
+        Ok(msg) => (StatusCode::OK, Html(msg)).into_response(),
+        Err(msg) => (StatusCode::SERVICE_UNAVAILABLE, Html(msg)).into_response(),
+    }
+}
+
+// Readiness: 200 only when both Mongo and Finnhub are usable.
+pub async fn health_ready(State(state): State<AppState>) -> impl IntoResponse {
+    let (mongo, finnhub) = tokio::join!(
+        state.db.run_command(doc! { "ping": 1 }, None),
+        check_finnhub(&state),
+    );
+
+    let mongo_line = match &mongo {
+        Ok(_) => "mongo: ok".to_string(),
+        Err(e) => format!("mongo error: {}", render::escape_html(&e.to_string())),
+    };
+    let finnhub_line = match &finnhub {
+        Ok(msg) | Err(msg) => msg.clone(),
+    };
+
+    let status = if mongo.is_ok() && finnhub.is_ok() {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    (status, Html(format!("{}\n{}", mongo_line, finnhub_line))).into_response()
+}
@@ -0,0 +1,73 @@
+use axum::{
+    extract::{Extension, State},
+    http::{HeaderMap, StatusCode},
+    response::{Html, IntoResponse, Response},
+};
+use serde_json::json;
+
+use crate::{
+    models::{CurrentUser, Preferences},
+    render,
+    services::{leaderboard_service, user_service},
+    AppState,
+};
+
+const LEADERBOARD_SIZE: usize = 20;
+
+fn is_htmx(headers: &HeaderMap) -> bool {
+    headers
+        .get("HX-Request")
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.eq_ignore_ascii_case("true"))
+        .unwrap_or(false)
+}
+
+// GET /leaderboard
+pub async fn get_leaderboard_page(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    user: Option<Extension<CurrentUser>>,
+) -> Response {
+    let currency = match user.as_ref() {
+        Some(Extension(u)) => user_service::get_preferences(&state, u.id).await.unwrap_or_default().display_currency,
+        None => Preferences::default().display_currency,
+    };
+
+    let ctx = match leaderboard_service::top_traders(&state, LEADERBOARD_SIZE).await {
+        Ok(entries) => json!({
+            "entries": entries,
+            "starting_balance": state.settings.starting_balance,
+            "display_currency": currency,
+            "error": serde_json::Value::Null,
+        }),
+        Err(e) => {
+            tracing::warn!("leaderboard query failed: {}", e);
+            json!({
+                "entries": [],
+                "starting_balance": state.settings.starting_balance,
+                "error": "Leaderboard is unavailable right now.",
+            })
+        }
+    };
+
+    let body = match state.hbs.render("pages/leaderboard", &ctx) {
+        Ok(s) => s,
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Html(format!("template error: {}", render::escape_html(&e.to_string()))),
+            )
+                .into_response()
+        }
+    };
+
+    if is_htmx(&headers) {
+        return (StatusCode::OK, Html(body)).into_response();
+    }
+
+    let user_ref = user.as_ref().map(|Extension(u)| u);
+    match render::render_full(&state, "Leaderboard", body, user_ref) {
+        Ok(page) => (StatusCode::OK, Html(page)).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, Html(e)).into_response(),
+    }
+}
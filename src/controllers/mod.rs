@@ -6,3 +6,8 @@ pub mod trading_controller;
 pub mod portfolio_controller;
 pub mod alerts_controller;
 pub mod realtime_controller;
+pub mod watchlist_controller;
+pub mod leaderboard_controller;
+pub mod api_controller;
+pub mod admin_controller;
+pub mod symbol;
@@ -1,14 +1,16 @@
 use axum::{
-    extract::{Extension, Path, State},
+    extract::{Extension, Query, State},
     http::{HeaderMap, StatusCode},
     response::{Html, IntoResponse, Response},
 };
+use serde::Deserialize;
 use serde_json::json;
 
 use crate::{
+    controllers::symbol::Symbol,
     models::CurrentUser,
-    render,
-    services::portfolio_service,
+    render::{self, ToastKind},
+    services::{portfolio_service, user_service},
     AppState,
 };
 
@@ -20,8 +22,18 @@ fn is_htmx(headers: &HeaderMap) -> bool {
         .unwrap_or(false)
 }
 
-fn fmt2(v: f64) -> String {
-    format!("{:.2}", v)
+fn lots_json(lots: &[portfolio_service::LotView]) -> Vec<serde_json::Value> {
+    lots.iter()
+        .map(|l| {
+            json!({
+                "qty": l.qty,
+                "price": l.price,
+                "bought_on": l.bought_on,
+                "pnl": l.pnl,
+                "pnl_class": l.pnl_class,
+            })
+        })
+        .collect()
 }
 
 // GET /portfolio (SSR page)
@@ -34,7 +46,7 @@ pub async fn get_portfolio_page(
         Err(e) => {
             return (
                 StatusCode::INTERNAL_SERVER_ERROR,
-                Html(format!("template error: {e}")),
+                Html(format!("template error: {}", render::escape_html(&e.to_string()))),
             )
                 .into_response()
         }
@@ -66,10 +78,10 @@ pub async fn get_portfolio_positions(
         return (StatusCode::OK, Html(html)).into_response();
     };
 
-    let views = match portfolio_service::list_portfolio_position_views(&state, u.id).await {
-        Ok(v) => v,
-        Err(_) => vec![],
-    };
+    let views = portfolio_service::list_portfolio_position_views(&state, u.id)
+        .await
+        .unwrap_or_default();
+    let prefs = user_service::get_preferences(&state, u.id).await.unwrap_or_default();
 
     let groups: Vec<serde_json::Value> = views
         .into_iter()
@@ -77,28 +89,134 @@ pub async fn get_portfolio_positions(
             json!({
                 "symbol": v.symbol,
                 "qty": v.qty,
-                "avg": fmt2(v.avg_price),
-                "avg_raw": v.avg_price,
-                "current_price": fmt2(v.last_price),
-                "pnl": fmt2(v.pnl),
-                "pnl_pct": fmt2(v.pnl_pct),
+                "avg": v.avg_price,
+                "current_price": v.last_price,
+                "quote_unavailable": v.quote_unavailable,
+                "pnl": v.pnl,
+                "pnl_pct": v.pnl_pct,
                 "pnl_class": v.pnl_class,
+                "day_change": v.day_change,
+                "day_change_pct": v.day_change_pct,
+                "day_change_class": v.day_change_class,
+                "held_since": v.held_since,
+                "days_held": v.days_held,
+                "annualized_pct": v.annualized_return_pct,
+                "lots": lots_json(&v.lots),
+            })
+        })
+        .collect();
+
+    let html = state
+        .hbs
+        .render(
+            "partials/portfolio_positions",
+            &json!({
+                "groups": groups,
+                "default_qty": prefs.default_qty,
+                "display_currency": prefs.display_currency,
+            }),
+        )
+        .unwrap_or_else(|e| format!("template error: {e}"));
+
+    (StatusCode::OK, Html(html)).into_response()
+}
+
+// GET /portfolio/summary (HTMX partial)
+pub async fn get_portfolio_summary(
+    State(state): State<AppState>,
+    user: Option<Extension<CurrentUser>>,
+) -> Response {
+    let Some(Extension(u)) = user else {
+        return (StatusCode::UNAUTHORIZED, Html("Unauthorized".to_string())).into_response();
+    };
+
+    let prefs = user_service::get_preferences(&state, u.id).await.unwrap_or_default();
+
+    let summary = match portfolio_service::portfolio_summary(&state, u.id).await {
+        Ok(s) => s,
+        Err(e) => return e.into_response(),
+    };
+    let dividends = match portfolio_service::dividend_income(&state, u.id).await {
+        Ok(d) => d,
+        Err(e) => return e.into_response(),
+    };
+    let breakdown = match portfolio_service::pnl_breakdown(&state, u.id).await {
+        Ok(b) => b,
+        Err(e) => return e.into_response(),
+    };
+
+    let html = state
+        .hbs
+        .render(
+            "partials/portfolio_summary",
+            &json!({
+                "cash": summary.cash,
+                "market_value": summary.market_value,
+                "cost_basis": summary.cost_basis,
+                "total_value": summary.total_value,
+                "pnl": summary.unrealized_pnl,
+                "pnl_pct": summary.unrealized_pnl_pct,
+                "pnl_class": summary.pnl_class,
+                "positions": summary.positions,
+                "realized_pnl": breakdown.realized,
+                "realized_class": breakdown.realized_class,
+                "total_pnl": breakdown.total,
+                "total_pnl_class": breakdown.total_class,
+                "dividends": dividends,
+                "display_currency": prefs.display_currency,
+            }),
+        )
+        .unwrap_or_else(|e| format!("template error: {e}"));
+
+    (StatusCode::OK, Html(html)).into_response()
+}
+
+// GET /portfolio/sectors (HTMX partial)
+pub async fn get_portfolio_sectors(
+    State(state): State<AppState>,
+    user: Option<Extension<CurrentUser>>,
+) -> Response {
+    let Some(Extension(u)) = user else {
+        return (StatusCode::UNAUTHORIZED, Html("Unauthorized".to_string())).into_response();
+    };
+
+    let prefs = user_service::get_preferences(&state, u.id).await.unwrap_or_default();
+
+    let sectors = match portfolio_service::positions_by_sector(&state, u.id).await {
+        Ok(s) => s,
+        Err(e) => return e.into_response(),
+    };
+
+    let items: Vec<serde_json::Value> = sectors
+        .iter()
+        .map(|s| {
+            json!({
+                "sector": s.sector,
+                "value": s.value,
+                "pct": s.pct,
+                "pct_label": format!("{:.1}%", s.pct),
             })
         })
         .collect();
 
     let html = state
         .hbs
-        .render("partials/portfolio_positions", &json!({ "groups": groups }))
+        .render(
+            "partials/portfolio_sectors",
+            &json!({ "items": items, "display_currency": prefs.display_currency }),
+        )
         .unwrap_or_else(|e| format!("template error: {e}"));
 
     (StatusCode::OK, Html(html)).into_response()
 }
 
 // GET /portfolio/position/:symbol (HTMX partial)
+// app.js refreshes a card with an outerHTML swap after a trade. Once the position is
+// gone (sold out) this answers 200 with an empty body so the swap removes the card;
+// a 404 would be ignored by HTMX and leave the stale card on the page.
 pub async fn get_portfolio_position_card(
     State(state): State<AppState>,
-    Path(symbol): Path<String>,
+    Symbol(symbol): Symbol,
     user: Option<Extension<CurrentUser>>,
 ) -> Response {
     let Some(Extension(u)) = user else {
@@ -107,18 +225,13 @@ pub async fn get_portfolio_position_card(
 
     let view_opt = match portfolio_service::get_portfolio_position_view(&state, u.id, &symbol).await {
         Ok(v) => v,
-        Err(e) => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Html(format!("db error: {e}")),
-            )
-                .into_response();
-        }
+        Err(e) => return e.into_response(),
     };
 
     let Some(view) = view_opt else {
-        return (StatusCode::NOT_FOUND, Html("Not found".to_string())).into_response();
+        return (StatusCode::OK, Html(String::new())).into_response();
     };
+    let prefs = user_service::get_preferences(&state, u.id).await.unwrap_or_default();
 
     let html = state
         .hbs
@@ -127,11 +240,21 @@ pub async fn get_portfolio_position_card(
             &json!({
                 "symbol": view.symbol,
                 "qty": view.qty,
-                "avg": fmt2(view.avg_price),
-                "current_price": fmt2(view.last_price),
-                "pnl": fmt2(view.pnl),
-                "pnl_pct": fmt2(view.pnl_pct),
+                "avg": view.avg_price,
+                "current_price": view.last_price,
+                "quote_unavailable": view.quote_unavailable,
+                "pnl": view.pnl,
+                "pnl_pct": view.pnl_pct,
                 "pnl_class": view.pnl_class,
+                "day_change": view.day_change,
+                "day_change_pct": view.day_change_pct,
+                "day_change_class": view.day_change_class,
+                "held_since": view.held_since,
+                "days_held": view.days_held,
+                "annualized_pct": view.annualized_return_pct,
+                "lots": lots_json(&view.lots),
+                "default_qty": prefs.default_qty,
+                "display_currency": prefs.display_currency,
             }),
         )
         .unwrap_or_else(|e| format!("template error: {e}"));
@@ -139,9 +262,27 @@ pub async fn get_portfolio_position_card(
     (StatusCode::OK, Html(html)).into_response()
 }
 
-// GET /portfolio/orders (HTMX partial)
+#[derive(Deserialize)]
+pub struct OrdersQuery {
+    pub symbol: Option<String>,
+    // YYYY-MM-DD, both inclusive
+    pub from: Option<String>,
+    pub to: Option<String>,
+}
+
+fn parse_day(s: Option<&str>) -> Result<Option<chrono::NaiveDate>, ()> {
+    match s.map(str::trim).filter(|s| !s.is_empty()) {
+        None => Ok(None),
+        Some(v) => chrono::NaiveDate::parse_from_str(v, "%Y-%m-%d")
+            .map(Some)
+            .map_err(|_| ()),
+    }
+}
+
+// GET /portfolio/orders?symbol=AAPL&from=2024-01-01&to=2024-01-31 (HTMX partial)
 pub async fn get_portfolio_orders(
     State(state): State<AppState>,
+    Query(q): Query<OrdersQuery>,
     user: Option<Extension<CurrentUser>>,
 ) -> Response {
     let Some(Extension(u)) = user else {
@@ -152,9 +293,28 @@ pub async fn get_portfolio_orders(
         return (StatusCode::OK, Html(html)).into_response();
     };
 
-    let views = portfolio_service::list_recent_order_views(&state, u.id, 50)
+    let (Ok(from), Ok(to)) = (parse_day(q.from.as_deref()), parse_day(q.to.as_deref())) else {
+        let html = render::toast_html(&state, ToastKind::Danger, "Dates must be in YYYY-MM-DD format.");
+        return (StatusCode::OK, Html(html)).into_response();
+    };
+
+    if let (Some(f), Some(t)) = (from, to)
+        && f > t
+    {
+        let html = render::toast_html(&state, ToastKind::Danger, r#""From" must be on or before "To"."#);
+        return (StatusCode::OK, Html(html)).into_response();
+    }
+
+    let filter = portfolio_service::OrderFilter {
+        symbol: q.symbol,
+        from: from.and_then(|d| d.and_hms_opt(0, 0, 0)).map(|d| d.and_utc().timestamp()),
+        to: to.and_then(|d| d.and_hms_opt(23, 59, 59)).map(|d| d.and_utc().timestamp()),
+    };
+
+    let views = portfolio_service::list_order_views_filtered(&state, u.id, &filter, 50)
         .await
         .unwrap_or_default();
+    let prefs = user_service::get_preferences(&state, u.id).await.unwrap_or_default();
 
     let items: Vec<serde_json::Value> = views
         .into_iter()
@@ -164,16 +324,67 @@ pub async fn get_portfolio_orders(
                 "symbol": o.symbol,
                 "side": o.side,
                 "qty": o.qty,
-                "price": fmt2(o.price),
-                "total": fmt2(o.total),
+                "price": o.price,
+                "total": o.total,
+                "slippage": o.slippage,
+                "status": o.status,
+                "status_class": o.status_class,
+                "filled_qty": o.filled_qty,
+                "partial": o.partial,
             })
         })
         .collect();
 
     let html = state
         .hbs
-        .render("partials/orders_list", &json!({ "items": items }))
+        .render(
+            "partials/orders_list",
+            &json!({ "items": items, "display_currency": prefs.display_currency }),
+        )
         .unwrap_or_else(|e| format!("template error: {e}"));
 
     (StatusCode::OK, Html(html)).into_response()
 }
+
+#[derive(Deserialize)]
+pub struct HistoryQuery {
+    pub range: Option<String>,
+}
+
+// GET /portfolio/history?range=30d (JSON for the performance chart)
+pub async fn get_portfolio_history(
+    State(state): State<AppState>,
+    Query(q): Query<HistoryQuery>,
+    user: Option<Extension<CurrentUser>>,
+) -> Response {
+    let Some(Extension(u)) = user else {
+        return (StatusCode::UNAUTHORIZED, Html("Unauthorized".to_string())).into_response();
+    };
+
+    let range = q.range.unwrap_or_else(|| "30d".to_string());
+    let Some(days) = portfolio_service::parse_range_days(&range) else {
+        return (StatusCode::BAD_REQUEST, Html("bad range".to_string())).into_response();
+    };
+
+    let points = match portfolio_service::portfolio_history(&state, u.id, days).await {
+        Ok(v) => v,
+        Err(e) => return e.into_response(),
+    };
+
+    let series: Vec<serde_json::Value> = points
+        .into_iter()
+        .map(|p| {
+            json!({
+                "date": p.date,
+                "total_value": p.total_value,
+                "cash": p.cash,
+            })
+        })
+        .collect();
+
+    (
+        StatusCode::OK,
+        axum::Json(json!({ "range": format!("{days}d"), "points": series })),
+    )
+        .into_response()
+}
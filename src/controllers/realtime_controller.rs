@@ -1,32 +1,46 @@
-use std::{convert::Infallible, time::Duration as StdDuration};
+use std::{collections::VecDeque, convert::Infallible, sync::Arc, time::Duration as StdDuration};
 
 use axum::{
     extract::{
-        ws::{Message, WebSocket, WebSocketUpgrade},
+        ws::{close_code, CloseFrame, Message, WebSocket, WebSocketUpgrade},
         Query, State, Extension,
     },
     http::StatusCode,
     response::{IntoResponse, sse::{Event, KeepAlive, Sse}},
 };
-use futures_util::{SinkExt, StreamExt};
 use serde::Deserialize;
-use tokio::time::{interval, Duration as TokioDuration};
-use tokio_tungstenite::{connect_async, tungstenite::protocol::Message as TMessage};
-use tokio::sync::broadcast::error::RecvError;
-
-use crate::{models::CurrentUser, AppState};
+use tokio::time::{interval, Duration as TokioDuration, Instant, MissedTickBehavior};
+use tokio::sync::broadcast::{self, error::RecvError};
+use mongodb::bson::oid::ObjectId;
+
+use crate::{
+    models::CurrentUser,
+    services::{
+        metrics::WsConnectionGuard,
+        portfolio_service, stocks_service,
+        trade_relay::{RelayEvent, TradeBatcher, TradeRelay, TradeTick},
+    },
+    AppState,
+};
 
 #[derive(Deserialize)]
 pub struct TradesWsQuery {
     pub symbol: String,
+    #[serde(default)]
+    pub raw: bool,
 }
 
-// GET /ws/trades?symbol=AAPL
+// GET /ws/trades?symbol=AAPL[&raw=true]
 pub async fn ws_trades(
     ws: WebSocketUpgrade,
     State(state): State<AppState>,
+    user: Option<Extension<CurrentUser>>,
     Query(q): Query<TradesWsQuery>,
 ) -> impl IntoResponse {
+    let Some(Extension(u)) = user else {
+        return (StatusCode::UNAUTHORIZED, "unauthorized").into_response();
+    };
+
     let symbol = q.symbol.trim().to_string();
     let token = state.settings.finnhub_api_key.trim().to_string();
 
@@ -41,93 +55,49 @@ pub async fn ws_trades(
             .into_response();
     }
 
-    ws.on_upgrade(move |socket| handle_trades_socket(socket, symbol, token))
+    let slot = state.ws_limiter.try_acquire(u.id);
+    let max = state.ws_limiter.max_per_user();
+    let relay = state.trades.clone();
+    let flush_every = TokioDuration::from_millis(state.settings.trade_flush_ms);
+    let format = FrameFormat::from_raw(q.raw);
+    ws.on_upgrade(move |socket| async move {
+        let Some(_slot) = slot else {
+            return reject_over_limit(socket, &u, max).await;
+        };
+        handle_trades_socket(socket, u, symbol, relay, flush_every, format).await
+    })
 }
 
-async fn handle_trades_socket(mut client_ws: WebSocket, symbol: String, token: String) {
-    let url = format!("wss://ws.finnhub.io/?token={}", token);
-
-    tracing::info!("WS client connected: symbol={}", symbol);
-    tracing::info!("Connecting to Finnhub WS...");
-
-    let (fh_ws, _) = match connect_async(url.as_str()).await {
-        Ok(x) => x,
-        Err(err) => {
-            tracing::error!("Finnhub WS connect failed: {}", err);
-            let _ = client_ws
-                .send(Message::Text(format!(
-                    r#"{{\"type\":\"error\",\"message\":\"Finnhub WS connect failed: {}\"}}"#,
-                    err
-                )))
-                .await;
-            let _ = client_ws.close().await;
-            return;
-        }
-    };
-
-    tracing::info!("Finnhub WS connected OK");
-
-    let (mut fh_write, mut fh_read) = fh_ws.split();
-
-    let sub = serde_json::json!({ "type": "subscribe", "symbol": symbol });
-    let _ = fh_write.send(TMessage::Text(sub.to_string())).await;
-
-    let mut ping = interval(TokioDuration::from_secs(25));
-
-    loop {
-        tokio::select! {
-            _ = ping.tick() => {
-                if client_ws.send(Message::Ping(b"ping".to_vec())).await.is_err() {
-                    break;
-                }
-            }
-
-            fh_msg = fh_read.next() => {
-                match fh_msg {
-                    Some(Ok(TMessage::Text(txt))) => {
-                        if client_ws.send(Message::Text(txt)).await.is_err() {
-                            break;
-                        }
-                    }
-                    Some(Ok(TMessage::Binary(bin))) => {
-                        if client_ws.send(Message::Binary(bin)).await.is_err() {
-                            break;
-                        }
-                    }
-                    Some(Ok(TMessage::Ping(payload))) => {
-                        let _ = fh_write.send(TMessage::Pong(payload)).await;
-                    }
-                    Some(Ok(TMessage::Pong(_))) => {}
-                    Some(Ok(TMessage::Close(_))) | None => break,
-                    Some(Ok(_)) => {}
-                    Some(Err(_)) => break,
-                }
-            }
-
-            client_msg = client_ws.recv() => {
-                match client_msg {
-                    Some(Ok(Message::Close(_))) | None => break,
-                    Some(Ok(_)) => {}
-                    Some(Err(_)) => break,
-                }
-            }
-        }
-    }
-
-    let _ = client_ws.close().await;
+async fn handle_trades_socket(
+    client_ws: WebSocket,
+    user: CurrentUser,
+    symbol: String,
+    relay: TradeRelay,
+    flush_every: TokioDuration,
+    format: FrameFormat,
+) {
+    tracing::info!("WS client connected: user={} symbol={}", user.username, symbol);
+    relay_trades(client_ws, vec![symbol], relay, flush_every, format).await;
 }
 
 #[derive(Deserialize)]
 pub struct TradesMultiWsQuery {
     pub symbols: String,
+    #[serde(default)]
+    pub raw: bool,
 }
 
-// GET /ws/trades_multi?symbols=AAPL,MSFT,TSLA
+// GET /ws/trades_multi?symbols=AAPL,MSFT,TSLA[&raw=true]
 pub async fn ws_trades_multi(
     ws: WebSocketUpgrade,
     State(state): State<AppState>,
+    user: Option<Extension<CurrentUser>>,
     Query(q): Query<TradesMultiWsQuery>,
 ) -> impl IntoResponse {
+    let Some(Extension(u)) = user else {
+        return (StatusCode::UNAUTHORIZED, "unauthorized").into_response();
+    };
+
     let token = state.settings.finnhub_api_key.trim().to_string();
     if token.is_empty() {
         return (
@@ -137,92 +107,313 @@ pub async fn ws_trades_multi(
             .into_response();
     }
 
-    let mut syms: Vec<String> = q
-        .symbols
-        .split(',')
-        .map(|s| s.trim().to_uppercase())
-        .filter(|s| !s.is_empty())
-        .collect();
-
-    syms.sort();
-    syms.dedup();
-
+    let syms = stocks_service::parse_symbol_list(&q.symbols, MAX_WS_SYMBOLS);
     if syms.is_empty() {
         return (StatusCode::BAD_REQUEST, "missing symbols").into_response();
     }
 
-    if syms.len() > 50 {
-        syms.truncate(50);
+    let slot = state.ws_limiter.try_acquire(u.id);
+    let max = state.ws_limiter.max_per_user();
+    let relay = state.trades.clone();
+    let flush_every = TokioDuration::from_millis(state.settings.trade_flush_ms);
+    let format = FrameFormat::from_raw(q.raw);
+    ws.on_upgrade(move |socket| async move {
+        let Some(_slot) = slot else {
+            return reject_over_limit(socket, &u, max).await;
+        };
+        handle_trades_multi_socket(socket, u, syms, relay, flush_every, format).await
+    })
+}
+
+async fn handle_trades_multi_socket(
+    client_ws: WebSocket,
+    user: CurrentUser,
+    symbols: Vec<String>,
+    relay: TradeRelay,
+    flush_every: TokioDuration,
+    format: FrameFormat,
+) {
+    tracing::info!("WS multi client connected: user={} symbols={:?}", user.username, symbols);
+    relay_trades(client_ws, symbols, relay, flush_every, format).await;
+}
+
+// Cap on symbols watched by one socket, across the initial query and later subscribes.
+pub const MAX_WS_SYMBOLS: usize = 50;
+
+#[derive(Deserialize)]
+struct ClientCommand {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    symbol: String,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum SymbolChange {
+    Subscribe(String),
+    Unsubscribe(String),
+}
+
+/// Applies a `{"type":"subscribe"|"unsubscribe","symbol":..}` frame from the browser
+/// to the socket's symbol set. Returns the change to pass on to the relay, if any.
+pub fn apply_client_command(
+    symbols: &mut Vec<String>,
+    text: &str,
+) -> Result<Option<SymbolChange>, String> {
+    let Ok(cmd) = serde_json::from_str::<ClientCommand>(text) else {
+        return Ok(None);
+    };
+
+    let symbol = cmd.symbol.trim().to_uppercase();
+    if symbol.is_empty() {
+        return Ok(None);
+    }
+
+    match cmd.kind.as_str() {
+        "subscribe" => {
+            if symbols.contains(&symbol) {
+                return Ok(None);
+            }
+            if symbols.len() >= MAX_WS_SYMBOLS {
+                return Err(format!("symbol limit reached ({})", MAX_WS_SYMBOLS));
+            }
+            symbols.push(symbol.clone());
+            symbols.sort();
+            Ok(Some(SymbolChange::Subscribe(symbol)))
+        }
+        "unsubscribe" => {
+            let Some(idx) = symbols.iter().position(|s| *s == symbol) else {
+                return Ok(None);
+            };
+            symbols.remove(idx);
+            Ok(Some(SymbolChange::Unsubscribe(symbol)))
+        }
+        _ => Ok(None),
     }
+}
 
-    ws.on_upgrade(move |socket| handle_trades_multi_socket(socket, syms, token))
+// The upgrade has already happened by the time the cap is known to be hit,
+// so the refusal is a policy close frame rather than an HTTP status.
+async fn reject_over_limit(mut socket: WebSocket, user: &CurrentUser, max: usize) {
+    tracing::warn!("WS rejected: user={} already has {} open sockets", user.username, max);
+    let _ = socket
+        .send(Message::Close(Some(CloseFrame {
+            code: close_code::POLICY,
+            reason: format!("too many connections (max {})", max).into(),
+        })))
+        .await;
 }
 
-async fn handle_trades_multi_socket(mut client_ws: WebSocket, symbols: Vec<String>, token: String) {
-    let url = format!("wss://ws.finnhub.io/?token={}", token);
-
-    tracing::info!("WS multi client connected: symbols={:?}", symbols);
-    tracing::info!("Connecting to Finnhub WS...");
-
-    let (fh_ws, _) = match connect_async(url.as_str()).await {
-        Ok(x) => x,
-        Err(err) => {
-            tracing::error!("Finnhub WS connect failed: {}", err);
-            let _ = client_ws
-                .send(Message::Text(format!(
-                    r#"{{\"type\":\"error\",\"message\":\"Finnhub WS connect failed: {}\"}}"#,
-                    err
-                )))
-                .await;
-            let _ = client_ws.close().await;
-            return;
+/// What the browser receives on a trades socket.
+///
+/// `Normalized` (the default) sends one `{"symbol","price","ts"}` message per
+/// coalesced trade and only `{"error": ..}` otherwise, so the front-end doesn't
+/// depend on Finnhub's wire format. `Raw` (`?raw=true`, for debugging) keeps the
+/// Finnhub-shaped `{"type":"trade","data":[..]}` frames and every status frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameFormat {
+    Normalized,
+    Raw,
+}
+
+impl FrameFormat {
+    pub fn from_raw(raw: bool) -> Self {
+        if raw { FrameFormat::Raw } else { FrameFormat::Normalized }
+    }
+
+    /// Drains the batcher into the text frames to send, possibly none.
+    pub fn trade_frames(self, batcher: &mut TradeBatcher) -> Vec<String> {
+        match self {
+            FrameFormat::Raw => batcher.flush().into_iter().collect(),
+            FrameFormat::Normalized => batcher.drain().iter().map(normalized_trade).collect(),
         }
-    };
+    }
+
+    /// The frame for a relay status or socket error; None if this format drops it.
+    pub fn status_frame(self, kind: &str, message: &str) -> Option<String> {
+        match self {
+            FrameFormat::Raw => Some(serde_json::json!({ "type": kind, "message": message }).to_string()),
+            FrameFormat::Normalized if kind == "error" => {
+                Some(serde_json::json!({ "error": message }).to_string())
+            }
+            FrameFormat::Normalized => None,
+        }
+    }
+}
 
-    let (mut fh_write, mut fh_read) = fh_ws.split();
+/// Sent in either format when Finnhub refuses a symbol this socket watches.
+pub fn unsupported_frame(symbol: &str) -> String {
+    serde_json::json!({ "type": "error", "code": "unsupported_symbol", "symbol": symbol }).to_string()
+}
+
+pub fn normalized_trade(t: &TradeTick) -> String {
+    serde_json::json!({ "symbol": t.symbol, "price": t.price, "ts": t.timestamp }).to_string()
+}
+
+// Browsers answer pings with a pong on their own. A socket that stays silent past
+// WS_PONG_TIMEOUT is half-open: drop it so its task and relay lease don't leak.
+pub const WS_PING_EVERY: TokioDuration = TokioDuration::from_secs(25);
+pub const WS_PONG_TIMEOUT: TokioDuration = TokioDuration::from_secs(60);
+
+/// True once nothing has come back from the client for longer than `timeout`.
+pub fn heartbeat_expired(last_seen: Instant, now: Instant, timeout: TokioDuration) -> bool {
+    now.saturating_duration_since(last_seen) > timeout
+}
+
+// This socket's references on the shared relay; released when the socket goes away.
+struct RelayLease {
+    relay: TradeRelay,
+    symbols: Vec<String>,
+}
+
+impl RelayLease {
+    fn new(relay: TradeRelay, symbols: Vec<String>) -> Self {
+        for s in &symbols {
+            relay.acquire(s);
+        }
+        Self { relay, symbols }
+    }
+}
 
-    for s in &symbols {
-        let sub = serde_json::json!({ "type": "subscribe", "symbol": s });
-        let _ = fh_write.send(TMessage::Text(sub.to_string())).await;
+impl RelayLease {
+    // Moves the lease to exactly `symbols`, acquiring and releasing the difference.
+    fn set_symbols(&mut self, mut symbols: Vec<String>) {
+        symbols.sort();
+        symbols.dedup();
+        for s in symbols.iter().filter(|s| !self.symbols.contains(s)) {
+            self.relay.acquire(s);
+        }
+        for s in self.symbols.iter().filter(|s| !symbols.contains(s)) {
+            self.relay.release(s);
+        }
+        self.symbols = symbols;
     }
 
-    let mut ping = interval(TokioDuration::from_secs(25));
+    // Drops one symbol early; false if this socket wasn't watching it.
+    fn forget(&mut self, symbol: &str) -> bool {
+        let Some(idx) = self.symbols.iter().position(|s| s == symbol) else {
+            return false;
+        };
+        self.symbols.remove(idx);
+        self.relay.release(symbol);
+        true
+    }
+}
+
+impl Drop for RelayLease {
+    fn drop(&mut self) {
+        for s in &self.symbols {
+            self.relay.release(s);
+        }
+    }
+}
+
+// Forwards the shared relay's trades for this socket's symbols to the browser,
+// coalesced per symbol and flushed every `flush_every`.
+// The relay owns the upstream connection and its reconnects; an "error" status
+// means it gave up, so the browser socket is closed too.
+async fn relay_trades(
+    mut client_ws: WebSocket,
+    symbols: Vec<String>,
+    relay: TradeRelay,
+    flush_every: TokioDuration,
+    format: FrameFormat,
+) {
+    let _conn = WsConnectionGuard::new();
+    let mut events = relay.subscribe();
+    let mut lease = RelayLease::new(relay, symbols);
+    let mut ping = interval(WS_PING_EVERY);
+    // any frame from the client (pongs included) counts as a sign of life
+    let mut last_seen = Instant::now();
+    let mut batcher = TradeBatcher::default();
+    let mut flush = interval(flush_every);
+    flush.set_missed_tick_behavior(MissedTickBehavior::Delay);
 
     loop {
         tokio::select! {
             _ = ping.tick() => {
+                if heartbeat_expired(last_seen, Instant::now(), WS_PONG_TIMEOUT) {
+                    tracing::info!("WS client stopped answering pings; closing");
+                    break;
+                }
                 if client_ws.send(Message::Ping(b"ping".to_vec())).await.is_err() {
                     break;
                 }
             }
 
-            fh_msg = fh_read.next() => {
-                match fh_msg {
-                    Some(Ok(TMessage::Text(txt))) => {
-                        if client_ws.send(Message::Text(txt)).await.is_err() {
-                            break;
-                        }
+            _ = flush.tick() => {
+                let mut gone = false;
+                for frame in format.trade_frames(&mut batcher) {
+                    if client_ws.send(Message::Text(frame)).await.is_err() {
+                        gone = true;
+                        break;
                     }
-                    Some(Ok(TMessage::Binary(bin))) => {
-                        if client_ws.send(Message::Binary(bin)).await.is_err() {
-                            break;
+                }
+                if gone {
+                    break;
+                }
+            }
+
+            ev = events.recv() => {
+                match ev {
+                    Ok(ev) => match &*ev {
+                        RelayEvent::Trades { symbol, trades } => {
+                            if lease.symbols.contains(symbol) {
+                                for t in trades {
+                                    batcher.push(t);
+                                }
+                            }
                         }
+                        RelayEvent::Status { kind, message } => {
+                            if let Some(frame) = format.status_frame(kind, message)
+                                && client_ws.send(Message::Text(frame)).await.is_err()
+                            {
+                                break;
+                            }
+                            if *kind == "error" {
+                                break;
+                            }
+                        }
+                        // The symbol is dropped from this socket; with nothing left to
+                        // watch the socket is closed rather than left silent.
+                        RelayEvent::Unsupported { symbol } => {
+                            if lease.forget(symbol) {
+                                if client_ws.send(Message::Text(unsupported_frame(symbol))).await.is_err() {
+                                    break;
+                                }
+                                if lease.symbols.is_empty() {
+                                    break;
+                                }
+                            }
+                        }
+                    },
+                    Err(RecvError::Lagged(n)) => {
+                        tracing::warn!("WS client lagged; skipped {} relay events", n);
                     }
-                    Some(Ok(TMessage::Ping(payload))) => {
-                        let _ = fh_write.send(TMessage::Pong(payload)).await;
-                    }
-                    Some(Ok(TMessage::Pong(_))) => {}
-                    Some(Ok(TMessage::Close(_))) | None => break,
-                    Some(Ok(_)) => {}
-                    Some(Err(_)) => break,
+                    Err(RecvError::Closed) => break,
                 }
             }
 
             client_msg = client_ws.recv() => {
+                if let Some(Ok(_)) = client_msg {
+                    last_seen = Instant::now();
+                }
                 match client_msg {
-                    Some(Ok(Message::Close(_))) | None => break,
+                    Some(Ok(Message::Close(_))) | None | Some(Err(_)) => break,
+                    Some(Ok(Message::Text(txt))) => match apply_client_command(&mut lease.symbols, &txt) {
+                        Ok(Some(SymbolChange::Subscribe(s))) => lease.relay.acquire(&s),
+                        Ok(Some(SymbolChange::Unsubscribe(s))) => lease.relay.release(&s),
+                        Ok(None) => {}
+                        Err(e) => {
+                            if let Some(frame) = format.status_frame("error", &e)
+                                && client_ws.send(Message::Text(frame)).await.is_err()
+                            {
+                                break;
+                            }
+                        }
+                    },
+                    // Pong (and Ping, which axum answers itself) only refresh last_seen
                     Some(Ok(_)) => {}
-                    Some(Err(_)) => break,
                 }
             }
         }
@@ -231,22 +422,174 @@ async fn handle_trades_multi_socket(mut client_ws: WebSocket, symbols: Vec<Strin
     let _ = client_ws.close().await;
 }
 
+// At most one priceUpdate per symbol per window, carrying the window's last price.
+pub const PRICE_PUSH_EVERY: StdDuration = StdDuration::from_secs(1);
+
+pub fn price_update_data(t: &TradeTick) -> String {
+    serde_json::json!({ "symbol": t.symbol, "price": t.price }).to_string()
+}
+
+// Relay trades for the symbols one user holds, coalesced per PRICE_PUSH_EVERY.
+struct PriceFeed {
+    events: broadcast::Receiver<Arc<RelayEvent>>,
+    lease: RelayLease,
+    batcher: TradeBatcher,
+    flush: tokio::time::Interval,
+    ready: VecDeque<TradeTick>,
+}
+
+impl PriceFeed {
+    fn new(relay: TradeRelay, symbols: Vec<String>) -> Self {
+        let events = relay.subscribe();
+        let mut flush = interval(PRICE_PUSH_EVERY);
+        flush.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        Self {
+            events,
+            lease: RelayLease::new(relay, symbols),
+            batcher: TradeBatcher::default(),
+            flush,
+            ready: VecDeque::new(),
+        }
+    }
+
+    // Cancel-safe: pending trades stay in the batcher if another event wins the select.
+    async fn next(&mut self) -> Option<TradeTick> {
+        loop {
+            if let Some(t) = self.ready.pop_front() {
+                return Some(t);
+            }
+            tokio::select! {
+                _ = self.flush.tick() => self.ready.extend(self.batcher.drain()),
+                ev = self.events.recv() => match ev {
+                    Ok(ev) => match &*ev {
+                        RelayEvent::Trades { symbol, trades } if self.lease.symbols.contains(symbol) => {
+                            for t in trades {
+                                self.batcher.push(t);
+                            }
+                        }
+                        RelayEvent::Unsupported { symbol } => {
+                            self.lease.forget(symbol);
+                        }
+                        _ => {}
+                    },
+                    Err(RecvError::Lagged(_)) => {}
+                    Err(RecvError::Closed) => return None,
+                },
+            }
+        }
+    }
+}
+
+async fn held_symbols(state: &AppState, user_id: ObjectId) -> Vec<String> {
+    portfolio_service::list_user_positions(state, user_id)
+        .await
+        .map(|ps| ps.into_iter().map(|p| p.symbol.to_uppercase()).collect())
+        .unwrap_or_default()
+}
+
+/// Sent instead of the events an `/events` subscriber missed by falling more than
+/// EVENTS_CHANNEL_CAPACITY behind. The dropped events can't be replayed, so the
+/// client has to refetch everything it shows; `data` is how many were dropped.
+pub const SSE_RESYNC_EVENT: &str = "resync";
+
+struct SseFeed {
+    state: AppState,
+    user_id: ObjectId,
+    app_events: broadcast::Receiver<String>,
+    // None without a Finnhub key; the app events still flow
+    prices: Option<PriceFeed>,
+}
+
+impl SseFeed {
+    // None once the app event channel is closed; polling it again would return
+    // Closed straight away, so the stream has to end there.
+    async fn next_event(&mut self) -> Option<Event> {
+        loop {
+            let prices = async {
+                match self.prices.as_mut() {
+                    Some(p) => p.next().await,
+                    None => std::future::pending().await,
+                }
+            };
+
+            tokio::select! {
+                ev = self.app_events.recv() => match ev {
+                    Ok(name) => {
+                        // internal: only the trader's own stream re-reads holdings
+                        if let Some(owner) = name.strip_prefix(portfolio_service::HOLDINGS_CHANGED_PREFIX) {
+                            if owner == self.user_id.to_hex() {
+                                self.follow_holdings().await;
+                            }
+                            continue;
+                        }
+                        return Some(Event::default().event(name).data("1"));
+                    }
+                    Err(RecvError::Lagged(n)) => {
+                        // a holdings change may be among the dropped events
+                        self.follow_holdings().await;
+                        return Some(Event::default().event(SSE_RESYNC_EVENT).data(n.to_string()));
+                    }
+                    Err(RecvError::Closed) => return None,
+                },
+                tick = prices => match tick {
+                    Some(t) => return Some(Event::default().event("priceUpdate").data(price_update_data(&t))),
+                    None => {
+                        self.prices = None;
+                        return Some(Event::default().event("ping").data("prices closed"));
+                    }
+                },
+            }
+        }
+    }
+
+    async fn follow_holdings(&mut self) {
+        if self.prices.is_none() {
+            return;
+        }
+        let symbols = held_symbols(&self.state, self.user_id).await;
+        if let Some(p) = self.prices.as_mut() {
+            p.lease.set_symbols(symbols);
+        }
+    }
+}
+
+/// The `/events` stream for one user: `app_events` as named events, plus
+/// `priceUpdate` for `held` symbols when given. Ends when `app_events` closes.
+pub fn sse_stream(
+    state: AppState,
+    user_id: ObjectId,
+    app_events: broadcast::Receiver<String>,
+    held: Option<Vec<String>>,
+) -> impl futures_util::stream::Stream<Item = Result<Event, Infallible>> {
+    let prices = held.map(|symbols| PriceFeed::new(state.trades.clone(), symbols));
+    let feed = SseFeed {
+        state,
+        user_id,
+        app_events,
+        prices,
+    };
+
+    futures_util::stream::unfold(feed, |mut feed| async {
+        let evt = feed.next_event().await?;
+        Some((Ok(evt), feed))
+    })
+}
+
 // GET /events  (SSE)
+// App-wide change events, plus `priceUpdate` for the symbols this user holds.
 pub async fn sse_events(
     State(state): State<AppState>,
-    Extension(_u): Extension<CurrentUser>,
+    Extension(u): Extension<CurrentUser>,
 ) -> Sse<impl futures_util::stream::Stream<Item = Result<Event, Infallible>>> {
-    let rx = state.events_tx.subscribe();
+    let app_events = state.events_tx.subscribe();
 
-    let stream = futures_util::stream::unfold(rx, |mut rx| async {
-        let evt = match rx.recv().await {
-            Ok(name) => Event::default().event(name).data("1"),
-            Err(RecvError::Lagged(_)) => Event::default().event("ping").data("lagged"),
-            Err(RecvError::Closed) => Event::default().event("ping").data("closed"),
-        };
+    let held = if state.settings.finnhub_api_key.trim().is_empty() {
+        None
+    } else {
+        Some(held_symbols(&state, u.id).await)
+    };
 
-        Some((Ok(evt), rx))
-    });
+    let stream = sse_stream(state, u.id, app_events, held);
 
     Sse::new(stream).keep_alive(
         KeepAlive::new()
@@ -1,16 +1,31 @@
 use axum::{
-    extract::{Extension, Path, Query, State},
+    extract::{Extension, Query, State},
     http::{HeaderMap, StatusCode},
     response::{Html, IntoResponse},
 };
 use serde::Deserialize;
 use serde_json::json;
 
-use crate::{models::CurrentUser, render, services::stocks_service, AppState};
+use crate::{
+    controllers::symbol::Symbol,
+    models::{CurrentUser, Preferences},
+    render,
+    services::{stocks_service, user_service},
+    AppState,
+};
 
 #[derive(Deserialize)]
 pub struct SearchQuery {
     pub q: Option<String>,
+    // Finnhub security type, e.g. "Common Stock"; empty means all
+    #[serde(rename = "type")]
+    pub kind: Option<String>,
+    // see stocks_service::symbol_exchange; absent falls back to DEFAULT_SEARCH_EXCHANGE,
+    // empty means all
+    pub exchange: Option<String>,
+    // paging for "more results"
+    pub offset: Option<usize>,
+    pub limit: Option<usize>,
 }
 
 fn is_htmx(headers: &HeaderMap) -> bool {
@@ -26,12 +41,13 @@ pub async fn get_search(
     headers: HeaderMap,
     user: Option<Extension<CurrentUser>>,
 ) -> axum::response::Response {
-    let body = match state.hbs.render("pages/search", &json!({})) {
+    let ctx = json!({ "exchange": state.settings.default_search_exchange });
+    let body = match state.hbs.render("pages/search", &ctx) {
         Ok(s) => s,
         Err(e) => {
             return (
                 StatusCode::INTERNAL_SERVER_ERROR,
-                Html(format!("template error: {e}")),
+                Html(format!("template error: {}", render::escape_html(&e.to_string()))),
             )
                 .into_response()
         }
@@ -54,7 +70,13 @@ pub async fn get_search_results(
 ) -> axum::response::Response {
     let q = query.q.unwrap_or_default().trim().to_string();
 
-    let data = stocks_service::search_results_ctx(&state, &q).await;
+    let offset = query.offset.unwrap_or(0);
+    let limit = query.limit.unwrap_or(stocks_service::DEFAULT_SEARCH_LIMIT);
+
+    let exchange = query.exchange.or_else(|| state.settings.default_search_exchange.clone());
+
+    let data =
+        stocks_service::search_results_ctx(&state, &q, query.kind.as_deref(), exchange.as_deref(), offset, limit).await;
 
     let html = state
         .hbs
@@ -67,18 +89,29 @@ pub async fn get_search_results(
 pub async fn get_details(
     State(state): State<AppState>,
     headers: HeaderMap,
-    Path(symbol): Path<String>,
+    Symbol(symbol): Symbol,
     user: Option<Extension<CurrentUser>>,
 ) -> axum::response::Response {
+    let default_qty = match user.as_ref() {
+        Some(Extension(u)) => user_service::get_preferences(&state, u.id).await.unwrap_or_default().default_qty,
+        None => Preferences::default().default_qty,
+    };
+
+    if let Some(Extension(u)) = user.as_ref()
+        && let Err(e) = user_service::record_recent_symbol(&state, u.id, &symbol).await
+    {
+        tracing::warn!("failed to record recent symbol {symbol} for {}: {e}", u.id);
+    }
+
     let body = match state
         .hbs
-        .render("pages/details", &json!({ "symbol": symbol }))
+        .render("pages/details", &json!({ "symbol": symbol, "default_qty": default_qty }))
     {
         Ok(s) => s,
         Err(e) => {
             return (
                 StatusCode::INTERNAL_SERVER_ERROR,
-                Html(format!("template error: {e}")),
+                Html(format!("template error: {}", render::escape_html(&e.to_string()))),
             )
                 .into_response()
         }
@@ -97,7 +130,7 @@ pub async fn get_details(
 
 pub async fn get_details_quote(
     State(state): State<AppState>,
-    Path(symbol): Path<String>,
+    Symbol(symbol): Symbol,
 ) -> axum::response::Response {
     let data = stocks_service::quote_ctx(&state, &symbol).await;
 
@@ -108,3 +141,21 @@ pub async fn get_details_quote(
 
     (StatusCode::OK, Html(html)).into_response()
 }
+
+// GET /recent (HTMX partial)
+pub async fn get_recent(State(state): State<AppState>, user: Option<Extension<CurrentUser>>) -> axum::response::Response {
+    let symbols = match user.as_ref() {
+        Some(Extension(u)) => user_service::recent_symbols(&state, u.id).await.unwrap_or_else(|e| {
+            tracing::warn!("failed to load recent symbols for {}: {e}", u.id);
+            vec![]
+        }),
+        None => vec![],
+    };
+
+    let html = state
+        .hbs
+        .render("partials/recent_symbols", &json!({ "symbols": symbols }))
+        .unwrap_or_else(|e| format!("template error: {e}"));
+
+    (StatusCode::OK, Html(html)).into_response()
+}
@@ -0,0 +1,47 @@
+//! `Symbol` extractor for routes with a `:symbol` segment.
+//!
+//! Normalizes the path symbol once ("aapl" and " AAPL" both become "AAPL",
+//! "binance:btcusdt" becomes "BINANCE:BTCUSDT") so handlers and services get the
+//! canonical form, and turns anything that isn't a symbol into a 400.
+
+use std::collections::HashMap;
+
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Path},
+    http::{request::Parts, StatusCode},
+    response::{Html, IntoResponse, Response},
+};
+
+use crate::services::stocks_service;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol(pub String);
+
+/// The `:symbol` segment was missing or not a valid symbol.
+#[derive(Debug, Clone, Copy)]
+pub struct InvalidSymbol;
+
+impl IntoResponse for InvalidSymbol {
+    fn into_response(self) -> Response {
+        (StatusCode::BAD_REQUEST, Html(r#"<div class="text-danger">Invalid symbol.</div>"#)).into_response()
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for Symbol {
+    type Rejection = InvalidSymbol;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        // by name, so routes with more segments (/alerts/:symbol/:id/delete) work too
+        let Path(params) = Path::<HashMap<String, String>>::from_request_parts(parts, state)
+            .await
+            .map_err(|_| InvalidSymbol)?;
+
+        let sym = stocks_service::normalize_symbol(params.get("symbol").ok_or(InvalidSymbol)?);
+        if !stocks_service::is_valid_symbol(&sym) {
+            return Err(InvalidSymbol);
+        }
+        Ok(Symbol(sym))
+    }
+}
@@ -1,104 +1,143 @@
 use axum::{
-    extract::{Extension, Form, Path, State},
-    http::{HeaderMap, HeaderValue, StatusCode},
+    extract::{Extension, Form, State},
+    http::{HeaderMap, StatusCode},
     response::{Html, IntoResponse, Response},
 };
+use mongodb::bson::oid::ObjectId;
 use serde::Deserialize;
 use serde_json::json;
 
 use crate::{
+    controllers::symbol::Symbol,
+    error::AppError,
     models::CurrentUser,
-    services::{portfolio_service, trading_service},
-    AppState,
+    render::{self, ToastKind},
+    services::{portfolio_service, trading_service, user_service},
+    templates, AppState,
 };
 
-fn hx_trigger_value(events: &[&str]) -> HeaderValue {
-    // HX-Trigger expects JSON: {"evt":true,...}
-    let mut s = String::from("{");
-    for (i, ev) in events.iter().enumerate() {
-        if i > 0 {
-            s.push(',');
-        }
-        s.push('"');
-        s.push_str(ev);
-        s.push_str("\":true");
-    }
-    s.push('}');
-    HeaderValue::from_str(&s).unwrap_or_else(|_| HeaderValue::from_static("{}"))
-}
+// HX-Trigger events fired after a successful fill.
+const TRADE_EVENTS: &[&str] = &["cashUpdated", "positionUpdated", "ordersUpdated"];
 
 fn unauthorized_snippet() -> Response {
     (StatusCode::UNAUTHORIZED, Html(r#"<div class=\"text-danger\">Unauthorized</div>"#.to_string())).into_response()
 }
 
-fn fmt2(v: f64) -> String {
-    format!("{:.2}", v)
+// Context for the details page's #positionPanel: the user's position in `symbol`,
+// or a "no position" note when there is none (or nobody is logged in).
+// `last_price` skips the live quote, e.g. with the fill price right after a trade.
+async fn position_panel_ctx(
+    state: &AppState,
+    user_id: Option<ObjectId>,
+    symbol: &str,
+    last_price: Option<f64>,
+) -> Result<serde_json::Value, AppError> {
+    let view = match user_id {
+        Some(id) => portfolio_service::get_position_view_priced(state, id, symbol, last_price).await?,
+        None => None,
+    };
+
+    let ctx = match (user_id, view) {
+        (Some(id), Some(view)) => {
+            let prefs = user_service::get_preferences(state, id).await.unwrap_or_default();
+            json!({
+                "has_position": true,
+                "symbol": view.symbol,
+                "qty": view.qty,
+                "avg_price": view.avg_price,
+                "last_price": view.last_price,
+                "quote_unavailable": view.quote_unavailable,
+                "pnl": view.pnl,
+                "pnl_pct": view.pnl_pct,
+                "pnl_class": view.pnl_class,
+                "day_range": view.day_range,
+                "display_currency": prefs.display_currency,
+            })
+        }
+        _ => json!({
+            "has_position": false,
+            "symbol": symbol,
+        }),
+    };
+
+    Ok(ctx)
 }
 
 // GET /position/:symbol (HTMX partial)
 pub async fn get_position_panel(
     State(state): State<AppState>,
-    Path(symbol): Path<String>,
+    Symbol(symbol): Symbol,
     user: Option<Extension<CurrentUser>>,
 ) -> Response {
-    let Some(Extension(u)) = user else {
-        let html = state
-            .hbs
-            .render(
-                "partials/position_panel",
-                &json!({
-                    "has_position": false,
-                    "symbol": symbol.to_uppercase(),
-                }),
+    let user_id = user.map(|Extension(u)| u.id);
+
+    // a fill from the last few seconds is as good as a quote and saves a Finnhub call
+    let last_price = match user_id {
+        Some(id) if state.settings.fill_price_max_age_secs > 0 => {
+            let latest = portfolio_service::latest_order_price(&state, id, &symbol)
+                .await
+                .unwrap_or(None);
+            portfolio_service::fresh_fill_price(
+                latest,
+                state.clock.timestamp(),
+                state.settings.fill_price_max_age_secs,
             )
-            .unwrap_or_else(|e| format!("template error: {e}"));
-        return (StatusCode::OK, Html(html)).into_response();
+        }
+        _ => None,
     };
 
-    let view_opt = match portfolio_service::get_portfolio_position_view(&state, u.id, &symbol).await {
-        Ok(v) => v,
+    let ctx = match position_panel_ctx(&state, user_id, &symbol, last_price).await {
+        Ok(ctx) => ctx,
+        Err(e) => return e.into_response(),
+    };
+
+    match render::render_or_500(&state, "partials/position_panel", &ctx) {
+        Ok(html) => (StatusCode::OK, Html(html)).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+// Success toast for a fill. Trades from the details page (they target #tradeMsg)
+// also get the refreshed #positionPanel out-of-band, so the panel doesn't need a
+// second request on positionUpdated.
+async fn trade_toast(
+    state: &AppState,
+    headers: &HeaderMap,
+    user_id: ObjectId,
+    symbol: &str,
+    fill_price: f64,
+    msg: &str,
+) -> Response {
+    let from_details = headers
+        .get("HX-Target")
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|t| t == "tradeMsg");
+    if !from_details {
+        return render::toast(state, ToastKind::Success, msg, TRADE_EVENTS);
+    }
+
+    // the fill went through either way; on failure the panel refreshes itself the usual way
+    let panel = match position_panel_ctx(state, Some(user_id), symbol, Some(fill_price)).await {
+        Ok(ctx) => render::render_or_500(state, "partials/position_panel", &ctx).ok(),
         Err(e) => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Html(format!("db error: {e}")),
-            )
-                .into_response();
+            e.report();
+            None
         }
     };
-
-    let Some(view) = view_opt else {
-        let html = state
-            .hbs
-            .render(
-                "partials/position_panel",
-                &json!({
-                    "has_position": false,
-                    "symbol": symbol.to_uppercase(),
-                }),
-            )
-            .unwrap_or_else(|e| format!("template error: {e}"));
-        return (StatusCode::OK, Html(html)).into_response();
+    let Some(panel) = panel else {
+        return render::toast(state, ToastKind::Success, msg, TRADE_EVENTS);
     };
 
-    let html = state
-        .hbs
-        .render(
-            "partials/position_panel",
-            &json!({
-                "has_position": true,
-                "symbol": view.symbol,
-                "qty": view.qty,
-                "avg_price": fmt2(view.avg_price),
-                "avg_price_raw": view.avg_price,
-                "last_price": fmt2(view.last_price),
-                "pnl": fmt2(view.pnl),
-                "pnl_pct": fmt2(view.pnl_pct),
-                "pnl_class": view.pnl_class,
-            }),
-        )
-        .unwrap_or_else(|e| format!("template error: {e}"));
+    let events: Vec<&str> = TRADE_EVENTS.iter().copied().filter(|e| *e != "positionUpdated").collect();
+    let mut res_headers = HeaderMap::new();
+    res_headers.insert("HX-Trigger", render::toast_trigger(ToastKind::Success, msg, &events));
 
-    (StatusCode::OK, Html(html)).into_response()
+    let body = format!(
+        r#"{}<div id="positionPanel" hx-swap-oob="innerHTML">{}</div>"#,
+        render::toast_html(state, ToastKind::Success, msg),
+        panel
+    );
+    (StatusCode::OK, res_headers, Html(body)).into_response()
 }
 
 #[derive(Deserialize)]
@@ -109,7 +148,8 @@ pub struct TradeForm {
 // POST /trade/:symbol/buy
 pub async fn post_trade_buy(
     State(state): State<AppState>,
-    Path(symbol): Path<String>,
+    headers: HeaderMap,
+    Symbol(symbol): Symbol,
     user: Option<Extension<CurrentUser>>,
     Form(form): Form<TradeForm>,
 ) -> Response {
@@ -121,59 +161,44 @@ pub async fn post_trade_buy(
     let qty: i64 = match qty_str.parse() {
         Ok(q) => q,
         Err(_) => {
-            return (
-                StatusCode::OK,
-                Html(r#"<div class=\"text-danger\">Enter a valid quantity.</div>"#.to_string()),
-            )
-                .into_response();
+            return render::error_toast(&state, StatusCode::UNPROCESSABLE_ENTITY, "Enter a valid quantity.");
         }
     };
 
     let result = match trading_service::market_buy(&state, u.id, &symbol, qty).await {
         Ok(r) => r,
-        Err(errs) => {
-            if let Some(v) = errs.get("balance") {
-                return (StatusCode::OK, Html(format!(r#"<div class=\"text-danger\">{}</div>"#, v))).into_response();
-            }
-            if let Some(v) = errs.get("qty") {
-                return (StatusCode::OK, Html(format!(r#"<div class=\"text-danger\">{}</div>"#, v))).into_response();
-            }
-            if let Some(v) = errs.get("_form") {
-                return (StatusCode::OK, Html(format!(r#"<div class=\"text-danger\">{}</div>"#, v))).into_response();
-            }
-            return (
-                StatusCode::OK,
-                Html(r#"<div class=\"text-danger\">Could not buy.</div>"#.to_string()),
-            )
-                .into_response();
+        Err(AppError::Validation(errs)) => {
+            let msg = ["balance", "qty", "price"]
+                .iter()
+                .find_map(|k| errs.get(*k))
+                .map(String::as_str)
+                .unwrap_or("Could not buy.");
+            return render::error_toast(&state, StatusCode::UNPROCESSABLE_ENTITY, msg);
         }
+        Err(e) => return render::app_error_toast(&state, &e),
     };
 
-    let mut headers = HeaderMap::new();
-    headers.insert(
-        "HX-Trigger",
-        hx_trigger_value(&["cashUpdated", "positionUpdated", "ordersUpdated"]),
-    );
+    let currency = user_service::get_preferences(&state, u.id)
+        .await
+        .unwrap_or_default()
+        .display_currency;
 
-    (
-        StatusCode::OK,
-        headers,
-        Html(format!(
-            r#"<div class=\"text-success\">Bought {} {} @ {} (Cost: {}, New balance: {})</div>"#,
-            result.qty,
-            result.symbol,
-            fmt2(result.fill_price),
-            fmt2(result.cost),
-            fmt2(result.new_cash)
-        )),
-    )
-        .into_response()
+    let msg = format!(
+        "Bought {} {} @ {} (Cost: {}, New balance: {})",
+        result.qty,
+        result.symbol,
+        templates::format_money(result.fill_price, &currency),
+        templates::format_money(result.cost, &currency),
+        templates::format_money(result.new_cash, &currency)
+    );
+    trade_toast(&state, &headers, u.id, &result.symbol, result.fill_price, &msg).await
 }
 
 // POST /trade/:symbol/sell
 pub async fn post_trade_sell(
     State(state): State<AppState>,
-    Path(symbol): Path<String>,
+    headers: HeaderMap,
+    Symbol(symbol): Symbol,
     user: Option<Extension<CurrentUser>>,
     Form(form): Form<TradeForm>,
 ) -> Response {
@@ -185,48 +210,115 @@ pub async fn post_trade_sell(
     let qty: i64 = match qty_str.parse() {
         Ok(q) => q,
         Err(_) => {
-            return (
-                StatusCode::OK,
-                Html(r#"<div class=\"text-danger\">Enter a valid quantity.</div>"#.to_string()),
-            )
-                .into_response();
+            return render::error_toast(&state, StatusCode::UNPROCESSABLE_ENTITY, "Enter a valid quantity.");
+        }
+    };
+
+    let result = trading_service::market_sell(&state, u.id, &symbol, qty).await;
+    sell_response(&state, &headers, u.id, result).await
+}
+
+#[derive(Deserialize)]
+pub struct SellFractionForm {
+    // share of the position to sell, 0 < fraction <= 1
+    pub fraction: String,
+}
+
+// POST /trade/:symbol/sell_fraction
+pub async fn post_trade_sell_fraction(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Symbol(symbol): Symbol,
+    user: Option<Extension<CurrentUser>>,
+    Form(form): Form<SellFractionForm>,
+) -> Response {
+    let Some(Extension(u)) = user else {
+        return unauthorized_snippet();
+    };
+
+    let fraction = match form.fraction.trim().parse::<f64>() {
+        Ok(f) if f > 0.0 && f <= 1.0 => f,
+        _ => {
+            return render::error_toast(&state, StatusCode::UNPROCESSABLE_ENTITY, "Choose a fraction between 0 and 1.");
         }
     };
 
-    let result = match trading_service::market_sell(&state, u.id, &symbol, qty).await {
+    let pos = match portfolio_service::get_user_position(&state, u.id, &symbol).await {
+        Ok(p) => p,
+        Err(e) => return render::app_error_toast(&state, &e),
+    };
+
+    let held = pos.map(|p| p.qty).unwrap_or(0).max(0);
+    if held == 0 {
+        return render::error_toast(&state, StatusCode::UNPROCESSABLE_ENTITY, "You have no position to sell.");
+    }
+
+    let qty = trading_service::fraction_qty(held, fraction);
+    if qty == 0 {
+        return render::error_toast(&state, StatusCode::UNPROCESSABLE_ENTITY, "That is less than one share.");
+    }
+
+    let result = trading_service::market_sell(&state, u.id, &symbol, qty).await;
+    sell_response(&state, &headers, u.id, result).await
+}
+
+// POST /trade/:symbol/sell_all
+pub async fn post_trade_sell_all(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Symbol(symbol): Symbol,
+    user: Option<Extension<CurrentUser>>,
+) -> Response {
+    let Some(Extension(u)) = user else {
+        return unauthorized_snippet();
+    };
+
+    let pos = match portfolio_service::get_user_position(&state, u.id, &symbol).await {
+        Ok(p) => p,
+        Err(e) => return render::app_error_toast(&state, &e),
+    };
+
+    let qty = pos.map(|p| p.qty).unwrap_or(0).max(0);
+    if qty == 0 {
+        return render::error_toast(&state, StatusCode::UNPROCESSABLE_ENTITY, "You have no position to sell.");
+    }
+
+    let result = trading_service::market_sell(&state, u.id, &symbol, qty).await;
+    sell_response(&state, &headers, u.id, result).await
+}
+
+async fn sell_response(
+    state: &AppState,
+    headers: &HeaderMap,
+    user_id: ObjectId,
+    result: Result<trading_service::SellResult, AppError>,
+) -> Response {
+    let result = match result {
         Ok(r) => r,
-        Err(errs) => {
-            if let Some(v) = errs.get("qty") {
-                return (StatusCode::OK, Html(format!(r#"<div class=\"text-danger\">{}</div>"#, v))).into_response();
-            }
-            if let Some(v) = errs.get("_form") {
-                return (StatusCode::OK, Html(format!(r#"<div class=\"text-danger\">{}</div>"#, v))).into_response();
-            }
-            return (
-                StatusCode::OK,
-                Html(r#"<div class=\"text-danger\">Could not sell.</div>"#.to_string()),
-            )
-                .into_response();
+        Err(AppError::Validation(errs)) => {
+            let msg = ["qty", "price"]
+                .iter()
+                .find_map(|k| errs.get(*k))
+                .map(String::as_str)
+                .unwrap_or("Could not sell.");
+            return render::error_toast(state, StatusCode::UNPROCESSABLE_ENTITY, msg);
         }
+        Err(e) => return render::app_error_toast(state, &e),
     };
 
-    let mut headers = HeaderMap::new();
-    headers.insert(
-        "HX-Trigger",
-        hx_trigger_value(&["cashUpdated", "positionUpdated", "ordersUpdated"]),
-    );
+    let currency = user_service::get_preferences(state, user_id)
+        .await
+        .unwrap_or_default()
+        .display_currency;
 
-    (
-        StatusCode::OK,
-        headers,
-        Html(format!(
-            r#"<div class=\"text-success\">Sold {} {} @ {} (Proceeds: {}, New balance: {})</div>"#,
-            result.qty,
-            result.symbol,
-            fmt2(result.fill_price),
-            fmt2(result.proceeds),
-            fmt2(result.new_cash)
-        )),
-    )
-        .into_response()
+    let msg = format!(
+        "Sold {} {} @ {} (Proceeds: {}, Realized P/L: {}, New balance: {})",
+        result.qty,
+        result.symbol,
+        templates::format_money(result.fill_price, &currency),
+        templates::format_money(result.proceeds, &currency),
+        templates::format_money(result.realized_pnl, &currency),
+        templates::format_money(result.new_cash, &currency)
+    );
+    trade_toast(state, headers, user_id, &result.symbol, result.fill_price, &msg).await
 }
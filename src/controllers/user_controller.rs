@@ -4,15 +4,19 @@ use axum::{
     http::{HeaderMap, HeaderValue, StatusCode},
     response::{Html, IntoResponse, Response},
 };
-use regex::Regex;
+use axum_extra::extract::cookie::CookieJar;
+use mongodb::bson::oid::ObjectId;
 use serde::Deserialize;
 use serde_json::json;
 
 use crate::{
+    auth,
     AppState,
-    models::CurrentUser,
-    render,
-    services::{account_service, user_service},
+    error::AppError,
+    models::{user::normalize_and_validate_email, CurrentUser, LoginSession, Preferences},
+    render::{self, ToastKind},
+    services::{account_service, auth_service, portfolio_service, session_service, user_service},
+    templates,
 };
 
 fn is_htmx(headers: &HeaderMap) -> bool {
@@ -30,10 +34,6 @@ fn render_page(state: &AppState, tpl: &str, ctx: serde_json::Value) -> String {
         .unwrap_or_else(|e| format!("template error: {e}"))
 }
 
-fn fmt2(x: f64) -> String {
-    format!("{:.2}", x)
-}
-
 pub async fn me(user: Option<Extension<CurrentUser>>) -> impl IntoResponse {
     match user {
         Some(Extension(u)) => (StatusCode::OK, axum::Json(u)).into_response(),
@@ -41,6 +41,61 @@ pub async fn me(user: Option<Extension<CurrentUser>>) -> impl IntoResponse {
     }
 }
 
+// ---------------- Profile ----------------
+
+async fn profile_ctx(state: &AppState, u: &CurrentUser) -> Result<serde_json::Value, AppError> {
+    let positions = portfolio_service::count_positions(state, u.id).await?;
+    let orders = portfolio_service::count_orders(state, u.id).await?;
+    let summary = portfolio_service::portfolio_summary(state, u.id).await?;
+    let prefs = user_service::get_preferences(state, u.id).await.unwrap_or_default();
+
+    // users carry no created_at; the ObjectId embeds the insert time
+    let member_since = chrono::DateTime::from_timestamp_millis(u.id.timestamp().timestamp_millis())
+        .map(|d| d.format("%Y-%m-%d").to_string())
+        .unwrap_or_default();
+
+    Ok(json!({
+        "username": u.username,
+        "email": u.email,
+        "member_since": member_since,
+        "positions": positions,
+        "orders": orders,
+        "total_value": summary.total_value,
+        "display_currency": prefs.display_currency,
+    }))
+}
+
+// GET /profile
+pub async fn get_profile(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    user: Option<Extension<CurrentUser>>,
+) -> Response {
+    let (status, ctx) = match user.as_ref() {
+        Some(Extension(u)) => match profile_ctx(&state, u).await {
+            Ok(ctx) => (StatusCode::OK, ctx),
+            Err(e) => {
+                e.report();
+                (e.status(), json!({ "error": e.message() }))
+            }
+        },
+        None => (StatusCode::UNAUTHORIZED, json!({ "error": "There was an error getting user" })),
+    };
+
+    let body = render_page(&state, "partials/profile_summary", ctx);
+
+    if is_htmx(&headers) {
+        return (status, Html(body)).into_response();
+    }
+
+    let user_ref = user.as_ref().map(|Extension(u)| u);
+
+    match render::render_full(&state, "Profile", body, user_ref) {
+        Ok(page) => (status, Html(page)).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, Html(e)).into_response(),
+    }
+}
+
 // ---------------- Settings ----------------
 
 pub async fn get_settings(
@@ -53,7 +108,7 @@ pub async fn get_settings(
         Err(e) => {
             return (
                 StatusCode::INTERNAL_SERVER_ERROR,
-                Html(format!("template error: {e}")),
+                Html(format!("template error: {}", render::escape_html(&e.to_string()))),
             )
                 .into_response();
         }
@@ -146,14 +201,13 @@ pub async fn post_settings_email(
     };
 
     // validate email
-    if new_email.is_empty() {
-        errors.insert("email".into(), json!("Email is required."));
-    } else {
-        let re = Regex::new(r"^[^\s@]+@[^\s@]+\.[^\s@]+$").unwrap();
-        if !re.is_match(&new_email) {
-            errors.insert("email".into(), json!("Please enter a valid email address."));
+    let new_email = match normalize_and_validate_email(&new_email) {
+        Ok(e) => e,
+        Err(msg) => {
+            errors.insert("email".into(), json!(msg));
+            new_email
         }
-    }
+    };
 
     // must differ from current email
     if errors.is_empty() && new_email.eq_ignore_ascii_case(&u.email) {
@@ -242,6 +296,7 @@ pub async fn get_settings_password(
 pub async fn post_settings_password(
     State(state): State<AppState>,
     _headers: HeaderMap,
+    jar: CookieJar,
     user: Option<Extension<CurrentUser>>,
     Form(form): Form<ChangePasswordForm>,
 ) -> Response {
@@ -276,16 +331,29 @@ pub async fn post_settings_password(
     if errors.is_empty() && password != re_password {
         errors.insert("rePassword".into(), json!("Passwords do not match."));
     }
-    if errors.is_empty() && password.len() < 6 {
-        errors.insert(
-            "password".into(),
-            json!("Password must be at least 6 characters."),
-        );
+    if errors.is_empty()
+        && let Err(msg) = auth_service::validate_password(&state, &password)
+    {
+        errors.insert("password".into(), json!(msg));
     }
 
+    let mut jar = jar;
     if errors.is_empty() {
         match user_service::change_password(&state, u.id, &password).await {
-            Ok(()) => {}
+            Ok(token_version) => {
+                // other sessions are revoked; keep this one logged in until it would
+                // have expired anyway, so a remember-me session stays long-lived
+                let exp = jar
+                    .get(&state.settings.jwt_cookie_name)
+                    .and_then(|c| auth::decode_claims(&state, c.value()))
+                    .map(|claims| claims.exp as i64)
+                    .unwrap_or_else(|| {
+                        (state.clock.now() + chrono::Duration::days(state.settings.jwt_ttl_days)).timestamp()
+                    });
+                if let Ok(token) = auth_service::make_jwt_expiring_at(&state, &u.id, token_version, exp) {
+                    jar = jar.add(auth_service::auth_cookie_expiring_at(&state, token, exp));
+                }
+            }
             Err(errs) => {
                 for (k, v) in errs {
                     errors.insert(k, json!(v));
@@ -305,7 +373,345 @@ pub async fn post_settings_password(
         )
         .unwrap_or_else(|e| format!("template error: {e}"));
 
-    (StatusCode::OK, Html(partial)).into_response()
+    (jar, (StatusCode::OK, Html(partial))).into_response()
+}
+
+#[derive(Deserialize)]
+pub struct PreferencesForm {
+    pub default_qty: String,
+    pub display_currency: String,
+}
+
+fn preferences_partial(state: &AppState, values: serde_json::Value, errors: serde_json::Value, succ: &str) -> String {
+    render_page(
+        state,
+        "partials/preferences",
+        json!({
+            "values": values,
+            "errors": errors,
+            "succ": succ,
+            "currencies": templates::DISPLAY_CURRENCIES,
+        }),
+    )
+}
+
+// GET /settings/preferences
+pub async fn get_settings_preferences(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    user: Option<Extension<CurrentUser>>,
+) -> Response {
+    let prefs = match user.as_ref() {
+        Some(Extension(u)) => user_service::get_preferences(&state, u.id).await.unwrap_or_default(),
+        None => Preferences::default(),
+    };
+
+    let partial = preferences_partial(&state, json!(prefs), json!({}), "");
+
+    if is_htmx(&headers) {
+        return (StatusCode::OK, Html(partial)).into_response();
+    }
+
+    let shell = render_page(&state, "pages/settings", json!({}));
+
+    let autoload = r##"<div hx-get="/settings/preferences" hx-trigger="load" hx-target="#rightPane" hx-swap="innerHTML"></div>"##;
+    let body = format!("{}{}", shell, autoload);
+
+    let user_ref = user.as_ref().map(|Extension(u)| u);
+
+    match render::render_full(&state, "Settings", body, user_ref) {
+        Ok(page) => (StatusCode::OK, Html(page)).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, Html(e)).into_response(),
+    }
+}
+
+// POST /settings/preferences
+pub async fn post_settings_preferences(
+    State(state): State<AppState>,
+    user: Option<Extension<CurrentUser>>,
+    Form(form): Form<PreferencesForm>,
+) -> Response {
+    let values = json!({
+        "default_qty": form.default_qty.trim(),
+        "display_currency": form.display_currency.trim(),
+    });
+
+    let Some(Extension(u)) = user else {
+        let errors = json!({ "_form": "There was an error getting user" });
+        return (StatusCode::OK, Html(preferences_partial(&state, values, errors, ""))).into_response();
+    };
+
+    let Ok(default_qty) = form.default_qty.trim().parse::<i64>() else {
+        let errors = json!({ "default_qty": "Enter a whole number." });
+        return (StatusCode::OK, Html(preferences_partial(&state, values, errors, ""))).into_response();
+    };
+
+    let html = match user_service::update_preferences(&state, u.id, default_qty, &form.display_currency).await {
+        Ok(prefs) => preferences_partial(&state, json!(prefs), json!({}), "Your preferences were saved."),
+        Err(errs) => preferences_partial(&state, values, json!(errs), ""),
+    };
+
+    (StatusCode::OK, Html(html)).into_response()
+}
+
+// GET /settings/sessions
+pub async fn get_settings_sessions(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    user: Option<Extension<CurrentUser>>,
+) -> Response {
+    let (items, error) = match user.as_ref() {
+        Some(Extension(u)) => match session_service::list_sessions(&state, u.id).await {
+            Ok(rows) => (rows.iter().map(session_row).collect(), None),
+            Err(e) => (vec![], Some(format!("db error: {e}"))),
+        },
+        None => (vec![], Some("There was an error getting user".to_string())),
+    };
+
+    let partial = render_page(
+        &state,
+        "partials/sessions",
+        json!({ "items": items, "error": error }),
+    );
+
+    if is_htmx(&headers) {
+        return (StatusCode::OK, Html(partial)).into_response();
+    }
+
+    let shell = render_page(&state, "pages/settings", json!({}));
+
+    let autoload = r##"<div hx-get="/settings/sessions" hx-trigger="load" hx-target="#rightPane" hx-swap="innerHTML"></div>"##;
+    let body = format!("{}{}", shell, autoload);
+
+    let user_ref = user.as_ref().map(|Extension(u)| u);
+
+    match render::render_full(&state, "Settings", body, user_ref) {
+        Ok(page) => (StatusCode::OK, Html(page)).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, Html(e)).into_response(),
+    }
+}
+
+fn session_row(s: &LoginSession) -> serde_json::Value {
+    let at = |ts: i64| {
+        chrono::DateTime::from_timestamp(ts, 0)
+            .map(|d| d.format("%Y-%m-%d %H:%M UTC").to_string())
+            .unwrap_or_default()
+    };
+
+    json!({
+        "created_at": at(s.created_at),
+        "ip": s.ip,
+        "user_agent": s.user_agent,
+        "revoked": s.revoked_at.is_some(),
+    })
+}
+
+// POST /settings/logout-all
+pub async fn post_settings_logout_all(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    jar: CookieJar,
+    user: Option<Extension<CurrentUser>>,
+) -> Response {
+    let Some(Extension(u)) = user else {
+        return (StatusCode::UNAUTHORIZED, Html("not logged in".to_string())).into_response();
+    };
+
+    if let Err(e) = user_service::bump_token_version(&state, u.id).await {
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Html(format!("db error: {}", render::escape_html(&e.to_string()))),
+        )
+            .into_response();
+    }
+
+    // the tokens are already dead; this only updates what the sessions page shows
+    if let Err(e) = session_service::revoke_all(&state, u.id).await {
+        tracing::warn!("failed to mark sessions signed out for {}: {}", u.id, e);
+    }
+
+    let jar = jar.add(auth_service::clear_auth_cookie(&state));
+
+    if is_htmx(&headers) {
+        let mut out = HeaderMap::new();
+        out.insert("HX-Redirect", HeaderValue::from_static("/login"));
+        return (jar, (StatusCode::OK, out, Html("".to_string()))).into_response();
+    }
+
+    (jar, (StatusCode::SEE_OTHER, [("Location", "/login")])).into_response()
+}
+
+#[derive(Deserialize)]
+pub struct ResetAccountForm {
+    #[serde(default)]
+    pub password: String,
+}
+
+pub async fn get_settings_reset_account(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    user: Option<Extension<CurrentUser>>,
+) -> Response {
+    let partial = render_page(
+        &state,
+        "partials/reset_account",
+        json!({ "starting_balance": format!("{:.2}", state.settings.starting_balance) }),
+    );
+
+    if is_htmx(&headers) {
+        return (StatusCode::OK, Html(partial)).into_response();
+    }
+
+    let shell = render_page(&state, "pages/settings", json!({}));
+
+    let autoload = r##"<div hx-get="/settings/reset-account" hx-trigger="load" hx-target="#rightPane" hx-swap="innerHTML"></div>"##;
+    let body = format!("{}{}", shell, autoload);
+
+    let user_ref = user.as_ref().map(|Extension(u)| u);
+
+    match render::render_full(&state, "Settings", body, user_ref) {
+        Ok(page) => (StatusCode::OK, Html(page)).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, Html(e)).into_response(),
+    }
+}
+
+// POST /settings/reset-account
+pub async fn post_settings_reset_account(
+    State(state): State<AppState>,
+    user: Option<Extension<CurrentUser>>,
+    Form(form): Form<ResetAccountForm>,
+) -> Response {
+    let Some(Extension(u)) = user else {
+        return render::error_toast(&state, StatusCode::UNAUTHORIZED, "There was an error getting user");
+    };
+
+    let password = form.password.trim();
+    if password.is_empty() {
+        return render::error_toast(&state, StatusCode::UNPROCESSABLE_ENTITY, "Password is required.");
+    }
+
+    match user_service::check_password(&state, u.id, password).await {
+        Ok(()) => {}
+        Err(errs) if errs.contains_key("password") => {
+            return render::error_toast(&state, StatusCode::UNPROCESSABLE_ENTITY, &errs["password"]);
+        }
+        Err(errs) => {
+            let msg = errs
+                .get("_form")
+                .cloned()
+                .unwrap_or_else(|| "Reset failed.".to_string());
+            return render::error_toast(&state, StatusCode::INTERNAL_SERVER_ERROR, &msg);
+        }
+    }
+
+    if let Err(e) = account_service::reset_account(&state, u.id).await {
+        tracing::error!("account reset failed for {}: {}", u.id, e);
+        return render::error_toast(&state, StatusCode::INTERNAL_SERVER_ERROR, "Reset failed, try again.");
+    }
+
+    render::toast(
+        &state,
+        ToastKind::Success,
+        "Your account was reset to the starting balance.",
+        &account_service::RESET_EVENTS,
+    )
+}
+
+#[derive(Deserialize)]
+pub struct DeleteAccountForm {
+    #[serde(default, rename = "confirmEmail")]
+    pub confirm_email: String,
+    #[serde(default)]
+    pub password: String,
+}
+
+pub async fn get_settings_delete_account(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    user: Option<Extension<CurrentUser>>,
+) -> Response {
+    let partial = render_page(
+        &state,
+        "partials/delete_account",
+        json!({ "values": {}, "errors": {} }),
+    );
+
+    if is_htmx(&headers) {
+        return (StatusCode::OK, Html(partial)).into_response();
+    }
+
+    let shell = render_page(&state, "pages/settings", json!({}));
+
+    let autoload = r##"<div hx-get="/settings/delete-account" hx-trigger="load" hx-target="#rightPane" hx-swap="innerHTML"></div>"##;
+    let body = format!("{}{}", shell, autoload);
+
+    let user_ref = user.as_ref().map(|Extension(u)| u);
+
+    match render::render_full(&state, "Settings", body, user_ref) {
+        Ok(page) => (StatusCode::OK, Html(page)).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, Html(e)).into_response(),
+    }
+}
+
+// POST /settings/delete-account
+pub async fn post_settings_delete_account(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    jar: CookieJar,
+    user: Option<Extension<CurrentUser>>,
+    Form(form): Form<DeleteAccountForm>,
+) -> Response {
+    let confirm_email = form.confirm_email.trim().to_string();
+    let password = form.password.trim().to_string();
+    let mut errors = serde_json::Map::new();
+
+    let Some(Extension(u)) = user else {
+        errors.insert("_form".into(), json!("There was an error getting user"));
+        let partial = render_page(
+            &state,
+            "partials/delete_account",
+            json!({ "values": { "confirmEmail": confirm_email }, "errors": errors }),
+        );
+        return (StatusCode::OK, Html(partial)).into_response();
+    };
+
+    // typed email acts as the confirmation guard
+    if !confirm_email.eq_ignore_ascii_case(&u.email) {
+        errors.insert(
+            "confirmEmail".into(),
+            json!("Type your current email to confirm."),
+        );
+    }
+    if password.is_empty() {
+        errors.insert("password".into(), json!("Password is required."));
+    }
+
+    if errors.is_empty()
+        && let Err(errs) = user_service::delete_account(&state, u.id, &password).await
+    {
+        for (k, v) in errs {
+            errors.insert(k, json!(v));
+        }
+    }
+
+    if !errors.is_empty() {
+        let partial = render_page(
+            &state,
+            "partials/delete_account",
+            json!({ "values": { "confirmEmail": confirm_email }, "errors": errors }),
+        );
+        return (StatusCode::OK, Html(partial)).into_response();
+    }
+
+    let jar = jar.add(auth_service::clear_auth_cookie(&state));
+
+    if is_htmx(&headers) {
+        let mut out = HeaderMap::new();
+        out.insert("HX-Redirect", HeaderValue::from_static("/"));
+        return (jar, (StatusCode::OK, out, Html("".to_string()))).into_response();
+    }
+
+    (jar, (StatusCode::SEE_OTHER, [("Location", "/")])).into_response()
 }
 
 // ---------------- Funds ----------------
@@ -320,7 +726,11 @@ pub async fn get_funds_page(
     }
 
     if is_htmx(&headers) {
-        let html = render_page(&state, "pages/funds", json!({}));
+        let html = render_page(
+            &state,
+            "pages/funds",
+            json!({ "idempotency_key": ObjectId::new().to_hex() }),
+        );
         return (StatusCode::OK, Html(html)).into_response();
     }
 
@@ -340,7 +750,11 @@ pub async fn get_funds_modal(
         let _ = account_service::get_or_create_account(&state, u.id).await;
     }
 
-    let html = render_page(&state, "partials/funds_modal", json!({}));
+    let html = render_page(
+        &state,
+        "partials/funds_modal",
+        json!({ "idempotency_key": ObjectId::new().to_hex() }),
+    );
     (StatusCode::OK, Html(html))
 }
 
@@ -353,28 +767,53 @@ pub async fn get_cash_badge(
         return (StatusCode::OK, Html("".to_string()));
     };
 
-    let acc = match account_service::get_or_create_account(&state, u.id).await {
-        Ok(a) => a,
+    // just looking at the badge doesn't open an account (and grant the starting cash)
+    let cash = match account_service::find_account(&state, u.id).await {
+        Ok(acc) => acc.map_or(0.0, |a| a.cash),
         Err(e) => {
             return (
                 StatusCode::INTERNAL_SERVER_ERROR,
-                Html(format!("db error: {e}")),
+                Html(format!("db error: {}", render::escape_html(&e.to_string()))),
             );
         }
     };
 
+    let prefs = user_service::get_preferences(&state, u.id).await.unwrap_or_default();
+
     let html = render_page(
         &state,
         "partials/cash_badge",
-        json!({ "cash": fmt2(acc.cash) }),
+        json!({ "cash": cash, "display_currency": prefs.display_currency }),
     );
     (StatusCode::OK, Html(html))
 }
 
+// Same rules for deposits and withdrawals.
+pub(crate) fn parse_amount(raw: &str) -> Result<f64, &'static str> {
+    let amount: f64 = raw
+        .trim()
+        .parse()
+        .map_err(|_| "There was an error with the amount!")?;
+
+    check_amount(amount)
+}
+
+// The part of parse_amount that also applies to amounts that arrive as JSON numbers.
+pub(crate) fn check_amount(amount: f64) -> Result<f64, &'static str> {
+    if !amount.is_finite() || amount <= 0.0 {
+        return Err("Amount must be bigger than zero!");
+    }
+
+    Ok(amount)
+}
+
 // POST /funds
 #[derive(Deserialize)]
 pub struct DepositForm {
     pub amount: String,
+    // generated per rendered form so a double submit deposits once
+    #[serde(default)]
+    pub idempotency_key: String,
 }
 
 pub async fn post_funds(
@@ -383,68 +822,74 @@ pub async fn post_funds(
     Form(form): Form<DepositForm>,
 ) -> Response {
     let Some(Extension(u)) = user else {
-        return (
-            StatusCode::OK,
-            Html(
-                r#"<div class=\"alert alert-danger mb-0\">There was an error getting user</div>"#
-                    .to_string(),
-            ),
-        )
-            .into_response();
+        return render::error_toast(&state, StatusCode::UNAUTHORIZED, "There was an error getting user");
     };
 
-    let amount_str = form.amount.trim();
-    let amount: f64 = match amount_str.parse() {
+    let amount = match parse_amount(&form.amount) {
         Ok(v) => v,
-        Err(_) => {
-            return (
-                StatusCode::OK,
-                Html(r#"<div class=\"alert alert-danger mb-0\">There was an error with the amount!</div>"#.to_string()),
-            )
-                .into_response();
-        }
+        Err(msg) => return render::error_toast(&state, StatusCode::UNPROCESSABLE_ENTITY, msg),
     };
 
-    if !amount.is_finite() || amount <= 0.0 {
-        return (
-            StatusCode::OK,
-            Html(
-                r#"<div class=\"alert alert-danger mb-0\">Amount must be bigger than zero!</div>"#
-                    .to_string(),
-            ),
-        )
-            .into_response();
-    }
-
-    match user_service::deposit_funds(&state, u.id, amount).await {
+    match user_service::deposit_funds(&state, u.id, amount, Some(&form.idempotency_key)).await {
         Ok(_acc) => {}
+        Err(errs) if errs.contains_key("amount") => {
+            return render::error_toast(&state, StatusCode::UNPROCESSABLE_ENTITY, &errs["amount"]);
+        }
         Err(errs) => {
             let msg = errs
                 .get("_form")
                 .cloned()
                 .unwrap_or_else(|| "Deposit failed.".to_string());
-            return (
-                StatusCode::OK,
-                Html(format!(
-                    r#"<div class=\"alert alert-danger mb-0\">{}</div>"#,
-                    msg
-                )),
-            )
-                .into_response();
+            return render::error_toast(&state, StatusCode::INTERNAL_SERVER_ERROR, &msg);
         }
     }
 
-    let msg =
-        r#"<div class=\"alert alert-success mb-0\">The deposit was successful!</div>"#.to_string();
-
-    let mut headers = HeaderMap::new();
-    headers.insert(
-        "HX-Trigger",
-        HeaderValue::from_static(r#"{\"cashUpdated\":true}"#),
-    );
-
     // also broadcast to other tabs
     let _ = state.events_tx.send("cashUpdated".to_string());
 
-    (StatusCode::OK, headers, Html(msg)).into_response()
+    render::toast(
+        &state,
+        ToastKind::Success,
+        "The deposit was successful!",
+        &["cashUpdated"],
+    )
+}
+
+// POST /funds/withdraw
+#[derive(Deserialize)]
+pub struct WithdrawForm {
+    pub amount: String,
+}
+
+pub async fn post_withdraw(
+    State(state): State<AppState>,
+    user: Option<Extension<CurrentUser>>,
+    Form(form): Form<WithdrawForm>,
+) -> Response {
+    let Some(Extension(u)) = user else {
+        return render::error_toast(&state, StatusCode::UNAUTHORIZED, "There was an error getting user");
+    };
+
+    let amount = match parse_amount(&form.amount) {
+        Ok(v) => v,
+        Err(msg) => return render::error_toast(&state, StatusCode::UNPROCESSABLE_ENTITY, msg),
+    };
+
+    if let Err(errs) = user_service::withdraw_funds(&state, u.id, amount).await {
+        if let Some(msg) = errs.get("amount") {
+            return render::error_toast(&state, StatusCode::UNPROCESSABLE_ENTITY, msg);
+        }
+        let msg = errs
+            .get("_form")
+            .cloned()
+            .unwrap_or_else(|| "Withdrawal failed.".to_string());
+        return render::error_toast(&state, StatusCode::INTERNAL_SERVER_ERROR, &msg);
+    }
+
+    render::toast(
+        &state,
+        ToastKind::Success,
+        "The withdrawal was successful!",
+        &["cashUpdated"],
+    )
 }
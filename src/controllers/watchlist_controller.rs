@@ -0,0 +1,161 @@
+use axum::{
+    extract::{Extension, State},
+    http::{HeaderMap, HeaderValue, StatusCode},
+    response::{Html, IntoResponse, Response},
+};
+use serde_json::json;
+
+use crate::{
+    controllers::symbol::Symbol,
+    models::CurrentUser,
+    render,
+    services::{user_service, watchlist_service},
+    AppState,
+};
+
+fn unauthorized_snippet() -> Response {
+    (
+        StatusCode::UNAUTHORIZED,
+        Html(r#"<div class="text-danger">Unauthorized</div>"#.to_string()),
+    )
+        .into_response()
+}
+
+fn change_class(d: f64) -> &'static str {
+    if d > 0.0 {
+        "text-success"
+    } else if d < 0.0 {
+        "text-danger"
+    } else {
+        "text-muted"
+    }
+}
+
+fn render_star(state: &AppState, symbol: &str, watched: bool) -> String {
+    state
+        .hbs
+        .render(
+            "partials/watch_star",
+            &json!({ "symbol": symbol, "watched": watched }),
+        )
+        .unwrap_or_else(|e| format!("template error: {e}"))
+}
+
+// GET /watchlist (HTMX partial)
+pub async fn get_watchlist(
+    State(state): State<AppState>,
+    user: Option<Extension<CurrentUser>>,
+) -> Response {
+    let Some(Extension(u)) = user else {
+        return unauthorized_snippet();
+    };
+
+    let items = match watchlist_service::list(&state, u.id).await {
+        Ok(v) => v,
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Html(format!("db error: {}", render::escape_html(&e.to_string()))),
+            )
+                .into_response();
+        }
+    };
+
+    let symbols: Vec<String> = items.iter().map(|w| w.symbol.clone()).collect();
+    let quotes = state.finnhub.quotes(&symbols).await;
+    let prefs = user_service::get_preferences(&state, u.id).await.unwrap_or_default();
+
+    let rows: Vec<serde_json::Value> = symbols
+        .iter()
+        .map(|sym| match quotes.get(sym) {
+            Some(q) => json!({
+                "symbol": sym,
+                "has_quote": true,
+                "price": q.c,
+                "change": q.d,
+                "change_pct": q.dp,
+                "change_class": change_class(q.d),
+            }),
+            None => json!({ "symbol": sym, "has_quote": false }),
+        })
+        .collect();
+
+    let html = state
+        .hbs
+        .render(
+            "partials/watchlist",
+            &json!({
+                "items": rows,
+                "has_items": !rows.is_empty(),
+                "display_currency": prefs.display_currency,
+            }),
+        )
+        .unwrap_or_else(|e| format!("template error: {e}"));
+
+    (StatusCode::OK, Html(html)).into_response()
+}
+
+// GET /watchlist/:symbol/star (HTMX partial)
+pub async fn get_watch_star(
+    State(state): State<AppState>,
+    Symbol(sym): Symbol,
+    user: Option<Extension<CurrentUser>>,
+) -> Response {
+    let Some(Extension(u)) = user else {
+        return unauthorized_snippet();
+    };
+
+    let watched = watchlist_service::is_watched(&state, u.id, &sym)
+        .await
+        .unwrap_or(false);
+
+    (StatusCode::OK, Html(render_star(&state, &sym, watched))).into_response()
+}
+
+// POST /watchlist/:symbol
+pub async fn post_watch(
+    State(state): State<AppState>,
+    Symbol(sym): Symbol,
+    user: Option<Extension<CurrentUser>>,
+) -> Response {
+    let Some(Extension(u)) = user else {
+        return unauthorized_snippet();
+    };
+
+    if let Err(e) = watchlist_service::add(&state, u.id, &sym).await {
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Html(format!("db error: {}", render::escape_html(&e.to_string()))),
+        )
+            .into_response();
+    }
+
+    let mut headers = HeaderMap::new();
+    headers.insert("HX-Trigger", HeaderValue::from_static("watchlistUpdated"));
+
+    (StatusCode::OK, headers, Html(render_star(&state, &sym, true))).into_response()
+}
+
+// POST /watchlist/:symbol/remove
+pub async fn post_unwatch(
+    State(state): State<AppState>,
+    Symbol(sym): Symbol,
+    user: Option<Extension<CurrentUser>>,
+) -> Response {
+    let Some(Extension(u)) = user else {
+        return unauthorized_snippet();
+    };
+
+    if let Err(e) = watchlist_service::remove(&state, u.id, &sym).await {
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Html(format!("db error: {}", render::escape_html(&e.to_string()))),
+        )
+            .into_response();
+    }
+
+    let mut headers = HeaderMap::new();
+    headers.insert("HX-Trigger", HeaderValue::from_static("watchlistUpdated"));
+
+    (StatusCode::OK, headers, Html(render_star(&state, &sym, false))).into_response()
+}
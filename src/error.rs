@@ -0,0 +1,118 @@
+//! Typed service errors.
+//!
+//! Services return `AppError` instead of pre-formatted strings; the real cause
+//! (Mongo/Finnhub message) is logged by `report` and only a safe message reaches
+//! the page. `IntoResponse` renders a plain snippet; HTMX handlers that want a
+//! toast go through `render::app_error_toast`, the JSON API maps it itself.
+
+use std::fmt;
+
+use axum::{
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+};
+
+use crate::{
+    render,
+    services::{auth_service::FieldErrors, finnhub::FinnhubError},
+};
+
+#[derive(Debug)]
+pub enum AppError {
+    // Mongo failure; the text is for logs only
+    Db(String),
+    NotFound,
+    Unauthorized,
+    // bad input, keyed by form field like the auth/user services
+    Validation(FieldErrors),
+    Finnhub(FinnhubError),
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::Db(e) => write!(f, "db error: {e}"),
+            AppError::NotFound => write!(f, "not found"),
+            AppError::Unauthorized => write!(f, "unauthorized"),
+            AppError::Validation(errs) => write!(f, "validation failed: {errs:?}"),
+            AppError::Finnhub(e) => write!(f, "finnhub error: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl From<mongodb::error::Error> for AppError {
+    fn from(e: mongodb::error::Error) -> Self {
+        AppError::Db(e.to_string())
+    }
+}
+
+impl From<FinnhubError> for AppError {
+    fn from(e: FinnhubError) -> Self {
+        AppError::Finnhub(e)
+    }
+}
+
+impl AppError {
+    /// Single-field validation error.
+    pub fn invalid(field: &str, message: &str) -> Self {
+        AppError::Validation(FieldErrors::from([(field.to_string(), message.to_string())]))
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            AppError::Db(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            AppError::NotFound => StatusCode::NOT_FOUND,
+            AppError::Unauthorized => StatusCode::UNAUTHORIZED,
+            AppError::Validation(_) | AppError::Finnhub(FinnhubError::NotFound) => {
+                StatusCode::UNPROCESSABLE_ENTITY
+            }
+            AppError::Finnhub(e) if e.is_transient() => StatusCode::SERVICE_UNAVAILABLE,
+            AppError::Finnhub(_) => StatusCode::BAD_GATEWAY,
+        }
+    }
+
+    /// What the user gets to see; never includes the underlying cause.
+    pub fn message(&self) -> String {
+        match self {
+            AppError::Db(_) => "Something went wrong. Try again.".to_string(),
+            AppError::NotFound => "Not found.".to_string(),
+            AppError::Unauthorized => "Unauthorized".to_string(),
+            AppError::Validation(errs) => {
+                // a stable pick when several fields failed
+                let mut keys: Vec<&String> = errs.keys().collect();
+                keys.sort();
+                keys.first()
+                    .and_then(|k| errs.get(*k))
+                    .cloned()
+                    .unwrap_or_else(|| "Invalid input.".to_string())
+            }
+            AppError::Finnhub(FinnhubError::NotFound) => "Unknown symbol.".to_string(),
+            AppError::Finnhub(e) if e.is_transient() => {
+                "Quote service is unavailable. Try again.".to_string()
+            }
+            AppError::Finnhub(_) => "Quote service error.".to_string(),
+        }
+    }
+
+    /// Logs server-side failures with their real cause; input errors are not logged.
+    pub fn report(&self) {
+        match self {
+            AppError::Db(_) => tracing::error!("{self}"),
+            AppError::Finnhub(e) if *e != FinnhubError::NotFound => tracing::warn!("{self}"),
+            _ => {}
+        }
+    }
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        self.report();
+        let html = format!(
+            r#"<div class="text-danger">{}</div>"#,
+            render::escape_html(&self.message())
+        );
+        (self.status(), Html(html)).into_response()
+    }
+}
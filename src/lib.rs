@@ -1,7 +1,15 @@
 pub mod config;
+pub mod error;
+pub mod logging;
 pub mod models;
 #[path = "middleware/auth.rs"]
 pub mod auth;
+#[path = "middleware/csrf.rs"]
+pub mod csrf;
+#[path = "middleware/access_log.rs"]
+pub mod access_log;
+#[path = "middleware/static_cache.rs"]
+pub mod static_cache;
 
 pub mod services;
 
@@ -20,4 +28,10 @@ pub struct AppState {
     pub settings: config::Settings,
     pub finnhub: services::finnhub::FinnhubClient,
     pub events_tx: tokio::sync::broadcast::Sender<String>,
+    pub trades: services::trade_relay::TradeRelay,
+    pub ws_limiter: services::ws_limiter::WsLimiter,
+    // "now" for services; tests swap in a FixedClock
+    pub clock: services::clock::SharedClock,
+    // when this process started serving, for /health/version's uptime
+    pub started_at: std::time::Instant,
 }
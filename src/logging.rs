@@ -0,0 +1,134 @@
+//! Log output setup. `LOG_FORMAT=json` switches to one JSON object per line for
+//! log aggregators; anything else keeps the human-readable format for local dev.
+//!
+//! JSON lines carry `timestamp`, `level`, `target`, the event fields and the
+//! fields of every enclosing span; the access log's `request` span contributes
+//! `request_id`, so handler logs can be joined with their access line.
+
+use std::fmt;
+
+use serde_json::{Map, Value};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::{
+    field::RecordFields,
+    fmt::{format::Writer, FmtContext, FormatEvent, FormatFields, FormattedFields},
+    registry::LookupSpan,
+    EnvFilter,
+};
+
+pub fn json_enabled() -> bool {
+    std::env::var("LOG_FORMAT")
+        .map(|v| v.trim().eq_ignore_ascii_case("json"))
+        .unwrap_or(false)
+}
+
+/// Installs the global subscriber; RUST_LOG controls verbosity, e.g. "info,access::static=off".
+pub fn init() {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+
+    if json_enabled() {
+        tracing_subscriber::fmt()
+            .with_env_filter(filter)
+            .fmt_fields(JsonFields)
+            .event_format(JsonFormat)
+            .init();
+    } else {
+        tracing_subscriber::fmt().with_env_filter(filter).init();
+    }
+}
+
+struct JsonVisitor<'a>(&'a mut Map<String, Value>);
+
+impl Visit for JsonVisitor<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), Value::from(value));
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().to_string(), Value::from(value));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().to_string(), Value::from(value));
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.0.insert(field.name().to_string(), Value::from(value));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().to_string(), Value::from(value));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0.insert(field.name().to_string(), Value::from(format!("{value:?}")));
+    }
+}
+
+fn record_into(map: &mut Map<String, Value>, fields: impl RecordFields) {
+    fields.record(&mut JsonVisitor(map));
+}
+
+/// Stores span fields as a JSON object so `JsonFormat` can merge them back in.
+pub struct JsonFields;
+
+impl<'writer> FormatFields<'writer> for JsonFields {
+    fn format_fields<R: RecordFields>(&self, mut writer: Writer<'writer>, fields: R) -> fmt::Result {
+        let mut map = Map::new();
+        record_into(&mut map, fields);
+        write!(writer, "{}", Value::Object(map))
+    }
+
+    fn add_fields(
+        &self,
+        current: &'writer mut FormattedFields<Self>,
+        fields: &tracing::span::Record<'_>,
+    ) -> fmt::Result {
+        let mut map = match serde_json::from_str::<Value>(&current.fields) {
+            Ok(Value::Object(map)) => map,
+            _ => Map::new(),
+        };
+        record_into(&mut map, fields);
+        current.fields = Value::Object(map).to_string();
+        Ok(())
+    }
+}
+
+pub struct JsonFormat;
+
+impl<S, N> FormatEvent<S, N> for JsonFormat
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(&self, ctx: &FmtContext<'_, S, N>, mut writer: Writer<'_>, event: &Event<'_>) -> fmt::Result {
+        let meta = event.metadata();
+
+        let mut line = Map::new();
+        line.insert("timestamp".into(), Value::from(chrono::Utc::now().to_rfc3339()));
+        line.insert("level".into(), Value::from(meta.level().as_str()));
+        line.insert("target".into(), Value::from(meta.target()));
+
+        if let Some(scope) = ctx.event_scope() {
+            for span in scope.from_root() {
+                let ext = span.extensions();
+                let Some(fields) = ext.get::<FormattedFields<N>>() else {
+                    continue;
+                };
+                let Ok(Value::Object(map)) = serde_json::from_str::<Value>(&fields.fields) else {
+                    continue;
+                };
+                for (k, v) in map {
+                    // access_log's span is `request{id=..}`
+                    let key = if span.name() == "request" && k == "id" { "request_id".to_string() } else { k };
+                    line.insert(key, v);
+                }
+            }
+        }
+
+        record_into(&mut line, event);
+
+        writeln!(writer, "{}", Value::Object(line))
+    }
+}
@@ -1,39 +1,76 @@
 use mongodb::Client;
 use std::net::SocketAddr;
-use tracing_subscriber;
 
-use rustmarket::{config, routes, services, templates, AppState};
+use rustmarket::{config, logging, routes, services, templates, AppState};
 
 #[tokio::main]
 async fn main() {
-    tracing_subscriber::fmt::init();
     dotenvy::dotenv().ok();
 
+    // LOG_FORMAT=json for log aggregators; RUST_LOG controls verbosity
+    logging::init();
+
     let settings = config::load();
 
+    match settings.dev_secret_risk() {
+        Some(config::DevSecretRisk::Fatal) => {
+            tracing::error!("APP_ENV=production but JWT_SECRET is the built-in dev secret; set JWT_SECRET or JWT_SECRETS");
+            std::process::exit(1);
+        }
+        Some(config::DevSecretRisk::Warn) => {
+            tokio::spawn(async {
+                let mut tick = tokio::time::interval(std::time::Duration::from_secs(300));
+                loop {
+                    tick.tick().await;
+                    tracing::warn!("JWT_SECRET is the built-in dev secret; anyone can forge logins until it is set");
+                }
+            });
+        }
+        None => {}
+    }
+
+    services::metrics::install();
+
     let client = Client::with_uri_str(&settings.mongodb_uri)
         .await
         .expect("Failed to connect to MongoDB");
     let db = client.database(&settings.mongodb_db);
 
-    services::db_init::ensure_indexes(&db)
+    services::db_init::ensure_indexes(&db, &settings)
         .await
         .expect("Failed to ensure MongoDB indexes");
 
+    services::migrations::run(&db)
+        .await
+        .expect("Failed to run MongoDB migrations");
+
     let finnhub = services::finnhub::FinnhubClient::new(settings.finnhub_api_key.clone());
-    let (events_tx, _events_rx) = tokio::sync::broadcast::channel::<String>(256);
+    let (events_tx, _events_rx) = tokio::sync::broadcast::channel::<String>(settings.events_channel_capacity);
+    // One upstream Finnhub socket shared by every trades WebSocket client
+    let trades = services::trade_relay::TradeRelay::spawn(settings.finnhub_api_key.clone());
+    let ws_limiter = services::ws_limiter::WsLimiter::new(settings.ws_max_per_user);
 
     let state = AppState {
-        hbs: templates::build_handlebars(),
+        hbs: templates::build_handlebars_with_reload(settings.template_hot_reload),
         db,
         settings: settings.clone(),
         finnhub,
         events_tx,
+        trades,
+        ws_limiter,
+        clock: services::clock::real(),
+        started_at: std::time::Instant::now(),
     };
 
     // Background alert monitoring
     services::alert_monitor::spawn_price_alert_monitor(state.clone());
 
+    // Daily portfolio value snapshots
+    services::snapshot_monitor::spawn_portfolio_snapshot_task(state.clone());
+
+    // Simulated dividends; off unless DIVIDEND_YIELD_PCT is set
+    services::dividend_monitor::spawn_dividend_task(state.clone());
+
     // Build router from feature routers
     let app = routes::app(state);
 
@@ -44,5 +81,8 @@ async fn main() {
     tracing::info!("listening on http://{}", addr);
 
     let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
-    axum::serve(listener, app).await.unwrap();
+    // ConnectInfo gives the login history a peer address when no proxy sets X-Forwarded-For
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+        .await
+        .unwrap();
 }
@@ -0,0 +1,58 @@
+//! Access log: one line per request with method, path, status, latency and a
+//! request id (taken from `x-request-id` when the proxy sets one).
+//!
+//! Lines are emitted under the `access` target, static assets under
+//! `access::static`, so e.g. `RUST_LOG=info,access::static=off` hides them.
+
+use std::time::Instant;
+
+use axum::{
+    body::Body,
+    http::{HeaderValue, Request},
+    middleware::Next,
+    response::Response,
+};
+use rand::RngCore;
+use tracing::Instrument;
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+fn new_request_id() -> String {
+    let mut bytes = [0u8; 8];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub async fn log_requests(req: Request<Body>, next: Next) -> Response {
+    let request_id = req
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .filter(|v| !v.is_empty() && v.len() <= 64)
+        .map(|v| v.to_string())
+        .unwrap_or_else(new_request_id);
+
+    let method = req.method().clone();
+    let path = req.uri().path().to_string();
+    let is_htmx = req.headers().contains_key("HX-Request");
+    let started = Instant::now();
+
+    // handler logs inherit the request id through this span
+    let span = tracing::info_span!("request", id = %request_id);
+    let mut res = next.run(req).instrument(span).await;
+
+    let status = res.status().as_u16();
+    let latency_ms = started.elapsed().as_secs_f64() * 1000.0;
+
+    if path.starts_with("/static/") {
+        tracing::info!(target: "access::static", request_id = %request_id, %method, %path, status, latency_ms);
+    } else {
+        tracing::info!(target: "access", request_id = %request_id, %method, %path, status, latency_ms, htmx = is_htmx);
+    }
+
+    if let Ok(v) = HeaderValue::from_str(&request_id) {
+        res.headers_mut().insert(REQUEST_ID_HEADER, v);
+    }
+
+    res
+}
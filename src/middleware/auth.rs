@@ -1,11 +1,11 @@
 use axum::{
     extract::State,
-    http::{header, HeaderMap, HeaderValue, Request, StatusCode},
+    http::{header, HeaderMap, HeaderValue, Method, Request, StatusCode},
     middleware::Next,
     response::{Html, IntoResponse, Redirect, Response},
 };
 
-use jsonwebtoken::{decode, Algorithm, DecodingKey, Validation};
+use jsonwebtoken::{decode, DecodingKey, Validation};
 use mongodb::bson::{doc, oid::ObjectId};
 use serde::{Deserialize, Serialize};
 
@@ -17,9 +17,29 @@ pub struct Claims {
     pub sub: String,
     // expiry (unix timestamp seconds)
     pub exp: usize,
+    // must match users.token_version, otherwise the token was revoked
+    #[serde(default)]
+    pub ver: i32,
 }
 
-fn get_cookie(headers: &HeaderMap, name: &str) -> Option<String> {
+/// Tries the current secret first, then each previous one still in rotation.
+pub fn decode_claims(state: &AppState, token: &str) -> Option<Claims> {
+    let mut validation = Validation::new(state.settings.jwt_algorithm);
+    validation.validate_exp = true;
+
+    std::iter::once(&state.settings.jwt_secret)
+        .chain(&state.settings.jwt_previous_secrets)
+        .find_map(|secret| {
+            decode::<Claims>(token, &DecodingKey::from_secret(secret.as_bytes()), &validation).ok()
+        })
+        .map(|data| data.claims)
+}
+
+pub fn token_is_current(claims: &Claims, user: &User) -> bool {
+    claims.ver == user.token_version
+}
+
+pub(crate) fn get_cookie(headers: &HeaderMap, name: &str) -> Option<String> {
     let raw = headers.get(header::COOKIE)?.to_str().ok()?;
 
     for part in raw.split(';') {
@@ -34,6 +54,30 @@ fn get_cookie(headers: &HeaderMap, name: &str) -> Option<String> {
     None
 }
 
+/// Token from an `Authorization: Bearer <jwt>` header, used by API clients without cookies.
+pub(crate) fn bearer_token(headers: &HeaderMap) -> Option<String> {
+    let raw = headers.get(header::AUTHORIZATION)?.to_str().ok()?;
+    let (scheme, token) = raw.trim().split_once(' ')?;
+    let token = token.trim();
+    (scheme.eq_ignore_ascii_case("bearer") && !token.is_empty()).then(|| token.to_string())
+}
+
+/// The JWT a request presents: the Bearer header wins over the auth cookie when both are sent.
+pub(crate) fn request_token(headers: &HeaderMap, cookie_name: &str) -> Option<String> {
+    bearer_token(headers).or_else(|| get_cookie(headers, cookie_name))
+}
+
+/// Validates a JWT (signature, expiry, token_version) and loads its user.
+pub async fn authenticate(state: &AppState, token: &str) -> Option<CurrentUser> {
+    let claims = decode_claims(state, token)?;
+    let user_id = ObjectId::parse_str(&claims.sub).ok()?;
+
+    let users = state.db.collection::<User>("users");
+    let user = users.find_one(doc! { "_id": user_id }, None).await.ok()??;
+
+    token_is_current(&claims, &user).then(|| CurrentUser::from(user))
+}
+
 pub async fn inject_current_user(
     State(state): State<AppState>,
     mut req: Request<axum::body::Body>,
@@ -41,30 +85,16 @@ pub async fn inject_current_user(
 ) -> Response {
     let cookie_name = state.settings.jwt_cookie_name.as_str();
 
-    if let Some(token) = get_cookie(req.headers(), cookie_name) {
-        let mut validation = Validation::new(Algorithm::HS256);
-        validation.validate_exp = true;
-
-        let decoded = decode::<Claims>(
-            &token,
-            &DecodingKey::from_secret(state.settings.jwt_secret.as_bytes()),
-            &validation,
-        );
-
-        if let Ok(data) = decoded {
-            if let Ok(user_id) = ObjectId::parse_str(&data.claims.sub) {
-                let users = state.db.collection::<User>("users");
-
-                if let Ok(Some(user)) = users.find_one(doc! { "_id": user_id }, None).await {
-                    // Store user in request extensions so handlers can access it
-                    req.extensions_mut().insert(CurrentUser::from(user));
-                }
-            }
-        }
+    if let Some(token) = request_token(req.headers(), cookie_name)
+        && let Some(user) = authenticate(&state, &token).await
+    {
+        // Store user in request extensions so handlers can access it
+        req.extensions_mut().insert(user);
     }
 
     next.run(req).await
 }
+
 fn is_htmx(headers: &HeaderMap) -> bool {
     headers
         .get("HX-Request")
@@ -87,6 +117,10 @@ fn is_public_path(path: &str) -> bool {
         || path == "/register"
         || path == "/logout"
         || path == "/favicon.ico"
+        // checks its own bearer token (or admin session); see home_controller::metrics
+        || path == "/metrics"
+        || path == "/health"
+        || path.starts_with("/health/")
         || path.starts_with("/static/")
 }
 
@@ -102,6 +136,11 @@ pub async fn require_auth(
         return next.run(req).await;
     }
 
+    // CORS preflights carry no credentials; the /api CorsLayer answers them
+    if req.method() == Method::OPTIONS && path.starts_with("/api/") {
+        return next.run(req).await;
+    }
+
     // If inject_current_user already put CurrentUser in extensions => authenticated
     if req.extensions().get::<CurrentUser>().is_some() {
         return next.run(req).await;
@@ -111,10 +150,15 @@ pub async fn require_auth(
     // - HTMX: force full redirect to /login
     // - Normal: 302 redirect to /login
     // - WebSocket: 401
+    // - API: 401 JSON
     if is_websocket(req.headers()) {
         return StatusCode::UNAUTHORIZED.into_response();
     }
 
+    if path.starts_with("/api/") {
+        return (StatusCode::UNAUTHORIZED, axum::Json(serde_json::json!({ "error": "unauthorized" }))).into_response();
+    }
+
     if is_htmx(req.headers()) {
         let mut headers = HeaderMap::new();
         headers.insert("HX-Redirect", HeaderValue::from_static("/login"));
@@ -123,3 +167,12 @@ pub async fn require_auth(
 
     Redirect::to("/login").into_response()
 }
+
+/// Guards the /admin router. It runs after `require_auth`, so the only callers
+/// left to turn away are signed-in users without `is_admin`.
+pub async fn require_admin(req: Request<axum::body::Body>, next: Next) -> Response {
+    match req.extensions().get::<CurrentUser>() {
+        Some(u) if u.is_admin => next.run(req).await,
+        _ => (StatusCode::FORBIDDEN, Html("Forbidden".to_string())).into_response(),
+    }
+}
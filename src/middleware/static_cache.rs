@@ -0,0 +1,38 @@
+//! Cache-Control for `/static`.
+//!
+//! Plain asset names (`/static/js/app.js`) change in place on deploy, so they get
+//! a short max-age. Fingerprinted names (`app.3f9a1c2b.js`) never change content
+//! and are cached for a year as immutable.
+
+use axum::{
+    body::Body,
+    http::{header, HeaderValue, Request},
+    middleware::Next,
+    response::Response,
+};
+
+pub const DEFAULT_CACHE_CONTROL: &str = "public, max-age=3600";
+pub const IMMUTABLE_CACHE_CONTROL: &str = "public, max-age=31536000, immutable";
+
+/// True for `name.<hash>.ext`, where hash is at least 8 hex digits.
+pub fn is_fingerprinted(path: &str) -> bool {
+    let file = path.rsplit('/').next().unwrap_or(path);
+    let mut parts = file.rsplit('.');
+    let (Some(_ext), Some(hash), Some(_stem)) = (parts.next(), parts.next(), parts.next()) else {
+        return false;
+    };
+    hash.len() >= 8 && hash.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Marks fingerprinted assets immutable; everything else falls through to the
+/// `SetResponseHeaderLayer` default set in `routes::app`.
+pub async fn mark_immutable(req: Request<Body>, next: Next) -> Response {
+    let fingerprinted = is_fingerprinted(req.uri().path());
+    let mut res = next.run(req).await;
+
+    if fingerprinted && res.status().is_success() {
+        res.headers_mut()
+            .insert(header::CACHE_CONTROL, HeaderValue::from_static(IMMUTABLE_CACHE_CONTROL));
+    }
+    res
+}
@@ -9,4 +9,10 @@ pub struct Account {
 
     pub cash: f64,
     pub updated_at: i64,
+
+    // running total of today's deposits ("YYYY-MM-DD", UTC) for the daily limit
+    #[serde(default)]
+    pub deposit_day: Option<String>,
+    #[serde(default)]
+    pub deposited_today: f64,
 }
@@ -9,11 +9,26 @@ pub struct Alert {
     pub user_id: ObjectId,
     pub symbol: String,
 
+    // "above", "below" or "between"; for "between" target_price is the band's low end
     pub condition: String,
     pub target_price: f64,
+    // high end of a "between" band; None for the other conditions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target_price_high: Option<f64>,
 
     pub created_at: i64,
 
     pub triggered: bool,
     pub triggered_at: Option<i64>,
+    // BSON date twin of triggered_at; TTL indexes only work on dates.
+    #[serde(default)]
+    pub triggered_on: Option<mongodb::bson::DateTime>,
+    // last price when the monitor fired it; null for alerts fired before this was
+    // recorded and for ones the browser reported
+    #[serde(default)]
+    pub triggered_price: Option<f64>,
+    // soft delete: hidden from every list, restorable for a short while, then
+    // removed by the TTL index (see db_init)
+    #[serde(default)]
+    pub deleted_at: Option<mongodb::bson::DateTime>,
 }
@@ -0,0 +1,20 @@
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+
+// Cash credited outside of trading, e.g. a simulated dividend.
+// (user_id, kind, symbol, period) is unique, so a period is paid at most once.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CashEvent {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+
+    pub user_id: ObjectId,
+    // "dividend"
+    pub kind: String,
+    pub symbol: String,
+    pub amount: f64,
+    // payout number: unix time / DIVIDEND_INTERVAL_SECS
+    pub period: i64,
+
+    pub created_at: i64,
+}
@@ -0,0 +1,15 @@
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+
+// One processed deposit form submission; (user_id, key) is unique.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DepositKey {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+
+    pub user_id: ObjectId,
+    pub key: String,
+    pub amount: f64,
+
+    pub created_at: i64,
+}
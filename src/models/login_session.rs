@@ -0,0 +1,21 @@
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+
+// One successful login, kept so users can spot sign-ins they don't recognize.
+// Only the newest session_service::MAX_SESSIONS_PER_USER rows are kept per user.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoginSession {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+
+    pub user_id: ObjectId,
+    // first X-Forwarded-For hop, else the socket peer; "unknown" when neither is known
+    pub ip: String,
+    #[serde(default)]
+    pub user_agent: String,
+
+    pub created_at: i64,
+    // set by "log out all devices", which revokes every token issued so far
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revoked_at: Option<i64>,
+}
@@ -3,9 +3,19 @@ pub mod account;
 pub mod position;
 pub mod alert;
 pub mod order;
+pub mod portfolio_snapshot;
+pub mod watchlist;
+pub mod deposit_key;
+pub mod cash_event;
+pub mod login_session;
 
-pub use user::{CurrentUser, User};
+pub use user::{CurrentUser, Preferences, User};
 pub use account::Account;
-pub use position::Position;
+pub use position::{Lot, Position};
 pub use alert::Alert;
-pub use order::Order;
+pub use order::{Order, ORDER_CANCELLED, ORDER_FILLED, ORDER_OPEN};
+pub use portfolio_snapshot::PortfolioSnapshot;
+pub use watchlist::WatchlistItem;
+pub use deposit_key::DepositKey;
+pub use cash_event::CashEvent;
+pub use login_session::LoginSession;
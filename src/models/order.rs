@@ -1,6 +1,14 @@
 use mongodb::bson::oid::ObjectId;
 use serde::{Deserialize, Serialize};
 
+pub const ORDER_OPEN: &str = "open";
+pub const ORDER_FILLED: &str = "filled";
+pub const ORDER_CANCELLED: &str = "cancelled";
+
+fn filled() -> String {
+    ORDER_FILLED.to_string()
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Order {
     #[serde(rename = "_id")]
@@ -9,7 +17,27 @@ pub struct Order {
     pub symbol: String,
     pub side: String,
     pub qty: i64,
+    // effective fill price, after any simulated slippage
     pub price: f64,
     pub total: f64,
     pub created_at: i64,
+    // sells only: proceeds minus the cost basis of the shares sold; missing on
+    // sells recorded before this was stored (see portfolio_service::realized_pnl)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub realized_pnl: Option<f64>,
+    // the quote the fill was based on; missing on orders from before slippage
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quoted_price: Option<f64>,
+    // ORDER_OPEN | ORDER_FILLED | ORDER_CANCELLED; every order before this was a market fill
+    #[serde(default = "filled")]
+    pub status: String,
+    // shares actually filled when fewer than qty; None means all of them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filled_qty: Option<i64>,
+}
+
+impl Order {
+    pub fn filled_qty(&self) -> i64 {
+        self.filled_qty.unwrap_or(self.qty)
+    }
 }
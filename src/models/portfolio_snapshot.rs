@@ -0,0 +1,17 @@
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PortfolioSnapshot {
+    #[serde(rename = "_id")]
+    pub id: ObjectId,
+
+    pub user_id: ObjectId,
+    // UTC day, "YYYY-MM-DD"; one snapshot per user per day
+    pub date: String,
+
+    pub total_value: f64,
+    pub cash: f64,
+
+    pub created_at: i64,
+}
@@ -12,5 +12,20 @@ pub struct Position {
     pub qty: i64,
     pub avg_price: f64,
 
+    // first buy; kept across averaging buys (old rows are backfilled by migrations)
+    #[serde(default)]
+    pub created_at: i64,
     pub updated_at: i64,
+
+    // open buy lots, oldest first; only recorded with COST_BASIS_METHOD=fifo
+    #[serde(default)]
+    pub lots: Vec<Lot>,
+}
+
+/// Shares from one buy that are still held.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Lot {
+    pub qty: i64,
+    pub price: f64,
+    pub bought_at: i64,
 }
@@ -1,6 +1,35 @@
+use std::sync::OnceLock;
+
 use mongodb::bson::oid::ObjectId;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 
+/// Trims and lowercases `raw` and checks it is a plausible address; every form
+/// that takes an email goes through here so they all agree. The error is the
+/// message to show under the field.
+pub fn normalize_and_validate_email(raw: &str) -> Result<String, &'static str> {
+    static EMAIL_RE: OnceLock<Regex> = OnceLock::new();
+    // local part: the usual atext characters; domain: dot-separated labels, at least two
+    let re = EMAIL_RE.get_or_init(|| {
+        Regex::new(
+            r"^[a-z0-9.!#$%&'*+/=?^_`{|}~-]+@[a-z0-9](?:[a-z0-9-]{0,61}[a-z0-9])?(?:\.[a-z0-9](?:[a-z0-9-]{0,61}[a-z0-9])?)+$",
+        )
+        .unwrap()
+    });
+
+    let email = raw.trim().to_lowercase();
+    if email.is_empty() {
+        return Err("Email is required.");
+    }
+    let local_ok = email
+        .split_once('@')
+        .is_some_and(|(local, _)| !local.starts_with('.') && !local.ends_with('.') && !local.contains(".."));
+    if email.len() > 254 || !local_ok || !re.is_match(&email) {
+        return Err("Invalid email.");
+    }
+    Ok(email)
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct User {
     #[serde(rename = "_id")]
@@ -10,6 +39,38 @@ pub struct User {
     pub username: String,
 
     pub password_hash: String,
+
+    // bumped to invalidate every outstanding JWT for this user
+    #[serde(default)]
+    pub token_version: i32,
+
+    // grants /admin; set directly in the database
+    #[serde(default)]
+    pub is_admin: bool,
+
+    #[serde(default)]
+    pub preferences: Preferences,
+
+    // last details pages viewed, most recent first; see user_service::record_recent_symbol
+    #[serde(default)]
+    pub recent_symbols: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Preferences {
+    // pre-filled into buy/sell quantity inputs
+    pub default_qty: i64,
+    // ISO code, one of templates::DISPLAY_CURRENCIES
+    pub display_currency: String,
+}
+
+impl Default for Preferences {
+    fn default() -> Self {
+        Self {
+            default_qty: 1,
+            display_currency: "USD".to_string(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -17,6 +78,8 @@ pub struct CurrentUser {
     pub id: ObjectId,
     pub email: String,
     pub username: String,
+    #[serde(default)]
+    pub is_admin: bool,
 }
 
 impl From<User> for CurrentUser {
@@ -25,6 +88,7 @@ impl From<User> for CurrentUser {
             id: u.id,
             email: u.email,
             username: u.username,
+            is_admin: u.is_admin,
         }
     }
 }
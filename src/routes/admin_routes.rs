@@ -0,0 +1,17 @@
+use axum::{middleware::from_fn, Router, routing::{get, post}};
+
+use crate::{AppState, controllers::admin_controller};
+
+// require_admin runs after the global require_auth, so everyone reaching it is signed in.
+pub fn add_routes(router: Router<AppState>) -> Router<AppState> {
+    let admin = Router::new()
+        .route("/admin", get(admin_controller::get_dashboard))
+        .route("/admin/stats", get(admin_controller::get_stats))
+        .route("/admin/users", get(admin_controller::get_users))
+        .route("/admin/users/:id/credit", post(admin_controller::post_credit))
+        .route("/admin/users/:id/reset", post(admin_controller::post_reset))
+        .route("/admin/profiles/:symbol/refresh", post(admin_controller::post_refresh_profile))
+        .route_layer(from_fn(crate::auth::require_admin));
+
+    router.merge(admin)
+}
@@ -7,7 +7,9 @@ pub fn add_routes(router: Router<AppState>) -> Router<AppState> {
         .route("/alerts/list", get(alerts_controller::get_watchlist_alerts))
         .route("/alerts/:symbol/list", get(alerts_controller::get_alerts_list))
         .route("/alerts/:symbol", post(alerts_controller::post_create_alert))
+        .route("/alerts/:symbol/delete_all", post(alerts_controller::post_delete_all_alerts))
         .route("/alerts/:symbol/:id/delete", post(alerts_controller::post_delete_alert))
         .route("/alerts/by-id/:id/delete", post(alerts_controller::post_delete_alert_global))
+        .route("/alerts/by-id/:id/restore", post(alerts_controller::post_restore_alert))
         .route("/alerts/by-id/:id/trigger", post(alerts_controller::post_trigger_alert))
 }
@@ -0,0 +1,52 @@
+use axum::{
+    http::{header, HeaderName, HeaderValue, Method},
+    routing::{get, post},
+    Router,
+};
+use tower_http::cors::{AllowOrigin, CorsLayer};
+
+use crate::{config::Settings, AppState, controllers::api_controller};
+
+// Only the JSON API is opened up; HTML routes stay same-origin.
+fn cors_layer(settings: &Settings) -> Option<CorsLayer> {
+    let origins: Vec<HeaderValue> = settings
+        .cors_allowed_origins
+        .iter()
+        .filter_map(|o| HeaderValue::from_str(o).ok())
+        .collect();
+    if origins.is_empty() {
+        return None;
+    }
+
+    Some(
+        CorsLayer::new()
+            .allow_origin(AllowOrigin::list(origins))
+            .allow_methods([Method::GET, Method::POST])
+            .allow_headers([
+                header::AUTHORIZATION,
+                header::CONTENT_TYPE,
+                HeaderName::from_static("x-csrf-token"),
+            ])
+            // lets a listed origin send the auth cookie; it still needs X-CSRF-Token
+            // (or a Bearer token) for POSTs
+            .allow_credentials(true),
+    )
+}
+
+pub fn add_routes(router: Router<AppState>, settings: &Settings) -> Router<AppState> {
+    let api = Router::new()
+        .route("/api/v1/quote/:symbol", get(api_controller::get_quote))
+        .route("/api/v1/quotes", get(api_controller::get_quotes))
+        .route("/api/v1/portfolio", get(api_controller::get_portfolio))
+        .route("/api/v1/orders", get(api_controller::get_orders))
+        .route("/api/v1/trade", post(api_controller::post_trade))
+        .route("/api/v1/funds", post(api_controller::post_funds))
+        .route("/api/v1/funds/withdraw", post(api_controller::post_withdraw));
+
+    let api = match cors_layer(settings) {
+        Some(cors) => api.layer(cors),
+        None => api,
+    };
+
+    router.merge(api)
+}
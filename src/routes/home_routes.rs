@@ -4,6 +4,11 @@ use crate::{AppState, controllers::home_controller};
 pub fn add_routes(router: Router<AppState>) -> Router<AppState> {
     router
         .route("/", get(home_controller::home))
+        .route("/favicon.ico", get(home_controller::favicon))
         .route("/health", get(home_controller::health))
+        .route("/metrics", get(home_controller::metrics))
         .route("/health/db", get(home_controller::health_db))
+        .route("/health/finnhub", get(home_controller::health_finnhub))
+        .route("/health/ready", get(home_controller::health_ready))
+        .route("/health/version", get(home_controller::health_version))
 }
@@ -1,6 +1,7 @@
 use axum::Router;
-use axum::middleware::from_fn_with_state;
-use tower_http::services::ServeDir;
+use axum::http::{header, HeaderValue};
+use axum::middleware::{from_fn, from_fn_with_state};
+use tower_http::{services::ServeDir, set_header::SetResponseHeaderLayer};
 
 use crate::{AppState, controllers::home_controller};
 
@@ -12,6 +13,10 @@ pub mod trading_routes;
 pub mod portfolio_routes;
 pub mod alerts_routes;
 pub mod realtime_routes;
+pub mod watchlist_routes;
+pub mod leaderboard_routes;
+pub mod api_routes;
+pub mod admin_routes;
 
 pub fn app(state: AppState) -> Router {
     let router = Router::<AppState>::new();
@@ -24,11 +29,25 @@ pub fn app(state: AppState) -> Router {
     let router = portfolio_routes::add_routes(router);
     let router = alerts_routes::add_routes(router);
     let router = realtime_routes::add_routes(router);
+    let router = watchlist_routes::add_routes(router);
+    let router = leaderboard_routes::add_routes(router);
+    let router = api_routes::add_routes(router, &state.settings);
+    let router = admin_routes::add_routes(router);
 
-    router
+    let static_files = Router::new()
         .nest_service("/static", ServeDir::new("static"))
+        .layer(from_fn(crate::static_cache::mark_immutable))
+        .layer(SetResponseHeaderLayer::if_not_present(
+            header::CACHE_CONTROL,
+            HeaderValue::from_static(crate::static_cache::DEFAULT_CACHE_CONTROL),
+        ));
+
+    router
+        .merge(static_files)
         .fallback(home_controller::not_found)
         .layer(from_fn_with_state(state.clone(), crate::auth::require_auth))
         .layer(from_fn_with_state(state.clone(), crate::auth::inject_current_user))
+        .layer(from_fn_with_state(state.clone(), crate::csrf::verify_csrf))
+        .layer(from_fn(crate::access_log::log_requests))
         .with_state(state)
 }
@@ -5,7 +5,10 @@ use crate::{AppState, controllers::portfolio_controller};
 pub fn add_routes(router: Router<AppState>) -> Router<AppState> {
     router
         .route("/portfolio", get(portfolio_controller::get_portfolio_page))
+        .route("/portfolio/summary", get(portfolio_controller::get_portfolio_summary))
         .route("/portfolio/positions", get(portfolio_controller::get_portfolio_positions))
+        .route("/portfolio/sectors", get(portfolio_controller::get_portfolio_sectors))
         .route("/portfolio/position/:symbol", get(portfolio_controller::get_portfolio_position_card))
+        .route("/portfolio/history", get(portfolio_controller::get_portfolio_history))
         .route("/portfolio/orders", get(portfolio_controller::get_portfolio_orders))
 }
@@ -7,4 +7,6 @@ pub fn add_routes(router: Router<AppState>) -> Router<AppState> {
         .route("/positions/:symbol", get(trading_controller::get_position_panel))
         .route("/trade/:symbol/buy", post(trading_controller::post_trade_buy))
         .route("/trade/:symbol/sell", post(trading_controller::post_trade_sell))
+        .route("/trade/:symbol/sell_fraction", post(trading_controller::post_trade_sell_fraction))
+        .route("/trade/:symbol/sell_all", post(trading_controller::post_trade_sell_all))
 }
@@ -1,9 +1,10 @@
-use axum::{Router, routing::get};
+use axum::{Router, routing::{get, post}};
 use crate::{AppState, controllers::user_controller};
 
 pub fn add_routes(router: Router<AppState>) -> Router<AppState> {
     router
         .route("/me", get(user_controller::me))
+        .route("/profile", get(user_controller::get_profile))
         .route("/settings", get(user_controller::get_settings))
         .route(
             "/settings/email",
@@ -13,7 +14,24 @@ pub fn add_routes(router: Router<AppState>) -> Router<AppState> {
             "/settings/password",
             get(user_controller::get_settings_password).post(user_controller::post_settings_password),
         )
+        .route(
+            "/settings/preferences",
+            get(user_controller::get_settings_preferences).post(user_controller::post_settings_preferences),
+        )
+        .route(
+            "/settings/reset-account",
+            get(user_controller::get_settings_reset_account)
+                .post(user_controller::post_settings_reset_account),
+        )
+        .route(
+            "/settings/delete-account",
+            get(user_controller::get_settings_delete_account)
+                .post(user_controller::post_settings_delete_account),
+        )
+        .route("/settings/sessions", get(user_controller::get_settings_sessions))
+        .route("/settings/logout-all", post(user_controller::post_settings_logout_all))
         .route("/funds", get(user_controller::get_funds_page).post(user_controller::post_funds))
+        .route("/funds/withdraw", post(user_controller::post_withdraw))
         .route("/funds/modal", get(user_controller::get_funds_modal))
         .route("/cash", get(user_controller::get_cash_badge))
 }
@@ -0,0 +1,10 @@
+use axum::{Router, routing::{get, post}};
+use crate::{AppState, controllers::watchlist_controller};
+
+pub fn add_routes(router: Router<AppState>) -> Router<AppState> {
+    router
+        .route("/watchlist", get(watchlist_controller::get_watchlist))
+        .route("/watchlist/:symbol", post(watchlist_controller::post_watch))
+        .route("/watchlist/:symbol/star", get(watchlist_controller::get_watch_star))
+        .route("/watchlist/:symbol/remove", post(watchlist_controller::post_unwatch))
+}
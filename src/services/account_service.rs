@@ -1,8 +1,29 @@
 use chrono::Utc;
 use mongodb::bson::{doc, oid::ObjectId};
+use mongodb::options::{FindOneAndUpdateOptions, ReturnDocument, UpdateOptions};
 
-use crate::{models::Account, AppState};
+use crate::{models::{Account, Alert, Order, PortfolioSnapshot, Position}, AppState};
 
+use super::portfolio_service;
+
+/// What a paper reset changes, for other tabs and the HX-Trigger alike.
+pub const RESET_EVENTS: [&str; 4] = ["positionUpdated", "ordersUpdated", "alertsUpdated", "cashUpdated"];
+
+/// The user's account, without opening one.
+pub async fn find_account(state: &AppState, user_id: ObjectId) -> Result<Option<Account>, String> {
+    let accounts = state.db.collection::<Account>("accounts");
+    accounts
+        .find_one(doc! { "_id": user_id }, None)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+pub async fn account_exists(state: &AppState, user_id: ObjectId) -> Result<bool, String> {
+    Ok(find_account(state, user_id).await?.is_some())
+}
+
+/// Opens the account with the starting balance on first use. The grant happens once:
+/// `_id` is the user id, so a concurrent second insert fails and we read the winner's.
 pub async fn get_or_create_account(state: &AppState, user_id: ObjectId) -> Result<Account, String> {
     let accounts = state.db.collection::<Account>("accounts");
 
@@ -12,27 +33,204 @@ pub async fn get_or_create_account(state: &AppState, user_id: ObjectId) -> Resul
 
     let acc = Account {
         id: user_id,
-        cash: 10_000.0,
+        cash: state.settings.starting_balance,
         updated_at: Utc::now().timestamp(),
+        deposit_day: None,
+        deposited_today: 0.0,
     };
 
+    match accounts.insert_one(&acc, None).await {
+        Ok(_) => Ok(acc),
+        Err(e) if e.to_string().contains("E11000") => find_account(state, user_id)
+            .await?
+            .ok_or_else(|| "account vanished after a duplicate insert".to_string()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+pub async fn set_cash(state: &AppState, user_id: ObjectId, cash: f64, updated_at: i64) -> Result<(), String> {
+    let accounts = state.db.collection::<Account>("accounts");
     accounts
-        .insert_one(&acc, None)
+        .update_one(
+            doc! { "_id": user_id },
+            doc! { "$set": { "cash": cash, "updated_at": updated_at } },
+            None,
+        )
         .await
         .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Adds `amount` to the account in place, without a read-modify-write race.
+/// Returns the account as it is afterwards.
+pub async fn credit_cash(state: &AppState, user_id: ObjectId, amount: f64) -> Result<Account, String> {
+    let accounts = state.db.collection::<Account>("accounts");
+
+    let opts = FindOneAndUpdateOptions::builder()
+        .return_document(ReturnDocument::After)
+        .build();
 
-    Ok(acc)
+    accounts
+        .find_one_and_update(
+            doc! { "_id": user_id },
+            doc! {
+                "$inc": { "cash": amount },
+                "$set": { "updated_at": Utc::now().timestamp() },
+            },
+            opts,
+        )
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "account not found".to_string())
 }
 
-pub async fn set_cash(state: &AppState, user_id: ObjectId, cash: f64, updated_at: i64) -> Result<(), String> {
+/// Takes `amount` out of the account only if it holds at least that much, in one
+/// conditional update. Ok(None) means the balance was too low.
+pub async fn debit_cash(state: &AppState, user_id: ObjectId, amount: f64) -> Result<Option<Account>, String> {
     let accounts = state.db.collection::<Account>("accounts");
+
+    let opts = FindOneAndUpdateOptions::builder()
+        .return_document(ReturnDocument::After)
+        .build();
+
     accounts
+        .find_one_and_update(
+            doc! { "_id": user_id, "cash": { "$gte": amount } },
+            doc! {
+                "$inc": { "cash": -amount },
+                "$set": { "updated_at": Utc::now().timestamp() },
+            },
+            opts,
+        )
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Counts `amount` against the account's deposits on `day`, as long as the total
+/// stays within `limit`. Ok(false) means the deposit would go over it.
+pub async fn reserve_daily_deposit(
+    state: &AppState,
+    user_id: ObjectId,
+    amount: f64,
+    limit: f64,
+    day: &str,
+) -> Result<bool, String> {
+    if amount > limit {
+        return Ok(false);
+    }
+
+    let accounts = state.db.collection::<Account>("accounts");
+
+    // One update both starts the day's total and adds to it, so two deposits racing
+    // to be the first of the day can't make one of them miss: either the stored day is
+    // stale (start over at `amount`) or it's today and there is room left.
+    let filter = doc! {
+        "_id": user_id,
+        "$or": [
+            { "deposit_day": { "$ne": day } },
+            { "deposited_today": { "$lte": limit - amount } },
+        ],
+    };
+    let update = vec![doc! {
+        "$set": {
+            "deposited_today": {
+                "$cond": [
+                    { "$eq": ["$deposit_day", day] },
+                    { "$add": [{ "$ifNull": ["$deposited_today", 0.0] }, amount] },
+                    amount,
+                ]
+            },
+            "deposit_day": day,
+        }
+    }];
+    let res = accounts
+        .update_one(filter, update, None)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(res.matched_count > 0)
+}
+
+/// Gives back a reservation whose deposit didn't go through.
+pub async fn release_daily_deposit(state: &AppState, user_id: ObjectId, amount: f64, day: &str) {
+    let accounts = state.db.collection::<Account>("accounts");
+    let _ = accounts
         .update_one(
-            doc! { "_id": user_id },
-            doc! { "$set": { "cash": cash, "updated_at": updated_at } },
+            doc! { "_id": user_id, "deposit_day": day },
+            doc! { "$inc": { "deposited_today": -amount } },
             None,
         )
+        .await;
+}
+
+/// Paper reset: positions, orders, alerts and equity history go, and cash is back
+/// at the starting balance, all in one transaction. The login, watchlist and
+/// deposit history stay.
+pub async fn reset_account(state: &AppState, user_id: ObjectId) -> Result<(), String> {
+    let accounts = state.db.collection::<Account>("accounts");
+
+    let mut session = accounts
+        .client()
+        .start_session(None)
+        .await
+        .map_err(|e| e.to_string())?;
+    session
+        .start_transaction(None)
         .await
         .map_err(|e| e.to_string())?;
+
+    let by_user = doc! { "user_id": user_id };
+
+    let res = async {
+        state
+            .db
+            .collection::<Position>("positions")
+            .delete_many_with_session(by_user.clone(), None, &mut session)
+            .await?;
+        state
+            .db
+            .collection::<Order>("orders")
+            .delete_many_with_session(by_user.clone(), None, &mut session)
+            .await?;
+        state
+            .db
+            .collection::<Alert>("alerts")
+            .delete_many_with_session(by_user.clone(), None, &mut session)
+            .await?;
+        // the old equity curve would only make the fresh start look like a crash
+        state
+            .db
+            .collection::<PortfolioSnapshot>("portfolio_snapshots")
+            .delete_many_with_session(by_user.clone(), None, &mut session)
+            .await?;
+        // the daily deposit total is left alone, so a reset can't lift the limit
+        accounts
+            .update_one_with_session(
+                doc! { "_id": user_id },
+                doc! {
+                    "$set": { "cash": state.settings.starting_balance, "updated_at": state.clock.timestamp() },
+                    "$setOnInsert": { "deposited_today": 0.0 },
+                },
+                UpdateOptions::builder().upsert(true).build(),
+                &mut session,
+            )
+            .await?;
+        Ok::<(), mongodb::error::Error>(())
+    }
+    .await;
+
+    if let Err(e) = res {
+        let _ = session.abort_transaction().await;
+        return Err(e.to_string());
+    }
+
+    session
+        .commit_transaction()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    for event in RESET_EVENTS {
+        let _ = state.events_tx.send(event.to_string());
+    }
+    let _ = state.events_tx.send(portfolio_service::holdings_changed_event(user_id));
     Ok(())
 }
@@ -0,0 +1,133 @@
+use std::collections::HashMap;
+
+use futures_util::StreamExt;
+use mongodb::bson::{doc, oid::ObjectId, Document};
+use mongodb::options::FindOptions;
+use serde::Serialize;
+
+use crate::{error::AppError, models::{Account, User}, AppState};
+
+use super::account_service;
+
+pub const USERS_PER_PAGE: i64 = 25;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AdminStats {
+    pub users: u64,
+    pub orders: u64,
+    pub alerts: u64,
+    // trade WebSockets open on this instance
+    pub ws_connections: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UserRow {
+    pub id: String,
+    pub username: String,
+    pub email: String,
+    pub is_admin: bool,
+    pub cash: f64,
+    pub member_since: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UserPage {
+    pub users: Vec<UserRow>,
+    // 1-based
+    pub page: u64,
+    pub pages: u64,
+    pub total: u64,
+}
+
+pub async fn stats(state: &AppState) -> Result<AdminStats, AppError> {
+    let count = |name: &str| state.db.collection::<Document>(name);
+
+    Ok(AdminStats {
+        users: count("users").count_documents(doc! {}, None).await?,
+        orders: count("orders").count_documents(doc! {}, None).await?,
+        alerts: count("alerts").count_documents(doc! { "deleted_at": null }, None).await?,
+        ws_connections: state.ws_limiter.total_open(),
+    })
+}
+
+/// Number of pages for `total` users; an empty list still has one (empty) page.
+pub fn page_count(total: u64) -> u64 {
+    total.div_ceil(USERS_PER_PAGE as u64).max(1)
+}
+
+/// Newest sign-ups first, with each user's cash.
+pub async fn list_users(state: &AppState, page: u64) -> Result<UserPage, AppError> {
+    let users = state.db.collection::<User>("users");
+
+    let total = users.count_documents(doc! {}, None).await?;
+    let pages = page_count(total);
+    let page = page.clamp(1, pages);
+
+    let opts = FindOptions::builder()
+        .sort(doc! { "_id": -1 })
+        .skip((page - 1) * USERS_PER_PAGE as u64)
+        .limit(USERS_PER_PAGE)
+        .build();
+
+    let mut cursor = users.find(doc! {}, opts).await?;
+    let mut found: Vec<User> = Vec::new();
+    while let Some(u) = cursor.next().await {
+        found.push(u?);
+    }
+
+    let ids: Vec<ObjectId> = found.iter().map(|u| u.id).collect();
+    let mut cursor = state
+        .db
+        .collection::<Account>("accounts")
+        .find(doc! { "_id": { "$in": ids } }, None)
+        .await?;
+    let mut cash: HashMap<ObjectId, f64> = HashMap::new();
+    while let Some(acc) = cursor.next().await {
+        let acc = acc?;
+        cash.insert(acc.id, acc.cash);
+    }
+
+    let rows = found
+        .into_iter()
+        .map(|u| UserRow {
+            id: u.id.to_hex(),
+            // no account yet: it opens with the starting balance on first use
+            cash: cash.get(&u.id).copied().unwrap_or(state.settings.starting_balance),
+            member_since: chrono::DateTime::from_timestamp_millis(u.id.timestamp().timestamp_millis())
+                .map(|d| d.format("%Y-%m-%d").to_string())
+                .unwrap_or_default(),
+            username: u.username,
+            email: u.email,
+            is_admin: u.is_admin,
+        })
+        .collect();
+
+    Ok(UserPage { users: rows, page, pages, total })
+}
+
+async fn ensure_user(state: &AppState, user_id: ObjectId) -> Result<(), AppError> {
+    let users = state.db.collection::<User>("users");
+    match users.find_one(doc! { "_id": user_id }, None).await? {
+        Some(_) => Ok(()),
+        None => Err(AppError::NotFound),
+    }
+}
+
+/// The same paper reset users can run from settings (`account_service::reset_account`).
+pub async fn reset_account(state: &AppState, user_id: ObjectId) -> Result<(), AppError> {
+    ensure_user(state, user_id).await?;
+
+    account_service::reset_account(state, user_id)
+        .await
+        .map_err(AppError::Db)
+}
+
+/// Adds `amount` to the user's cash; admin credits don't count toward the daily deposit limit.
+pub async fn credit_account(state: &AppState, user_id: ObjectId, amount: f64) -> Result<Account, AppError> {
+    ensure_user(state, user_id).await?;
+
+    // credit_funds only fails on the database, under "_form"
+    super::user_service::credit_funds(state, user_id, amount)
+        .await
+        .map_err(|errs| AppError::Db(errs.into_values().collect::<Vec<_>>().join(", ")))
+}
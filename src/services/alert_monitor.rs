@@ -1,16 +1,37 @@
-use mongodb::bson::doc;
 use std::time::Duration;
 use tokio::time;
 
-use crate::{AppState, models::Alert};
+use crate::{AppState, models::Alert, services::{alerts_service, finnhub::QUOTE_CACHE_TTL, metrics::ALERTS_TRIGGERED_TOTAL, monitor_lease}};
+
+pub const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+// With several replicas only the lease holder fetches quotes and broadcasts.
+// A few missed renewals hand the job to another instance.
+pub const LEASE_TASK: &str = "alert_monitor";
+pub const LEASE_TTL: Duration = Duration::from_secs(3 * POLL_INTERVAL.as_secs());
+
+// run_tick goes through the shared quote cache: a symbol a portfolio page quoted moments ago
+// is reused, and the monitor's own quotes serve later portfolio reads. A cached price is at
+// most one TTL old, so keeping the TTL under the poll interval means no tick repeats the
+// previous tick's price.
+const _: () = assert!(QUOTE_CACHE_TTL.as_millis() < POLL_INTERVAL.as_millis());
 
 pub fn spawn_price_alert_monitor(state: AppState) {
     tokio::spawn(async move {
-        let mut interval = time::interval(Duration::from_secs(5));
+        let mut interval = time::interval(POLL_INTERVAL);
 
         loop {
             interval.tick().await;
 
+            match monitor_lease::try_acquire(&state.db, LEASE_TASK, monitor_lease::instance_id(), LEASE_TTL).await {
+                Ok(true) => {}
+                Ok(false) => continue,
+                Err(e) => {
+                    eprintln!("[alert-monitor] lease error: {}", e);
+                    continue;
+                }
+            }
+
             if let Err(e) = run_tick(&state).await {
                 eprintln!("[alert-monitor] tick error: {}", e);
             }
@@ -27,7 +48,7 @@ async fn run_tick(state: &AppState) -> Result<(), String> {
     let alerts = state.db.collection::<Alert>("alerts");
 
     let mut cursor = alerts
-        .find(doc! { "triggered": false }, None)
+        .find(doc! { "triggered": false, "deleted_at": null }, None)
         .await
         .map_err(|e| e.to_string())?;
 
@@ -41,13 +62,15 @@ async fn run_tick(state: &AppState) -> Result<(), String> {
         return Ok(());
     }
 
+    // one concurrent batch for every symbol; a symbol whose quote failed is skipped
+    let symbols: Vec<String> = by_symbol.keys().cloned().collect();
+    let quotes = state.finnhub.quotes(&symbols).await;
+
     let mut triggered_any = false;
-    let now = chrono::Utc::now().timestamp();
 
     for (sym, group) in by_symbol {
-        let quote = match state.finnhub.quote(&sym).await {
-            Ok(q) => q,
-            Err(_) => continue,
+        let Some(quote) = quotes.get(&sym) else {
+            continue;
         };
 
         let price = quote.c;
@@ -56,23 +79,23 @@ async fn run_tick(state: &AppState) -> Result<(), String> {
         }
 
         for a in group {
-            let hit = (a.condition == "above" && price >= a.target_price)
-                || (a.condition == "below" && price <= a.target_price);
-
-            if !hit {
+            if !alerts_service::alert_hit(&a, price) {
                 continue;
             }
 
             let res = alerts
                 .update_one(
-                    doc! { "_id": a.id, "triggered": false },
-                    doc! { "$set": { "triggered": true, "triggered_at": now } },
+                    doc! { "_id": a.id, "triggered": false, "deleted_at": null },
+                    alerts_service::triggered_update(state.clock.as_ref(), Some(price)),
                     None,
                 )
                 .await;
 
-            if res.is_ok() {
+            if let Ok(r) = res
+                && r.modified_count > 0
+            {
                 triggered_any = true;
+                metrics::counter!(ALERTS_TRIGGERED_TOTAL, "source" => "monitor").increment(1);
             }
         }
     }
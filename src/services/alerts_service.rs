@@ -1,18 +1,67 @@
 use std::collections::BTreeMap;
 
-use chrono::Utc;
 use futures_util::StreamExt;
-use mongodb::bson::{doc, oid::ObjectId};
+use mongodb::bson::{doc, oid::ObjectId, DateTime as BsonDateTime, Document};
 use mongodb::options::FindOptions;
 
-use crate::{models::Alert, AppState};
+use crate::{
+    error::AppError,
+    models::Alert,
+    services::{clock::Clock, metrics::ALERTS_TRIGGERED_TOTAL, stocks_service},
+    AppState,
+};
+
+/// How long after a delete the alert can still be restored.
+pub const RESTORE_WINDOW_SECS: i64 = 5 * 60;
+
+// "above" fires at or over the target, "below" at or under it.
+pub fn condition_met(condition: &str, target_price: f64, price: f64) -> bool {
+    (condition == "above" && price >= target_price) || (condition == "below" && price <= target_price)
+}
+
+// "between" fires once the price is inside [low, high], both ends included.
+pub fn band_met(low: f64, high: f64, price: f64) -> bool {
+    price >= low && price <= high
+}
+
+/// Whether `price` fires `alert`, whatever its condition.
+pub fn alert_hit(alert: &Alert, price: f64) -> bool {
+    match (alert.condition.as_str(), alert.target_price_high) {
+        ("between", Some(high)) => band_met(alert.target_price, high, price),
+        ("between", None) => false,
+        (cond, _) => condition_met(cond, alert.target_price, price),
+    }
+}
+
+// Marks an alert as fired at `price` (None when it isn't known). triggered_on feeds
+// the retention TTL index (see db_init).
+pub fn triggered_update(clock: &dyn Clock, price: Option<f64>) -> Document {
+    doc! {
+        "$set": {
+            "triggered": true,
+            "triggered_at": clock.timestamp(),
+            "triggered_on": clock.bson_now(),
+            "triggered_price": price,
+        }
+    }
+}
+
+/// "YYYY-MM-DD" (UTC) of a triggered_at timestamp.
+pub fn triggered_date(triggered_at: i64) -> Option<String> {
+    chrono::DateTime::from_timestamp(triggered_at, 0).map(|d| d.format("%Y-%m-%d").to_string())
+}
+
+// Deletes are soft; deleted_at is a BSON date so the TTL index can purge it later.
+fn soft_delete_update(clock: &dyn Clock) -> Document {
+    doc! { "$set": { "deleted_at": clock.bson_now() } }
+}
 
 pub async fn list_user_symbol_alerts(
     state: &AppState,
     user_id: ObjectId,
     symbol: &str,
 ) -> Result<Vec<Alert>, String> {
-    let sym = symbol.to_uppercase();
+    let sym = stocks_service::normalize_symbol(symbol);
     let alerts = state.db.collection::<Alert>("alerts");
 
     let find_opts = FindOptions::builder()
@@ -20,7 +69,7 @@ pub async fn list_user_symbol_alerts(
         .build();
 
     let mut cursor = alerts
-        .find(doc! { "user_id": user_id, "symbol": &sym }, find_opts)
+        .find(doc! { "user_id": user_id, "symbol": &sym, "deleted_at": null }, find_opts)
         .await
         .map_err(|e| e.to_string())?;
 
@@ -38,26 +87,48 @@ pub async fn create_alert(
     symbol: &str,
     condition: &str,
     target_price: f64,
-) -> Result<Alert, String> {
-    let sym = symbol.to_uppercase();
+    target_price_high: Option<f64>,
+) -> Result<Alert, AppError> {
+    let sym = stocks_service::normalize_symbol(symbol);
+    let condition = condition.to_lowercase();
+
+    // only a band has a high end; it's dropped for the other conditions
+    let target_price_high = match (condition.as_str(), target_price_high) {
+        ("between", Some(high)) if high.is_finite() && high > target_price => Some(high),
+        ("between", _) => {
+            return Err(AppError::invalid("targetPriceHigh", "Upper price must be above the lower price"));
+        }
+        _ => None,
+    };
     let alerts = state.db.collection::<Alert>("alerts");
-    let now = Utc::now().timestamp();
+    let now = state.clock.timestamp();
+
+    // only live alerts cost the monitor anything; fired and deleted ones don't count
+    if let Some(max) = state.settings.max_alerts_per_user {
+        let active = alerts
+            .count_documents(doc! { "user_id": user_id, "triggered": false, "deleted_at": null }, None)
+            .await?;
+        if active >= max {
+            return Err(AppError::invalid("alerts", "Alert limit reached"));
+        }
+    }
 
     let alert = Alert {
         id: ObjectId::new(),
         user_id,
         symbol: sym,
-        condition: condition.to_lowercase(),
+        condition,
         target_price,
+        target_price_high,
         created_at: now,
         triggered: false,
         triggered_at: None,
+        triggered_on: None,
+        triggered_price: None,
+        deleted_at: None,
     };
 
-    alerts
-        .insert_one(&alert, None)
-        .await
-        .map_err(|e| e.to_string())?;
+    alerts.insert_one(&alert, None).await?;
 
     let _ = state.events_tx.send("alertsUpdated".to_string());
 
@@ -70,11 +141,15 @@ pub async fn delete_alert_for_symbol(
     symbol: &str,
     alert_id: ObjectId,
 ) -> Result<(), String> {
-    let sym = symbol.to_uppercase();
+    let sym = stocks_service::normalize_symbol(symbol);
     let alerts = state.db.collection::<Alert>("alerts");
 
     alerts
-        .delete_one(doc! { "_id": alert_id, "user_id": user_id, "symbol": &sym }, None)
+        .update_one(
+            doc! { "_id": alert_id, "user_id": user_id, "symbol": &sym, "deleted_at": null },
+            soft_delete_update(state.clock.as_ref()),
+            None,
+        )
         .await
         .map_err(|e| e.to_string())?;
 
@@ -91,7 +166,11 @@ pub async fn delete_alert_global(
     let alerts = state.db.collection::<Alert>("alerts");
 
     alerts
-        .delete_one(doc! { "_id": alert_id, "user_id": user_id }, None)
+        .update_one(
+            doc! { "_id": alert_id, "user_id": user_id, "deleted_at": null },
+            soft_delete_update(state.clock.as_ref()),
+            None,
+        )
         .await
         .map_err(|e| e.to_string())?;
 
@@ -100,18 +179,65 @@ pub async fn delete_alert_global(
     Ok(())
 }
 
+/// Soft-deletes every live alert the user has on `symbol`; returns how many.
+pub async fn delete_symbol_alerts(state: &AppState, user_id: ObjectId, symbol: &str) -> Result<u64, String> {
+    let sym = stocks_service::normalize_symbol(symbol);
+    let alerts = state.db.collection::<Alert>("alerts");
+
+    let res = alerts
+        .update_many(
+            doc! { "user_id": user_id, "symbol": &sym, "deleted_at": null },
+            soft_delete_update(state.clock.as_ref()),
+            None,
+        )
+        .await
+        .map_err(|e| e.to_string())?;
+
+    if res.modified_count > 0 {
+        let _ = state.events_tx.send("alertsUpdated".to_string());
+    }
+
+    Ok(res.modified_count)
+}
+
+/// Undoes a delete made within the last `RESTORE_WINDOW_SECS`; false when there
+/// is nothing (or nothing recent enough) to restore.
+pub async fn restore_alert(
+    state: &AppState,
+    user_id: ObjectId,
+    alert_id: ObjectId,
+) -> Result<bool, String> {
+    let alerts = state.db.collection::<Alert>("alerts");
+    let since = BsonDateTime::from_millis(state.clock.bson_now().timestamp_millis() - RESTORE_WINDOW_SECS * 1000);
+
+    let res = alerts
+        .update_one(
+            doc! { "_id": alert_id, "user_id": user_id, "deleted_at": { "$gte": since } },
+            doc! { "$unset": { "deleted_at": "" } },
+            None,
+        )
+        .await
+        .map_err(|e| e.to_string())?;
+
+    if res.modified_count > 0 {
+        let _ = state.events_tx.send("alertsUpdated".to_string());
+    }
+
+    Ok(res.modified_count > 0)
+}
+
 pub async fn trigger_alert(
     state: &AppState,
     user_id: ObjectId,
     alert_id: ObjectId,
 ) -> Result<bool, String> {
     let alerts = state.db.collection::<Alert>("alerts");
-    let now = Utc::now().timestamp();
 
     let res = alerts
         .update_one(
-            doc! { "_id": alert_id, "user_id": user_id, "triggered": false },
-            doc! { "$set": { "triggered": true, "triggered_at": now } },
+            doc! { "_id": alert_id, "user_id": user_id, "triggered": false, "deleted_at": null },
+            // reported by the browser; its price isn't trusted
+            triggered_update(state.clock.as_ref(), None),
             None,
         )
         .await
@@ -119,6 +245,10 @@ pub async fn trigger_alert(
 
     let _ = state.events_tx.send("alertsUpdated".to_string());
 
+    if res.matched_count > 0 {
+        metrics::counter!(ALERTS_TRIGGERED_TOTAL, "source" => "client").increment(1);
+    }
+
     Ok(res.matched_count > 0)
 }
 
@@ -130,7 +260,7 @@ pub async fn list_user_alerts_grouped(
     let find_opts = FindOptions::builder().sort(doc! { "created_at": -1 }).build();
 
     let mut cursor = alerts
-        .find(doc! { "user_id": user_id }, find_opts)
+        .find(doc! { "user_id": user_id, "deleted_at": null }, find_opts)
         .await
         .map_err(|e| e.to_string())?;
 
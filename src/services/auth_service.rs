@@ -1,8 +1,8 @@
 use std::collections::HashMap;
 
-use axum_extra::extract::cookie::{Cookie, SameSite};
+use axum_extra::extract::cookie::Cookie;
 use bcrypt::{hash, verify, DEFAULT_COST};
-use chrono::{Duration, Utc};
+use chrono::Duration;
 use jsonwebtoken::{encode, EncodingKey, Header};
 use mongodb::bson::{doc, oid::ObjectId};
 
@@ -14,40 +14,98 @@ pub type FieldErrors = HashMap<String, String>;
 struct Claims {
     sub: String,
     exp: usize,
+    ver: i32,
 }
 
-pub fn make_jwt_with_days(state: &AppState, user_id: &ObjectId, days: i64) -> Result<String, String> {
-    let exp = (Utc::now() + Duration::days(days)).timestamp() as usize;
+pub fn make_jwt_with_days(
+    state: &AppState,
+    user_id: &ObjectId,
+    token_version: i32,
+    days: i64,
+) -> Result<String, String> {
+    let exp = (state.clock.now() + Duration::days(days)).timestamp();
+    make_jwt_expiring_at(state, user_id, token_version, exp)
+}
 
+/// Like `make_jwt_with_days` but with a fixed expiry (unix seconds), e.g. to reissue a
+/// session without changing how long it lasts.
+pub fn make_jwt_expiring_at(
+    state: &AppState,
+    user_id: &ObjectId,
+    token_version: i32,
+    exp: i64,
+) -> Result<String, String> {
     let claims = Claims {
         sub: user_id.to_hex(),
-        exp,
+        exp: exp as usize,
+        ver: token_version,
     };
 
     encode(
-        &Header::default(),
+        &Header::new(state.settings.jwt_algorithm),
         &claims,
         &EncodingKey::from_secret(state.settings.jwt_secret.as_bytes()),
     )
     .map_err(|e| e.to_string())
 }
 
-pub fn auth_cookie(state: &AppState, token: String) -> Cookie<'static> {
-    let mut cookie = Cookie::new(state.settings.jwt_cookie_name.clone(), token);
-    cookie.set_http_only(true);
-    cookie.set_same_site(SameSite::Lax);
+/// Rules for a new password: `password_min_len` characters, plus lower case,
+/// upper case and a digit when `strong_passwords` is on. The error names the
+/// first rule that failed. Existing passwords are never re-checked at login.
+pub fn validate_password(state: &AppState, password: &str) -> Result<(), String> {
+    let min = state.settings.password_min_len;
+    if password.chars().count() < min {
+        return Err(format!("Password must be at least {min} characters."));
+    }
+
+    if state.settings.strong_passwords {
+        if !password.chars().any(|c| c.is_lowercase()) {
+            return Err("Password must include a lower-case letter.".to_string());
+        }
+        if !password.chars().any(|c| c.is_uppercase()) {
+            return Err("Password must include an upper-case letter.".to_string());
+        }
+        if !password.chars().any(|c| c.is_ascii_digit()) {
+            return Err("Password must include a digit.".to_string());
+        }
+    }
+
+    Ok(())
+}
+
+// Name, path, domain and flags shared by the set and clear cookies; a removal only
+// reaches the browser's cookie if these match what was set.
+fn base_auth_cookie(state: &AppState, value: String) -> Cookie<'static> {
+    let settings = &state.settings;
+    let mut cookie = Cookie::new(settings.jwt_cookie_name.clone(), value);
     cookie.set_path("/");
-    if state.settings.cookie_secure {
+    cookie.set_http_only(true);
+    cookie.set_same_site(settings.cookie_same_site);
+    if let Some(domain) = &settings.cookie_domain {
+        cookie.set_domain(domain.clone());
+    }
+    if settings.auth_cookie_secure() {
         cookie.set_secure(true);
     }
     cookie
 }
 
+pub fn auth_cookie(state: &AppState, token: String, days: i64) -> Cookie<'static> {
+    let mut cookie = base_auth_cookie(state, token);
+    cookie.set_max_age(time::Duration::days(days));
+    cookie
+}
+
+/// Auth cookie that expires together with a token issued by `make_jwt_expiring_at`.
+pub fn auth_cookie_expiring_at(state: &AppState, token: String, exp: i64) -> Cookie<'static> {
+    let mut cookie = base_auth_cookie(state, token);
+    let remaining = (exp - state.clock.now().timestamp()).max(0);
+    cookie.set_max_age(time::Duration::seconds(remaining));
+    cookie
+}
+
 pub fn clear_auth_cookie(state: &AppState) -> Cookie<'static> {
-    let mut cookie = Cookie::new(state.settings.jwt_cookie_name.clone(), "");
-    cookie.set_path("/");
-    cookie.set_http_only(true);
-    cookie.set_same_site(SameSite::Lax);
+    let mut cookie = base_auth_cookie(state, String::new());
     cookie.make_removal();
     cookie
 }
@@ -127,6 +185,7 @@ pub async fn register_user(
                 "email": email,
                 "username": username,
                 "password_hash": pw_hash,
+                "token_version": 0,
             },
             None,
         )
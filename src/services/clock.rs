@@ -0,0 +1,64 @@
+//! Where services get "now" from.
+//!
+//! `AppState.clock` is a `RealClock` in the app; tests swap in a `FixedClock`
+//! to pin or step time for cooldowns, undo windows and token expiry.
+
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Duration, Utc};
+use mongodb::bson::DateTime as BsonDateTime;
+
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+
+    /// Unix seconds, the format our documents store.
+    fn timestamp(&self) -> i64 {
+        self.now().timestamp()
+    }
+
+    /// For BSON date fields (the ones TTL indexes watch).
+    fn bson_now(&self) -> BsonDateTime {
+        BsonDateTime::from_millis(self.now().timestamp_millis())
+    }
+}
+
+pub type SharedClock = Arc<dyn Clock>;
+
+/// The system clock.
+pub struct RealClock;
+
+impl Clock for RealClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+pub fn real() -> SharedClock {
+    Arc::new(RealClock)
+}
+
+/// A clock that only moves when told to.
+pub struct FixedClock {
+    at: Mutex<DateTime<Utc>>,
+}
+
+impl FixedClock {
+    pub fn new(at: DateTime<Utc>) -> Self {
+        Self { at: Mutex::new(at) }
+    }
+
+    pub fn set(&self, at: DateTime<Utc>) {
+        *self.at.lock().unwrap() = at;
+    }
+
+    pub fn advance(&self, by: Duration) {
+        let mut at = self.at.lock().unwrap();
+        *at += by;
+    }
+}
+
+impl Clock for FixedClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.at.lock().unwrap()
+    }
+}
@@ -1,10 +1,42 @@
+use std::time::Duration;
+
 use mongodb::{
     bson::doc,
+    error::{Error, ErrorKind},
     options::IndexOptions,
-    Database, IndexModel,
+    Collection, Database, IndexModel,
 };
 
-pub async fn ensure_indexes(db: &Database) -> Result<(), String> {
+use crate::config::Settings;
+
+pub const ALERTS_TTL_INDEX: &str = "alerts_triggered_ttl";
+pub const ALERTS_DELETED_TTL_INDEX: &str = "alerts_deleted_ttl";
+pub const POSITIONS_OPEN_INDEX: &str = "positions_open_user_symbol";
+
+// Well past alerts_service::RESTORE_WINDOW_SECS, so an undo never races the purge.
+pub const DELETED_ALERT_RETENTION: Duration = Duration::from_secs(24 * 60 * 60);
+
+// IndexOptionsConflict: same keys/name, different options (e.g. a changed TTL).
+fn is_index_options_conflict(e: &Error) -> bool {
+    matches!(&*e.kind, ErrorKind::Command(c) if c.code == 85 || c.code == 86)
+}
+
+// IndexNotFound, or NamespaceNotFound when the collection doesn't exist yet.
+fn is_missing_index(e: &Error) -> bool {
+    matches!(&*e.kind, ErrorKind::Command(c) if c.code == 26 || c.code == 27)
+}
+
+// Drops an index that a newer one replaces; already gone is fine, anything else
+// is returned so a stale unique index can't quietly keep rejecting writes.
+async fn drop_superseded_index(col: &Collection<mongodb::bson::Document>, name: &str) -> Result<(), String> {
+    match col.drop_index(name, None).await {
+        Ok(()) => Ok(()),
+        Err(e) if is_missing_index(&e) => Ok(()),
+        Err(e) => Err(format!("dropping index {name}: {e}")),
+    }
+}
+
+pub async fn ensure_indexes(db: &Database, settings: &Settings) -> Result<(), String> {
     {
         let col = db.collection::<mongodb::bson::Document>("users");
         let model = IndexModel::builder()
@@ -19,9 +51,30 @@ pub async fn ensure_indexes(db: &Database) -> Result<(), String> {
 
     {
         let col = db.collection::<mongodb::bson::Document>("positions");
+
+        // the old full unique index has the same keys; it must go first
+        drop_superseded_index(&col, "user_id_1_symbol_1").await?;
+
+        // one open row per user/symbol; rows holding nothing (closed, or a sell
+        // waiting on delete_empty_position) fall outside the filter and may pile up
         let model = IndexModel::builder()
             .keys(doc! { "user_id": 1, "symbol": 1 })
-            .options(IndexOptions::builder().unique(true).build())
+            .options(
+                IndexOptions::builder()
+                    .name(POSITIONS_OPEN_INDEX.to_string())
+                    .unique(true)
+                    .partial_filter_expression(doc! { "qty": { "$gt": 0 } })
+                    .build(),
+            )
+            .build();
+
+        col.create_index(model, None)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        // a partial index can't serve queries without the qty filter
+        let model = IndexModel::builder()
+            .keys(doc! { "user_id": 1, "updated_at": -1 })
             .build();
 
         col.create_index(model, None)
@@ -47,7 +100,165 @@ pub async fn ensure_indexes(db: &Database) -> Result<(), String> {
             .build();
 
         let _ = col.create_index(model, None).await;
+
+        // Per-symbol alert lists filter on { user_id, symbol, deleted_at: null }; the
+        // user_id prefix also serves the user-wide alerts page.
+        let model = IndexModel::builder()
+            .keys(doc! { "user_id": 1, "symbol": 1, "deleted_at": 1 })
+            .build();
+
+        col.create_index(model, None)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        // superseded by the index above
+        drop_superseded_index(&col, "user_id_1_symbol_1").await?;
+
+        // soft-deleted alerts are purged once they can no longer be restored
+        let model = IndexModel::builder()
+            .keys(doc! { "deleted_at": 1 })
+            .options(
+                IndexOptions::builder()
+                    .name(ALERTS_DELETED_TTL_INDEX.to_string())
+                    .expire_after(DELETED_ALERT_RETENTION)
+                    .build(),
+            )
+            .build();
+
+        col.create_index(model, None)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+
+    // accounts: keyed by the user's id as _id, so every lookup already uses
+    // the built-in _id index and needs nothing extra here.
+
+    ensure_alerts_ttl(db, settings.alert_retention_days).await?;
+
+    {
+        let col = db.collection::<mongodb::bson::Document>("watchlists");
+        let model = IndexModel::builder()
+            .keys(doc! { "user_id": 1, "symbol": 1 })
+            .options(IndexOptions::builder().unique(true).build())
+            .build();
+
+        col.create_index(model, None)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+
+    {
+        let col = db.collection::<mongodb::bson::Document>("portfolio_snapshots");
+        let model = IndexModel::builder()
+            .keys(doc! { "user_id": 1, "date": 1 })
+            .options(IndexOptions::builder().unique(true).build())
+            .build();
+
+        col.create_index(model, None)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+
+    {
+        // the unique key is what makes a replayed deposit a no-op
+        let col = db.collection::<mongodb::bson::Document>("deposit_keys");
+        let model = IndexModel::builder()
+            .keys(doc! { "user_id": 1, "key": 1 })
+            .options(IndexOptions::builder().unique(true).build())
+            .build();
+
+        col.create_index(model, None)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+
+    {
+        // one dividend per position per payout period, however many instances run the task
+        let col = db.collection::<mongodb::bson::Document>("cash_events");
+        let model = IndexModel::builder()
+            .keys(doc! { "user_id": 1, "kind": 1, "symbol": 1, "period": 1 })
+            .options(IndexOptions::builder().unique(true).build())
+            .build();
+
+        col.create_index(model, None)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+
+    {
+        // login history is listed and pruned per user, newest first
+        let col = db.collection::<mongodb::bson::Document>("sessions");
+        let model = IndexModel::builder()
+            .keys(doc! { "user_id": 1, "created_at": -1 })
+            .build();
+
+        col.create_index(model, None)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+
+    {
+        // lapsed background-task leases; a live holder keeps pushing expires_at out
+        let col = db.collection::<mongodb::bson::Document>("monitor_leases");
+        let model = IndexModel::builder()
+            .keys(doc! { "expires_at": 1 })
+            .options(IndexOptions::builder().expire_after(Duration::from_secs(0)).build())
+            .build();
+
+        col.create_index(model, None)
+            .await
+            .map_err(|e| e.to_string())?;
     }
 
     Ok(())
 }
+
+/// Triggered alerts expire `retention_days` after `triggered_on`; untriggered
+/// alerts are outside the partial filter and are kept forever. Safe to run on
+/// every start: a changed retention window is applied in place via collMod.
+pub async fn ensure_alerts_ttl(db: &Database, retention_days: u64) -> Result<(), String> {
+    let secs = retention_days * 24 * 60 * 60;
+    let col = db.collection::<mongodb::bson::Document>("alerts");
+    let model = IndexModel::builder()
+        .keys(doc! { "triggered_on": 1 })
+        .options(
+            IndexOptions::builder()
+                .name(ALERTS_TTL_INDEX.to_string())
+                .expire_after(Duration::from_secs(secs))
+                .partial_filter_expression(doc! { "triggered": true })
+                .build(),
+        )
+        .build();
+
+    match col.create_index(model, None).await {
+        Ok(_) => Ok(()),
+        Err(e) if is_index_options_conflict(&e) => db
+            .run_command(
+                doc! {
+                    "collMod": "alerts",
+                    "index": { "name": ALERTS_TTL_INDEX, "expireAfterSeconds": secs as i64 },
+                },
+                None,
+            )
+            .await
+            .map(|_| ())
+            .map_err(|e| e.to_string()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Positions written before `created_at` existed get their last update time as
+/// the best available "held since". Only touches documents missing the field.
+pub async fn backfill_position_created_at(db: &Database) -> Result<u64, String> {
+    let col = db.collection::<mongodb::bson::Document>("positions");
+    let res = col
+        .update_many(
+            doc! { "created_at": { "$exists": false } },
+            vec![doc! { "$set": { "created_at": "$updated_at" } }],
+            None,
+        )
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(res.modified_count)
+}
@@ -0,0 +1,139 @@
+//! Simulated dividends for long-term holds. With DIVIDEND_YIELD_PCT set, every
+//! DIVIDEND_INTERVAL_SECS each position is paid its slice of the yearly yield on
+//! its market value, recorded in `cash_events` and credited to cash.
+
+use std::collections::HashSet;
+use std::time::Duration;
+
+use chrono::Utc;
+use futures_util::StreamExt;
+use mongodb::bson::{doc, oid::ObjectId};
+use tokio::time;
+
+use crate::{AppState, models::{CashEvent, Position}};
+
+use super::account_service;
+
+const SECS_PER_YEAR: f64 = 365.0 * 86_400.0;
+
+/// Dividend for one interval on `qty` shares at `price`, given an annual yield in percent.
+pub fn dividend_amount(qty: i64, price: f64, yield_pct: f64, interval_secs: u64) -> f64 {
+    let annual = (qty as f64) * price * yield_pct / 100.0;
+    let amount = annual * (interval_secs as f64) / SECS_PER_YEAR;
+    // whole cents, like the deposit form
+    (amount * 100.0).round() / 100.0
+}
+
+pub fn spawn_dividend_task(state: AppState) {
+    if state.settings.dividend_yield_pct.is_none() {
+        return;
+    }
+
+    tokio::spawn(async move {
+        let secs = state.settings.dividend_interval_secs;
+        let mut interval = time::interval(Duration::from_secs(secs));
+
+        loop {
+            interval.tick().await;
+
+            match run_tick(&state).await {
+                Ok(0) => {}
+                Ok(n) => tracing::info!("[dividend-monitor] paid {} dividends", n),
+                Err(e) => eprintln!("[dividend-monitor] tick error: {}", e),
+            }
+        }
+    });
+}
+
+// Records the payout; false when this period was already paid (a restart or a second instance).
+async fn claim_dividend(state: &AppState, event: &CashEvent) -> Result<bool, String> {
+    let events = state.db.collection::<CashEvent>("cash_events");
+    match events.insert_one(event, None).await {
+        Ok(_) => Ok(true),
+        Err(e) if e.to_string().contains("E11000") => Ok(false),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+async fn release_dividend(state: &AppState, id: ObjectId) {
+    let events = state.db.collection::<CashEvent>("cash_events");
+    let _ = events.delete_one(doc! { "_id": id }, None).await;
+}
+
+/// Pays every held position for the current period; returns how many were paid.
+pub async fn run_tick(state: &AppState) -> Result<u64, String> {
+    let Some(yield_pct) = state.settings.dividend_yield_pct else {
+        return Ok(0);
+    };
+    let interval_secs = state.settings.dividend_interval_secs;
+
+    let positions = state.db.collection::<Position>("positions");
+    let mut cursor = positions
+        .find(doc! { "qty": { "$gt": 0 } }, None)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let mut held: Vec<Position> = Vec::new();
+    let mut symbols: HashSet<String> = HashSet::new();
+    while let Some(item) = cursor.next().await {
+        let p = item.map_err(|e| e.to_string())?;
+        symbols.insert(p.symbol.to_uppercase());
+        held.push(p);
+    }
+
+    let symbols: Vec<String> = symbols.into_iter().collect();
+    let quotes = state.finnhub.quotes(&symbols).await;
+
+    let now = Utc::now().timestamp();
+    let period = now / interval_secs as i64;
+    let mut paid = 0;
+
+    for p in held {
+        let sym = p.symbol.to_uppercase();
+        // fall back to cost when the quote is unavailable, like the snapshot task
+        let price = quotes
+            .get(&sym)
+            .map(|q| q.c)
+            .filter(|c| *c > 0.0)
+            .unwrap_or(p.avg_price);
+
+        let amount = dividend_amount(p.qty, price, yield_pct, interval_secs);
+        if amount <= 0.0 {
+            continue;
+        }
+
+        let event = CashEvent {
+            id: ObjectId::new(),
+            user_id: p.user_id,
+            kind: "dividend".to_string(),
+            symbol: sym,
+            amount,
+            period,
+            created_at: now,
+        };
+
+        match claim_dividend(state, &event).await {
+            Ok(true) => {}
+            Ok(false) => continue,
+            Err(e) => {
+                eprintln!("[dividend-monitor] user {} failed: {}", p.user_id, e);
+                continue;
+            }
+        }
+
+        if let Err(e) = account_service::credit_cash(state, p.user_id, amount).await {
+            // nothing was credited, so the next tick in this period may retry
+            release_dividend(state, event.id).await;
+            eprintln!("[dividend-monitor] user {} failed: {}", p.user_id, e);
+            continue;
+        }
+
+        paid += 1;
+    }
+
+    if paid > 0 {
+        let _ = state.events_tx.send("cashUpdated".to_string());
+    }
+
+    Ok(paid)
+}
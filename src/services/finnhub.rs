@@ -1,17 +1,204 @@
-use reqwest::Client;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use futures_util::{stream, StreamExt};
+use reqwest::{Client, StatusCode};
 use serde::{Deserialize, Serialize};
 
+use super::metrics::{FINNHUB_REQUESTS_TOTAL, FINNHUB_REQUEST_SECONDS};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FinnhubError {
+    MissingKey,
+    RateLimited,
+    // unknown symbol (HTTP 404 or Finnhub's all-zero quote)
+    NotFound,
+    Http(StatusCode),
+    Network(String),
+    Decode(String),
+}
+
+impl fmt::Display for FinnhubError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FinnhubError::MissingKey => write!(f, "FINNHUB_API_KEY is missing in .env"),
+            FinnhubError::RateLimited => write!(f, "Finnhub rate limit reached"),
+            FinnhubError::NotFound => write!(f, "Unknown symbol"),
+            FinnhubError::Http(status) => write!(f, "Finnhub request failed: {status}"),
+            FinnhubError::Network(e) => write!(f, "Finnhub unreachable: {e}"),
+            FinnhubError::Decode(e) => write!(f, "Unexpected Finnhub response: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for FinnhubError {}
+
+impl FinnhubError {
+    pub fn from_status(status: StatusCode) -> Self {
+        match status {
+            StatusCode::TOO_MANY_REQUESTS => FinnhubError::RateLimited,
+            StatusCode::NOT_FOUND => FinnhubError::NotFound,
+            s => FinnhubError::Http(s),
+        }
+    }
+
+    // true when retrying later could succeed (as opposed to a bad symbol/key)
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            FinnhubError::RateLimited | FinnhubError::Http(_) | FinnhubError::Network(_)
+        )
+    }
+}
+
+// Search-as-you-type repeats the same queries a lot; answers barely change within a minute.
+const SEARCH_CACHE_TTL: Duration = Duration::from_secs(60);
+
+type CachedSearch = (Instant, Arc<SearchResponse>);
+
+/// Successful search responses keyed by normalized query, dropped after `ttl`.
+#[derive(Clone)]
+pub struct SearchCache {
+    ttl: Duration,
+    entries: Arc<Mutex<HashMap<String, CachedSearch>>>,
+}
+
+impl SearchCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Case and whitespace don't change Finnhub's answer, so "  apple  Inc" == "apple inc".
+    pub fn normalize(query: &str) -> String {
+        query.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase()
+    }
+
+    pub fn get(&self, query: &str) -> Option<Arc<SearchResponse>> {
+        let entries = self.entries.lock().unwrap();
+        entries
+            .get(&Self::normalize(query))
+            .filter(|(at, _)| at.elapsed() < self.ttl)
+            .map(|(_, resp)| resp.clone())
+    }
+
+    pub fn insert(&self, query: &str, resp: Arc<SearchResponse>) {
+        let mut entries = self.entries.lock().unwrap();
+        // Expired entries are only pruned here, which is enough to keep the map small.
+        entries.retain(|_, (at, _)| at.elapsed() < self.ttl);
+        entries.insert(Self::normalize(query), (Instant::now(), resp));
+    }
+}
+
+// Portfolio renders and the alert monitor quote the same symbols; sharing answers for a few
+// seconds saves calls. Must stay below the monitor's poll interval so every tick sees a fresh
+// price (see alert_monitor::POLL_INTERVAL).
+pub const QUOTE_CACHE_TTL: Duration = Duration::from_secs(3);
+
+/// Successful quotes keyed by upper-cased symbol, dropped after `ttl`.
+#[derive(Clone)]
+pub struct QuoteCache {
+    ttl: Duration,
+    entries: Arc<Mutex<HashMap<String, (Instant, QuoteResponse)>>>,
+}
+
+impl QuoteCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub fn get(&self, symbol: &str) -> Option<QuoteResponse> {
+        let entries = self.entries.lock().unwrap();
+        entries
+            .get(&symbol.to_uppercase())
+            .filter(|(at, _)| at.elapsed() < self.ttl)
+            .map(|(_, quote)| quote.clone())
+    }
+
+    pub fn insert(&self, symbol: &str, quote: QuoteResponse) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|_, (at, _)| at.elapsed() < self.ttl);
+        entries.insert(symbol.to_uppercase(), (Instant::now(), quote));
+    }
+}
+
+// Whether a symbol exists barely changes, so the answer outlives any price by far.
+const KNOWN_SYMBOL_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// Whether Finnhub had a quote for a symbol, keyed by upper-cased symbol, dropped after `ttl`.
+#[derive(Clone)]
+pub struct KnownSymbols {
+    ttl: Duration,
+    entries: Arc<Mutex<HashMap<String, (Instant, bool)>>>,
+}
+
+impl KnownSymbols {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub fn get(&self, symbol: &str) -> Option<bool> {
+        let entries = self.entries.lock().unwrap();
+        entries
+            .get(&symbol.to_uppercase())
+            .filter(|(at, _)| at.elapsed() < self.ttl)
+            .map(|(_, exists)| *exists)
+    }
+
+    pub fn insert(&self, symbol: &str, exists: bool) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|_, (at, _)| at.elapsed() < self.ttl);
+        entries.insert(symbol.to_uppercase(), (Instant::now(), exists));
+    }
+}
+
+// Readiness probes come every few seconds; one real call per window tells us just as well
+// whether Finnhub answers, without spending the rate limit on it.
+pub const UPSTREAM_CHECK_TTL: Duration = Duration::from_secs(30);
+
+type UpstreamCheck = (Instant, Result<(), FinnhubError>);
+
+// Finnhub's free tier allows 30 calls/s; a handful in flight keeps batches well under it.
+pub const MAX_CONCURRENT_QUOTES: usize = 8;
+
+const DEFAULT_BASE_URL: &str = "https://finnhub.io/api/v1";
+
 #[derive(Clone)]
 pub struct FinnhubClient {
     http: Client,
     api_key: String,
+    base_url: String,
+    search_cache: SearchCache,
+    quote_cache: QuoteCache,
+    known_symbols: KnownSymbols,
+    upstream_check: Arc<Mutex<Option<UpstreamCheck>>>,
 }
 
 impl FinnhubClient {
     pub fn new(api_key: String) -> Self {
+        Self::with_base_url(api_key, DEFAULT_BASE_URL)
+    }
+
+    /// Points the REST calls somewhere else, e.g. a local stub server in tests.
+    pub fn with_base_url(api_key: String, base_url: &str) -> Self {
         Self {
             http: Client::new(),
             api_key,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            search_cache: SearchCache::new(SEARCH_CACHE_TTL),
+            quote_cache: QuoteCache::new(QUOTE_CACHE_TTL),
+            known_symbols: KnownSymbols::new(KNOWN_SYMBOL_TTL),
+            upstream_check: Arc::new(Mutex::new(None)),
         }
     }
 
@@ -19,60 +206,225 @@ impl FinnhubClient {
         !self.api_key.trim().is_empty()
     }
 
-    pub async fn search(&self, q: &str) -> Result<SearchResponse, String> {
+    fn record_call<T>(endpoint: &'static str, started: Instant, res: &Result<T, FinnhubError>) {
+        let outcome = match res {
+            Ok(_) => "ok",
+            Err(FinnhubError::RateLimited) => "rate_limited",
+            Err(FinnhubError::NotFound) => "not_found",
+            Err(_) => "error",
+        };
+        metrics::counter!(FINNHUB_REQUESTS_TOTAL, "endpoint" => endpoint, "outcome" => outcome)
+            .increment(1);
+        metrics::histogram!(FINNHUB_REQUEST_SECONDS, "endpoint" => endpoint)
+            .record(started.elapsed().as_secs_f64());
+    }
+
+    pub async fn search(&self, q: &str) -> Result<Arc<SearchResponse>, FinnhubError> {
         if !self.has_key() {
-            return Err("FINNHUB_API_KEY is missing in .env".to_string());
+            return Err(FinnhubError::MissingKey);
+        }
+
+        if let Some(cached) = self.search_cache.get(q) {
+            return Ok(cached);
         }
 
-        let url = "https://finnhub.io/api/v1/search";
+        let started = Instant::now();
+        let res = self.fetch_search(q).await;
+        Self::record_call("search", started, &res);
+
+        let resp = Arc::new(res?);
+        self.search_cache.insert(q, resp.clone());
+        Ok(resp)
+    }
+
+    async fn fetch_search(&self, q: &str) -> Result<SearchResponse, FinnhubError> {
+        let url = format!("{}/search", self.base_url);
         let res = self
             .http
             .get(url)
             .query(&[("q", q), ("token", &self.api_key)])
             .send()
             .await
-            .map_err(|e| e.to_string())?;
+            .map_err(|e| FinnhubError::Network(e.to_string()))?;
 
         if !res.status().is_success() {
-            let status = res.status();
-            let body = res.text().await.unwrap_or_default();
-            return Err(format!("Finnhub search failed: {status} {body}"));
+            return Err(FinnhubError::from_status(res.status()));
         }
 
-        res.json::<SearchResponse>().await.map_err(|e| e.to_string())
+        res.json::<SearchResponse>()
+            .await
+            .map_err(|e| FinnhubError::Decode(e.to_string()))
     }
 
-    pub async fn quote(&self, symbol: &str) -> Result<QuoteResponse, String> {
+    pub async fn quote(&self, symbol: &str) -> Result<QuoteResponse, FinnhubError> {
         if !self.has_key() {
-            return Err("FINNHUB_API_KEY is missing in .env".to_string());
+            return Err(FinnhubError::MissingKey);
+        }
+
+        if let Some(cached) = self.quote_cache.get(symbol) {
+            return Ok(cached);
         }
 
-        let url = "https://finnhub.io/api/v1/quote";
+        let started = Instant::now();
+        let res = self.fetch_quote(symbol).await;
+        Self::record_call("quote", started, &res);
+
+        let quote = res?;
+        self.quote_cache.insert(symbol, quote.clone());
+        Ok(quote)
+    }
+
+    /// Whether Finnhub answers a quote. The outcome is remembered for
+    /// `UPSTREAM_CHECK_TTL`, so health probes make at most one call per window.
+    pub async fn check_upstream(&self) -> Result<(), FinnhubError> {
+        if !self.has_key() {
+            return Err(FinnhubError::MissingKey);
+        }
+
+        if let Some((at, res)) = self.upstream_check.lock().unwrap().as_ref()
+            && at.elapsed() < UPSTREAM_CHECK_TTL
+        {
+            return res.clone();
+        }
+
+        let started = Instant::now();
+        let res = self.fetch_quote("AAPL").await;
+        Self::record_call("quote", started, &res);
+
+        let res = res.map(|_| ());
+        *self.upstream_check.lock().unwrap() = Some((Instant::now(), res.clone()));
+        res
+    }
+
+    async fn fetch_quote(&self, symbol: &str) -> Result<QuoteResponse, FinnhubError> {
+        let url = format!("{}/quote", self.base_url);
         let res = self
             .http
             .get(url)
             .query(&[("symbol", symbol), ("token", &self.api_key)])
             .send()
             .await
-            .map_err(|e| e.to_string())?;
+            .map_err(|e| FinnhubError::Network(e.to_string()))?;
 
         if !res.status().is_success() {
-            let status = res.status();
-            let body = res.text().await.unwrap_or_default();
-            return Err(format!("Finnhub quote failed: {status} {body}"));
+            return Err(FinnhubError::from_status(res.status()));
         }
 
-        res.json::<QuoteResponse>().await.map_err(|e| e.to_string())
+        let quote = res
+            .json::<QuoteResponse>()
+            .await
+            .map_err(|e| FinnhubError::Decode(e.to_string()))?;
+
+        // Finnhub answers unknown symbols with 200 and an all-zero quote
+        if quote.c == 0.0 && quote.t == 0 {
+            return Err(FinnhubError::NotFound);
+        }
+
+        Ok(quote)
+    }
+
+    /// Whether Finnhub knows `symbol`, i.e. quotes it with a real price. Yes/no answers
+    /// are cached; a missing key or a transient failure comes back as the error.
+    pub async fn symbol_exists(&self, symbol: &str) -> Result<bool, FinnhubError> {
+        if let Some(known) = self.known_symbols.get(symbol) {
+            return Ok(known);
+        }
+
+        let exists = match self.quote(symbol).await {
+            Ok(q) => q.c.is_finite() && q.c > 0.0,
+            Err(FinnhubError::NotFound) => false,
+            Err(e) => return Err(e),
+        };
+        self.known_symbols.insert(symbol, exists);
+        Ok(exists)
+    }
+
+    /// The company profile for `symbol`, straight from Finnhub; `Ok(None)` when it has
+    /// none. Not cached here: stocks_service keeps profiles in Mongo.
+    pub async fn company_profile(&self, symbol: &str) -> Result<Option<CompanyProfile>, FinnhubError> {
+        if !self.has_key() {
+            return Err(FinnhubError::MissingKey);
+        }
+
+        let started = Instant::now();
+        let res = self.fetch_profile(symbol).await;
+        Self::record_call("profile", started, &res);
+
+        match res {
+            Ok(p) => Ok(Some(p)),
+            Err(FinnhubError::NotFound) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn fetch_profile(&self, symbol: &str) -> Result<CompanyProfile, FinnhubError> {
+        let url = format!("{}/stock/profile2", self.base_url);
+        let res = self
+            .http
+            .get(url)
+            .query(&[("symbol", symbol), ("token", &self.api_key)])
+            .send()
+            .await
+            .map_err(|e| FinnhubError::Network(e.to_string()))?;
+
+        if !res.status().is_success() {
+            return Err(FinnhubError::from_status(res.status()));
+        }
+
+        let profile = res
+            .json::<CompanyProfile>()
+            .await
+            .map_err(|e| FinnhubError::Decode(e.to_string()))?;
+
+        // unknown symbols come back as 200 with an empty object
+        if profile.ticker.is_empty() && profile.name.is_empty() {
+            return Err(FinnhubError::NotFound);
+        }
+
+        Ok(profile)
+    }
+
+    // Fetches the quotes concurrently, at most MAX_CONCURRENT_QUOTES in flight so a
+    // long list doesn't burst past the rate limit; symbols whose quote failed are left out.
+    pub async fn quotes(&self, symbols: &[String]) -> HashMap<String, QuoteResponse> {
+        let futs: Vec<_> = symbols
+            .iter()
+            .map(|s| async move { (s.clone(), self.quote(s).await) })
+            .collect();
+
+        let results: Vec<(String, Result<QuoteResponse, FinnhubError>)> = stream::iter(futs)
+            .buffer_unordered(MAX_CONCURRENT_QUOTES)
+            .collect()
+            .await;
+
+        results
+            .into_iter()
+            .filter_map(|(s, res)| res.ok().map(|q| (s, q)))
+            .collect()
     }
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct CompanyProfile {
+    #[serde(default)]
+    pub ticker: String,
+    #[serde(default)]
+    pub name: String,
+    // Finnhub's industry classification, e.g. "Technology"; what we group sectors by
+    #[serde(default, rename = "finnhubIndustry")]
+    pub finnhub_industry: String,
+    // image URL; empty when Finnhub has none
+    #[serde(default)]
+    pub logo: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct SearchResponse {
     pub count: i64,
     pub result: Vec<SearchItem>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct SearchItem {
     pub description: String,
 
@@ -85,13 +437,20 @@ pub struct SearchItem {
     pub kind: String,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+// Finnhub sends `null` rather than 0 for the change of a symbol it doesn't know.
+fn null_as_zero<'de, D: serde::Deserializer<'de>>(de: D) -> Result<f64, D::Error> {
+    Ok(Option::<f64>::deserialize(de)?.unwrap_or(0.0))
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct QuoteResponse {
     // current
     pub c: f64,
-    // change
+    // change; null for unknown symbols
+    #[serde(default, deserialize_with = "null_as_zero")]
     pub d: f64,
-    // percent change
+    // percent change; null for unknown symbols
+    #[serde(default, deserialize_with = "null_as_zero")]
     pub dp: f64,
     // high
     pub h: f64,
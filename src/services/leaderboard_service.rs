@@ -0,0 +1,103 @@
+use std::collections::HashMap;
+
+use futures_util::StreamExt;
+use mongodb::bson::{doc, oid::ObjectId, Bson, Document};
+use serde::Serialize;
+
+use crate::{models::User, AppState};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LeaderboardEntry {
+    pub rank: usize,
+    pub username: String,
+    pub total_value: f64,
+    // vs. the configured starting balance
+    pub return_pct: f64,
+    pub return_class: &'static str,
+}
+
+/// Orders `(username, total_value)` pairs by return and keeps the first `limit`.
+pub fn rank_traders(equities: Vec<(String, f64)>, starting_balance: f64, limit: usize) -> Vec<LeaderboardEntry> {
+    let mut equities = equities;
+    equities.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    equities
+        .into_iter()
+        .take(limit)
+        .enumerate()
+        .map(|(i, (username, total_value))| {
+            let return_pct = if starting_balance > 0.0 {
+                (total_value - starting_balance) / starting_balance * 100.0
+            } else {
+                0.0
+            };
+            LeaderboardEntry {
+                rank: i + 1,
+                username,
+                total_value,
+                return_pct,
+                return_class: if return_pct >= 0.0 { "text-success" } else { "text-danger" },
+            }
+        })
+        .collect()
+}
+
+/// Ranks everyone who has placed at least one order by their latest daily
+/// portfolio snapshot, so it costs no quotes; values lag by up to a day.
+pub async fn top_traders(state: &AppState, limit: usize) -> Result<Vec<LeaderboardEntry>, String> {
+    let traders: Vec<ObjectId> = state
+        .db
+        .collection::<Document>("orders")
+        .distinct("user_id", None, None)
+        .await
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .filter_map(|b| match b {
+            Bson::ObjectId(id) => Some(id),
+            _ => None,
+        })
+        .collect();
+
+    if traders.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let pipeline = vec![
+        doc! { "$match": { "user_id": { "$in": &traders } } },
+        doc! { "$sort": { "date": -1 } },
+        doc! { "$group": { "_id": "$user_id", "total_value": { "$first": "$total_value" } } },
+    ];
+
+    let mut cursor = state
+        .db
+        .collection::<Document>("portfolio_snapshots")
+        .aggregate(pipeline, None)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let mut latest: HashMap<ObjectId, f64> = HashMap::new();
+    while let Some(row) = cursor.next().await {
+        let row = row.map_err(|e| e.to_string())?;
+        if let (Ok(id), Ok(total)) = (row.get_object_id("_id"), row.get_f64("total_value")) {
+            latest.insert(id, total);
+        }
+    }
+
+    let ids: Vec<ObjectId> = latest.keys().copied().collect();
+    let mut cursor = state
+        .db
+        .collection::<User>("users")
+        .find(doc! { "_id": { "$in": ids } }, None)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let mut equities: Vec<(String, f64)> = Vec::new();
+    while let Some(user) = cursor.next().await {
+        let user = user.map_err(|e| e.to_string())?;
+        if let Some(total) = latest.get(&user.id) {
+            equities.push((user.username, *total));
+        }
+    }
+
+    Ok(rank_traders(equities, state.settings.starting_balance, limit))
+}
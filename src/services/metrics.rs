@@ -0,0 +1,54 @@
+use std::sync::OnceLock;
+
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder, PrometheusHandle};
+
+pub const TRADES_TOTAL: &str = "rustmarket_trades_total";
+pub const FINNHUB_REQUESTS_TOTAL: &str = "rustmarket_finnhub_requests_total";
+pub const FINNHUB_REQUEST_SECONDS: &str = "rustmarket_finnhub_request_duration_seconds";
+pub const ALERTS_TRIGGERED_TOTAL: &str = "rustmarket_alerts_triggered_total";
+pub const WS_ACTIVE_CONNECTIONS: &str = "rustmarket_ws_active_connections";
+
+// Finnhub answers in tens to hundreds of milliseconds; the tail covers the client timeout.
+pub const REQUEST_SECONDS_BUCKETS: &[f64] = &[0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+static HANDLE: OnceLock<PrometheusHandle> = OnceLock::new();
+
+// Installs the global recorder once; later calls are no-ops. Latencies are exported
+// as bucketed histograms rather than the exporter's default summaries, so they can
+// be aggregated across instances.
+pub fn install() {
+    HANDLE.get_or_init(|| {
+        PrometheusBuilder::new()
+            .set_buckets_for_metric(Matcher::Full(FINNHUB_REQUEST_SECONDS.to_string()), REQUEST_SECONDS_BUCKETS)
+            .expect("Invalid histogram buckets")
+            .install_recorder()
+            .expect("Failed to install metrics recorder")
+    });
+}
+
+// Prometheus text exposition, or None if install() was never called.
+pub fn render() -> Option<String> {
+    HANDLE.get().map(|h| h.render())
+}
+
+// Keeps the active-connections gauge in sync for as long as a socket lives.
+pub struct WsConnectionGuard;
+
+impl WsConnectionGuard {
+    pub fn new() -> Self {
+        metrics::gauge!(WS_ACTIVE_CONNECTIONS).increment(1.0);
+        Self
+    }
+}
+
+impl Default for WsConnectionGuard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for WsConnectionGuard {
+    fn drop(&mut self) {
+        metrics::gauge!(WS_ACTIVE_CONNECTIONS).decrement(1.0);
+    }
+}
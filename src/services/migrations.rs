@@ -0,0 +1,154 @@
+use futures_util::future::BoxFuture;
+use mongodb::{
+    bson::doc,
+    options::UpdateOptions,
+    Database,
+};
+
+// One-off data migrations, run in order at startup after the indexes exist.
+// The applied version lives in meta { _id: "schema_version", version }.
+// Never reorder or remove entries; append new ones. Each must be idempotent,
+// since a crash between running it and bumping the version re-runs it.
+
+type Migration = fn(&Database) -> BoxFuture<'_, Result<(), String>>;
+
+const MIGRATIONS: &[(&str, Migration)] = &[
+    ("backfill positions.created_at", |db| Box::pin(backfill_position_created_at(db))),
+    ("backfill alerts.triggered_on", |db| Box::pin(backfill_alert_triggered_on(db))),
+    ("lowercase users.email", |db| Box::pin(lowercase_user_emails(db))),
+    ("backfill alerts.triggered_price", |db| Box::pin(backfill_alert_triggered_price(db))),
+    ("backfill orders.status", |db| Box::pin(backfill_order_status(db))),
+];
+
+const META: &str = "meta";
+const VERSION_ID: &str = "schema_version";
+
+pub fn latest_version() -> i32 {
+    MIGRATIONS.len() as i32
+}
+
+pub async fn current_version(db: &Database) -> Result<i32, String> {
+    let meta = db.collection::<mongodb::bson::Document>(META);
+    let found = meta
+        .find_one(doc! { "_id": VERSION_ID }, None)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(found.and_then(|d| d.get_i32("version").ok()).unwrap_or(0))
+}
+
+async fn set_version(db: &Database, version: i32) -> Result<(), String> {
+    db.collection::<mongodb::bson::Document>(META)
+        .update_one(
+            doc! { "_id": VERSION_ID },
+            doc! { "$set": { "version": version } },
+            UpdateOptions::builder().upsert(true).build(),
+        )
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Applies every migration newer than the stored version; returns the final version.
+pub async fn run(db: &Database) -> Result<i32, String> {
+    let mut version = current_version(db).await?;
+
+    for (idx, (name, migrate)) in MIGRATIONS.iter().enumerate() {
+        let target = idx as i32 + 1;
+        if target <= version {
+            continue;
+        }
+
+        tracing::info!("running migration {}: {}", target, name);
+        migrate(db)
+            .await
+            .map_err(|e| format!("migration {target} ({name}) failed: {e}"))?;
+        set_version(db, target).await?;
+        version = target;
+    }
+
+    Ok(version)
+}
+
+async fn backfill_position_created_at(db: &Database) -> Result<(), String> {
+    let n = super::db_init::backfill_position_created_at(db).await?;
+    tracing::info!("backfilled created_at on {} positions", n);
+    Ok(())
+}
+
+// Alerts triggered before triggered_on existed would never hit the TTL index.
+async fn backfill_alert_triggered_on(db: &Database) -> Result<(), String> {
+    db.collection::<mongodb::bson::Document>("alerts")
+        .update_many(
+            doc! {
+                "triggered": true,
+                "triggered_on": { "$exists": false },
+                "triggered_at": { "$type": "number" },
+            },
+            vec![doc! {
+                "$set": { "triggered_on": { "$toDate": { "$multiply": ["$triggered_at", 1000] } } }
+            }],
+            None,
+        )
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+// Emails are now stored and looked up normalized (trimmed, lowercase). A user whose
+// lowercased address is already taken by another account is left as is and logged.
+async fn lowercase_user_emails(db: &Database) -> Result<(), String> {
+    use futures_util::StreamExt;
+
+    let users = db.collection::<mongodb::bson::Document>("users");
+    let mut cursor = users
+        .find(doc! { "email": { "$regex": "[A-Z]|^\\s|\\s$" } }, None)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    while let Some(user) = cursor.next().await {
+        let user = user.map_err(|e| e.to_string())?;
+        let (Ok(id), Ok(email)) = (user.get_object_id("_id"), user.get_str("email")) else {
+            continue;
+        };
+        let lowered = email.trim().to_lowercase();
+
+        match users
+            .update_one(doc! { "_id": id }, doc! { "$set": { "email": &lowered } }, None)
+            .await
+        {
+            Ok(_) => {}
+            Err(e) if e.to_string().contains("E11000") => {
+                tracing::warn!("not lowercasing email of user {}: {} is taken", id, lowered);
+            }
+            Err(e) => return Err(e.to_string()),
+        }
+    }
+    Ok(())
+}
+
+// The price wasn't recorded before; older triggered alerts get an explicit null.
+async fn backfill_alert_triggered_price(db: &Database) -> Result<(), String> {
+    db.collection::<mongodb::bson::Document>("alerts")
+        .update_many(
+            doc! { "triggered": true, "triggered_price": { "$exists": false } },
+            doc! { "$set": { "triggered_price": null } },
+            None,
+        )
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+// Orders had no status before; all of them were market orders filled in full.
+async fn backfill_order_status(db: &Database) -> Result<(), String> {
+    db.collection::<mongodb::bson::Document>("orders")
+        .update_many(
+            doc! { "status": { "$exists": false } },
+            doc! { "$set": { "status": "filled" } },
+            None,
+        )
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
@@ -1,6 +1,14 @@
 pub mod finnhub;
 pub mod db_init;
+pub mod migrations;
 pub mod alert_monitor;
+pub mod snapshot_monitor;
+pub mod dividend_monitor;
+pub mod monitor_lease;
+pub mod metrics;
+pub mod trade_relay;
+pub mod ws_limiter;
+pub mod clock;
 
 pub mod auth_service;
 pub mod account_service;
@@ -9,3 +17,7 @@ pub mod portfolio_service;
 pub mod alerts_service;
 pub mod user_service;
 pub mod stocks_service;
+pub mod watchlist_service;
+pub mod leaderboard_service;
+pub mod admin_service;
+pub mod session_service;
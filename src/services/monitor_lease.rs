@@ -0,0 +1,47 @@
+//! Leases that let only one app instance run a background tick at a time.
+//!
+//! A lease is a `monitor_leases` document `{ _id: <task>, holder, expires_at }`.
+//! The holder renews it every tick; once it lapses (the holder died or stalled)
+//! any other instance may take it over. With a single instance the lease is
+//! always free or already ours, so nothing changes.
+
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use mongodb::{
+    bson::{doc, oid::ObjectId, DateTime as BsonDateTime, Document},
+    options::FindOneAndUpdateOptions,
+    Database,
+};
+
+/// Identifies this process as a lease holder.
+pub fn instance_id() -> &'static str {
+    static ID: OnceLock<String> = OnceLock::new();
+    ID.get_or_init(|| ObjectId::new().to_hex())
+}
+
+/// Takes or renews the `task` lease for `holder` until `ttl` from now.
+/// Returns false while another holder's lease is still live.
+pub async fn try_acquire(db: &Database, task: &str, holder: &str, ttl: Duration) -> Result<bool, String> {
+    let leases = db.collection::<Document>("monitor_leases");
+    let now = BsonDateTime::now();
+    let expires_at = BsonDateTime::from_millis(now.timestamp_millis() + ttl.as_millis() as i64);
+
+    let res = leases
+        .find_one_and_update(
+            doc! {
+                "_id": task,
+                "$or": [ { "holder": holder }, { "expires_at": { "$lte": now } } ],
+            },
+            doc! { "$set": { "holder": holder, "expires_at": expires_at } },
+            FindOneAndUpdateOptions::builder().upsert(true).build(),
+        )
+        .await;
+
+    match res {
+        Ok(_) => Ok(true),
+        // the filter missed because someone else holds it, so the upsert hit their _id
+        Err(e) if e.to_string().contains("E11000") => Ok(false),
+        Err(e) => Err(e.to_string()),
+    }
+}
@@ -1,9 +1,13 @@
+use std::collections::HashMap;
+
 use futures_util::StreamExt;
 
-use mongodb::bson::{doc, oid::ObjectId};
+use mongodb::bson::{doc, oid::ObjectId, Document};
 use mongodb::options::FindOptions;
 
-use crate::{models::{Order, Position}, AppState};
+use crate::{config::CostBasisMethod, error::AppError, models::{Order, PortfolioSnapshot, Position, ORDER_CANCELLED, ORDER_FILLED, ORDER_OPEN}, AppState};
+
+use super::{account_service, finnhub::QuoteResponse, stocks_service::{self, DayRange}, trading_service};
 
 #[derive(Debug, Clone)]
 pub struct PositionView {
@@ -11,9 +15,77 @@ pub struct PositionView {
     pub qty: i64,
     pub avg_price: f64,
     pub last_price: f64,
+    // no usable quote: last_price is the cost basis (as in the snapshots), so the
+    // position counts at cost rather than as a total loss
+    pub quote_unavailable: bool,
     pub pnl: f64,
     pub pnl_pct: f64,
     pub pnl_class: &'static str,
+    // intraday move of the whole position (quote.d * qty) and quote.dp
+    pub day_change: f64,
+    pub day_change_pct: f64,
+    pub day_change_class: &'static str,
+    // "YYYY-MM-DD" of the first buy, if known
+    pub held_since: Option<String>,
+    // whole days since the first buy (at least 1) and pnl_pct scaled to a year;
+    // None when created_at is unknown
+    pub days_held: Option<i64>,
+    pub annualized_return_pct: Option<f64>,
+    // today's open/high/low/prev close from the quote; None without a live quote
+    pub day_range: Option<DayRange>,
+    // open lots, oldest first; empty unless COST_BASIS_METHOD=fifo
+    pub lots: Vec<LotView>,
+}
+
+#[derive(Debug, Clone)]
+pub struct LotView {
+    pub qty: i64,
+    pub price: f64,
+    pub bought_on: Option<String>,
+    pub pnl: f64,
+    pub pnl_class: &'static str,
+}
+
+#[derive(Debug, Clone)]
+pub struct PortfolioSummary {
+    pub cash: f64,
+    pub market_value: f64,
+    pub cost_basis: f64,
+    pub total_value: f64,
+    pub unrealized_pnl: f64,
+    pub unrealized_pnl_pct: f64,
+    pub pnl_class: &'static str,
+    pub positions: usize,
+}
+
+// Bucket for holdings whose company profile has no industry (ETFs, crypto, unknowns).
+pub const UNKNOWN_SECTOR: &str = "Unknown";
+
+// One sector's share of the held market value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SectorAllocation {
+    pub sector: String,
+    pub value: f64,
+    pub pct: f64,
+}
+
+// Lifetime gains split into what was locked in by sells and what is still on paper.
+#[derive(Debug, Clone)]
+pub struct PnlBreakdown {
+    pub realized: f64,
+    pub unrealized: f64,
+    pub total: f64,
+    pub realized_class: &'static str,
+    pub unrealized_class: &'static str,
+    pub total_class: &'static str,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct OrderFilter {
+    pub symbol: Option<String>,
+    // inclusive unix-second bounds on created_at
+    pub from: Option<i64>,
+    pub to: Option<i64>,
 }
 
 #[derive(Debug, Clone)]
@@ -24,6 +96,23 @@ pub struct OrderView {
     pub qty: i64,
     pub price: f64,
     pub total: f64,
+    // what slippage cost the user on this order; None when it filled at the quote
+    pub slippage: Option<f64>,
+    pub status: String,
+    pub status_class: &'static str,
+    // shares filled so far; below qty for a partial fill
+    pub filled_qty: i64,
+    pub partial: bool,
+}
+
+/// Bootstrap badge class for an order status.
+pub fn order_status_class(status: &str) -> &'static str {
+    match status {
+        ORDER_FILLED => "text-bg-success",
+        ORDER_OPEN => "text-bg-primary",
+        ORDER_CANCELLED => "text-bg-secondary",
+        _ => "text-bg-light",
+    }
 }
 
 fn pnl_class(pnl: f64) -> &'static str {
@@ -36,67 +125,96 @@ fn pnl_class(pnl: f64) -> &'static str {
     }
 }
 
-pub async fn list_user_positions(state: &AppState, user_id: ObjectId) -> Result<Vec<Position>, String> {
+/// Sent on the app event channel, next to the public `positionUpdated`, when one
+/// user's holdings change: `/events` streams re-read positions only for their own
+/// user instead of on every trade. Never forwarded to browsers.
+pub const HOLDINGS_CHANGED_PREFIX: &str = "holdingsChanged:";
+
+pub fn holdings_changed_event(user_id: ObjectId) -> String {
+    format!("{HOLDINGS_CHANGED_PREFIX}{}", user_id.to_hex())
+}
+
+// Closed rows can sit beside the open one (the unique index only covers qty > 0),
+// so reads here only ever look at rows that hold shares.
+pub async fn list_user_positions(state: &AppState, user_id: ObjectId) -> Result<Vec<Position>, AppError> {
     let positions = state.db.collection::<Position>("positions");
     let find_opts = FindOptions::builder().sort(doc! { "updated_at": -1 }).build();
 
     let mut cursor = positions
-        .find(doc! { "user_id": user_id }, find_opts)
-        .await
-        .map_err(|e| e.to_string())?;
+        .find(doc! { "user_id": user_id, "qty": { "$gt": 0 } }, find_opts)
+        .await?;
 
     let mut out: Vec<Position> = vec![];
     while let Some(res) = cursor.next().await {
-        out.push(res.map_err(|e| e.to_string())?);
+        out.push(res?);
     }
     Ok(out)
 }
 
-pub async fn get_user_position(state: &AppState, user_id: ObjectId, symbol: &str) -> Result<Option<Position>, String> {
+pub async fn get_user_position(state: &AppState, user_id: ObjectId, symbol: &str) -> Result<Option<Position>, AppError> {
     let sym = symbol.to_uppercase();
     let positions = state.db.collection::<Position>("positions");
-    positions
-        .find_one(doc! { "user_id": user_id, "symbol": &sym }, None)
-        .await
-        .map_err(|e| e.to_string())
+    Ok(positions
+        .find_one(doc! { "user_id": user_id, "symbol": &sym, "qty": { "$gt": 0 } }, None)
+        .await?)
 }
 
-pub async fn list_portfolio_position_views(state: &AppState, user_id: ObjectId) -> Result<Vec<PositionView>, String> {
-    let positions = list_user_positions(state, user_id).await?;
+pub fn held_since(created_at: i64) -> Option<String> {
+    if created_at <= 0 {
+        return None;
+    }
+    chrono::DateTime::from_timestamp(created_at, 0).map(|d| d.format("%Y-%m-%d").to_string())
+}
 
-    let mut views: Vec<PositionView> = vec![];
-    for p in positions {
-        let symbol = p.symbol.to_uppercase();
-        let last = state.finnhub.quote(&symbol).await.ok().map(|x| x.c).unwrap_or(0.0);
-
-        let pnl = (last - p.avg_price) * (p.qty as f64);
-        let pct = if p.avg_price > 0.0 {
-            ((last - p.avg_price) / p.avg_price) * 100.0
-        } else {
-            0.0
-        };
-
-        views.push(PositionView {
-            symbol,
-            qty: p.qty,
-            avg_price: p.avg_price,
-            last_price: last,
-            pnl,
-            pnl_pct: pct,
-            pnl_class: pnl_class(pnl),
-        });
+/// Days between the first buy and `now`, never less than one so a same-day
+/// position doesn't blow up the annualized figure.
+pub fn days_held(created_at: i64, now: i64) -> Option<i64> {
+    if created_at <= 0 {
+        return None;
     }
+    Some(((now - created_at) / 86_400).max(1))
+}
 
-    Ok(views)
+/// Below this, compounding a few days' move over a year gives absurd rates.
+pub const MIN_ANNUALIZED_DAYS: i64 = 30;
+
+/// Compounds `pnl_pct` over `days` to a yearly rate, in percent. None for holds
+/// shorter than `MIN_ANNUALIZED_DAYS` or when the rate isn't a finite number.
+pub fn annualized_return_pct(pnl_pct: f64, days: i64) -> Option<f64> {
+    if days < MIN_ANNUALIZED_DAYS {
+        return None;
+    }
+    let growth = 1.0 + pnl_pct / 100.0;
+    if growth <= 0.0 {
+        return Some(-100.0);
+    }
+    let annualized = (growth.powf(365.0 / days as f64) - 1.0) * 100.0;
+    annualized.is_finite().then_some(annualized)
 }
 
-pub async fn get_portfolio_position_view(state: &AppState, user_id: ObjectId, symbol: &str) -> Result<Option<PositionView>, String> {
-    let Some(p) = get_user_position(state, user_id, symbol).await? else {
-        return Ok(None);
-    };
+fn lot_views(p: &Position, last: f64) -> Vec<LotView> {
+    trading_service::open_lots(p)
+        .into_iter()
+        .map(|l| {
+            let pnl = (last - l.price) * (l.qty as f64);
+            LotView {
+                qty: l.qty,
+                price: l.price,
+                bought_on: held_since(l.bought_at),
+                pnl,
+                pnl_class: pnl_class(pnl),
+            }
+        })
+        .collect()
+}
+
+/// `now` (unix seconds) is when the position is viewed; it sets the days held.
+pub fn position_view(p: &Position, quote: Option<&QuoteResponse>, with_lots: bool, now: i64) -> PositionView {
+    let live = quote.map(|q| q.c).filter(|c| *c > 0.0);
+    let last = live.unwrap_or(p.avg_price);
+    let day_change = quote.map(|q| q.d * (p.qty as f64)).unwrap_or(0.0);
+    let day_change_pct = quote.map(|q| q.dp).unwrap_or(0.0);
 
-    let sym = p.symbol.to_uppercase();
-    let last = state.finnhub.quote(&sym).await.ok().map(|x| x.c).unwrap_or(0.0);
     let pnl = (last - p.avg_price) * (p.qty as f64);
     let pct = if p.avg_price > 0.0 {
         ((last - p.avg_price) / p.avg_price) * 100.0
@@ -104,41 +222,337 @@ pub async fn get_portfolio_position_view(state: &AppState, user_id: ObjectId, sy
         0.0
     };
 
-    Ok(Some(PositionView {
-        symbol: sym,
+    let days = days_held(p.created_at, now);
+
+    PositionView {
+        symbol: p.symbol.to_uppercase(),
         qty: p.qty,
         avg_price: p.avg_price,
         last_price: last,
+        quote_unavailable: live.is_none(),
         pnl,
         pnl_pct: pct,
         pnl_class: pnl_class(pnl),
-    }))
+        day_change,
+        day_change_pct,
+        day_change_class: pnl_class(day_change),
+        held_since: held_since(p.created_at),
+        days_held: days,
+        annualized_return_pct: days.and_then(|d| annualized_return_pct(pct, d)),
+        day_range: quote.and_then(stocks_service::day_range),
+        lots: if with_lots { lot_views(p, last) } else { Vec::new() },
+    }
+}
+
+pub async fn list_portfolio_position_views(state: &AppState, user_id: ObjectId) -> Result<Vec<PositionView>, AppError> {
+    let positions = list_user_positions(state, user_id).await?;
+
+    let symbols: Vec<String> = positions.iter().map(|p| p.symbol.to_uppercase()).collect();
+    let quotes = state.finnhub.quotes(&symbols).await;
+    let with_lots = state.settings.cost_basis_method == CostBasisMethod::Fifo;
+    let now = state.clock.now().timestamp();
+
+    let views = positions
+        .iter()
+        .map(|p| position_view(p, quotes.get(&p.symbol.to_uppercase()), with_lots, now))
+        .collect();
+
+    Ok(views)
+}
+
+pub async fn get_portfolio_position_view(state: &AppState, user_id: ObjectId, symbol: &str) -> Result<Option<PositionView>, AppError> {
+    get_position_view_priced(state, user_id, symbol, None).await
+}
+
+/// Like `get_portfolio_position_view`, but values the position at `last_price`
+/// when given instead of asking Finnhub. The day change is unknown then and shows as 0,
+/// and there is no day range.
+pub async fn get_position_view_priced(
+    state: &AppState,
+    user_id: ObjectId,
+    symbol: &str,
+    last_price: Option<f64>,
+) -> Result<Option<PositionView>, AppError> {
+    let Some(p) = get_user_position(state, user_id, symbol).await? else {
+        return Ok(None);
+    };
+
+    let quote = match last_price {
+        Some(c) => Some(QuoteResponse { c, d: 0.0, dp: 0.0, h: c, l: c, o: c, pc: c, t: 0 }),
+        None => state.finnhub.quote(&p.symbol.to_uppercase()).await.ok(),
+    };
+
+    let with_lots = state.settings.cost_basis_method == CostBasisMethod::Fifo;
+
+    let mut view = position_view(&p, quote.as_ref(), with_lots, state.clock.now().timestamp());
+    if last_price.is_some() {
+        view.day_range = None;
+    }
+    Ok(Some(view))
+}
+
+/// Price and time of the user's most recent order in `symbol`.
+pub async fn latest_order_price(state: &AppState, user_id: ObjectId, symbol: &str) -> Result<Option<(f64, i64)>, AppError> {
+    let filter = OrderFilter {
+        symbol: Some(symbol.to_string()),
+        ..OrderFilter::default()
+    };
+    let orders = list_orders_filtered(state, user_id, &filter, 1).await?;
+
+    Ok(orders.first().map(|o| (o.price, o.created_at)))
+}
+
+/// The order price if it was filled within `max_age_secs` of `now`; None means quote live.
+pub fn fresh_fill_price(order: Option<(f64, i64)>, now: i64, max_age_secs: i64) -> Option<f64> {
+    let (price, at) = order?;
+    (price > 0.0 && (0..=max_age_secs).contains(&(now - at))).then_some(price)
+}
+
+pub fn summarize(cash: f64, views: &[PositionView]) -> PortfolioSummary {
+    let market_value: f64 = views.iter().map(|v| v.last_price * (v.qty as f64)).sum();
+    let cost_basis: f64 = views.iter().map(|v| v.avg_price * (v.qty as f64)).sum();
+    let unrealized_pnl = market_value - cost_basis;
+    let unrealized_pnl_pct = if cost_basis > 0.0 {
+        (unrealized_pnl / cost_basis) * 100.0
+    } else {
+        0.0
+    };
+
+    PortfolioSummary {
+        cash,
+        market_value,
+        cost_basis,
+        total_value: cash + market_value,
+        unrealized_pnl,
+        unrealized_pnl_pct,
+        pnl_class: pnl_class(unrealized_pnl),
+        positions: views.len(),
+    }
+}
+
+pub async fn portfolio_summary(state: &AppState, user_id: ObjectId) -> Result<PortfolioSummary, AppError> {
+    let acc = account_service::get_or_create_account(state, user_id)
+        .await
+        .map_err(AppError::Db)?;
+    let views = list_portfolio_position_views(state, user_id).await?;
+
+    Ok(summarize(acc.cash, &views))
+}
+
+/// Realized P&L over `orders` (oldest first). Sells that stored their own figure
+/// count as is; older sells are priced against the running average cost of the
+/// buys before them.
+pub fn realized_pnl(orders: &[Order]) -> f64 {
+    // symbol -> (shares, cost basis of those shares)
+    let mut books: HashMap<String, (i64, f64)> = HashMap::new();
+    let mut realized = 0.0;
+
+    for o in orders {
+        let (qty, cost) = books.entry(o.symbol.to_uppercase()).or_insert((0, 0.0));
+
+        match o.side.as_str() {
+            "buy" => {
+                *qty += o.qty;
+                *cost += o.total;
+            }
+            "sell" => {
+                let gain = o.realized_pnl.unwrap_or_else(|| {
+                    let avg = if *qty > 0 { *cost / (*qty as f64) } else { o.price };
+                    o.total - avg * (o.qty as f64)
+                });
+                realized += gain;
+
+                *qty -= o.qty;
+                *cost -= o.total - gain;
+                if *qty <= 0 {
+                    *qty = 0;
+                    *cost = 0.0;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    realized
+}
+
+pub fn breakdown(realized: f64, unrealized: f64) -> PnlBreakdown {
+    let total = realized + unrealized;
+    PnlBreakdown {
+        realized,
+        unrealized,
+        total,
+        realized_class: pnl_class(realized),
+        unrealized_class: pnl_class(unrealized),
+        total_class: pnl_class(total),
+    }
+}
+
+/// Realized gains from every sell plus the unrealized P&L of what is still held.
+/// Someone who sold everything has it all realized and zero unrealized.
+pub async fn pnl_breakdown(state: &AppState, user_id: ObjectId) -> Result<PnlBreakdown, AppError> {
+    let orders = state.db.collection::<Order>("orders");
+    let find_opts = FindOptions::builder().sort(doc! { "created_at": 1 }).build();
+
+    let mut cursor = orders.find(doc! { "user_id": user_id }, find_opts).await?;
+    let mut history: Vec<Order> = vec![];
+    while let Some(res) = cursor.next().await {
+        history.push(res?);
+    }
+
+    let views = list_portfolio_position_views(state, user_id).await?;
+    let unrealized = summarize(0.0, &views).unrealized_pnl;
+
+    Ok(breakdown(realized_pnl(&history), unrealized))
+}
+
+/// Sums `(sector, market value)` pairs per sector, largest first (ties by name);
+/// `pct` is each sector's share of the total.
+pub fn group_by_sector(holdings: &[(String, f64)]) -> Vec<SectorAllocation> {
+    let mut totals: HashMap<&str, f64> = HashMap::new();
+    for (sector, value) in holdings {
+        *totals.entry(sector.as_str()).or_default() += value;
+    }
+    let total: f64 = totals.values().sum();
+
+    let mut out: Vec<SectorAllocation> = totals
+        .into_iter()
+        .map(|(sector, value)| SectorAllocation {
+            sector: sector.to_string(),
+            value,
+            pct: if total > 0.0 { value / total * 100.0 } else { 0.0 },
+        })
+        .collect();
+    out.sort_by(|a, b| b.value.total_cmp(&a.value).then_with(|| a.sector.cmp(&b.sector)));
+    out
+}
+
+/// Market value of the user's positions grouped by Finnhub industry.
+pub async fn positions_by_sector(state: &AppState, user_id: ObjectId) -> Result<Vec<SectorAllocation>, AppError> {
+    let views = list_portfolio_position_views(state, user_id).await?;
+
+    let symbols: Vec<String> = views.iter().map(|v| v.symbol.clone()).collect();
+    let profiles = stocks_service::get_profiles(state, &symbols).await;
+
+    let holdings: Vec<(String, f64)> = views
+        .iter()
+        .map(|v| {
+            let sector = profiles
+                .get(&v.symbol)
+                .map(|p| p.finnhub_industry.trim())
+                .filter(|s| !s.is_empty())
+                .unwrap_or(UNKNOWN_SECTOR);
+            (sector.to_string(), v.last_price * (v.qty as f64))
+        })
+        .collect();
+
+    Ok(group_by_sector(&holdings))
+}
+
+pub async fn count_positions(state: &AppState, user_id: ObjectId) -> Result<u64, AppError> {
+    let positions = state.db.collection::<Position>("positions");
+    Ok(positions
+        .count_documents(doc! { "user_id": user_id, "qty": { "$gt": 0 } }, None)
+        .await?)
 }
 
-pub async fn list_recent_orders(state: &AppState, user_id: ObjectId, limit: i64) -> Result<Vec<Order>, String> {
+pub async fn count_orders(state: &AppState, user_id: ObjectId) -> Result<u64, AppError> {
+    let orders = state.db.collection::<Order>("orders");
+    Ok(orders.count_documents(doc! { "user_id": user_id }, None).await?)
+}
+
+/// Total simulated dividends credited to the user so far.
+pub async fn dividend_income(state: &AppState, user_id: ObjectId) -> Result<f64, AppError> {
+    let pipeline = vec![
+        doc! { "$match": { "user_id": user_id, "kind": "dividend" } },
+        doc! { "$group": { "_id": null, "total": { "$sum": "$amount" } } },
+    ];
+
+    let mut cursor = state
+        .db
+        .collection::<Document>("cash_events")
+        .aggregate(pipeline, None)
+        .await?;
+
+    match cursor.next().await {
+        Some(row) => Ok(row?.get_f64("total").unwrap_or(0.0)),
+        None => Ok(0.0),
+    }
+}
+
+pub fn order_filter_doc(user_id: ObjectId, filter: &OrderFilter) -> Document {
+    let mut q = doc! { "user_id": user_id };
+
+    if let Some(sym) = filter.symbol.as_deref().map(str::trim).filter(|s| !s.is_empty()) {
+        q.insert("symbol", sym.to_uppercase());
+    }
+
+    let mut range = Document::new();
+    if let Some(from) = filter.from {
+        range.insert("$gte", from);
+    }
+    if let Some(to) = filter.to {
+        range.insert("$lte", to);
+    }
+    if !range.is_empty() {
+        q.insert("created_at", range);
+    }
+
+    q
+}
+
+pub async fn list_recent_orders(state: &AppState, user_id: ObjectId, limit: i64) -> Result<Vec<Order>, AppError> {
+    list_orders_filtered(state, user_id, &OrderFilter::default(), limit).await
+}
+
+pub async fn list_orders_filtered(
+    state: &AppState,
+    user_id: ObjectId,
+    filter: &OrderFilter,
+    limit: i64,
+) -> Result<Vec<Order>, AppError> {
     let orders = state.db.collection::<Order>("orders");
     let find_opts = FindOptions::builder().sort(doc! { "created_at": -1 }).limit(limit).build();
 
     let mut cursor = orders
-        .find(doc! { "user_id": user_id }, find_opts)
-        .await
-        .map_err(|e| e.to_string())?;
+        .find(order_filter_doc(user_id, filter), find_opts)
+        .await?;
 
     let mut out: Vec<Order> = vec![];
     while let Some(res) = cursor.next().await {
-        out.push(res.map_err(|e| e.to_string())?);
+        out.push(res?);
     }
     Ok(out)
 }
 
-pub async fn list_recent_order_views(state: &AppState, user_id: ObjectId, limit: i64) -> Result<Vec<OrderView>, String> {
-    let orders = list_recent_orders(state, user_id, limit).await?;
+/// The extra paid on a buy or given up on a sell versus the quote, for the whole order.
+/// None for orders without a recorded quote or that filled at it.
+pub fn order_slippage(o: &Order) -> Option<f64> {
+    let quoted = o.quoted_price?;
+    let per_share = if o.side == "buy" { o.price - quoted } else { quoted - o.price };
+    let cost = per_share * (o.qty as f64);
+    (cost.abs() > 1e-9).then_some(cost)
+}
+
+pub async fn list_recent_order_views(state: &AppState, user_id: ObjectId, limit: i64) -> Result<Vec<OrderView>, AppError> {
+    list_order_views_filtered(state, user_id, &OrderFilter::default(), limit).await
+}
+
+pub async fn list_order_views_filtered(
+    state: &AppState,
+    user_id: ObjectId,
+    filter: &OrderFilter,
+    limit: i64,
+) -> Result<Vec<OrderView>, AppError> {
+    let orders = list_orders_filtered(state, user_id, filter, limit).await?;
 
     let mut out: Vec<OrderView> = vec![];
     for o in orders {
         let dt = chrono::DateTime::from_timestamp(o.created_at, 0)
             .map(|d| d.format("%Y-%m-%d %H:%M").to_string())
             .unwrap_or_else(|| o.created_at.to_string());
+        let slippage = order_slippage(&o);
+        let filled_qty = o.filled_qty();
 
         out.push(OrderView {
             created_at: dt,
@@ -147,8 +561,41 @@ pub async fn list_recent_order_views(state: &AppState, user_id: ObjectId, limit:
             qty: o.qty,
             price: o.price,
             total: o.total,
+            slippage,
+            status_class: order_status_class(&o.status),
+            status: o.status,
+            filled_qty,
+            partial: filled_qty < o.qty,
         });
     }
 
     Ok(out)
 }
+
+// "30d" -> 30; capped at two years of daily points
+pub fn parse_range_days(range: &str) -> Option<i64> {
+    let days = range.trim().strip_suffix('d')?.parse::<i64>().ok()?;
+    if days <= 0 {
+        return None;
+    }
+    Some(days.min(730))
+}
+
+pub async fn portfolio_history(state: &AppState, user_id: ObjectId, days: i64) -> Result<Vec<PortfolioSnapshot>, AppError> {
+    let snapshots = state.db.collection::<PortfolioSnapshot>("portfolio_snapshots");
+
+    let since = (chrono::Utc::now() - chrono::Duration::days(days))
+        .format("%Y-%m-%d")
+        .to_string();
+    let find_opts = FindOptions::builder().sort(doc! { "date": 1 }).build();
+
+    let mut cursor = snapshots
+        .find(doc! { "user_id": user_id, "date": { "$gte": since } }, find_opts)
+        .await?;
+
+    let mut out: Vec<PortfolioSnapshot> = vec![];
+    while let Some(res) = cursor.next().await {
+        out.push(res?);
+    }
+    Ok(out)
+}
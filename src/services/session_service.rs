@@ -0,0 +1,135 @@
+//! Login history for the settings "Sessions" page.
+//!
+//! Every successful login adds a `sessions` row; only the newest
+//! `MAX_SESSIONS_PER_USER` are kept. Rows are history, not live tokens: JWTs
+//! stay stateless and are revoked through `users.token_version`.
+
+use std::net::SocketAddr;
+
+use axum::http::HeaderMap;
+use futures_util::StreamExt;
+use mongodb::bson::{doc, oid::ObjectId, Document};
+use mongodb::options::FindOptions;
+
+use crate::{models::LoginSession, AppState};
+
+pub const MAX_SESSIONS_PER_USER: u64 = 20;
+
+const MAX_USER_AGENT_LEN: usize = 256;
+
+/// The client address: the first `X-Forwarded-For` hop when a proxy set one,
+/// else the socket peer.
+pub fn client_ip(headers: &HeaderMap, peer: Option<SocketAddr>) -> String {
+    let forwarded = headers
+        .get("x-forwarded-for")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.split(',').next())
+        .map(|v| v.trim())
+        .filter(|v| !v.is_empty() && v.len() <= 64);
+
+    match (forwarded, peer) {
+        (Some(ip), _) => ip.to_string(),
+        (None, Some(addr)) => addr.ip().to_string(),
+        (None, None) => "unknown".to_string(),
+    }
+}
+
+pub fn user_agent(headers: &HeaderMap) -> String {
+    headers
+        .get(axum::http::header::USER_AGENT)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.chars().take(MAX_USER_AGENT_LEN).collect())
+        .unwrap_or_default()
+}
+
+pub async fn record_login(
+    state: &AppState,
+    user_id: ObjectId,
+    ip: &str,
+    user_agent: &str,
+) -> Result<(), String> {
+    let sessions = state.db.collection::<LoginSession>("sessions");
+
+    let row = LoginSession {
+        id: ObjectId::new(),
+        user_id,
+        ip: ip.to_string(),
+        user_agent: user_agent.to_string(),
+        created_at: state.clock.timestamp(),
+        revoked_at: None,
+    };
+
+    sessions
+        .insert_one(&row, None)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    prune(state, user_id).await
+}
+
+// Drops everything past the newest MAX_SESSIONS_PER_USER rows.
+async fn prune(state: &AppState, user_id: ObjectId) -> Result<(), String> {
+    let sessions = state.db.collection::<Document>("sessions");
+    let opts = FindOptions::builder()
+        .sort(doc! { "created_at": -1, "_id": -1 })
+        .skip(MAX_SESSIONS_PER_USER)
+        .projection(doc! { "_id": 1 })
+        .build();
+
+    let mut cursor = sessions
+        .find(doc! { "user_id": user_id }, opts)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let mut stale: Vec<ObjectId> = vec![];
+    while let Some(res) = cursor.next().await {
+        if let Ok(id) = res.map_err(|e| e.to_string())?.get_object_id("_id") {
+            stale.push(id);
+        }
+    }
+
+    if stale.is_empty() {
+        return Ok(());
+    }
+
+    sessions
+        .delete_many(doc! { "_id": { "$in": stale } }, None)
+        .await
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+/// Newest first.
+pub async fn list_sessions(state: &AppState, user_id: ObjectId) -> Result<Vec<LoginSession>, String> {
+    let sessions = state.db.collection::<LoginSession>("sessions");
+    let opts = FindOptions::builder()
+        .sort(doc! { "created_at": -1, "_id": -1 })
+        .limit(MAX_SESSIONS_PER_USER as i64)
+        .build();
+
+    let mut cursor = sessions
+        .find(doc! { "user_id": user_id }, opts)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let mut out: Vec<LoginSession> = vec![];
+    while let Some(res) = cursor.next().await {
+        out.push(res.map_err(|e| e.to_string())?);
+    }
+    Ok(out)
+}
+
+/// Marks the user's recorded sessions as signed out, for "log out all devices".
+pub async fn revoke_all(state: &AppState, user_id: ObjectId) -> Result<u64, String> {
+    let sessions = state.db.collection::<LoginSession>("sessions");
+
+    sessions
+        .update_many(
+            doc! { "user_id": user_id, "revoked_at": null },
+            doc! { "$set": { "revoked_at": state.clock.timestamp() } },
+            None,
+        )
+        .await
+        .map(|r| r.modified_count)
+        .map_err(|e| e.to_string())
+}
@@ -0,0 +1,97 @@
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+use chrono::Utc;
+use futures_util::StreamExt;
+use mongodb::bson::doc;
+use mongodb::options::UpdateOptions;
+use tokio::time;
+
+use crate::{AppState, models::{Account, Position}};
+
+pub fn spawn_portfolio_snapshot_task(state: AppState) {
+    tokio::spawn(async move {
+        let secs = state.settings.snapshot_interval_secs;
+        let mut interval = time::interval(Duration::from_secs(secs));
+
+        loop {
+            interval.tick().await;
+
+            if let Err(e) = run_tick(&state).await {
+                eprintln!("[snapshot-monitor] tick error: {}", e);
+            }
+        }
+    });
+}
+
+async fn run_tick(state: &AppState) -> Result<(), String> {
+    let positions = state.db.collection::<Position>("positions");
+    let accounts = state.db.collection::<Account>("accounts");
+    let snapshots = state.db.collection::<mongodb::bson::Document>("portfolio_snapshots");
+
+    let mut cursor = positions
+        .find(doc! { "qty": { "$gt": 0 } }, None)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let mut by_user: HashMap<_, Vec<Position>> = HashMap::new();
+    let mut symbols: HashSet<String> = HashSet::new();
+    while let Some(item) = cursor.next().await {
+        let p = item.map_err(|e| e.to_string())?;
+        symbols.insert(p.symbol.to_uppercase());
+        by_user.entry(p.user_id).or_default().push(p);
+    }
+
+    // one quote per distinct symbol for the whole tick
+    let symbols: Vec<String> = symbols.into_iter().collect();
+    let quotes = state.finnhub.quotes(&symbols).await;
+
+    let now = Utc::now();
+    let date = now.format("%Y-%m-%d").to_string();
+
+    let mut cursor = accounts
+        .find(doc! {}, None)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    while let Some(item) = cursor.next().await {
+        let acc = item.map_err(|e| e.to_string())?;
+
+        let market_value: f64 = by_user
+            .get(&acc.id)
+            .map(|ps| {
+                ps.iter()
+                    .map(|p| {
+                        // fall back to cost when the quote is unavailable
+                        let last = quotes
+                            .get(&p.symbol.to_uppercase())
+                            .map(|q| q.c)
+                            .filter(|c| *c > 0.0)
+                            .unwrap_or(p.avg_price);
+                        last * (p.qty as f64)
+                    })
+                    .sum()
+            })
+            .unwrap_or(0.0);
+
+        let res = snapshots
+            .update_one(
+                doc! { "user_id": acc.id, "date": &date },
+                doc! {
+                    "$set": {
+                        "total_value": acc.cash + market_value,
+                        "cash": acc.cash,
+                        "created_at": now.timestamp(),
+                    }
+                },
+                UpdateOptions::builder().upsert(true).build(),
+            )
+            .await;
+
+        if let Err(e) = res {
+            eprintln!("[snapshot-monitor] user {} failed: {}", acc.id, e);
+        }
+    }
+
+    Ok(())
+}
@@ -1,8 +1,147 @@
+use std::collections::HashMap;
+
+use futures_util::{stream, StreamExt};
+use mongodb::bson::doc;
+use mongodb::options::ReplaceOptions;
+use serde::{Deserialize, Serialize};
 use serde_json::json;
 
-use crate::{AppState};
+use crate::{
+    error::AppError,
+    services::finnhub::{CompanyProfile, FinnhubError, QuoteResponse, SearchItem, MAX_CONCURRENT_QUOTES},
+    AppState,
+};
+
+// Page size for /search/results; `limit` can ask for up to MAX_SEARCH_LIMIT.
+pub const DEFAULT_SEARCH_LIMIT: usize = 10;
+pub const MAX_SEARCH_LIMIT: usize = 50;
+
+// A last trade older than this (e.g. market closed) is flagged as stale on the details page.
+pub const QUOTE_STALE_SECS: i64 = 15 * 60;
+
+const NO_DATA: &str = "No data for this symbol";
+
+/// Canonical form of a symbol: trimmed and upper-cased, keeping an exchange
+/// prefix such as crypto's "BINANCE:BTCUSDT" (each side trimmed on its own).
+pub fn normalize_symbol(raw: &str) -> String {
+    raw.split(':')
+        .map(|part| part.trim().to_uppercase())
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+/// Plain tickers ("AAPL", "BRK.B", "^GSPC") or one "EXCHANGE:PAIR" prefix
+/// ("BINANCE:BTCUSDT", "OANDA:EUR_USD"). Expects a normalized symbol.
+pub fn is_valid_symbol(sym: &str) -> bool {
+    let part_ok = |p: &str| {
+        !p.is_empty()
+            && p.len() <= 32
+            && p.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_' | '^' | '='))
+    };
+
+    match sym.split_once(':') {
+        Some((exchange, pair)) => part_ok(exchange) && part_ok(pair) && !pair.contains(':'),
+        None => part_ok(sym),
+    }
+}
+
+/// A comma-separated `?symbols=` list: normalized, invalid entries dropped, sorted,
+/// deduped and cut to the first `max`.
+pub fn parse_symbol_list(raw: &str, max: usize) -> Vec<String> {
+    let mut syms: Vec<String> = raw
+        .split(',')
+        .map(normalize_symbol)
+        .filter(|s| is_valid_symbol(s))
+        .collect();
+
+    syms.sort();
+    syms.dedup();
+    syms.truncate(max);
+    syms
+}
+
+// Finnhub calls ETFs "ETP"; accept the name users actually type.
+fn type_matches(kind: &str, filter: &str) -> bool {
+    let filter = if filter.eq_ignore_ascii_case("etf") { "ETP" } else { filter };
+    kind.eq_ignore_ascii_case(filter)
+}
+
+/// The exchange a search symbol trades on, from Finnhub's suffix convention: bare
+/// tickers are "US", "VOD.L" is "L", "SAP.DE" is "DE". One-letter A–C suffixes are US
+/// share classes ("BRK.B"), and prefixed pairs ("BINANCE:BTCUSDT") use their prefix.
+pub fn symbol_exchange(symbol: &str) -> String {
+    let symbol = symbol.trim().to_uppercase();
+    if let Some((prefix, _)) = symbol.split_once(':') {
+        return prefix.to_string();
+    }
+
+    match symbol.rsplit_once('.') {
+        Some((_, suffix)) if !matches!(suffix, "A" | "B" | "C") => suffix.to_string(),
+        _ => "US".to_string(),
+    }
+}
+
+fn matching_results<'a>(
+    items: &'a [SearchItem],
+    kind: Option<&str>,
+    exchange: Option<&str>,
+) -> impl Iterator<Item = &'a SearchItem> {
+    let kind = kind.map(str::trim).filter(|k| !k.is_empty()).map(str::to_string);
+    let exchange = exchange.map(str::trim).filter(|e| !e.is_empty()).map(str::to_uppercase);
+
+    items
+        .iter()
+        .filter(|it| !it.symbol.trim().is_empty())
+        .filter(move |it| kind.as_deref().is_none_or(|k| type_matches(&it.kind, k)))
+        .filter(move |it| exchange.as_deref().is_none_or(|e| symbol_exchange(&it.symbol) == e))
+}
+
+/// Drops entries without a symbol, keeps only `kind` (e.g. "Common Stock", "ETF") and
+/// `exchange` (see `symbol_exchange`) when given, and caps the list at 10.
+pub fn filter_results<'a>(items: &'a [SearchItem], kind: Option<&str>, exchange: Option<&str>) -> Vec<&'a SearchItem> {
+    matching_results(items, kind, exchange).take(DEFAULT_SEARCH_LIMIT).collect()
+}
+
+/// One page of search matches.
+#[derive(Debug)]
+pub struct SearchPage<'a> {
+    pub items: Vec<&'a SearchItem>,
+    // matches across all pages
+    pub total: usize,
+    // where the next page starts, if there is one
+    pub next_offset: Option<usize>,
+}
+
+/// `filter_results` with paging; `limit` is clamped to 1..=MAX_SEARCH_LIMIT.
+pub fn page_results<'a>(
+    items: &'a [SearchItem],
+    kind: Option<&str>,
+    exchange: Option<&str>,
+    offset: usize,
+    limit: usize,
+) -> SearchPage<'a> {
+    let limit = limit.clamp(1, MAX_SEARCH_LIMIT);
+    let all: Vec<&SearchItem> = matching_results(items, kind, exchange).collect();
+    let total = all.len();
 
-pub async fn search_results_ctx(state: &AppState, query: &str) -> serde_json::Value {
+    let items: Vec<&SearchItem> = all.into_iter().skip(offset).take(limit).collect();
+    let end = offset.saturating_add(items.len());
+    let next_offset = (end < total).then_some(end);
+
+    SearchPage { items, total, next_offset }
+}
+
+/// Context for `partials/search_results`. Pages after the first (`offset` > 0)
+/// render as bare rows appended to the list; Finnhub is only hit on a cache miss.
+/// An empty `exchange` means every exchange.
+pub async fn search_results_ctx(
+    state: &AppState,
+    query: &str,
+    kind: Option<&str>,
+    exchange: Option<&str>,
+    offset: usize,
+    limit: usize,
+) -> serde_json::Value {
     let q = query.trim().to_string();
 
     if q.is_empty() {
@@ -15,11 +154,11 @@ pub async fn search_results_ctx(state: &AppState, query: &str) -> serde_json::Va
 
     match state.finnhub.search(&q).await {
         Ok(resp) => {
-            let results: Vec<_> = resp
-                .result
+            let page = page_results(&resp.result, kind, exchange, offset, limit);
+            let count = page.items.len();
+            let results: Vec<_> = page
+                .items
                 .into_iter()
-                .filter(|it| !it.symbol.trim().is_empty())
-                .take(10)
                 .map(|it| {
                     json!({
                         "symbol": it.symbol,
@@ -38,7 +177,14 @@ pub async fn search_results_ctx(state: &AppState, query: &str) -> serde_json::Va
 
             json!({
                 "query": q,
+                "type": kind.unwrap_or_default(),
+                "exchange": exchange.unwrap_or_default(),
                 "results": results_val,
+                "count": offset + count,
+                "total": page.total,
+                "next_offset": page.next_offset,
+                "limit": limit.clamp(1, MAX_SEARCH_LIMIT),
+                "append": offset > 0,
                 "error": serde_json::Value::Null
             })
         }
@@ -50,9 +196,143 @@ pub async fn search_results_ctx(state: &AppState, query: &str) -> serde_json::Va
     }
 }
 
+/// Whether a quote stamped `t` (unix seconds) is stale at `now`, plus its
+/// "YYYY-MM-DD HH:MM UTC" time. A missing timestamp counts as stale.
+pub fn quote_freshness(t: i64, now: i64) -> (bool, Option<String>) {
+    let updated_at = (t > 0)
+        .then(|| chrono::DateTime::from_timestamp(t, 0))
+        .flatten()
+        .map(|d| d.format("%Y-%m-%d %H:%M UTC").to_string());
+    (t <= 0 || now - t > QUOTE_STALE_SECS, updated_at)
+}
+
+/// Where `price` sits between the day's `low` (0%) and `high` (100%), clamped.
+/// None when there is no range yet (missing figures or a flat day).
+pub fn day_range_pct(price: f64, low: f64, high: f64) -> Option<f64> {
+    if !(price.is_finite() && low > 0.0 && high > low) {
+        return None;
+    }
+    Some(((price - low) / (high - low) * 100.0).clamp(0.0, 100.0))
+}
+
+/// The session figures the details page and position panel show next to the price.
+#[derive(Debug, Clone, Serialize)]
+pub struct DayRange {
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub prev_close: f64,
+    pub pct: Option<f64>,
+}
+
+/// None when Finnhub sent no session figures (it zeroes them for unknown symbols).
+pub fn day_range(q: &QuoteResponse) -> Option<DayRange> {
+    if q.h <= 0.0 || q.l <= 0.0 {
+        return None;
+    }
+    Some(DayRange {
+        open: q.o,
+        high: q.h,
+        low: q.l,
+        prev_close: q.pc,
+        pct: day_range_pct(q.c, q.l, q.h),
+    })
+}
+
 pub async fn quote_ctx(state: &AppState, symbol: &str) -> serde_json::Value {
     match state.finnhub.quote(symbol).await {
-        Ok(q) => json!({ "quote": q, "error": serde_json::Value::Null }),
-        Err(err) => json!({ "quote": serde_json::Value::Null, "error": err }),
+        // delisted symbols can come back with a timestamp but no price
+        Ok(q) if !q.c.is_finite() || q.c <= 0.0 => {
+            json!({ "quote": serde_json::Value::Null, "error": NO_DATA })
+        }
+        Ok(q) => {
+            let (stale, updated_at) = quote_freshness(q.t, chrono::Utc::now().timestamp());
+            json!({
+                "day_range": day_range(&q),
+                "quote": q,
+                "error": serde_json::Value::Null,
+                "stale": stale,
+                "updated_at": updated_at,
+            })
+        }
+        Err(FinnhubError::NotFound) => json!({ "quote": serde_json::Value::Null, "error": NO_DATA }),
+        Err(err) => json!({ "quote": serde_json::Value::Null, "error": err.to_string() }),
     }
 }
+
+// A `profiles` document. `profile` is null when Finnhub has none (ETFs, crypto), so
+// those symbols aren't refetched on every view either.
+#[derive(Debug, Serialize, Deserialize)]
+struct StoredProfile {
+    #[serde(rename = "_id")]
+    symbol: String,
+    profile: Option<CompanyProfile>,
+    fetched_at: i64,
+}
+
+pub fn profile_is_fresh(fetched_at: i64, now: i64, max_age_hours: u64) -> bool {
+    now - fetched_at < (max_age_hours as i64) * 60 * 60
+}
+
+/// The company profile from the `profiles` collection, refetched from Finnhub once it
+/// is older than PROFILE_MAX_AGE_HOURS. A stale copy is still served if Finnhub fails.
+pub async fn get_profile(state: &AppState, symbol: &str) -> Result<Option<CompanyProfile>, AppError> {
+    let sym = normalize_symbol(symbol);
+    let profiles = state.db.collection::<StoredProfile>("profiles");
+
+    let stored = profiles.find_one(doc! { "_id": &sym }, None).await?;
+    if let Some(s) = &stored
+        && profile_is_fresh(s.fetched_at, state.clock.timestamp(), state.settings.profile_max_age_hours)
+    {
+        return Ok(s.profile.clone());
+    }
+
+    match refresh_profile(state, &sym).await {
+        Ok(p) => Ok(p),
+        Err(e) => match stored {
+            Some(s) => {
+                tracing::warn!("serving stale profile for {sym}: {e}");
+                Ok(s.profile)
+            }
+            None => Err(e),
+        },
+    }
+}
+
+/// Fetches the profile from Finnhub regardless of age and stores it.
+pub async fn refresh_profile(state: &AppState, symbol: &str) -> Result<Option<CompanyProfile>, AppError> {
+    let sym = normalize_symbol(symbol);
+    let profile = state.finnhub.company_profile(&sym).await?;
+
+    let stored = StoredProfile {
+        symbol: sym.clone(),
+        profile: profile.clone(),
+        fetched_at: state.clock.timestamp(),
+    };
+    state
+        .db
+        .collection::<StoredProfile>("profiles")
+        .replace_one(doc! { "_id": &sym }, &stored, ReplaceOptions::builder().upsert(true).build())
+        .await?;
+
+    Ok(profile)
+}
+
+/// `get_profile` for several symbols, a few at a time; symbols without a profile or
+/// whose lookup failed are left out.
+pub async fn get_profiles(state: &AppState, symbols: &[String]) -> HashMap<String, CompanyProfile> {
+    let futs: Vec<_> = symbols
+        .iter()
+        .map(|s| async move { (s.clone(), get_profile(state, s).await) })
+        .collect();
+
+    let results: Vec<(String, Result<Option<CompanyProfile>, AppError>)> = stream::iter(futs)
+        .buffer_unordered(MAX_CONCURRENT_QUOTES)
+        .collect()
+        .await;
+
+    results
+        .into_iter()
+        .filter_map(|(s, res)| res.ok().flatten().map(|p| (s, p)))
+        .collect()
+}
@@ -0,0 +1,406 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures_util::{SinkExt, StreamExt};
+use serde::Deserialize;
+use tokio::sync::{broadcast, mpsc};
+use tokio_tungstenite::{connect_async, tungstenite::protocol::Message};
+
+// Upstream failures in a row before subscribers are told to give up.
+const MAX_RECONNECT_ATTEMPTS: u32 = 5;
+
+type FinnhubStream = tokio_tungstenite::WebSocketStream<
+    tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>,
+>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TradeTick {
+    pub symbol: String,
+    pub price: f64,
+    pub volume: f64,
+    // Unix milliseconds, as Finnhub sends it.
+    pub timestamp: i64,
+}
+
+#[derive(Debug, Clone)]
+pub enum RelayEvent {
+    // Trades from one Finnhub frame for a single symbol.
+    Trades { symbol: String, trades: Vec<TradeTick> },
+    // Upstream connection state: kind is "status" or "error".
+    Status { kind: &'static str, message: String },
+    // Finnhub refused this symbol (e.g. not on the free tier); holders should drop it.
+    Unsupported { symbol: String },
+}
+
+enum Command {
+    Acquire(String),
+    Release(String),
+}
+
+/// Reference counts per symbol; the upstream is subscribed while a count is non-zero.
+#[derive(Debug, Default)]
+pub struct SymbolRefs {
+    counts: HashMap<String, usize>,
+}
+
+impl SymbolRefs {
+    /// Returns true when this is the first reference, i.e. upstream must subscribe.
+    pub fn acquire(&mut self, symbol: &str) -> bool {
+        let count = self.counts.entry(symbol.to_string()).or_insert(0);
+        *count += 1;
+        *count == 1
+    }
+
+    /// Returns true when the last reference is gone, i.e. upstream must unsubscribe.
+    pub fn release(&mut self, symbol: &str) -> bool {
+        let Some(count) = self.counts.get_mut(symbol) else {
+            return false;
+        };
+        *count -= 1;
+        if *count == 0 {
+            self.counts.remove(symbol);
+            return true;
+        }
+        false
+    }
+
+    pub fn count(&self, symbol: &str) -> usize {
+        self.counts.get(symbol).copied().unwrap_or(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+
+    pub fn symbols(&self) -> impl Iterator<Item = &String> {
+        self.counts.keys()
+    }
+}
+
+/// Handle to the single upstream Finnhub socket shared by every `/ws/trades*` client.
+#[derive(Clone)]
+pub struct TradeRelay {
+    cmd_tx: mpsc::UnboundedSender<Command>,
+    events_tx: broadcast::Sender<Arc<RelayEvent>>,
+}
+
+impl TradeRelay {
+    pub fn spawn(token: String) -> Self {
+        let (cmd_tx, cmd_rx) = mpsc::unbounded_channel();
+        let (events_tx, _events_rx) = broadcast::channel(1024);
+
+        tokio::spawn(run(token, cmd_rx, events_tx.clone()));
+
+        Self { cmd_tx, events_tx }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<Arc<RelayEvent>> {
+        self.events_tx.subscribe()
+    }
+
+    pub fn acquire(&self, symbol: &str) {
+        let _ = self.cmd_tx.send(Command::Acquire(symbol.to_string()));
+    }
+
+    pub fn release(&self, symbol: &str) {
+        let _ = self.cmd_tx.send(Command::Release(symbol.to_string()));
+    }
+}
+
+#[derive(Deserialize)]
+struct RawTrade {
+    s: String,
+    p: f64,
+    #[serde(default)]
+    v: f64,
+    #[serde(default)]
+    t: i64,
+}
+
+#[derive(Deserialize)]
+struct TradeFrame {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    data: Vec<serde_json::Value>,
+}
+
+/// Parses a Finnhub `{"type":"trade","data":[{"p":..,"s":..,"v":..,"t":..}]}` frame and
+/// groups its trades by symbol. Anything that isn't a trade frame (pings, errors) yields nothing.
+pub fn split_trades(text: &str) -> Vec<(String, Vec<TradeTick>)> {
+    let Ok(frame) = serde_json::from_str::<TradeFrame>(text) else {
+        return Vec::new();
+    };
+    if frame.kind != "trade" {
+        return Vec::new();
+    }
+
+    let mut by_symbol: Vec<(String, Vec<TradeTick>)> = Vec::new();
+    for raw in frame.data {
+        let Ok(raw) = serde_json::from_value::<RawTrade>(raw) else {
+            continue;
+        };
+        let tick = TradeTick {
+            symbol: raw.s,
+            price: raw.p,
+            volume: raw.v,
+            timestamp: raw.t,
+        };
+        match by_symbol.iter_mut().find(|(s, _)| *s == tick.symbol) {
+            Some((_, trades)) => trades.push(tick),
+            None => by_symbol.push((tick.symbol.clone(), vec![tick])),
+        }
+    }
+
+    by_symbol
+}
+
+#[derive(Deserialize)]
+struct ErrorFrame {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    msg: String,
+}
+
+/// The message of a Finnhub `{"type":"error","msg":..}` frame, if `text` is one.
+pub fn upstream_error(text: &str) -> Option<String> {
+    let frame = serde_json::from_str::<ErrorFrame>(text).ok()?;
+    (frame.kind == "error").then_some(frame.msg)
+}
+
+/// Which watched symbol an upstream error is about: the one the message names,
+/// else the latest subscribe, since Finnhub answers a bad subscribe right away.
+pub fn erroring_symbol<'a>(
+    msg: &str,
+    watched: impl IntoIterator<Item = &'a String>,
+    last_subscribed: Option<&str>,
+) -> Option<String> {
+    let watched: Vec<&String> = watched.into_iter().collect();
+
+    let named = msg
+        .split(|c: char| !(c.is_ascii_alphanumeric() || c == '.' || c == ':' || c == '-'))
+        .find_map(|w| watched.iter().find(|s| s.eq_ignore_ascii_case(w)));
+    if let Some(sym) = named {
+        return Some(sym.to_string());
+    }
+
+    last_subscribed
+        .filter(|last| watched.iter().any(|s| s == last))
+        .map(str::to_string)
+}
+
+/// Coalesces trades per symbol between flushes into one "last price + volume" update.
+#[derive(Debug, Default)]
+pub struct TradeBatcher {
+    pending: BTreeMap<String, TradeTick>,
+}
+
+impl TradeBatcher {
+    pub fn push(&mut self, tick: &TradeTick) {
+        match self.pending.get_mut(&tick.symbol) {
+            Some(agg) => {
+                if tick.timestamp >= agg.timestamp {
+                    agg.price = tick.price;
+                    agg.timestamp = tick.timestamp;
+                }
+                agg.volume += tick.volume;
+            }
+            None => {
+                self.pending.insert(tick.symbol.clone(), tick.clone());
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Drains the window: one coalesced tick per symbol, in symbol order.
+    pub fn drain(&mut self) -> Vec<TradeTick> {
+        std::mem::take(&mut self.pending).into_values().collect()
+    }
+
+    /// Drains the window as one Finnhub-shaped trade frame (one entry per symbol),
+    /// or None if nothing traded.
+    pub fn flush(&mut self) -> Option<String> {
+        if self.pending.is_empty() {
+            return None;
+        }
+
+        let data: Vec<serde_json::Value> = self
+            .drain()
+            .into_iter()
+            .map(|t| serde_json::json!({ "s": t.symbol, "p": t.price, "v": t.volume, "t": t.timestamp }))
+            .collect();
+
+        Some(serde_json::json!({ "type": "trade", "data": data }).to_string())
+    }
+}
+
+fn upstream_message(kind: &str, symbol: &str) -> Message {
+    Message::Text(serde_json::json!({ "type": kind, "symbol": symbol }).to_string())
+}
+
+fn backoff(attempt: u32) -> Duration {
+    // 0.5s, 1s, 2s, 4s, 8s ...
+    Duration::from_millis(500 * 2u64.pow(attempt.saturating_sub(1).min(4)))
+}
+
+fn publish_status(events_tx: &broadcast::Sender<Arc<RelayEvent>>, kind: &'static str, message: &str) {
+    let _ = events_tx.send(Arc::new(RelayEvent::Status {
+        kind,
+        message: message.to_string(),
+    }));
+}
+
+// Applies a command to the ref counts; returns the upstream frame it requires, if any.
+fn apply(refs: &mut SymbolRefs, cmd: Command) -> Option<Message> {
+    match cmd {
+        Command::Acquire(s) => refs.acquire(&s).then(|| upstream_message("subscribe", &s)),
+        Command::Release(s) => refs.release(&s).then(|| upstream_message("unsubscribe", &s)),
+    }
+}
+
+enum PumpEnd {
+    Idle,
+    UpstreamLost,
+    Shutdown,
+}
+
+async fn run(
+    token: String,
+    mut cmd_rx: mpsc::UnboundedReceiver<Command>,
+    events_tx: broadcast::Sender<Arc<RelayEvent>>,
+) {
+    let url = format!("wss://ws.finnhub.io/?token={}", token);
+    let mut refs = SymbolRefs::default();
+    let mut attempt: u32 = 0;
+
+    loop {
+        // No one is watching anything: stay disconnected until a client shows up.
+        while refs.is_empty() {
+            match cmd_rx.recv().await {
+                Some(cmd) => {
+                    apply(&mut refs, cmd);
+                }
+                None => return,
+            }
+            attempt = 0;
+        }
+
+        tracing::info!("Connecting to Finnhub WS...");
+        let lost = match connect_async(url.as_str()).await {
+            Ok((fh_ws, _)) => {
+                tracing::info!("Finnhub WS connected OK");
+                if attempt > 0 {
+                    publish_status(&events_tx, "status", "reconnected");
+                }
+                attempt = 0;
+
+                match pump(fh_ws, &mut refs, &mut cmd_rx, &events_tx).await {
+                    PumpEnd::Idle => continue,
+                    PumpEnd::Shutdown => return,
+                    PumpEnd::UpstreamLost => "Finnhub WS connection lost".to_string(),
+                }
+            }
+            Err(e) => format!("Finnhub WS connect failed: {}", e),
+        };
+
+        attempt += 1;
+        if attempt > MAX_RECONNECT_ATTEMPTS {
+            tracing::error!("{}; giving up after {} attempts", lost, MAX_RECONNECT_ATTEMPTS);
+            publish_status(&events_tx, "error", &lost);
+            attempt = 0;
+        } else {
+            tracing::warn!("{}; reconnecting (attempt {})", lost, attempt);
+            publish_status(&events_tx, "status", "reconnecting");
+        }
+
+        // Keep tracking clients during the backoff so the next connect subscribes the right set.
+        let sleep = tokio::time::sleep(backoff(attempt.max(1)));
+        tokio::pin!(sleep);
+        loop {
+            tokio::select! {
+                _ = &mut sleep => break,
+                cmd = cmd_rx.recv() => match cmd {
+                    Some(cmd) => {
+                        apply(&mut refs, cmd);
+                    }
+                    None => return,
+                },
+            }
+        }
+    }
+}
+
+async fn pump(
+    fh_ws: FinnhubStream,
+    refs: &mut SymbolRefs,
+    cmd_rx: &mut mpsc::UnboundedReceiver<Command>,
+    events_tx: &broadcast::Sender<Arc<RelayEvent>>,
+) -> PumpEnd {
+    let (mut fh_write, mut fh_read) = fh_ws.split();
+
+    let mut last_subscribed: Option<String> = None;
+    for s in refs.symbols() {
+        if fh_write.send(upstream_message("subscribe", s)).await.is_err() {
+            return PumpEnd::UpstreamLost;
+        }
+        last_subscribed = Some(s.clone());
+    }
+
+    loop {
+        tokio::select! {
+            cmd = cmd_rx.recv() => {
+                let Some(cmd) = cmd else {
+                    let _ = fh_write.close().await;
+                    return PumpEnd::Shutdown;
+                };
+                let acquired = match &cmd {
+                    Command::Acquire(s) => Some(s.clone()),
+                    Command::Release(_) => None,
+                };
+                if let Some(msg) = apply(refs, cmd) {
+                    if fh_write.send(msg).await.is_err() {
+                        return PumpEnd::UpstreamLost;
+                    }
+                    if acquired.is_some() {
+                        last_subscribed = acquired;
+                    }
+                }
+                if refs.is_empty() {
+                    tracing::info!("No trade subscribers left; closing Finnhub WS");
+                    let _ = fh_write.close().await;
+                    return PumpEnd::Idle;
+                }
+            }
+
+            fh_msg = fh_read.next() => {
+                match fh_msg {
+                    Some(Ok(Message::Text(txt))) => {
+                        if let Some(msg) = upstream_error(&txt) {
+                            match erroring_symbol(&msg, refs.symbols(), last_subscribed.as_deref()) {
+                                Some(symbol) => {
+                                    tracing::warn!("Finnhub refused {}: {}", symbol, msg);
+                                    let _ = events_tx.send(Arc::new(RelayEvent::Unsupported { symbol }));
+                                }
+                                None => tracing::warn!("Finnhub WS error: {}", msg),
+                            }
+                            continue;
+                        }
+                        for (symbol, trades) in split_trades(&txt) {
+                            let _ = events_tx.send(Arc::new(RelayEvent::Trades { symbol, trades }));
+                        }
+                    }
+                    Some(Ok(Message::Ping(payload))) => {
+                        let _ = fh_write.send(Message::Pong(payload)).await;
+                    }
+                    Some(Ok(Message::Close(_))) | None | Some(Err(_)) => return PumpEnd::UpstreamLost,
+                    Some(Ok(_)) => {}
+                }
+            }
+        }
+    }
+}
@@ -1,15 +1,16 @@
 use std::collections::HashMap;
 
-use chrono::Utc;
 use mongodb::bson::{doc, oid::ObjectId};
-use mongodb::options::UpdateOptions;
+use mongodb::options::{FindOneAndUpdateOptions, FindOneOptions, ReturnDocument};
 
 use crate::{
-    models::{Order, Position},
+    config::CostBasisMethod,
+    error::AppError,
+    models::{Lot, Order, Position, ORDER_FILLED},
     AppState,
 };
 
-use super::{account_service, auth_service::FieldErrors};
+use super::{account_service, auth_service::FieldErrors, metrics::TRADES_TOTAL, portfolio_service, stocks_service};
 
 #[derive(Debug, Clone)]
 pub struct BuyResult {
@@ -28,133 +29,287 @@ pub struct SellResult {
     pub fill_price: f64,
     pub proceeds: f64,
     pub new_cash: f64,
+    // proceeds minus the cost basis of the shares sold, per COST_BASIS_METHOD
+    pub realized_pnl: f64,
     pub remaining: Option<Position>,
 }
 
-async fn get_position(state: &AppState, user_id: ObjectId, symbol: &str) -> Result<Option<Position>, String> {
+async fn get_position(state: &AppState, user_id: ObjectId, symbol: &str) -> Result<Option<Position>, AppError> {
     let positions = state.db.collection::<Position>("positions");
-    positions
-        .find_one(doc! { "user_id": user_id, "symbol": symbol }, None)
+    // closed rows may share the symbol; the open one wins
+    let opts = FindOneOptions::builder().sort(doc! { "qty": -1 }).build();
+    Ok(positions
+        .find_one(doc! { "user_id": user_id, "symbol": symbol }, opts)
+        .await?)
+}
+
+/// Adds a buy of `qty` shares at `price` to the user's position in one update,
+/// opening it if there is none. Quantity and average are worked out from the stored
+/// row, never from an earlier read, so a sell landing in between keeps its effect.
+pub async fn add_shares(
+    state: &AppState,
+    user_id: ObjectId,
+    symbol: &str,
+    qty: i64,
+    price: f64,
+    lot: Option<&Lot>,
+    now: i64,
+) -> Result<Position, AppError> {
+    let positions = state.db.collection::<Position>("positions");
+
+    let held = doc! { "$ifNull": ["$qty", 0_i64] };
+    let mut set = doc! {
+        "qty": { "$add": [held.clone(), qty] },
+        // a row emptied by a sell (and not yet deleted) starts over at this price
+        "avg_price": {
+            "$cond": [
+                { "$gt": [held.clone(), 0_i64] },
+                {
+                    "$divide": [
+                        { "$add": [{ "$multiply": ["$avg_price", held.clone()] }, price * (qty as f64)] },
+                        { "$add": [held, qty] },
+                    ]
+                },
+                price,
+            ]
+        },
+        "created_at": { "$ifNull": ["$created_at", now] },
+        "updated_at": now,
+    };
+    if let Some(lot) = lot {
+        let lot = doc! { "qty": lot.qty, "price": lot.price, "bought_at": lot.bought_at };
+        set.insert("lots", doc! { "$concatArrays": [{ "$ifNull": ["$lots", []] }, [lot]] });
+    }
+
+    // closed rows may share the symbol; the open one wins
+    let opts = FindOneAndUpdateOptions::builder()
+        .sort(doc! { "qty": -1 })
+        .upsert(true)
+        .return_document(ReturnDocument::After)
+        .build();
+    let filter = doc! { "user_id": user_id, "symbol": symbol };
+    let update = vec![doc! { "$set": set }];
+
+    let res = match positions
+        .find_one_and_update(filter.clone(), update.clone(), opts.clone())
         .await
-        .map_err(|e| e.to_string())
+    {
+        // two first buys raced to open the position; the loser adds to the winner's row
+        Err(e) if e.to_string().contains("E11000") => positions.find_one_and_update(filter, update, opts).await?,
+        res => res?,
+    };
+
+    res.ok_or_else(|| AppError::Db("position missing after upsert".to_string()))
 }
 
-async fn upsert_position(state: &AppState, pos: &Position) -> Result<(), String> {
+/// Takes `qty` shares off a position in one conditional update, so a concurrent buy's
+/// shares are never overwritten. Ok(None) means it no longer holds that many.
+pub async fn take_shares(state: &AppState, id: ObjectId, qty: i64, now: i64) -> Result<Option<Position>, AppError> {
     let positions = state.db.collection::<Position>("positions");
-    positions
-        .update_one(
-            doc! { "_id": pos.id },
+
+    let opts = FindOneAndUpdateOptions::builder()
+        .return_document(ReturnDocument::After)
+        .build();
+
+    Ok(positions
+        .find_one_and_update(
+            doc! { "_id": id, "qty": { "$gte": qty } },
             doc! {
-                "$set": {
-                    "user_id": pos.user_id,
-                    "symbol": &pos.symbol,
-                    "qty": pos.qty,
-                    "avg_price": pos.avg_price,
-                    "updated_at": pos.updated_at,
-                }
+                "$inc": { "qty": -qty },
+                "$set": { "updated_at": now },
             },
-            UpdateOptions::builder().upsert(true).build(),
+            opts,
         )
-        .await
-        .map_err(|e| e.to_string())?;
-    Ok(())
+        .await?)
 }
 
-async fn delete_position(state: &AppState, id: ObjectId) -> Result<(), String> {
+/// FIFO counterpart of `take_shares`: writes the lots left after a sell, but only if the
+/// position still holds `expected_qty` (the lots were worked out from that read).
+/// Ok(None) means something else changed it in between.
+pub async fn take_lots(
+    state: &AppState,
+    id: ObjectId,
+    expected_qty: i64,
+    lots: &[Lot],
+    now: i64,
+) -> Result<Option<Position>, AppError> {
     let positions = state.db.collection::<Position>("positions");
-    positions
-        .delete_one(doc! { "_id": id }, None)
-        .await
-        .map_err(|e| e.to_string())?;
-    Ok(())
+
+    let qty: i64 = lots.iter().map(|l| l.qty).sum();
+    let lots_bson: Vec<_> = lots
+        .iter()
+        .map(|l| doc! { "qty": l.qty, "price": l.price, "bought_at": l.bought_at })
+        .collect();
+
+    let mut set = doc! { "qty": qty, "lots": lots_bson, "updated_at": now };
+    if let Some(avg) = lots_avg_price(lots) {
+        set.insert("avg_price", avg);
+    }
+
+    let opts = FindOneAndUpdateOptions::builder()
+        .return_document(ReturnDocument::After)
+        .build();
+
+    Ok(positions
+        .find_one_and_update(doc! { "_id": id, "qty": expected_qty }, doc! { "$set": set }, opts)
+        .await?)
+}
+
+/// Removes a position only while it is still empty: a buy landing between the
+/// sell and this call keeps its shares. Safe to repeat.
+pub async fn delete_empty_position(state: &AppState, id: ObjectId) -> Result<bool, AppError> {
+    let positions = state.db.collection::<Position>("positions");
+    let res = positions.delete_one(doc! { "_id": id, "qty": 0 }, None).await?;
+    Ok(res.deleted_count > 0)
 }
 
-pub async fn get_user_position(state: &AppState, user_id: ObjectId, symbol: &str) -> Result<Option<Position>, String> {
-    let sym = symbol.to_uppercase();
+pub async fn get_user_position(state: &AppState, user_id: ObjectId, symbol: &str) -> Result<Option<Position>, AppError> {
+    let sym = stocks_service::normalize_symbol(symbol);
     get_position(state, user_id, &sym).await
 }
 
-pub async fn market_buy(state: &AppState, user_id: ObjectId, symbol: &str, qty: i64) -> Result<BuyResult, FieldErrors> {
+// Finnhub can answer with c == 0 (unknown/delisted); filling at that price would hand out free shares.
+fn has_valid_price(price: f64) -> bool {
+    price.is_finite() && price > 0.0
+}
+
+/// Whole shares in `fraction` of a `held`-share position, rounded down.
+pub fn fraction_qty(held: i64, fraction: f64) -> i64 {
+    ((held as f64) * fraction).floor() as i64
+}
+
+/// `quoted` moved `bps` basis points against the trader: up for buys, down for sells.
+pub fn slipped_price(quoted: f64, bps: f64, buy: bool) -> f64 {
+    let factor = bps / 10_000.0;
+    if buy {
+        quoted * (1.0 + factor)
+    } else {
+        quoted * (1.0 - factor)
+    }
+}
+
+/// True when holding `qty` shares at `price` would go over the configured cap.
+pub fn exceeds_position_limit(qty: i64, price: f64, cap: Option<f64>) -> bool {
+    cap.is_some_and(|cap| (qty as f64) * price > cap)
+}
+
+/// The position's open lots, oldest first, reconciled with its qty: shares bought
+/// before lots were recorded become one leading lot at `avg_price`, and lots already
+/// sold under the average method are dropped from the front.
+pub fn open_lots(pos: &Position) -> Vec<Lot> {
+    let tracked: i64 = pos.lots.iter().map(|l| l.qty).sum();
+
+    if tracked >= pos.qty {
+        return consume_fifo(&pos.lots, tracked - pos.qty).0;
+    }
+
+    let mut lots = vec![Lot {
+        qty: pos.qty - tracked,
+        price: pos.avg_price,
+        bought_at: pos.created_at,
+    }];
+    lots.extend(pos.lots.iter().cloned());
+    lots
+}
+
+/// Takes `qty` shares off the oldest lots first; returns the lots left and the cost
+/// basis of the shares taken. Asking for more than the lots hold takes them all.
+pub fn consume_fifo(lots: &[Lot], qty: i64) -> (Vec<Lot>, f64) {
+    let mut left = qty.max(0);
+    let mut cost = 0.0;
+    let mut remaining = Vec::with_capacity(lots.len());
+
+    for lot in lots {
+        let take = lot.qty.min(left);
+        left -= take;
+        cost += lot.price * (take as f64);
+
+        if take < lot.qty {
+            remaining.push(Lot {
+                qty: lot.qty - take,
+                ..lot.clone()
+            });
+        }
+    }
+
+    (remaining, cost)
+}
+
+/// Weighted average price of the lots; None when they hold no shares.
+pub fn lots_avg_price(lots: &[Lot]) -> Option<f64> {
+    let qty: i64 = lots.iter().map(|l| l.qty).sum();
+    if qty <= 0 {
+        return None;
+    }
+    let cost: f64 = lots.iter().map(|l| l.price * (l.qty as f64)).sum();
+    Some(cost / (qty as f64))
+}
+
+pub async fn market_buy(state: &AppState, user_id: ObjectId, symbol: &str, qty: i64) -> Result<BuyResult, AppError> {
     let mut errs: FieldErrors = HashMap::new();
 
-    let sym = symbol.to_uppercase();
+    let sym = stocks_service::normalize_symbol(symbol);
 
-    if sym.trim().is_empty() {
+    if sym.is_empty() {
         errs.insert("symbol".into(), "Missing symbol.".into());
+    } else if !stocks_service::is_valid_symbol(&sym) {
+        errs.insert("symbol".into(), "Invalid symbol.".into());
     }
     if qty <= 0 {
         errs.insert("qty".into(), "Enter a valid quantity.".into());
     }
     if !errs.is_empty() {
-        return Err(errs);
+        return Err(AppError::Validation(errs));
     }
 
-    let quote = match state.finnhub.quote(&sym).await {
-        Ok(q) => q,
-        Err(e) => {
-            errs.insert("_form".into(), format!("Quote error: {e}"));
-            return Err(errs);
-        }
-    };
+    let quote = state.finnhub.quote(&sym).await?;
+    if !has_valid_price(quote.c) {
+        return Err(AppError::invalid("price", &format!("No valid market price for {sym}")));
+    }
 
-    let price = quote.c;
+    let price = slipped_price(quote.c, state.settings.slippage_bps, true);
     let total = price * (qty as f64);
 
-    let mut acc = match account_service::get_or_create_account(state, user_id).await {
-        Ok(a) => a,
-        Err(e) => {
-            errs.insert("_form".into(), format!("db error: {e}"));
-            return Err(errs);
-        }
-    };
+    let acc = account_service::get_or_create_account(state, user_id)
+        .await
+        .map_err(AppError::Db)?;
 
     if acc.cash < total {
-        errs.insert("balance".into(), "Not enough cash.".into());
-        return Err(errs);
+        return Err(AppError::invalid("balance", "Not enough cash."));
     }
 
-    let pos_opt = match get_position(state, user_id, &sym).await {
-        Ok(p) => p,
-        Err(e) => {
-            errs.insert("_form".into(), format!("db error: {e}"));
-            return Err(errs);
-        }
-    };
+    let pos_opt = get_position(state, user_id, &sym).await?;
 
-    let now = Utc::now().timestamp();
+    let new_qty = pos_opt.as_ref().map_or(0, |p| p.qty) + qty;
+    if exceeds_position_limit(new_qty, price, state.settings.max_position_notional) {
+        return Err(AppError::invalid("qty", "Position size limit reached."));
+    }
 
-    let new_pos = match pos_opt {
-        Some(mut p) => {
-            let new_qty = p.qty + qty;
-            let new_avg = ((p.avg_price * (p.qty as f64)) + total) / (new_qty as f64);
-            p.qty = new_qty;
-            p.avg_price = new_avg;
-            p.updated_at = now;
-            p
-        }
-        None => Position {
-            id: ObjectId::new(),
-            user_id,
-            symbol: sym.clone(),
-            qty,
-            avg_price: price,
-            updated_at: now,
-        },
-    };
+    let now = state.clock.timestamp();
 
-    if let Err(e) = upsert_position(state, &new_pos).await {
-        errs.insert("_form".into(), format!("db error: {e}"));
-        return Err(errs);
-    }
+    let lot = (state.settings.cost_basis_method == CostBasisMethod::Fifo).then_some(Lot {
+        qty,
+        price,
+        bought_at: now,
+    });
 
-    // deduct cash
-    acc.cash -= total;
-    acc.updated_at = now;
+    // pay first, in one conditional update, so a withdrawal racing this buy can't spend the same cash
+    let Some(acc) = account_service::debit_cash(state, user_id, total)
+        .await
+        .map_err(AppError::Db)?
+    else {
+        return Err(AppError::invalid("balance", "Not enough cash."));
+    };
 
-    if let Err(e) = account_service::set_cash(state, user_id, acc.cash, acc.updated_at).await {
-        errs.insert("_form".into(), format!("db error: {e}"));
-        return Err(errs);
-    }
+    let new_pos = match add_shares(state, user_id, &sym, qty, price, lot.as_ref(), now).await {
+        Ok(p) => p,
+        Err(e) => {
+            if let Err(refund) = account_service::credit_cash(state, user_id, total).await {
+                tracing::error!("failed to refund {total} to {user_id} after a failed buy: {refund}");
+            }
+            return Err(e);
+        }
+    };
 
     // store order
     let orders = state.db.collection::<Order>("orders");
@@ -167,12 +322,18 @@ pub async fn market_buy(state: &AppState, user_id: ObjectId, symbol: &str, qty:
         price,
         total,
         created_at: now,
+        realized_pnl: None,
+        quoted_price: Some(quote.c),
+        status: ORDER_FILLED.to_string(),
+        filled_qty: None,
     };
     let _ = orders.insert_one(order, None).await;
+    metrics::counter!(TRADES_TOTAL, "side" => "buy").increment(1);
 
     // broadcast so other tabs/pages update
     let _ = state.events_tx.send("ordersUpdated".to_string());
     let _ = state.events_tx.send("positionUpdated".to_string());
+    let _ = state.events_tx.send(portfolio_service::holdings_changed_event(user_id));
     let _ = state.events_tx.send("cashUpdated".to_string());
 
     Ok(BuyResult {
@@ -185,81 +346,74 @@ pub async fn market_buy(state: &AppState, user_id: ObjectId, symbol: &str, qty:
     })
 }
 
-pub async fn market_sell(state: &AppState, user_id: ObjectId, symbol: &str, qty: i64) -> Result<SellResult, FieldErrors> {
+pub async fn market_sell(state: &AppState, user_id: ObjectId, symbol: &str, qty: i64) -> Result<SellResult, AppError> {
     let mut errs: FieldErrors = HashMap::new();
 
-    let sym = symbol.to_uppercase();
+    let sym = stocks_service::normalize_symbol(symbol);
 
-    if sym.trim().is_empty() {
+    if sym.is_empty() {
         errs.insert("symbol".into(), "Missing symbol.".into());
+    } else if !stocks_service::is_valid_symbol(&sym) {
+        errs.insert("symbol".into(), "Invalid symbol.".into());
     }
     if qty <= 0 {
         errs.insert("qty".into(), "Enter a valid quantity.".into());
     }
     if !errs.is_empty() {
-        return Err(errs);
+        return Err(AppError::Validation(errs));
     }
 
-    let quote = match state.finnhub.quote(&sym).await {
-        Ok(q) => q,
-        Err(e) => {
-            errs.insert("_form".into(), format!("Quote error: {e}"));
-            return Err(errs);
-        }
-    };
+    let quote = state.finnhub.quote(&sym).await?;
+    if !has_valid_price(quote.c) {
+        return Err(AppError::invalid("price", &format!("No valid market price for {sym}")));
+    }
 
-    let price = quote.c;
+    let price = slipped_price(quote.c, state.settings.slippage_bps, false);
 
-    let pos_opt = match get_position(state, user_id, &sym).await {
-        Ok(p) => p,
-        Err(e) => {
-            errs.insert("_form".into(), format!("db error: {e}"));
-            return Err(errs);
-        }
-    };
+    let pos_opt = get_position(state, user_id, &sym).await?;
 
-    let Some(mut pos) = pos_opt else {
-        errs.insert("qty".into(), "You have no position to sell.".into());
-        return Err(errs);
+    let Some(pos) = pos_opt else {
+        return Err(AppError::invalid("qty", "You have no position to sell."));
     };
 
     if qty > pos.qty {
-        errs.insert("qty".into(), "You don't have that many shares.".into());
-        return Err(errs);
+        return Err(AppError::invalid("qty", "You don't have that many shares."));
     }
 
     let proceeds = price * (qty as f64);
-    let now = Utc::now().timestamp();
-
-    pos.qty -= qty;
-    pos.updated_at = now;
+    let now = state.clock.timestamp();
+
+    let (pos, cost_basis) = match state.settings.cost_basis_method {
+        CostBasisMethod::Average => {
+            let cost_basis = pos.avg_price * (qty as f64);
+            // another sell may have taken the shares since the read above
+            let Some(pos) = take_shares(state, pos.id, qty, now).await? else {
+                return Err(AppError::invalid("qty", "You don't have that many shares."));
+            };
+            (pos, cost_basis)
+        }
+        CostBasisMethod::Fifo => {
+            let (lots, cost_basis) = consume_fifo(&open_lots(&pos), qty);
+            let Some(pos) = take_lots(state, pos.id, pos.qty, &lots, now).await? else {
+                return Err(AppError::invalid("qty", "Your position changed. Try again."));
+            };
+            (pos, cost_basis)
+        }
+    };
 
     let remaining = if pos.qty == 0 {
-        let _ = delete_position(state, pos.id).await;
+        let _ = delete_empty_position(state, pos.id).await;
         None
     } else {
-        if let Err(e) = upsert_position(state, &pos).await {
-            errs.insert("_form".into(), format!("db error: {e}"));
-            return Err(errs);
-        }
-        Some(pos.clone())
+        Some(pos)
     };
 
-    let mut acc = match account_service::get_or_create_account(state, user_id).await {
-        Ok(a) => a,
-        Err(e) => {
-            errs.insert("_form".into(), format!("db error: {e}"));
-            return Err(errs);
-        }
-    };
-
-    acc.cash += proceeds;
-    acc.updated_at = now;
-
-    if let Err(e) = account_service::set_cash(state, user_id, acc.cash, acc.updated_at).await {
-        errs.insert("_form".into(), format!("db error: {e}"));
-        return Err(errs);
-    }
+    account_service::get_or_create_account(state, user_id)
+        .await
+        .map_err(AppError::Db)?;
+    let acc = account_service::credit_cash(state, user_id, proceeds)
+        .await
+        .map_err(AppError::Db)?;
 
     // store order
     let orders = state.db.collection::<Order>("orders");
@@ -272,11 +426,17 @@ pub async fn market_sell(state: &AppState, user_id: ObjectId, symbol: &str, qty:
         price,
         total: proceeds,
         created_at: now,
+        realized_pnl: Some(proceeds - cost_basis),
+        quoted_price: Some(quote.c),
+        status: ORDER_FILLED.to_string(),
+        filled_qty: None,
     };
     let _ = orders.insert_one(order, None).await;
+    metrics::counter!(TRADES_TOTAL, "side" => "sell").increment(1);
 
     let _ = state.events_tx.send("ordersUpdated".to_string());
     let _ = state.events_tx.send("positionUpdated".to_string());
+    let _ = state.events_tx.send(portfolio_service::holdings_changed_event(user_id));
     let _ = state.events_tx.send("cashUpdated".to_string());
 
     Ok(SellResult {
@@ -285,6 +445,7 @@ pub async fn market_sell(state: &AppState, user_id: ObjectId, symbol: &str, qty:
         fill_price: price,
         proceeds,
         new_cash: acc.cash,
+        realized_pnl: proceeds - cost_basis,
         remaining,
     })
 }
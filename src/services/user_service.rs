@@ -1,8 +1,9 @@
 use bcrypt::verify;
 use chrono::Utc;
 use mongodb::bson::{doc, oid::ObjectId};
+use mongodb::options::{FindOneAndUpdateOptions, ReturnDocument};
 
-use crate::{models::{Account, User}, AppState};
+use crate::{models::{Account, Alert, CashEvent, DepositKey, LoginSession, Order, PortfolioSnapshot, Position, Preferences, User, WatchlistItem}, templates, AppState};
 
 use super::{account_service, auth_service::FieldErrors};
 
@@ -27,7 +28,7 @@ pub async fn change_email(state: &AppState, user_id: ObjectId, new_email: &str)
     Ok(())
 }
 
-pub async fn change_password(state: &AppState, user_id: ObjectId, new_password: &str) -> Result<(), FieldErrors> {
+pub async fn change_password(state: &AppState, user_id: ObjectId, new_password: &str) -> Result<i32, FieldErrors> {
     let mut errs = FieldErrors::new();
 
     let users = state.db.collection::<User>("users");
@@ -56,10 +57,58 @@ pub async fn change_password(state: &AppState, user_id: ObjectId, new_password:
         }
     };
 
-    if let Err(e) = users
-        .update_one(doc! { "_id": user_id }, doc! { "$set": { "password_hash": pw_hash } }, None)
+    // changing the password also revokes every other session
+    let opts = FindOneAndUpdateOptions::builder()
+        .return_document(ReturnDocument::After)
+        .build();
+
+    match users
+        .find_one_and_update(
+            doc! { "_id": user_id },
+            doc! { "$set": { "password_hash": pw_hash }, "$inc": { "token_version": 1 } },
+            opts,
+        )
         .await
     {
+        Ok(Some(u)) => Ok(u.token_version),
+        Ok(None) => {
+            errs.insert("_form".into(), "User not found.".into());
+            Err(errs)
+        }
+        Err(e) => {
+            errs.insert("_form".into(), format!("db error: {e}"));
+            Err(errs)
+        }
+    }
+}
+
+/// Re-authentication for destructive settings actions.
+pub async fn check_password(state: &AppState, user_id: ObjectId, password: &str) -> Result<(), FieldErrors> {
+    let mut errs = FieldErrors::new();
+
+    let users = state.db.collection::<User>("users");
+
+    let db_user = match users.find_one(doc! { "_id": user_id }, None).await {
+        Ok(Some(u)) => u,
+        _ => {
+            errs.insert("_form".into(), "User not found.".into());
+            return Err(errs);
+        }
+    };
+
+    if !verify(password, &db_user.password_hash).unwrap_or(false) {
+        errs.insert("password".into(), "Password is incorrect.".into());
+        return Err(errs);
+    }
+
+    Ok(())
+}
+
+pub async fn delete_account(state: &AppState, user_id: ObjectId, password: &str) -> Result<(), FieldErrors> {
+    check_password(state, user_id, password).await?;
+
+    let mut errs = FieldErrors::new();
+    if let Err(e) = delete_user_data(state, user_id).await {
         errs.insert("_form".into(), format!("db error: {e}"));
         return Err(errs);
     }
@@ -67,25 +116,332 @@ pub async fn change_password(state: &AppState, user_id: ObjectId, new_password:
     Ok(())
 }
 
-pub async fn deposit_funds(state: &AppState, user_id: ObjectId, amount: f64) -> Result<Account, FieldErrors> {
+// All-or-nothing removal of everything owned by the user.
+async fn delete_user_data(state: &AppState, user_id: ObjectId) -> Result<(), String> {
+    let users = state.db.collection::<User>("users");
+
+    let mut session = users
+        .client()
+        .start_session(None)
+        .await
+        .map_err(|e| e.to_string())?;
+    session
+        .start_transaction(None)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let by_user = doc! { "user_id": user_id };
+
+    let res = async {
+        state
+            .db
+            .collection::<Position>("positions")
+            .delete_many_with_session(by_user.clone(), None, &mut session)
+            .await?;
+        state
+            .db
+            .collection::<Order>("orders")
+            .delete_many_with_session(by_user.clone(), None, &mut session)
+            .await?;
+        state
+            .db
+            .collection::<Alert>("alerts")
+            .delete_many_with_session(by_user.clone(), None, &mut session)
+            .await?;
+        state
+            .db
+            .collection::<DepositKey>("deposit_keys")
+            .delete_many_with_session(by_user.clone(), None, &mut session)
+            .await?;
+        state
+            .db
+            .collection::<CashEvent>("cash_events")
+            .delete_many_with_session(by_user.clone(), None, &mut session)
+            .await?;
+        state
+            .db
+            .collection::<LoginSession>("sessions")
+            .delete_many_with_session(by_user.clone(), None, &mut session)
+            .await?;
+        state
+            .db
+            .collection::<PortfolioSnapshot>("portfolio_snapshots")
+            .delete_many_with_session(by_user.clone(), None, &mut session)
+            .await?;
+        state
+            .db
+            .collection::<WatchlistItem>("watchlists")
+            .delete_many_with_session(by_user.clone(), None, &mut session)
+            .await?;
+        state
+            .db
+            .collection::<Account>("accounts")
+            .delete_one_with_session(doc! { "_id": user_id }, None, &mut session)
+            .await?;
+        // recent_symbols and preferences live on the user document itself
+        users
+            .delete_one_with_session(doc! { "_id": user_id }, None, &mut session)
+            .await?;
+        Ok::<(), mongodb::error::Error>(())
+    }
+    .await;
+
+    if let Err(e) = res {
+        let _ = session.abort_transaction().await;
+        return Err(e.to_string());
+    }
+
+    session
+        .commit_transaction()
+        .await
+        .map_err(|e| e.to_string())
+}
+
+pub const MAX_DEFAULT_QTY: i64 = 1_000_000;
+
+// Missing users and older documents both come back as the defaults.
+pub async fn get_preferences(state: &AppState, user_id: ObjectId) -> Result<Preferences, String> {
+    let users = state.db.collection::<User>("users");
+
+    users
+        .find_one(doc! { "_id": user_id }, None)
+        .await
+        .map_err(|e| e.to_string())
+        .map(|u| u.map(|u| u.preferences).unwrap_or_default())
+}
+
+pub async fn update_preferences(
+    state: &AppState,
+    user_id: ObjectId,
+    default_qty: i64,
+    display_currency: &str,
+) -> Result<Preferences, FieldErrors> {
     let mut errs = FieldErrors::new();
 
-    let mut acc = match account_service::get_or_create_account(state, user_id).await {
-        Ok(a) => a,
+    if !(1..=MAX_DEFAULT_QTY).contains(&default_qty) {
+        errs.insert("default_qty".into(), format!("Quantity must be between 1 and {MAX_DEFAULT_QTY}."));
+    }
+
+    let currency = display_currency.trim().to_uppercase();
+    if !templates::DISPLAY_CURRENCIES.contains(&currency.as_str()) {
+        errs.insert("display_currency".into(), "Unsupported currency.".into());
+    }
+
+    if !errs.is_empty() {
+        return Err(errs);
+    }
+
+    let prefs = Preferences {
+        default_qty,
+        display_currency: currency,
+    };
+
+    let users = state.db.collection::<User>("users");
+    if let Err(e) = users
+        .update_one(
+            doc! { "_id": user_id },
+            doc! { "$set": {
+                "preferences.default_qty": prefs.default_qty,
+                "preferences.display_currency": &prefs.display_currency,
+            } },
+            None,
+        )
+        .await
+    {
+        errs.insert("_form".into(), format!("db error: {e}"));
+        return Err(errs);
+    }
+
+    Ok(prefs)
+}
+
+pub const MAX_RECENT_SYMBOLS: i32 = 10;
+
+/// Moves `symbol` to the front of the user's recent list, dropping an older copy and
+/// anything past MAX_RECENT_SYMBOLS. One pipeline update, so concurrent views can't
+/// leave duplicates behind.
+pub async fn record_recent_symbol(state: &AppState, user_id: ObjectId, symbol: &str) -> Result<(), String> {
+    let users = state.db.collection::<User>("users");
+
+    let rest = doc! {
+        "$filter": {
+            "input": { "$ifNull": ["$recent_symbols", []] },
+            "cond": { "$ne": ["$$this", symbol] },
+        }
+    };
+    let update = vec![doc! {
+        "$set": {
+            "recent_symbols": { "$slice": [{ "$concatArrays": [[symbol], rest] }, MAX_RECENT_SYMBOLS] }
+        }
+    }];
+
+    users
+        .update_one(doc! { "_id": user_id }, update, None)
+        .await
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+/// Most recent first; empty for unknown users.
+pub async fn recent_symbols(state: &AppState, user_id: ObjectId) -> Result<Vec<String>, String> {
+    let users = state.db.collection::<User>("users");
+
+    users
+        .find_one(doc! { "_id": user_id }, None)
+        .await
+        .map_err(|e| e.to_string())
+        .map(|u| u.map(|u| u.recent_symbols).unwrap_or_default())
+}
+
+pub async fn bump_token_version(state: &AppState, user_id: ObjectId) -> Result<i32, String> {
+    let users = state.db.collection::<User>("users");
+
+    let opts = FindOneAndUpdateOptions::builder()
+        .return_document(ReturnDocument::After)
+        .build();
+
+    users
+        .find_one_and_update(doc! { "_id": user_id }, doc! { "$inc": { "token_version": 1 } }, opts)
+        .await
+        .map_err(|e| e.to_string())?
+        .map(|u| u.token_version)
+        .ok_or_else(|| "user not found".to_string())
+}
+
+// Records `key` for `user_id`; false when it was already recorded (a replayed submit).
+async fn claim_deposit_key(state: &AppState, user_id: ObjectId, key: &str, amount: f64) -> Result<bool, String> {
+    let keys = state.db.collection::<DepositKey>("deposit_keys");
+    let record = DepositKey {
+        id: ObjectId::new(),
+        user_id,
+        key: key.to_string(),
+        amount,
+        created_at: Utc::now().timestamp(),
+    };
+
+    match keys.insert_one(&record, None).await {
+        Ok(_) => Ok(true),
+        Err(e) if e.to_string().contains("E11000") => Ok(false),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+async fn release_deposit_key(state: &AppState, user_id: ObjectId, key: &str) {
+    let keys = state.db.collection::<DepositKey>("deposit_keys");
+    let _ = keys.delete_one(doc! { "user_id": user_id, "key": key }, None).await;
+}
+
+/// A user's own deposit: adds `amount` to their cash within the daily deposit limit.
+/// With an `idempotency_key`, only the first submission using that key deposits;
+/// repeats return the current account unchanged. Going over the limit is an
+/// "amount" error.
+pub async fn deposit_funds(
+    state: &AppState,
+    user_id: ObjectId,
+    amount: f64,
+    idempotency_key: Option<&str>,
+) -> Result<Account, FieldErrors> {
+    add_funds(state, user_id, amount, idempotency_key, state.settings.daily_deposit_limit).await
+}
+
+/// Adds `amount` outside the daily deposit limit, e.g. an admin credit or the seed balance.
+pub async fn credit_funds(state: &AppState, user_id: ObjectId, amount: f64) -> Result<Account, FieldErrors> {
+    add_funds(state, user_id, amount, None, None).await
+}
+
+async fn add_funds(
+    state: &AppState,
+    user_id: ObjectId,
+    amount: f64,
+    idempotency_key: Option<&str>,
+    daily_limit: Option<f64>,
+) -> Result<Account, FieldErrors> {
+    let mut errs = FieldErrors::new();
+
+    let key = idempotency_key.map(str::trim).filter(|k| !k.is_empty());
+
+    if let Some(key) = key {
+        match claim_deposit_key(state, user_id, key, amount).await {
+            Ok(true) => {}
+            Ok(false) => {
+                return account_service::get_or_create_account(state, user_id)
+                    .await
+                    .map_err(|e| {
+                        errs.insert("_form".into(), format!("db error: {e}"));
+                        errs
+                    });
+            }
+            Err(e) => {
+                errs.insert("_form".into(), format!("db error: {e}"));
+                return Err(errs);
+            }
+        }
+    }
+
+    if let Err(e) = account_service::get_or_create_account(state, user_id).await {
+        if let Some(key) = key {
+            release_deposit_key(state, user_id, key).await;
+        }
+        errs.insert("_form".into(), format!("db error: {e}"));
+        return Err(errs);
+    }
+
+    let day = state.clock.now().format("%Y-%m-%d").to_string();
+    if let Some(limit) = daily_limit {
+        let reserved = account_service::reserve_daily_deposit(state, user_id, amount, limit, &day).await;
+        if !matches!(reserved, Ok(true)) {
+            if let Some(key) = key {
+                release_deposit_key(state, user_id, key).await;
+            }
+            match reserved {
+                Err(e) => errs.insert("_form".into(), format!("db error: {e}")),
+                _ => errs.insert("amount".into(), "Daily deposit limit reached".into()),
+            };
+            return Err(errs);
+        }
+    }
+
+    let acc = match account_service::credit_cash(state, user_id, amount).await {
+        Ok(acc) => acc,
         Err(e) => {
+            // nothing was deposited, so let the same submission be retried
+            if let Some(key) = key {
+                release_deposit_key(state, user_id, key).await;
+            }
+            if daily_limit.is_some() {
+                account_service::release_daily_deposit(state, user_id, amount, &day).await;
+            }
             errs.insert("_form".into(), format!("db error: {e}"));
             return Err(errs);
         }
     };
 
-    acc.cash += amount;
-    acc.updated_at = Utc::now().timestamp();
+    let _ = state.events_tx.send("cashUpdated".to_string());
+
+    Ok(acc)
+}
+
+pub async fn withdraw_funds(state: &AppState, user_id: ObjectId, amount: f64) -> Result<Account, FieldErrors> {
+    let mut errs = FieldErrors::new();
 
-    if let Err(e) = account_service::set_cash(state, user_id, acc.cash, acc.updated_at).await {
+    // make sure there is an account to take from (new users start with the default balance)
+    if let Err(e) = account_service::get_or_create_account(state, user_id).await {
         errs.insert("_form".into(), format!("db error: {e}"));
         return Err(errs);
     }
 
+    let acc = match account_service::debit_cash(state, user_id, amount).await {
+        Ok(Some(a)) => a,
+        Ok(None) => {
+            errs.insert("amount".into(), "Insufficient funds".into());
+            return Err(errs);
+        }
+        Err(e) => {
+            errs.insert("_form".into(), format!("db error: {e}"));
+            return Err(errs);
+        }
+    };
+
     let _ = state.events_tx.send("cashUpdated".to_string());
 
     Ok(acc)
@@ -0,0 +1,67 @@
+use chrono::Utc;
+use futures_util::StreamExt;
+use mongodb::bson::{doc, oid::ObjectId};
+use mongodb::options::{FindOptions, UpdateOptions};
+
+use crate::{models::WatchlistItem, AppState};
+
+pub async fn add(state: &AppState, user_id: ObjectId, symbol: &str) -> Result<(), String> {
+    let sym = symbol.to_uppercase();
+    let watchlists = state.db.collection::<WatchlistItem>("watchlists");
+
+    // idempotent: starring twice keeps the original created_at
+    watchlists
+        .update_one(
+            doc! { "user_id": user_id, "symbol": &sym },
+            doc! { "$setOnInsert": { "created_at": Utc::now().timestamp() } },
+            UpdateOptions::builder().upsert(true).build(),
+        )
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let _ = state.events_tx.send("watchlistUpdated".to_string());
+
+    Ok(())
+}
+
+pub async fn remove(state: &AppState, user_id: ObjectId, symbol: &str) -> Result<(), String> {
+    let sym = symbol.to_uppercase();
+    let watchlists = state.db.collection::<WatchlistItem>("watchlists");
+
+    watchlists
+        .delete_one(doc! { "user_id": user_id, "symbol": &sym }, None)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let _ = state.events_tx.send("watchlistUpdated".to_string());
+
+    Ok(())
+}
+
+pub async fn list(state: &AppState, user_id: ObjectId) -> Result<Vec<WatchlistItem>, String> {
+    let watchlists = state.db.collection::<WatchlistItem>("watchlists");
+    let find_opts = FindOptions::builder().sort(doc! { "symbol": 1 }).build();
+
+    let mut cursor = watchlists
+        .find(doc! { "user_id": user_id }, find_opts)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let mut out: Vec<WatchlistItem> = vec![];
+    while let Some(res) = cursor.next().await {
+        out.push(res.map_err(|e| e.to_string())?);
+    }
+    Ok(out)
+}
+
+pub async fn is_watched(state: &AppState, user_id: ObjectId, symbol: &str) -> Result<bool, String> {
+    let sym = symbol.to_uppercase();
+    let watchlists = state.db.collection::<WatchlistItem>("watchlists");
+
+    let found = watchlists
+        .find_one(doc! { "user_id": user_id, "symbol": &sym }, None)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(found.is_some())
+}
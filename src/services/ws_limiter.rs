@@ -0,0 +1,66 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use mongodb::bson::oid::ObjectId;
+
+/// Caps how many trade WebSockets one user may hold open at once.
+#[derive(Clone)]
+pub struct WsLimiter {
+    max_per_user: usize,
+    open: Arc<Mutex<HashMap<ObjectId, usize>>>,
+}
+
+/// One counted connection; the slot is given back when this is dropped.
+pub struct WsSlot {
+    user_id: ObjectId,
+    open: Arc<Mutex<HashMap<ObjectId, usize>>>,
+}
+
+impl WsLimiter {
+    pub fn new(max_per_user: usize) -> Self {
+        Self {
+            max_per_user,
+            open: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub fn max_per_user(&self) -> usize {
+        self.max_per_user
+    }
+
+    /// Takes a slot for `user_id`, or None if the user is already at the cap.
+    pub fn try_acquire(&self, user_id: ObjectId) -> Option<WsSlot> {
+        let mut open = self.open.lock().unwrap();
+        let count = open.entry(user_id).or_insert(0);
+        if *count >= self.max_per_user {
+            return None;
+        }
+        *count += 1;
+
+        Some(WsSlot {
+            user_id,
+            open: self.open.clone(),
+        })
+    }
+
+    pub fn open_for(&self, user_id: &ObjectId) -> usize {
+        self.open.lock().unwrap().get(user_id).copied().unwrap_or(0)
+    }
+
+    /// Open connections across all users.
+    pub fn total_open(&self) -> usize {
+        self.open.lock().unwrap().values().sum()
+    }
+}
+
+impl Drop for WsSlot {
+    fn drop(&mut self) {
+        let mut open = self.open.lock().unwrap();
+        if let Some(count) = open.get_mut(&self.user_id) {
+            *count -= 1;
+            if *count == 0 {
+                open.remove(&self.user_id);
+            }
+        }
+    }
+}
@@ -1,6 +1,153 @@
+use axum::{
+    http::{HeaderMap, HeaderValue, StatusCode},
+    response::{Html, IntoResponse, Response},
+};
 use serde_json::json;
 
-use crate::{models::CurrentUser, AppState};
+use crate::{error::AppError, models::CurrentUser, AppState};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToastKind {
+    Success,
+    Danger,
+    Warning,
+}
+
+impl ToastKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ToastKind::Success => "success",
+            ToastKind::Danger => "danger",
+            ToastKind::Warning => "warning",
+        }
+    }
+}
+
+/// For the few responses built with `format!` instead of a template: makes error
+/// text (which can echo input or upstream bodies) safe to swap into the page.
+pub fn escape_html(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for ch in s.chars() {
+        match ch {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#x27;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Inline feedback snippet (`partials/toast`) for an HTMX target like `#tradeMsg`.
+pub fn toast_html(state: &AppState, kind: ToastKind, message: &str) -> String {
+    state
+        .hbs
+        .render("partials/toast", &json!({ "kind": kind.as_str(), "message": message }))
+        .unwrap_or_else(|e| format!("template error: {e}"))
+}
+
+/// HX-Trigger value that raises `showToast` along with any extra `events`.
+pub fn toast_trigger(kind: ToastKind, message: &str, events: &[&str]) -> HeaderValue {
+    trigger_header(json!({ "kind": kind.as_str(), "message": message }), events)
+}
+
+/// Like `toast_trigger`, with an Undo button on the toast that POSTs to `undo_url`.
+pub fn undo_toast_trigger(kind: ToastKind, message: &str, undo_url: &str, events: &[&str]) -> HeaderValue {
+    trigger_header(
+        json!({ "kind": kind.as_str(), "message": message, "undo": undo_url }),
+        events,
+    )
+}
+
+fn trigger_header(toast: serde_json::Value, events: &[&str]) -> HeaderValue {
+    let mut map = serde_json::Map::new();
+    map.insert("showToast".to_string(), toast);
+    for &e in events {
+        map.insert(e.to_string(), serde_json::Value::Bool(true));
+    }
+
+    // Header values must be visible ASCII, so escape anything else as \uXXXX.
+    let mut out = String::new();
+    for ch in serde_json::Value::Object(map).to_string().chars() {
+        if ch.is_ascii() {
+            out.push(ch);
+        } else {
+            let mut buf = [0u16; 2];
+            for unit in ch.encode_utf16(&mut buf) {
+                out.push_str(&format!("\\u{:04x}", unit));
+            }
+        }
+    }
+
+    HeaderValue::from_str(&out).unwrap_or_else(|_| HeaderValue::from_static("{}"))
+}
+
+/// Consistent HTMX feedback: the inline snippet plus an HX-Trigger that pops a toast.
+pub fn toast(state: &AppState, kind: ToastKind, message: &str, events: &[&str]) -> Response {
+    let mut headers = HeaderMap::new();
+    headers.insert("HX-Trigger", toast_trigger(kind, message, events));
+
+    (StatusCode::OK, headers, Html(toast_html(state, kind, message))).into_response()
+}
+
+/// `toast` whose popup offers to undo the action via `undo_url`.
+pub fn undo_toast(state: &AppState, kind: ToastKind, message: &str, undo_url: &str, events: &[&str]) -> Response {
+    let mut headers = HeaderMap::new();
+    headers.insert("HX-Trigger", undo_toast_trigger(kind, message, undo_url, events));
+
+    (StatusCode::OK, headers, Html(toast_html(state, kind, message))).into_response()
+}
+
+/// Gives an error snippet its real status: 422 for bad input, 500 for server faults.
+/// app.js still swaps those into the target; with HTMX_ERROR_STATUS=false the
+/// snippet goes out as 200 like before.
+pub fn error_response(state: &AppState, status: StatusCode, snippet: impl IntoResponse) -> Response {
+    let mut res = snippet.into_response();
+    if state.settings.htmx_error_status {
+        *res.status_mut() = status;
+    }
+    res
+}
+
+/// A template that failed to render; responds with a generic snippet (500 unless
+/// HTMX_ERROR_STATUS=false) so Handlebars paths and messages never reach the page.
+#[derive(Debug, Clone, Copy)]
+pub struct RenderFailed {
+    status: StatusCode,
+}
+
+impl IntoResponse for RenderFailed {
+    fn into_response(self) -> Response {
+        let html = r#"<div class="text-danger">Something went wrong. Try again.</div>"#;
+        (self.status, Html(html)).into_response()
+    }
+}
+
+/// Renders `tpl`, logging the cause when the template fails.
+pub fn render_or_500(state: &AppState, tpl: &str, ctx: &serde_json::Value) -> Result<String, RenderFailed> {
+    state.hbs.render(tpl, ctx).map_err(|e| {
+        tracing::error!("rendering {tpl} failed: {e}");
+        let status = if state.settings.htmx_error_status {
+            StatusCode::INTERNAL_SERVER_ERROR
+        } else {
+            StatusCode::OK
+        };
+        RenderFailed { status }
+    })
+}
+
+/// Danger `toast` sent through `error_response`.
+pub fn error_toast(state: &AppState, status: StatusCode, message: &str) -> Response {
+    error_response(state, status, toast(state, ToastKind::Danger, message, &[]))
+}
+
+/// `error_toast` for a service error: logs the cause, shows only the safe message.
+pub fn app_error_toast(state: &AppState, err: &AppError) -> Response {
+    err.report();
+    error_toast(state, err.status(), &err.message())
+}
 
 pub fn render_shell(
     state: &AppState,
@@ -15,6 +162,7 @@ pub fn render_shell(
                 "id": u.id.to_hex(),
                 "email": u.email,
                 "username": u.username,
+                "is_admin": u.is_admin,
             }),
         ),
         None => (false, serde_json::Value::Null),
@@ -47,6 +195,7 @@ pub fn render_full(
                 "id": u.id.to_hex(),
                 "email": u.email,
                 "username": u.username,
+                "is_admin": u.is_admin,
             }),
         ),
         None => (false, serde_json::Value::Null),
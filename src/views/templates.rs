@@ -1,9 +1,95 @@
-use handlebars::{handlebars_helper, Handlebars, JsonValue};
+use handlebars::{
+    handlebars_helper, Context, Handlebars, Helper, HelperResult, JsonValue, Output, RenderContext,
+    RenderErrorReason,
+};
 use std::path::Path;
 use std::sync::Arc;
 
 pub type Hbs = Arc<Handlebars<'static>>;
 
+// With the `embed-templates` feature the sources are compiled into the binary,
+// so a deploy doesn't need the templates/ folder next to it.
+#[cfg(feature = "embed-templates")]
+macro_rules! embedded {
+    ($path:literal) => {
+        Some(include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/", $path)))
+    };
+}
+
+#[cfg(not(feature = "embed-templates"))]
+macro_rules! embedded {
+    ($path:literal) => {
+        None
+    };
+}
+
+macro_rules! template_files {
+    ($($name:literal => $path:literal),* $(,)?) => {
+        &[$(($name, $path, embedded!($path))),*]
+    };
+}
+
+// (registry name, path relative to the crate root, embedded source)
+const TEMPLATES: &[(&str, &str, Option<&str>)] = template_files![
+    "layouts/base" => "templates/layouts/base.hbs",
+
+    "pages/home" => "templates/pages/home.hbs",
+    "pages/not_found" => "templates/pages/not_found.hbs",
+    "pages/login" => "templates/pages/login.hbs",
+    "pages/register" => "templates/pages/register.hbs",
+    "pages/search" => "templates/pages/search.hbs",
+    "pages/details" => "templates/pages/details.hbs",
+    "pages/portfolio" => "templates/pages/portfolio.hbs",
+    "pages/alerts" => "templates/pages/alerts.hbs",
+    "pages/funds" => "templates/pages/funds.hbs",
+    "pages/settings" => "templates/pages/settings.hbs",
+    "pages/leaderboard" => "templates/pages/leaderboard.hbs",
+    "pages/admin" => "templates/pages/admin.hbs",
+
+    "partials/search_results" => "templates/partials/search_results.hbs",
+    "partials/recent_symbols" => "templates/partials/recent_symbols.hbs",
+    "partials/quote" => "templates/partials/quote.hbs",
+    "partials/alerts_list" => "templates/partials/alerts_list.hbs",
+    "partials/watchlist_alerts" => "templates/partials/watchlist_alerts.hbs",
+    "partials/watchlist" => "templates/partials/watchlist.hbs",
+    "partials/watch_star" => "templates/partials/watch_star.hbs",
+    "partials/position_panel" => "templates/partials/position_panel.hbs",
+    "partials/portfolio_positions" => "templates/partials/portfolio_positions.hbs",
+    "partials/portfolio_summary" => "templates/partials/portfolio_summary.hbs",
+    "partials/portfolio_sectors" => "templates/partials/portfolio_sectors.hbs",
+
+    "partials/portfolio_position_card" => "templates/partials/portfolio_position_card.hbs",
+
+    "partials/funds_modal" => "templates/partials/funds_modal.hbs",
+    "partials/cash_badge" => "templates/partials/cash_badge.hbs",
+
+    "partials/change_email" => "templates/partials/change_email.hbs",
+    "partials/change_password" => "templates/partials/change_password.hbs",
+    "partials/delete_account" => "templates/partials/delete_account.hbs",
+    "partials/reset_account" => "templates/partials/reset_account.hbs",
+    "partials/preferences" => "templates/partials/preferences.hbs",
+    "partials/sessions" => "templates/partials/sessions.hbs",
+    "partials/orders_list" => "templates/partials/orders_list.hbs",
+    "partials/profile_summary" => "templates/partials/profile_summary.hbs",
+    "partials/admin_stats" => "templates/partials/admin_stats.hbs",
+    "partials/admin_users" => "templates/partials/admin_users.hbs",
+    "partials/toast" => "templates/partials/toast.hbs",
+
+    // Partials resolve through the template registry, so these reload like the rest.
+    "navbar" => "templates/partials/navbar.hbs",
+    "footer" => "templates/partials/footer.hbs",
+];
+
+/// True when the binary carries its own copy of the templates.
+pub fn templates_embedded() -> bool {
+    cfg!(feature = "embed-templates")
+}
+
+fn register_embedded(hb: &mut Handlebars<'static>, name: &str, src: &str) {
+    hb.register_template_string(name, src)
+        .unwrap_or_else(|e| panic!("Failed to register embedded {name}: {e}"));
+}
+
 fn register_file(hb: &mut Handlebars<'static>, name: &str, path: &str) {
     if Path::new(path).exists() {
         hb.register_template_file(name, path)
@@ -13,51 +99,109 @@ fn register_file(hb: &mut Handlebars<'static>, name: &str, path: &str) {
     }
 }
 
+// (ISO code, symbol, decimals) for the display currencies users can pick.
+// Amounts are only formatted differently, never converted.
+const CURRENCY_FORMATS: &[(&str, &str, u32)] = &[("USD", "$", 2), ("EUR", "€", 2), ("GBP", "£", 2), ("JPY", "¥", 0)];
+
+pub const DISPLAY_CURRENCIES: &[&str] = &["USD", "EUR", "GBP", "JPY"];
+
+/// `1234.5` -> `$1,234.50`, `-3.2` -> `-$3.20`.
+pub fn format_currency(v: f64) -> String {
+    format_money(v, "USD")
+}
+
+/// Like `format_currency` with the symbol and decimals of `code`; unknown codes fall back to USD.
+pub fn format_money(v: f64, code: &str) -> String {
+    let (_, symbol, decimals) = CURRENCY_FORMATS
+        .iter()
+        .find(|(c, _, _)| c.eq_ignore_ascii_case(code))
+        .unwrap_or(&CURRENCY_FORMATS[0]);
+    let scale = 10_i64.pow(*decimals);
+
+    let minor = (v * scale as f64).round() as i64;
+    let sign = if minor < 0 { "-" } else { "" };
+    let minor = minor.unsigned_abs();
+
+    let digits = (minor / scale as u64).to_string();
+    let mut whole = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, ch) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            whole.push(',');
+        }
+        whole.push(ch);
+    }
+
+    if *decimals == 0 {
+        return format!("{}{}{}", sign, symbol, whole);
+    }
+    format!(
+        "{}{}{}.{:0width$}",
+        sign,
+        symbol,
+        whole,
+        minor % scale as u64,
+        width = *decimals as usize
+    )
+}
+
+// {{currency x}} formats in the root context's `display_currency` (USD when absent).
+fn currency_helper(
+    h: &Helper,
+    _: &Handlebars,
+    ctx: &Context,
+    _: &mut RenderContext,
+    out: &mut dyn Output,
+) -> HelperResult {
+    let v = h
+        .param(0)
+        .and_then(|p| p.value().as_f64())
+        .ok_or(RenderErrorReason::ParamNotFoundForIndex("currency", 0))?;
+    let code = ctx
+        .data()
+        .get("display_currency")
+        .and_then(|c| c.as_str())
+        .unwrap_or("USD");
+
+    out.write(&format_money(v, code))?;
+    Ok(())
+}
+
+/// `3.214` -> `+3.21%`, `-1.5` -> `-1.50%`, `0` -> `0.00%`.
+pub fn format_pct(v: f64) -> String {
+    let rounded = (v * 100.0).round() / 100.0;
+    if rounded > 0.0 {
+        format!("+{:.2}%", rounded)
+    } else if rounded < 0.0 {
+        format!("{:.2}%", rounded)
+    } else {
+        "0.00%".to_string()
+    }
+}
+
 pub fn build_handlebars() -> Hbs {
+    build_handlebars_with_reload(false)
+}
+
+// With hot reload on, handlebars re-reads each template file on every render,
+// so template edits show up without a restart. Production keeps the cached set.
+pub fn build_handlebars_with_reload(hot_reload: bool) -> Hbs {
     let mut hb = Handlebars::new();
+    // Must be set before any template is registered to take effect.
+    hb.set_dev_mode(hot_reload);
 
     handlebars_helper!(eq: |a: JsonValue, b: JsonValue| a == b);
     hb.register_helper("eq", Box::new(eq));
 
-    register_file(&mut hb, "layouts/base", "templates/layouts/base.hbs");
-
-    register_file(&mut hb, "pages/home", "templates/pages/home.hbs");
-    register_file(&mut hb, "pages/not_found", "templates/pages/not_found.hbs");
-    register_file(&mut hb, "pages/login", "templates/pages/login.hbs");
-    register_file(&mut hb, "pages/register", "templates/pages/register.hbs");
-    register_file(&mut hb, "pages/search", "templates/pages/search.hbs");
-    register_file(&mut hb, "pages/details", "templates/pages/details.hbs");
-    register_file(&mut hb, "pages/portfolio", "templates/pages/portfolio.hbs");
-    register_file(&mut hb, "pages/alerts", "templates/pages/alerts.hbs");
-    register_file(&mut hb, "pages/funds", "templates/pages/funds.hbs");
-    register_file(&mut hb, "pages/settings", "templates/pages/settings.hbs");
-
-    register_file(&mut hb, "partials/search_results", "templates/partials/search_results.hbs");
-    register_file(&mut hb, "partials/quote", "templates/partials/quote.hbs");
-    register_file(&mut hb, "partials/alerts_list", "templates/partials/alerts_list.hbs");
-    register_file(&mut hb, "partials/watchlist_alerts", "templates/partials/watchlist_alerts.hbs");
-    register_file(&mut hb, "partials/position_panel", "templates/partials/position_panel.hbs");
-    register_file(&mut hb, "partials/portfolio_positions", "templates/partials/portfolio_positions.hbs");
-
-    register_file(&mut hb, "partials/portfolio_position_card", "templates/partials/portfolio_position_card.hbs");
-
-    register_file(&mut hb, "partials/funds_modal", "templates/partials/funds_modal.hbs");
-    register_file(&mut hb, "partials/cash_badge", "templates/partials/cash_badge.hbs");
-
-    register_file(&mut hb, "partials/change_email", "templates/partials/change_email.hbs");
-    register_file(&mut hb, "partials/change_password", "templates/partials/change_password.hbs");
-    register_file(&mut hb, "partials/orders_list", "templates/partials/orders_list.hbs");
-    if Path::new("templates/partials/navbar.hbs").exists() {
-        let navbar = std::fs::read_to_string("templates/partials/navbar.hbs")
-            .expect("partials/navbar.hbs");
-        hb.register_partial("navbar", navbar)
-            .expect("register navbar partial");
-    }
-    if Path::new("templates/partials/footer.hbs").exists() {
-        let footer = std::fs::read_to_string("templates/partials/footer.hbs")
-            .expect("partials/footer.hbs");
-        hb.register_partial("footer", footer)
-            .expect("register footer partial");
+    hb.register_helper("currency", Box::new(currency_helper));
+    handlebars_helper!(pct: |v: f64| format_pct(v));
+    hb.register_helper("pct", Box::new(pct));
+
+    for (name, path, embedded) in TEMPLATES {
+        match embedded {
+            // Hot reload needs the files on disk; embedded copies are only a fallback then.
+            Some(src) if !hot_reload || !Path::new(path).exists() => register_embedded(&mut hb, name, src),
+            _ => register_file(&mut hb, name, path),
+        }
     }
 
     Arc::new(hb)
@@ -5,10 +5,11 @@
 		return document.querySelector('[data-symbol-details="1"]');
 	}
 
-	function shouldTrigger(cond, price, target) {
+	function shouldTrigger(cond, price, target, targetHigh) {
 		if (!Number.isFinite(price) || !Number.isFinite(target)) return false;
 		if (cond === "above") return price >= target;
 		if (cond === "below") return price <= target;
+		if (cond === "between") return Number.isFinite(targetHigh) && price >= target && price <= targetHigh;
 		return false;
 	}
 
@@ -25,8 +26,10 @@
 					swap: "innerHTML",
 				});
 			} else {
+				const csrf = (document.cookie.match(/(?:^|;\s*)csrf_token=([^;]*)/) || [])[1] || "";
 				const res = await fetch(`/alerts/by-id/${encodeURIComponent(id)}/trigger`, {
 					method: "POST",
+					headers: { "X-CSRF-Token": decodeURIComponent(csrf) },
 				});
 				const html = await res.text();
 				const msgEl = wrap.querySelector("#alertsMsg");
@@ -62,10 +65,12 @@
 			const id = el.dataset.alertId;
 			const cond = (el.dataset.condition || "").toLowerCase();
 			const target = Number(el.dataset.target);
+			// empty for non-band alerts, which Number() would turn into 0
+			const targetHigh = el.dataset.targetHigh ? Number(el.dataset.targetHigh) : NaN;
 			const triggered = el.dataset.triggered === "1";
 
 			if (triggered) continue;
-			if (!shouldTrigger(cond, price, target)) continue;
+			if (!shouldTrigger(cond, price, target, targetHigh)) continue;
 
 			triggerAlert(id, wrap);
 		}
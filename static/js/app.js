@@ -1,4 +1,43 @@
 (() => {
+	function readCookie(name) {
+		const prefix = `${name}=`;
+		const part = document.cookie
+			.split(";")
+			.map((c) => c.trim())
+			.find((c) => c.startsWith(prefix));
+		return part ? decodeURIComponent(part.slice(prefix.length)) : "";
+	}
+
+	// Echo the CSRF cookie on every HTMX request (see src/middleware/csrf.rs)
+	document.body.addEventListener("htmx:configRequest", (e) => {
+		const token = readCookie("csrf_token");
+		if (token) e.detail.headers["X-CSRF-Token"] = token;
+	});
+
+	// Bad symbols (400), validation (422) and server (500) errors carry a snippet
+	// meant for the target; HTMX drops error bodies by default (see render::error_response).
+	document.body.addEventListener("htmx:beforeSwap", (e) => {
+		const status = e.detail.xhr.status;
+		if (status === 400 || status === 422 || status === 500) {
+			e.detail.shouldSwap = true;
+			e.detail.isError = false;
+		}
+	});
+
+	function newIdempotencyKey() {
+		if (window.crypto && crypto.randomUUID) return crypto.randomUUID();
+		return `${Date.now().toString(36)}-${Math.random().toString(36).slice(2)}`;
+	}
+
+	// A double click reuses the rendered key, so the server deposits once; once the
+	// request is answered, the next submit is a new deposit and needs a new key.
+	document.body.addEventListener("htmx:afterRequest", (e) => {
+		const form = e.detail && e.detail.elt;
+		if (!form || form.tagName !== "FORM") return;
+		const input = form.querySelector('input[name="idempotency_key"]');
+		if (input) input.value = newIdempotencyKey();
+	});
+
 	function getFundsModalEl() {
 		return document.getElementById("staticBackdrop");
 	}
@@ -28,6 +67,47 @@
 		}
 	});
 
+	// Fired by HX-Trigger {"showToast":{"kind":..,"message":..}} (see render::toast)
+	document.body.addEventListener("showToast", (e) => {
+		const d = e.detail || {};
+		const stack = document.getElementById("toastStack");
+		if (!stack || !d.message || typeof bootstrap === "undefined") return;
+
+		const el = document.createElement("div");
+		el.className = `toast align-items-center text-bg-${d.kind || "secondary"} border-0`;
+		el.setAttribute("role", d.kind === "danger" ? "alert" : "status");
+
+		const row = document.createElement("div");
+		row.className = "d-flex";
+		const body = document.createElement("div");
+		body.className = "toast-body";
+		body.textContent = d.message;
+		row.appendChild(body);
+		// {"undo": url}: the action can be reverted with a POST to url
+		if (d.undo && typeof htmx !== "undefined") {
+			const undo = document.createElement("button");
+			undo.type = "button";
+			undo.className = "btn btn-sm btn-light my-auto";
+			undo.textContent = "Undo";
+			undo.addEventListener("click", () => {
+				htmx.ajax("POST", d.undo, { swap: "none" });
+				bootstrap.Toast.getOrCreateInstance(el).hide();
+			});
+			row.appendChild(undo);
+		}
+		const close = document.createElement("button");
+		close.type = "button";
+		close.className = "btn-close btn-close-white me-2 m-auto";
+		close.setAttribute("data-bs-dismiss", "toast");
+		close.setAttribute("aria-label", "Close");
+		row.appendChild(close);
+		el.appendChild(row);
+
+		stack.appendChild(el);
+		el.addEventListener("hidden.bs.toast", () => el.remove());
+		bootstrap.Toast.getOrCreateInstance(el, { delay: d.undo ? 8000 : 4000 }).show();
+	});
+
 	function normalizeSymbol(sym) {
 		return (sym || "").toString().trim().toUpperCase();
 	}
@@ -130,6 +130,16 @@ function updateQuoteUI(price) {
 			return (Math.round(x * 100) / 100).toFixed(2);
 		}
 
+		// Same output as the server-side `currency` / `pct` template helpers
+		function fmtMoney(x, currency) {
+			return x.toLocaleString("en-US", { style: "currency", currency: currency || "USD" });
+		}
+
+		function fmtPct(x) {
+			const r = Math.round(x * 100) / 100;
+			return (r > 0 ? "+" : "") + fmt2(r) + "%";
+		}
+
 		function updatePositionUI(price) {
 			const pos = wrap.querySelector('[data-position-panel="1"]');
 			if (!pos) return;
@@ -145,13 +155,13 @@ function updateQuoteUI(price) {
 			const pnlPct = pos.querySelector('[data-role="pos-pnl-pct"]');
 			if (!lastEl || !pnlRow || !pnlVal || !pnlPct) return;
 
-			lastEl.textContent = fmt2(price);
+			lastEl.textContent = fmtMoney(price, pos.dataset.currency);
 
 			const pnl = (price - avg) * qty;
 			const pct = avg > 0 ? ((price - avg) / avg) * 100 : 0;
 
-			pnlVal.textContent = (pnl > 0 ? "+" : "") + fmt2(pnl);
-			pnlPct.textContent = (pct > 0 ? "+" : "") + fmt2(pct);
+			pnlVal.textContent = fmtMoney(pnl, pos.dataset.currency);
+			pnlPct.textContent = fmtPct(pct);
 
 			pnlRow.classList.remove(
 				"text-success",
@@ -195,6 +205,7 @@ function updateQuoteUI(price) {
 		// --- WS connect + reconnect ---
 		let ws = null;
 		let reconnectTimer = null;
+		let unsupported = false;
 
 		function connect() {
 			if (reconnectTimer) clearTimeout(reconnectTimer);
@@ -210,38 +221,45 @@ function updateQuoteUI(price) {
 				} catch {
 					return;
 				}
-				if (msg.type !== "trade" || !Array.isArray(msg.data)) return;
+				if (msg.code === "unsupported_symbol") {
+					// the server closes the socket; retrying would hit the same refusal
+					unsupported = true;
+					console.warn(`live trades not available for ${msg.symbol}`);
+					return;
+				}
+				if (msg.error) {
+					console.warn("trades socket:", msg.error);
+					return;
+				}
+				if (!msg.symbol) return;
 
 				const intervalSec = resSec();
 
-				for (const tr of msg.data) {
-					const price = Number(tr.p);
-					lastTradePrice = price;
-					const tSec = Math.floor(Number(tr.t) / 1000);
-					if (!Number.isFinite(price) || !Number.isFinite(tSec))
-						continue;
-
-					ticks.push({ t: tSec, p: price });
-					trimTicks();
-
-					const bt = bucketTimeSec(tSec, intervalSec);
-
-					if (!lastBar || lastBar.time !== bt) {
-						lastBar = {
-							time: bt,
-							open: price,
-							high: price,
-							low: price,
-							close: price,
-						};
-						bars.push(lastBar);
-						series.update(lastBar);
-					} else {
-						lastBar.high = Math.max(lastBar.high, price);
-						lastBar.low = Math.min(lastBar.low, price);
-						lastBar.close = price;
-						series.update(lastBar);
-					}
+				const price = Number(msg.price);
+				const tSec = Math.floor(Number(msg.ts) / 1000);
+				if (!Number.isFinite(price) || !Number.isFinite(tSec)) return;
+				lastTradePrice = price;
+
+				ticks.push({ t: tSec, p: price });
+				trimTicks();
+
+				const bt = bucketTimeSec(tSec, intervalSec);
+
+				if (!lastBar || lastBar.time !== bt) {
+					lastBar = {
+						time: bt,
+						open: price,
+						high: price,
+						low: price,
+						close: price,
+					};
+					bars.push(lastBar);
+					series.update(lastBar);
+				} else {
+					lastBar.high = Math.max(lastBar.high, price);
+					lastBar.low = Math.min(lastBar.low, price);
+					lastBar.close = price;
+					series.update(lastBar);
 				}
 
 				// ✅ NEW: broadcast latest trade price to other components (alerts, etc.)
@@ -255,7 +273,9 @@ function updateQuoteUI(price) {
 				}
 			};
 
-			ws.onclose = () => {
+			ws.onclose = (ev) => {
+				// 1008: server refused (connection cap); retrying won't help
+				if (ev.code === 1008 || unsupported) return;
 				reconnectTimer = setTimeout(connect, 2000);
 			};
 
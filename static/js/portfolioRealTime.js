@@ -10,11 +10,24 @@
   let ws = null;
   let reconnectTimer = null;
   let activeKey = "";
+  let activeSymbols = [];
+  // symbols the server said Finnhub won't stream; left out of later connects
+  const unsupported = new Set();
 
   function fmt2(n) {
     return (Math.round(n * 100) / 100).toFixed(2);
   }
 
+  // Same output as the server-side `currency` / `pct` template helpers
+  function fmtMoney(n, currency) {
+    return n.toLocaleString("en-US", { style: "currency", currency: currency || "USD" });
+  }
+
+  function fmtPct(n) {
+    const r = Math.round(n * 100) / 100;
+    return `${r > 0 ? "+" : ""}${fmt2(r)}%`;
+  }
+
   function getSymbols() {
     const container = document.getElementById("portfolioPositions");
     if (!container) return [];
@@ -23,7 +36,7 @@
     const out = [];
     for (const c of cards) {
       const s = (c.dataset.symbol || "").trim().toUpperCase();
-      if (s) out.push(s);
+      if (s && !unsupported.has(s)) out.push(s);
     }
     out.sort();
     // dedupe
@@ -40,7 +53,8 @@
 
     // Last
     const lastEl = card.querySelector(".js-last");
-    if (lastEl) lastEl.textContent = `$${fmt2(price)}`;
+    const currency = card.dataset.currency;
+    if (lastEl) lastEl.textContent = fmtMoney(price, currency);
 
     // P/L
     const pnl = (price - avg) * qty;
@@ -50,8 +64,8 @@
     const pnlVal = card.querySelector(".js-pnl-val");
     const pnlPct = card.querySelector(".js-pnl-pct");
 
-    if (pnlVal) pnlVal.textContent = `${pnl >= 0 ? "+" : ""}${fmt2(pnl)}`;
-    if (pnlPct) pnlPct.textContent = `${pct >= 0 ? "+" : ""}${fmt2(pct)}`;
+    if (pnlVal) pnlVal.textContent = fmtMoney(pnl, currency);
+    if (pnlPct) pnlPct.textContent = fmtPct(pct);
 
     if (pnlBox) {
       pnlBox.classList.remove("text-success", "text-danger", "text-muted");
@@ -62,18 +76,23 @@
   function onTradeMessage(ev) {
     let msg;
     try { msg = JSON.parse(ev.data); } catch { return; }
-    if (msg.type !== "trade" || !Array.isArray(msg.data)) return;
+    if (msg.code === "unsupported_symbol") {
+      const s = String(msg.symbol || "").toUpperCase();
+      unsupported.add(s);
+      activeSymbols = activeSymbols.filter((x) => x !== s);
+      activeKey = activeSymbols.join(",");
+      return;
+    }
+    if (msg.error) { console.warn("trades socket:", msg.error); return; }
 
-    for (const tr of msg.data) {
-      const symbol = String(tr.s || "").toUpperCase();
-      const price = Number(tr.p);
-      if (!symbol || !Number.isFinite(price)) continue;
+    const symbol = String(msg.symbol || "").toUpperCase();
+    const price = Number(msg.price);
+    if (!symbol || !Number.isFinite(price)) return;
 
-      updateCard(symbol, price);
+    updateCard(symbol, price);
 
-      // Optional: keep compatibility with your existing alertsRealtime.js / chart flow
-      document.dispatchEvent(new CustomEvent("rm:tradePrice", { detail: { symbol, price } }));
-    }
+    // Optional: keep compatibility with your existing alertsRealtime.js / chart flow
+    document.dispatchEvent(new CustomEvent("rm:tradePrice", { detail: { symbol, price } }));
   }
 
   function closeWs() {
@@ -81,11 +100,29 @@
     ws = null;
   }
 
+  // Adjust the open socket's subscriptions instead of reconnecting.
+  function resubscribe(symbols) {
+    for (const s of activeSymbols) {
+      if (!symbols.includes(s)) ws.send(JSON.stringify({ type: "unsubscribe", symbol: s }));
+    }
+    for (const s of symbols) {
+      if (!activeSymbols.includes(s)) ws.send(JSON.stringify({ type: "subscribe", symbol: s }));
+    }
+  }
+
   function connectFor(symbols) {
     const key = symbols.join(",");
     if (key === activeKey) return;
 
+    if (symbols.length && ws && ws.readyState === WebSocket.OPEN) {
+      resubscribe(symbols);
+      activeKey = key;
+      activeSymbols = symbols;
+      return;
+    }
+
     activeKey = key;
+    activeSymbols = symbols;
     closeWs();
 
     if (!symbols.length) return;
@@ -95,8 +132,10 @@
 
     ws.onmessage = onTradeMessage;
 
-    ws.onclose = () => {
+    ws.onclose = (ev) => {
       if (reconnectTimer) clearTimeout(reconnectTimer);
+      // 1008: server refused (connection cap); retrying won't help
+      if (ev.code === 1008) return;
       reconnectTimer = setTimeout(() => {
         // reconnect with latest symbols
         start();
@@ -136,4 +175,11 @@
 
   // Also re-scan after your buy/sell events refresh cards
   document.body.addEventListener("positionUpdated", () => start());
+
+  // Held-symbol prices pushed over /events; keeps cards fresh even without the trades socket
+  document.addEventListener("rm:priceUpdate", (e) => {
+    const symbol = String(e.detail?.symbol || "").toUpperCase();
+    const price = Number(e.detail?.price);
+    if (symbol && Number.isFinite(price)) updateCard(symbol, price);
+  });
 })();
@@ -18,6 +18,19 @@
     es.addEventListener("positionUpdated", () => fire("positionUpdated"));
     es.addEventListener("cashUpdated", () => fire("cashUpdated"));
     es.addEventListener("ordersUpdated", () => fire("ordersUpdated"));
+    es.addEventListener("watchlistUpdated", () => fire("watchlistUpdated"));
+
+    // the server dropped events we were too slow for; refresh everything
+    es.addEventListener("resync", () => {
+      ["alertsUpdated", "positionUpdated", "cashUpdated", "ordersUpdated", "watchlistUpdated"].forEach(fire);
+    });
+
+    // {"symbol":"AAPL","price":150.2} for a symbol the user holds, at most once a second
+    es.addEventListener("priceUpdate", (e) => {
+      let detail;
+      try { detail = JSON.parse(e.data); } catch { return; }
+      document.dispatchEvent(new CustomEvent("rm:priceUpdate", { detail }));
+    });
 
     es.onerror = () => {
       try { es.close(); } catch {}
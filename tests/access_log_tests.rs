@@ -0,0 +1,40 @@
+use axum::{http::Request, middleware::from_fn, routing::get, Router};
+use rustmarket::access_log;
+use tower::ServiceExt;
+
+fn app() -> Router {
+    Router::new()
+        .route("/ping", get(|| async { "pong" }))
+        .layer(from_fn(access_log::log_requests))
+}
+
+#[tokio::test]
+async fn response_carries_generated_request_id() {
+    let req = Request::builder()
+        .uri("/ping")
+        .body(axum::body::Body::empty())
+        .unwrap();
+
+    let res = app().oneshot(req).await.unwrap();
+    let id = res
+        .headers()
+        .get(access_log::REQUEST_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("");
+    assert_eq!(id.len(), 16);
+}
+
+#[tokio::test]
+async fn incoming_request_id_is_preserved() {
+    let req = Request::builder()
+        .uri("/ping")
+        .header(access_log::REQUEST_ID_HEADER, "abc-123")
+        .body(axum::body::Body::empty())
+        .unwrap();
+
+    let res = app().oneshot(req).await.unwrap();
+    assert_eq!(
+        res.headers().get(access_log::REQUEST_ID_HEADER).unwrap(),
+        "abc-123"
+    );
+}
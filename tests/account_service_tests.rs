@@ -0,0 +1,83 @@
+mod common;
+
+use std::sync::Arc;
+
+use mongodb::bson::oid::ObjectId;
+use rustmarket::services::{clock::FixedClock, user_service};
+use rustmarket::{services, AppState};
+
+async fn scratch_state(starting_balance: f64) -> Option<AppState> {
+    let mut state = common::scratch_state().await?;
+    state.settings.starting_balance = starting_balance;
+    Some(state)
+}
+
+#[tokio::test]
+async fn new_account_opens_with_configured_starting_balance() {
+    let Some(state) = scratch_state(2_500.0).await else { return };
+    let user_id = ObjectId::new();
+
+    let acc = services::account_service::get_or_create_account(&state, user_id)
+        .await
+        .unwrap();
+    assert_eq!(acc.cash, 2_500.0);
+
+    // an existing account keeps its balance
+    services::account_service::set_cash(&state, user_id, 42.0, 0).await.unwrap();
+    let acc = services::account_service::get_or_create_account(&state, user_id)
+        .await
+        .unwrap();
+    assert_eq!(acc.cash, 42.0);
+
+    state.db.drop(None).await.unwrap();
+}
+
+#[tokio::test]
+async fn deposits_stop_at_the_daily_limit_and_reset_the_next_day() {
+    let Some(mut state) = scratch_state(0.0).await else { return };
+    state.settings.daily_deposit_limit = Some(1_000.0);
+    let clock = Arc::new(FixedClock::new(chrono::Utc::now()));
+    state.clock = clock.clone();
+    let user_id = ObjectId::new();
+
+    user_service::deposit_funds(&state, user_id, 600.0, None).await.unwrap();
+    let errs = user_service::deposit_funds(&state, user_id, 500.0, None).await.unwrap_err();
+    assert_eq!(errs["amount"], "Daily deposit limit reached");
+
+    let acc = user_service::deposit_funds(&state, user_id, 400.0, None).await.unwrap();
+    assert_eq!(acc.cash, 1_000.0);
+
+    // admin credits don't count
+    user_service::credit_funds(&state, user_id, 5_000.0).await.unwrap();
+
+    clock.advance(chrono::Duration::days(1));
+    let acc = user_service::deposit_funds(&state, user_id, 1_000.0, None).await.unwrap();
+    assert_eq!(acc.cash, 7_000.0);
+
+    state.db.drop(None).await.unwrap();
+}
+
+#[tokio::test]
+async fn racing_first_deposits_of_the_day_share_the_limit() {
+    let Some(mut state) = scratch_state(0.0).await else { return };
+    state.settings.daily_deposit_limit = Some(100.0);
+    let clock = Arc::new(FixedClock::new(chrono::Utc::now()));
+    state.clock = clock.clone();
+    let user_id = ObjectId::new();
+
+    // yesterday's total is spent, so every deposit below races to start today's
+    user_service::deposit_funds(&state, user_id, 100.0, None).await.unwrap();
+    clock.advance(chrono::Duration::days(1));
+
+    let results = futures_util::future::join_all((0..10).map(|_| user_service::deposit_funds(&state, user_id, 15.0, None))).await;
+    let ok = results.iter().filter(|r| r.is_ok()).count();
+    assert_eq!(ok, 6, "{results:?}");
+    for errs in results.iter().filter_map(|r| r.as_ref().err()) {
+        assert_eq!(errs["amount"], "Daily deposit limit reached");
+    }
+
+    let acc = services::account_service::find_account(&state, user_id).await.unwrap().unwrap();
+    assert_eq!(acc.cash, 190.0);
+
+    state.db.drop(None).await.unwrap();
+}
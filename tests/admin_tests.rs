@@ -0,0 +1,123 @@
+mod common;
+
+use axum::{
+    http::{header, Request, StatusCode},
+    middleware::from_fn,
+    routing::{get, post},
+    Router,
+};
+use mongodb::bson::oid::ObjectId;
+use rustmarket::models::CurrentUser;
+use rustmarket::error::AppError;
+use rustmarket::services::{self, admin_service};
+use rustmarket::{auth, controllers::admin_controller};
+use tower::ServiceExt;
+use common::{test_state, scratch_state, response_body_string};
+
+fn test_user(is_admin: bool) -> CurrentUser {
+    CurrentUser {
+        id: ObjectId::new(),
+        email: "test@example.com".to_string(),
+        username: "test".to_string(),
+        is_admin,
+    }
+}
+
+fn guarded() -> Router {
+    Router::new()
+        .route("/admin", get(|| async { "dashboard" }))
+        .route_layer(from_fn(auth::require_admin))
+}
+
+#[tokio::test]
+async fn non_admins_get_403() {
+    let mut req = Request::builder().uri("/admin").body(axum::body::Body::empty()).unwrap();
+    req.extensions_mut().insert(test_user(false));
+
+    let res = guarded().oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn admins_pass_through() {
+    let mut req = Request::builder().uri("/admin").body(axum::body::Body::empty()).unwrap();
+    req.extensions_mut().insert(test_user(true));
+
+    let res = guarded().oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(response_body_string(res).await, "dashboard");
+}
+
+#[tokio::test]
+async fn credit_rejects_a_bad_amount_before_touching_the_db() {
+    let state = test_state().await;
+    let app = Router::new()
+        .route("/admin/users/:id/credit", post(admin_controller::post_credit))
+        .with_state(state);
+
+    let req = Request::builder()
+        .method("POST")
+        .uri(format!("/admin/users/{}/credit", ObjectId::new().to_hex()))
+        .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+        .body(axum::body::Body::from("amount=-5"))
+        .unwrap();
+
+    let res = app.oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(response_body_string(res).await.contains("bigger than zero"));
+}
+
+#[tokio::test]
+async fn reset_with_a_malformed_id_is_404() {
+    let state = test_state().await;
+    let app = Router::new()
+        .route("/admin/users/:id/reset", post(admin_controller::post_reset))
+        .with_state(state);
+
+    let req = Request::builder()
+        .method("POST")
+        .uri("/admin/users/not-an-id/reset")
+        .body(axum::body::Body::empty())
+        .unwrap();
+
+    let res = app.oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+}
+
+#[test]
+fn page_count_rounds_up_and_never_hits_zero() {
+    assert_eq!(admin_service::page_count(0), 1);
+    assert_eq!(admin_service::page_count(admin_service::USERS_PER_PAGE as u64), 1);
+    assert_eq!(admin_service::page_count(admin_service::USERS_PER_PAGE as u64 + 1), 2);
+}
+
+#[tokio::test]
+async fn admin_reset_matches_the_self_service_reset() {
+    let Some(state) = scratch_state().await else { return };
+    let user_id = services::auth_service::register_user(&state, "reset-me", "reset-me@example.com", "secret123")
+        .await
+        .unwrap();
+    services::account_service::set_cash(&state, user_id, 1.0, 0).await.unwrap();
+    services::alerts_service::create_alert(&state, user_id, "AAPL", "above", 150.0, None)
+        .await
+        .unwrap();
+
+    admin_service::reset_account(&state, user_id).await.unwrap();
+
+    let alerts = state
+        .db
+        .collection::<mongodb::bson::Document>("alerts")
+        .count_documents(mongodb::bson::doc! { "user_id": user_id }, None)
+        .await
+        .unwrap();
+    assert_eq!(alerts, 0);
+    let acc = services::account_service::find_account(&state, user_id).await.unwrap().unwrap();
+    assert_eq!(acc.cash, state.settings.starting_balance);
+
+    assert!(matches!(
+        admin_service::reset_account(&state, ObjectId::new()).await,
+        Err(AppError::NotFound)
+    ));
+
+    state.db.drop(None).await.unwrap();
+}
@@ -0,0 +1,57 @@
+mod common;
+
+use axum::{
+    http::{header, Request, StatusCode},
+    routing::{get, post},
+    Router,
+};
+use mongodb::bson::oid::ObjectId;
+use rustmarket::controllers::alerts_controller;
+use rustmarket::models::CurrentUser;
+use rustmarket::services::finnhub::FinnhubClient;
+use tower::ServiceExt;
+use common::test_state;
+
+// Finnhub's answer, verbatim, for a symbol it doesn't know.
+const UNKNOWN_SYMBOL_QUOTE: &str = r#"{"c":0,"d":null,"dp":null,"h":0,"l":0,"o":0,"pc":0,"t":0}"#;
+
+async fn unknown_symbol_server() -> String {
+    let app = Router::new().route(
+        "/quote",
+        get(|| async { ([(header::CONTENT_TYPE, "application/json")], UNKNOWN_SYMBOL_QUOTE) }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    format!("http://{addr}")
+}
+
+#[tokio::test]
+async fn alert_on_a_symbol_finnhub_does_not_know_is_refused() {
+    let mut state = test_state().await;
+    state.settings.validate_alert_symbols = true;
+    state.finnhub = FinnhubClient::with_base_url("test-key".to_string(), &unknown_symbol_server().await);
+
+    let app = Router::new()
+        .route("/alerts/:symbol", post(alerts_controller::post_create_alert))
+        .with_state(state);
+
+    let mut req = Request::builder()
+        .method("POST")
+        .uri("/alerts/APPL")
+        .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+        .body(axum::body::Body::from("targetPrice=100&condition=above"))
+        .unwrap();
+    req.extensions_mut().insert(CurrentUser {
+        id: ObjectId::new(),
+        email: "test@example.com".to_string(),
+        username: "test".to_string(),
+        is_admin: false,
+    });
+
+    let res = app.oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let trigger = res.headers().get("HX-Trigger").and_then(|v| v.to_str().ok()).unwrap_or_default();
+    assert!(trigger.contains("Unknown symbol"), "{trigger}");
+    assert!(!trigger.contains("alertsUpdated"));
+}
@@ -0,0 +1,154 @@
+mod common;
+
+use std::sync::Arc;
+
+use mongodb::bson::oid::ObjectId;
+use rustmarket::services::{alerts_service, clock::FixedClock};
+use common::scratch_state;
+
+#[test]
+fn condition_met_above_and_below() {
+    assert!(alerts_service::condition_met("above", 100.0, 100.0));
+    assert!(alerts_service::condition_met("above", 100.0, 101.5));
+    assert!(!alerts_service::condition_met("above", 100.0, 99.99));
+
+    assert!(alerts_service::condition_met("below", 100.0, 100.0));
+    assert!(alerts_service::condition_met("below", 100.0, 80.0));
+    assert!(!alerts_service::condition_met("below", 100.0, 100.01));
+}
+
+#[test]
+fn condition_met_unknown_condition_never_fires() {
+    assert!(!alerts_service::condition_met("sideways", 100.0, 100.0));
+}
+
+#[test]
+fn band_met_includes_both_ends() {
+    assert!(alerts_service::band_met(100.0, 110.0, 100.0));
+    assert!(alerts_service::band_met(100.0, 110.0, 105.0));
+    assert!(alerts_service::band_met(100.0, 110.0, 110.0));
+    assert!(!alerts_service::band_met(100.0, 110.0, 99.99));
+    assert!(!alerts_service::band_met(100.0, 110.0, 110.01));
+}
+
+#[tokio::test]
+async fn between_alert_needs_a_high_above_the_low() {
+    let Some(state) = scratch_state().await else { return };
+    let user_id = ObjectId::new();
+
+    for high in [None, Some(100.0), Some(90.0)] {
+        match alerts_service::create_alert(&state, user_id, "AAPL", "between", 100.0, high).await {
+            Err(rustmarket::error::AppError::Validation(errs)) => assert!(errs.contains_key("targetPriceHigh")),
+            other => panic!("expected a validation error for {high:?}, got {other:?}"),
+        }
+    }
+
+    let band = alerts_service::create_alert(&state, user_id, "AAPL", "between", 100.0, Some(110.0)).await.unwrap();
+    assert_eq!(band.target_price_high, Some(110.0));
+    assert!(alerts_service::alert_hit(&band, 105.0));
+    assert!(!alerts_service::alert_hit(&band, 120.0));
+
+    // the high end only means something for a band
+    let above = alerts_service::create_alert(&state, user_id, "AAPL", "above", 100.0, Some(110.0)).await.unwrap();
+    assert_eq!(above.target_price_high, None);
+    assert!(alerts_service::alert_hit(&above, 120.0));
+
+    state.db.drop(None).await.unwrap();
+}
+
+#[tokio::test]
+async fn deleted_alert_is_hidden_until_restored() {
+    let Some(state) = scratch_state().await else { return };
+    let user_id = ObjectId::new();
+
+    let alert = alerts_service::create_alert(&state, user_id, "AAPL", "above", 200.0, None).await.unwrap();
+    alerts_service::delete_alert_global(&state, user_id, alert.id).await.unwrap();
+
+    assert!(alerts_service::list_user_symbol_alerts(&state, user_id, "AAPL").await.unwrap().is_empty());
+    assert!(alerts_service::list_user_alerts_grouped(&state, user_id).await.unwrap().is_empty());
+
+    assert!(alerts_service::restore_alert(&state, user_id, alert.id).await.unwrap());
+    assert_eq!(alerts_service::list_user_symbol_alerts(&state, user_id, "AAPL").await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn restore_is_refused_after_the_window() {
+    let Some(mut state) = scratch_state().await else { return };
+    let clock = Arc::new(FixedClock::new(chrono::Utc::now()));
+    state.clock = clock.clone();
+    let user_id = ObjectId::new();
+
+    let alert = alerts_service::create_alert(&state, user_id, "AAPL", "below", 100.0, None).await.unwrap();
+    alerts_service::delete_alert_global(&state, user_id, alert.id).await.unwrap();
+    // someone else can't restore a fresh delete
+    assert!(!alerts_service::restore_alert(&state, ObjectId::new(), alert.id).await.unwrap());
+
+    clock.advance(chrono::Duration::seconds(alerts_service::RESTORE_WINDOW_SECS + 60));
+    assert!(!alerts_service::restore_alert(&state, user_id, alert.id).await.unwrap());
+}
+
+#[test]
+fn triggered_update_stamps_the_clock_time() {
+    let at = chrono::DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+    let update = alerts_service::triggered_update(&FixedClock::new(at), Some(187.5));
+    let set = update.get_document("$set").unwrap();
+
+    assert_eq!(set.get_i64("triggered_at").unwrap(), 1_700_000_000);
+    assert_eq!(set.get_datetime("triggered_on").unwrap().timestamp_millis(), 1_700_000_000_000);
+    assert_eq!(set.get_f64("triggered_price").unwrap(), 187.5);
+
+    let unknown = alerts_service::triggered_update(&FixedClock::new(at), None);
+    assert!(unknown.get_document("$set").unwrap().is_null("triggered_price"));
+}
+
+#[tokio::test]
+async fn delete_symbol_alerts_only_touches_that_symbol() {
+    let Some(state) = scratch_state().await else { return };
+    let user_id = ObjectId::new();
+
+    alerts_service::create_alert(&state, user_id, "AAPL", "above", 200.0, None).await.unwrap();
+    alerts_service::create_alert(&state, user_id, "AAPL", "below", 150.0, None).await.unwrap();
+    alerts_service::create_alert(&state, user_id, "MSFT", "above", 500.0, None).await.unwrap();
+
+    assert_eq!(alerts_service::delete_symbol_alerts(&state, user_id, "aapl").await.unwrap(), 2);
+    assert_eq!(alerts_service::delete_symbol_alerts(&state, user_id, "AAPL").await.unwrap(), 0);
+
+    assert!(alerts_service::list_user_symbol_alerts(&state, user_id, "AAPL").await.unwrap().is_empty());
+    assert_eq!(alerts_service::list_user_symbol_alerts(&state, user_id, "MSFT").await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn crypto_alert_keeps_the_exchange_prefix() {
+    let Some(state) = scratch_state().await else { return };
+    let user_id = ObjectId::new();
+
+    let alert = alerts_service::create_alert(&state, user_id, "binance:btcusdt", "above", 70_000.0, None).await.unwrap();
+    assert_eq!(alert.symbol, "BINANCE:BTCUSDT");
+
+    let listed = alerts_service::list_user_symbol_alerts(&state, user_id, "BINANCE:BTCUSDT").await.unwrap();
+    assert_eq!(listed.len(), 1);
+
+    state.db.drop(None).await.unwrap();
+}
+
+#[tokio::test]
+async fn create_alert_stops_at_the_per_user_limit() {
+    let Some(mut state) = scratch_state().await else { return };
+    state.settings.max_alerts_per_user = Some(2);
+    let user_id = ObjectId::new();
+
+    let first = alerts_service::create_alert(&state, user_id, "AAPL", "above", 200.0, None).await.unwrap();
+    alerts_service::create_alert(&state, user_id, "MSFT", "above", 500.0, None).await.unwrap();
+
+    match alerts_service::create_alert(&state, user_id, "NVDA", "above", 900.0, None).await {
+        Err(rustmarket::error::AppError::Validation(errs)) => assert_eq!(errs["alerts"], "Alert limit reached"),
+        other => panic!("expected the limit, got {other:?}"),
+    }
+
+    // a fired alert frees its slot
+    alerts_service::trigger_alert(&state, user_id, first.id).await.unwrap();
+    alerts_service::create_alert(&state, user_id, "NVDA", "above", 900.0, None).await.unwrap();
+
+    // other users have their own allowance
+    alerts_service::create_alert(&state, ObjectId::new(), "AAPL", "above", 200.0, None).await.unwrap();
+}
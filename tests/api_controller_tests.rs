@@ -0,0 +1,219 @@
+mod common;
+
+use axum::{
+    http::{header, Request, StatusCode},
+    routing::{get, post},
+    Router,
+};
+use http_body_util::BodyExt;
+use mongodb::bson::oid::ObjectId;
+use rustmarket::models::CurrentUser;
+use rustmarket::{config, controllers::api_controller, routes, AppState};
+use tower::ServiceExt;
+use common::test_state;
+
+fn app(state: AppState) -> Router {
+    Router::new()
+        .route("/api/v1/quote/:symbol", get(api_controller::get_quote))
+        .route("/api/v1/quotes", get(api_controller::get_quotes))
+        .route("/api/v1/orders", get(api_controller::get_orders))
+        .route("/api/v1/trade", post(api_controller::post_trade))
+        .route("/api/v1/funds", post(api_controller::post_funds))
+        .route("/api/v1/funds/withdraw", post(api_controller::post_withdraw))
+        .with_state(state)
+}
+
+fn test_user() -> CurrentUser {
+    CurrentUser {
+        id: ObjectId::new(),
+        email: "test@example.com".to_string(),
+        username: "test".to_string(),
+        is_admin: false,
+    }
+}
+
+async fn response_json(res: axum::response::Response) -> serde_json::Value {
+    let bytes = res.into_body().collect().await.unwrap().to_bytes();
+    serde_json::from_slice(&bytes).unwrap()
+}
+
+#[tokio::test]
+async fn get_quote_unauthorized_returns_401_json() {
+    let state = test_state().await;
+
+    let req = Request::builder()
+        .uri("/api/v1/quote/AAPL")
+        .body(axum::body::Body::empty())
+        .unwrap();
+
+    let res = app(state).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(response_json(res).await["error"], "unauthorized");
+}
+
+#[tokio::test]
+async fn get_quotes_without_valid_symbols_is_400() {
+    let state = test_state().await;
+
+    let mut req = Request::builder()
+        .uri("/api/v1/quotes?symbols=,%20,bad%20sym")
+        .body(axum::body::Body::empty())
+        .unwrap();
+    req.extensions_mut().insert(test_user());
+
+    let res = app(state).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(response_json(res).await["error"], "missing symbols");
+}
+
+#[tokio::test]
+async fn post_trade_rejects_unknown_side() {
+    let state = test_state().await;
+
+    let mut req = Request::builder()
+        .method("POST")
+        .uri("/api/v1/trade")
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(axum::body::Body::from(r#"{"symbol":"AAPL","side":"short","qty":1}"#))
+        .unwrap();
+    req.extensions_mut().insert(test_user());
+
+    let res = app(state).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(response_json(res).await["error"].as_str().unwrap().contains("side"));
+}
+
+#[tokio::test]
+async fn post_trade_invalid_qty_returns_field_errors() {
+    let state = test_state().await;
+
+    let mut req = Request::builder()
+        .method("POST")
+        .uri("/api/v1/trade")
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(axum::body::Body::from(r#"{"symbol":"AAPL","side":"buy","qty":0}"#))
+        .unwrap();
+    req.extensions_mut().insert(test_user());
+
+    let res = app(state).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(response_json(res).await["errors"]["qty"], "Enter a valid quantity.");
+}
+
+#[tokio::test]
+async fn post_trade_malformed_body_is_a_json_error() {
+    let state = test_state().await;
+
+    let mut req = Request::builder()
+        .method("POST")
+        .uri("/api/v1/trade")
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(axum::body::Body::from(r#"{"symbol":"AAPL","side":"buy","qty":"lots"}"#))
+        .unwrap();
+    req.extensions_mut().insert(test_user());
+
+    let res = app(state).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(response_json(res).await["error"].is_string());
+}
+
+#[tokio::test]
+async fn post_funds_nonpositive_amount_returns_field_errors() {
+    for uri in ["/api/v1/funds", "/api/v1/funds/withdraw"] {
+        let state = test_state().await;
+
+        let mut req = Request::builder()
+            .method("POST")
+            .uri(uri)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(axum::body::Body::from(r#"{"amount":0}"#))
+            .unwrap();
+        req.extensions_mut().insert(test_user());
+
+        let res = app(state).oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY, "{uri}");
+        assert_eq!(response_json(res).await["errors"]["amount"], "Amount must be bigger than zero!");
+    }
+}
+
+#[tokio::test]
+async fn post_funds_unauthorized_returns_401_json() {
+    let state = test_state().await;
+
+    let req = Request::builder()
+        .method("POST")
+        .uri("/api/v1/funds")
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(axum::body::Body::from(r#"{"amount":10}"#))
+        .unwrap();
+
+    let res = app(state).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(response_json(res).await["error"], "unauthorized");
+}
+
+#[tokio::test]
+async fn get_orders_rejects_inverted_range() {
+    let state = test_state().await;
+
+    let mut req = Request::builder()
+        .uri("/api/v1/orders?from=200&to=100")
+        .body(axum::body::Body::empty())
+        .unwrap();
+    req.extensions_mut().insert(test_user());
+
+    let res = app(state).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+fn preflight(uri: &str, origin: &str) -> Request<axum::body::Body> {
+    Request::builder()
+        .method("OPTIONS")
+        .uri(uri)
+        .header(header::ORIGIN, origin)
+        .header(header::ACCESS_CONTROL_REQUEST_METHOD, "POST")
+        .body(axum::body::Body::empty())
+        .unwrap()
+}
+
+#[test]
+fn cors_origins_are_trimmed_and_wildcard_is_dropped() {
+    assert_eq!(
+        config::parse_origins(" https://app.example/ , *, ,http://localhost:5173"),
+        vec!["https://app.example".to_string(), "http://localhost:5173".to_string()]
+    );
+}
+
+#[tokio::test]
+async fn api_preflight_allows_configured_origin_with_credentials() {
+    let mut state = test_state().await;
+    state.settings.cors_allowed_origins = vec!["https://app.example".to_string()];
+    let app = routes::app(state);
+
+    let res = app.oneshot(preflight("/api/v1/trade", "https://app.example")).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.headers().get(header::ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(), "https://app.example");
+    assert_eq!(res.headers().get(header::ACCESS_CONTROL_ALLOW_CREDENTIALS).unwrap(), "true");
+}
+
+#[tokio::test]
+async fn cors_is_not_applied_to_other_origins_or_html_routes() {
+    let mut state = test_state().await;
+    state.settings.cors_allowed_origins = vec!["https://app.example".to_string()];
+    let app = routes::app(state);
+
+    let res = app
+        .clone()
+        .oneshot(preflight("/api/v1/trade", "https://evil.example"))
+        .await
+        .unwrap();
+    assert!(res.headers().get(header::ACCESS_CONTROL_ALLOW_ORIGIN).is_none());
+
+    let req = Request::builder()
+        .uri("/login")
+        .header(header::ORIGIN, "https://app.example")
+        .body(axum::body::Body::empty())
+        .unwrap();
+    let res = app.oneshot(req).await.unwrap();
+    assert!(res.headers().get(header::ACCESS_CONTROL_ALLOW_ORIGIN).is_none());
+}
@@ -1,38 +1,13 @@
+mod common;
+
 use axum::{
     http::{header, Request, StatusCode},
     routing::post,
     Router,
 };
-use http_body_util::BodyExt;
-use mongodb::Client;
-use rustmarket::{controllers::auth_controller, config, services, templates, AppState};
+use rustmarket::{controllers::auth_controller, services};
 use tower::ServiceExt;
-
-async fn test_state() -> AppState {
-    let mut settings = config::load();
-    settings.finnhub_api_key = String::new();
-
-    let client = Client::with_uri_str(&settings.mongodb_uri)
-        .await
-        .expect("mongodb client");
-    let db = client.database(&settings.mongodb_db);
-
-    let finnhub = services::finnhub::FinnhubClient::new(settings.finnhub_api_key.clone());
-    let (events_tx, _events_rx) = tokio::sync::broadcast::channel::<String>(16);
-
-    AppState {
-        hbs: templates::build_handlebars(),
-        db,
-        settings,
-        finnhub,
-        events_tx,
-    }
-}
-
-async fn response_body_string(res: axum::response::Response) -> String {
-    let bytes = res.into_body().collect().await.unwrap().to_bytes();
-    String::from_utf8_lossy(&bytes).to_string()
-}
+use common::{test_state, response_body_string};
 
 #[tokio::test]
 async fn post_login_missing_fields_renders_errors() {
@@ -236,3 +211,111 @@ async fn post_register_missing_repeat_password_renders_error() {
     let body = response_body_string(res).await;
     assert!(body.contains("Repeat password is required."));
 }
+
+// The attributes of the <input> with the given id, whitespace-collapsed.
+fn input_attrs(body: &str, id: &str) -> Vec<String> {
+    let at = body.find(&format!("id=\"{id}\"")).unwrap_or_else(|| panic!("no #{id} in page"));
+    let start = body[..at].rfind("<input").expect("inside an <input>");
+    let end = at + body[at..].find('>').expect("closed <input>");
+    body[start + "<input".len()..end].split_whitespace().map(str::to_string).collect()
+}
+
+#[tokio::test]
+async fn post_login_validation_error_keeps_remember_me_checked() {
+    let state = test_state().await;
+    let app = Router::new()
+        .route("/login", post(auth_controller::post_login))
+        .with_state(state);
+
+    for (form, checked) in [("email=&password=&rememberMe=on", true), ("email=&password=", false)] {
+        let req = Request::builder()
+            .method("POST")
+            .uri("/login")
+            .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .body(axum::body::Body::from(form))
+            .unwrap();
+
+        let res = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let body = response_body_string(res).await;
+        assert!(body.contains("Email is required."));
+        let attrs = input_attrs(&body, "rememberMe");
+        assert!(attrs.contains(&"type=\"checkbox\"".to_string()));
+        assert!(attrs.contains(&"name=\"rememberMe\"".to_string()));
+        assert_eq!(attrs.contains(&"checked".to_string()), checked, "{form}: {attrs:?}");
+    }
+}
+
+#[tokio::test]
+async fn post_register_validation_error_keeps_remember_me_checked() {
+    let state = test_state().await;
+    let app = Router::new()
+        .route("/register", post(auth_controller::post_register))
+        .with_state(state);
+
+    let req = Request::builder()
+        .method("POST")
+        .uri("/register")
+        .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+        .body(axum::body::Body::from("username=&email=&password=&rePassword=&rememberMe=on"))
+        .unwrap();
+
+    let res = app.oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let body = response_body_string(res).await;
+    assert!(input_attrs(&body, "rememberMe").contains(&"checked".to_string()));
+}
+
+#[tokio::test]
+async fn validate_password_reports_the_failed_rule() {
+    let mut state = test_state().await;
+    state.settings.password_min_len = 8;
+    state.settings.strong_passwords = false;
+
+    assert_eq!(
+        services::auth_service::validate_password(&state, "short"),
+        Err("Password must be at least 8 characters.".to_string())
+    );
+    assert!(services::auth_service::validate_password(&state, "alllowercase").is_ok());
+
+    state.settings.strong_passwords = true;
+    assert_eq!(
+        services::auth_service::validate_password(&state, "ALLUPPERCASE1"),
+        Err("Password must include a lower-case letter.".to_string())
+    );
+    assert_eq!(
+        services::auth_service::validate_password(&state, "alllowercase1"),
+        Err("Password must include an upper-case letter.".to_string())
+    );
+    assert_eq!(
+        services::auth_service::validate_password(&state, "NoDigitsHere"),
+        Err("Password must include a digit.".to_string())
+    );
+    assert!(services::auth_service::validate_password(&state, "Mixed123").is_ok());
+}
+
+#[tokio::test]
+async fn post_register_weak_password_renders_error_when_strong_passwords_on() {
+    let mut state = test_state().await;
+    state.settings.strong_passwords = true;
+    let app = Router::new()
+        .route("/register", post(auth_controller::post_register))
+        .with_state(state);
+
+    let req = Request::builder()
+        .method("POST")
+        .uri("/register")
+        .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+        .body(axum::body::Body::from(
+            "username=TestUser&email=test%40example.com&password=abcdefgh&rePassword=abcdefgh",
+        ))
+        .unwrap();
+
+    let res = app.oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let body = response_body_string(res).await;
+    assert!(body.contains("upper-case letter"));
+}
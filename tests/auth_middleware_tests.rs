@@ -0,0 +1,292 @@
+mod common;
+
+use axum::{
+    http::{header, Request, StatusCode},
+    middleware::from_fn_with_state,
+    routing::{get, post},
+    Router,
+};
+use http_body_util::BodyExt;
+use mongodb::bson::oid::ObjectId;
+use rustmarket::{auth, config, controllers::user_controller, models::User, services};
+use tower::ServiceExt;
+use common::{test_state, scratch_state};
+
+fn test_user(token_version: i32) -> User {
+    User {
+        id: ObjectId::new(),
+        email: "test@example.com".to_string(),
+        username: "test".to_string(),
+        is_admin: false,
+        password_hash: String::new(),
+        token_version,
+        preferences: Default::default(),
+        recent_symbols: vec![],
+    }
+}
+
+#[tokio::test]
+async fn token_is_rejected_after_token_version_bump() {
+    let state = test_state().await;
+    let mut user = test_user(0);
+
+    let token = services::auth_service::make_jwt_with_days(&state, &user.id, user.token_version, 1)
+        .expect("jwt");
+    let claims = auth::decode_claims(&state, &token).expect("claims");
+
+    assert_eq!(claims.sub, user.id.to_hex());
+    assert!(auth::token_is_current(&claims, &user));
+
+    // e.g. password change or "log out all devices"
+    user.token_version += 1;
+    assert!(!auth::token_is_current(&claims, &user));
+}
+
+#[tokio::test]
+async fn token_signed_with_other_secret_is_rejected() {
+    let state = test_state().await;
+    let user = test_user(0);
+
+    let mut other = state.clone();
+    other.settings.jwt_secret = "some-other-secret".to_string();
+
+    let token = services::auth_service::make_jwt_with_days(&other, &user.id, 0, 1).expect("jwt");
+    assert!(auth::decode_claims(&state, &token).is_none());
+}
+
+#[tokio::test]
+async fn token_signed_with_previous_secret_still_validates() {
+    let mut state = test_state().await;
+    let user = test_user(0);
+
+    let mut before = state.clone();
+    before.settings.jwt_secret = "old-secret".to_string();
+    let token = services::auth_service::make_jwt_with_days(&before, &user.id, 0, 1).expect("jwt");
+
+    // rotated: a new signing secret, the old one kept for decoding
+    state.settings.jwt_secret = "new-secret".to_string();
+    state.settings.jwt_previous_secrets = vec!["old-secret".to_string()];
+    let claims = auth::decode_claims(&state, &token).expect("claims");
+    assert_eq!(claims.sub, user.id.to_hex());
+
+    // and once it's dropped from the list, the token is dead
+    state.settings.jwt_previous_secrets.clear();
+    assert!(auth::decode_claims(&state, &token).is_none());
+}
+
+#[tokio::test]
+async fn token_must_use_the_configured_algorithm() {
+    let mut state = test_state().await;
+    let user = test_user(0);
+
+    let token = services::auth_service::make_jwt_with_days(&state, &user.id, 0, 1).expect("jwt");
+    state.settings.jwt_algorithm = jsonwebtoken::Algorithm::HS512;
+    assert!(auth::decode_claims(&state, &token).is_none());
+
+    let token = services::auth_service::make_jwt_with_days(&state, &user.id, 0, 1).expect("jwt");
+    assert!(auth::decode_claims(&state, &token).is_some());
+}
+
+#[tokio::test]
+async fn token_expires_by_the_clock_it_was_minted_with() {
+    let mut state = test_state().await;
+    let user = test_user(0);
+
+    // minted three days ago with a one-day lifetime
+    let minted_at = chrono::Utc::now() - chrono::Duration::days(3);
+    state.clock = std::sync::Arc::new(services::clock::FixedClock::new(minted_at));
+    let token = services::auth_service::make_jwt_with_days(&state, &user.id, 0, 1).expect("jwt");
+    assert!(auth::decode_claims(&state, &token).is_none());
+
+    let token = services::auth_service::make_jwt_with_days(&state, &user.id, 0, 5).expect("jwt");
+    assert!(auth::decode_claims(&state, &token).is_some());
+}
+
+#[test]
+fn jwt_settings_parse() {
+    assert_eq!(config::parse_jwt_secrets(" new, old ,,"), vec!["new", "old"]);
+    assert!(config::parse_jwt_secrets(" , ").is_empty());
+    assert_eq!(config::parse_jwt_algorithm("hs384"), Some(jsonwebtoken::Algorithm::HS384));
+    assert_eq!(config::parse_jwt_algorithm("RS256"), None);
+}
+
+#[tokio::test]
+async fn me_accepts_bearer_token_and_prefers_it_over_cookie() {
+    let Some(state) = scratch_state().await else { return };
+
+    let bearer_id = services::auth_service::register_user(&state, "bearer", "bearer@example.com", "secret123")
+        .await
+        .unwrap();
+    let cookie_id = services::auth_service::register_user(&state, "cookie", "cookie@example.com", "secret123")
+        .await
+        .unwrap();
+    let bearer = services::auth_service::make_jwt_with_days(&state, &bearer_id, 0, 1).unwrap();
+    let cookie = services::auth_service::make_jwt_with_days(&state, &cookie_id, 0, 1).unwrap();
+
+    let app = Router::new()
+        .route("/me", get(user_controller::me))
+        .layer(from_fn_with_state(state.clone(), auth::inject_current_user))
+        .with_state(state.clone());
+
+    let req = Request::builder()
+        .uri("/me")
+        .header(header::AUTHORIZATION, format!("Bearer {bearer}"))
+        .header(header::COOKIE, format!("{}={cookie}", state.settings.jwt_cookie_name))
+        .body(axum::body::Body::empty())
+        .unwrap();
+
+    let res = app.clone().oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let bytes = res.into_body().collect().await.unwrap().to_bytes();
+    assert!(String::from_utf8_lossy(&bytes).contains("bearer@example.com"));
+
+    let req = Request::builder()
+        .uri("/me")
+        .header(header::AUTHORIZATION, "Bearer not-a-jwt")
+        .body(axum::body::Body::empty())
+        .unwrap();
+    let res = app.oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+    state.db.drop(None).await.unwrap();
+}
+
+#[tokio::test]
+async fn me_returns_current_user_from_auth_cookie() {
+    let Some(state) = scratch_state().await else { return };
+
+    let user_id = services::auth_service::register_user(&state, "viacookie", "viacookie@example.com", "secret123")
+        .await
+        .unwrap();
+    let token = services::auth_service::make_jwt_with_days(&state, &user_id, 0, 1).unwrap();
+
+    let app = Router::new()
+        .route("/me", get(user_controller::me))
+        .layer(from_fn_with_state(state.clone(), auth::inject_current_user))
+        .with_state(state.clone());
+
+    let req = Request::builder()
+        .uri("/me")
+        .header(header::COOKIE, format!("{}={token}", state.settings.jwt_cookie_name))
+        .body(axum::body::Body::empty())
+        .unwrap();
+
+    let res = app.oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let bytes = res.into_body().collect().await.unwrap().to_bytes();
+    let me: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(me["email"], "viacookie@example.com");
+    assert_eq!(me["username"], "viacookie");
+
+    state.db.drop(None).await.unwrap();
+}
+
+#[tokio::test]
+async fn reissued_token_and_cookie_keep_the_original_expiry() {
+    let state = test_state().await;
+    let user = test_user(0);
+    let exp = (state.clock.now() + chrono::Duration::days(30)).timestamp();
+
+    let token = services::auth_service::make_jwt_expiring_at(&state, &user.id, 0, exp).expect("jwt");
+    assert_eq!(auth::decode_claims(&state, &token).map(|c| c.exp as i64), Some(exp));
+
+    let cookie = services::auth_service::auth_cookie_expiring_at(&state, token, exp);
+    let max_age = cookie.max_age().expect("max-age").whole_seconds();
+    assert!((30 * 86_400 - 5..=30 * 86_400).contains(&max_age), "max-age {max_age}");
+}
+
+#[tokio::test]
+async fn password_change_keeps_a_remember_me_session_long_lived() {
+    let Some(mut state) = scratch_state().await else { return };
+    state.settings.jwt_ttl_days = 1;
+
+    let user_id = services::auth_service::register_user(&state, "keepme", "keepme@example.com", "secret123")
+        .await
+        .unwrap();
+    // what login issues with "remember me" ticked
+    let exp = (state.clock.now() + chrono::Duration::days(30)).timestamp();
+    let token = services::auth_service::make_jwt_expiring_at(&state, &user_id, 0, exp).unwrap();
+
+    let app = Router::new()
+        .route("/settings/password", post(user_controller::post_settings_password))
+        .layer(from_fn_with_state(state.clone(), auth::inject_current_user))
+        .with_state(state.clone());
+
+    let req = Request::builder()
+        .method("POST")
+        .uri("/settings/password")
+        .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+        .header(header::COOKIE, format!("{}={token}", state.settings.jwt_cookie_name))
+        .body(axum::body::Body::from("password=Another123&rePassword=Another123"))
+        .unwrap();
+
+    let res = app.oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let set_cookie = res
+        .headers()
+        .get(header::SET_COOKIE)
+        .and_then(|v| v.to_str().ok())
+        .expect("session cookie reissued")
+        .to_string();
+    let cookie = axum_extra::extract::cookie::Cookie::parse(set_cookie).unwrap();
+
+    let claims = auth::decode_claims(&state, cookie.value()).expect("valid token");
+    assert_eq!(claims.exp as i64, exp);
+    assert_eq!(claims.ver, 1);
+    assert!(cookie.max_age().expect("max-age").whole_days() >= 29);
+
+    state.db.drop(None).await.unwrap();
+}
+
+#[test]
+fn dev_jwt_secret_is_fatal_only_in_production() {
+    let mut settings = config::load();
+    settings.jwt_secret = config::DEV_JWT_SECRET.to_string();
+    settings.jwt_previous_secrets.clear();
+    settings.is_production = false;
+    settings.cookie_secure = false;
+    assert_eq!(settings.dev_secret_risk(), None);
+
+    settings.cookie_secure = true;
+    assert_eq!(settings.dev_secret_risk(), Some(config::DevSecretRisk::Warn));
+
+    settings.is_production = true;
+    assert_eq!(settings.dev_secret_risk(), Some(config::DevSecretRisk::Fatal));
+
+    // still accepted for decoding is just as bad
+    settings.jwt_secret = "real-secret".to_string();
+    settings.jwt_previous_secrets = vec![config::DEV_JWT_SECRET.to_string()];
+    assert_eq!(settings.dev_secret_risk(), Some(config::DevSecretRisk::Fatal));
+
+    settings.jwt_previous_secrets.clear();
+    assert_eq!(settings.dev_secret_risk(), None);
+}
+
+#[tokio::test]
+async fn auth_cookie_follows_configured_attributes() {
+    use axum_extra::extract::cookie::SameSite;
+
+    assert_eq!(config::parse_same_site(" STRICT "), Some(SameSite::Strict));
+    assert_eq!(config::parse_same_site("none"), Some(SameSite::None));
+    assert_eq!(config::parse_same_site("sometimes"), None);
+
+    let mut state = test_state().await;
+    state.settings.cookie_secure = false;
+    state.settings.cookie_domain = Some(".example.com".to_string());
+    state.settings.cookie_same_site = SameSite::Strict;
+
+    let set = services::auth_service::auth_cookie(&state, "tok".to_string(), 1);
+    let clear = services::auth_service::clear_auth_cookie(&state);
+    for cookie in [&set, &clear] {
+        assert_eq!(cookie.domain(), Some("example.com"));
+        assert_eq!(cookie.same_site(), Some(SameSite::Strict));
+        assert_eq!(cookie.secure(), None);
+    }
+
+    // browsers reject SameSite=None without Secure
+    state.settings.cookie_same_site = SameSite::None;
+    let set = services::auth_service::auth_cookie(&state, "tok".to_string(), 1);
+    assert_eq!(set.secure(), Some(true));
+    assert_eq!(services::auth_service::clear_auth_cookie(&state).secure(), Some(true));
+}
@@ -0,0 +1,93 @@
+//! Fixtures shared by the integration tests (`mod common;`).
+//!
+//! Tests that need MongoDB take a throwaway database from `scratch_db` or
+//! `scratch_state`. Without a reachable server those return None and the test
+//! skips itself, unless `REQUIRE_MONGO` is set: then the skip becomes a failure,
+//! so a run against a real database can't pass by silently skipping.
+
+#![allow(dead_code)]
+
+use std::time::Duration;
+
+use axum::response::Response;
+use http_body_util::BodyExt;
+use mongodb::{bson::doc, options::ClientOptions, Client, Database};
+use rustmarket::config::{self, Settings};
+use rustmarket::services::{self, finnhub::FinnhubClient};
+use rustmarket::{templates, AppState};
+
+pub const REQUIRE_MONGO_ENV: &str = "REQUIRE_MONGO";
+
+pub fn app_state(settings: Settings, db: Database, finnhub: FinnhubClient) -> AppState {
+    let (events_tx, _events_rx) = tokio::sync::broadcast::channel::<String>(16);
+    let trades = services::trade_relay::TradeRelay::spawn(String::new());
+    let ws_limiter = services::ws_limiter::WsLimiter::new(settings.ws_max_per_user);
+
+    AppState {
+        hbs: templates::build_handlebars(),
+        db,
+        settings,
+        finnhub,
+        events_tx,
+        trades,
+        ws_limiter,
+        clock: services::clock::real(),
+        started_at: std::time::Instant::now(),
+    }
+}
+
+/// State on the configured database. The client connects lazily, so handlers
+/// that fail before touching Mongo work without one.
+pub async fn test_state() -> AppState {
+    let mut settings = config::load();
+    settings.finnhub_api_key = String::new();
+
+    let client = Client::with_uri_str(&settings.mongodb_uri)
+        .await
+        .expect("mongodb client");
+    let db = client.database(&settings.mongodb_db);
+
+    let finnhub = FinnhubClient::new(settings.finnhub_api_key.clone());
+    app_state(settings, db, finnhub)
+}
+
+/// A fresh, empty database; drop it at the end of the test.
+pub async fn scratch_db() -> Option<Database> {
+    match connect_scratch_db().await {
+        Some(db) => Some(db),
+        None if std::env::var_os(REQUIRE_MONGO_ENV).is_some() => {
+            panic!("MongoDB not reachable and {REQUIRE_MONGO_ENV} is set")
+        }
+        None => {
+            eprintln!("MongoDB not reachable; skipping");
+            None
+        }
+    }
+}
+
+async fn connect_scratch_db() -> Option<Database> {
+    let settings = config::load();
+    let mut opts = ClientOptions::parse(&settings.mongodb_uri).await.ok()?;
+    opts.server_selection_timeout = Some(Duration::from_secs(1));
+    let client = Client::with_options(opts).ok()?;
+
+    let db = client.database(&format!("{}_test_{}", settings.mongodb_db, rand::random::<u32>()));
+    db.run_command(doc! { "ping": 1 }, None).await.ok()?;
+    Some(db)
+}
+
+/// Like test_state but on a scratch database with the production indexes.
+pub async fn scratch_state() -> Option<AppState> {
+    let db = scratch_db().await?;
+    let mut state = test_state().await;
+    services::db_init::ensure_indexes(&db, &state.settings)
+        .await
+        .expect("ensure_indexes");
+    state.db = db;
+    Some(state)
+}
+
+pub async fn response_body_string(res: Response) -> String {
+    let bytes = res.into_body().collect().await.unwrap().to_bytes();
+    String::from_utf8_lossy(&bytes).to_string()
+}
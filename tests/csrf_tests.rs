@@ -0,0 +1,118 @@
+mod common;
+
+use axum::{
+    http::{header, Request, StatusCode},
+    middleware::from_fn_with_state,
+    routing::post,
+    Router,
+};
+use rustmarket::{csrf, AppState};
+use tower::ServiceExt;
+use common::test_state;
+
+fn app(state: AppState) -> Router {
+    Router::new()
+        .route("/echo", post(|| async { "ok" }).get(|| async { "ok" }))
+        .layer(from_fn_with_state(state.clone(), csrf::verify_csrf))
+        .with_state(state)
+}
+
+#[tokio::test]
+async fn get_issues_csrf_cookie() {
+    let state = test_state().await;
+
+    let req = Request::builder()
+        .uri("/echo")
+        .body(axum::body::Body::empty())
+        .unwrap();
+
+    let res = app(state).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let set_cookie = res
+        .headers()
+        .get(header::SET_COOKIE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("");
+    assert!(set_cookie.starts_with("csrf_token="));
+}
+
+#[tokio::test]
+async fn post_without_token_is_forbidden() {
+    let state = test_state().await;
+
+    let req = Request::builder()
+        .method("POST")
+        .uri("/echo")
+        .header(header::COOKIE, "csrf_token=abc123")
+        .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+        .body(axum::body::Body::from("qty=1"))
+        .unwrap();
+
+    let res = app(state).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn post_with_mismatched_header_is_forbidden() {
+    let state = test_state().await;
+
+    let req = Request::builder()
+        .method("POST")
+        .uri("/echo")
+        .header(header::COOKIE, "csrf_token=abc123")
+        .header("X-CSRF-Token", "zzz999")
+        .body(axum::body::Body::empty())
+        .unwrap();
+
+    let res = app(state).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn post_with_matching_header_passes() {
+    let state = test_state().await;
+
+    let req = Request::builder()
+        .method("POST")
+        .uri("/echo")
+        .header(header::COOKIE, "csrf_token=abc123")
+        .header("X-CSRF-Token", "abc123")
+        .body(axum::body::Body::empty())
+        .unwrap();
+
+    let res = app(state).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn post_with_matching_form_field_passes() {
+    let state = test_state().await;
+
+    let req = Request::builder()
+        .method("POST")
+        .uri("/echo")
+        .header(header::COOKIE, "csrf_token=abc123")
+        .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+        .body(axum::body::Body::from("qty=1&csrf_token=abc123"))
+        .unwrap();
+
+    let res = app(state).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn post_with_bearer_token_skips_csrf() {
+    let state = test_state().await;
+
+    let req = Request::builder()
+        .method("POST")
+        .uri("/echo")
+        .header(header::AUTHORIZATION, "Bearer some.jwt.token")
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(axum::body::Body::from("{}"))
+        .unwrap();
+
+    let res = app(state).oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+}
@@ -0,0 +1,160 @@
+mod common;
+
+use futures_util::StreamExt;
+use mongodb::{
+    bson::{doc, oid::ObjectId}, Database,
+};
+use rustmarket::{config, services::db_init};
+use common::scratch_db;
+
+async fn ttl_seconds(db: &Database) -> Option<i64> {
+    let mut cursor = db
+        .collection::<mongodb::bson::Document>("alerts")
+        .list_indexes(None)
+        .await
+        .ok()?;
+    while let Some(Ok(idx)) = cursor.next().await {
+        let opts = idx.options.unwrap_or_default();
+        if opts.name.as_deref() == Some(db_init::ALERTS_TTL_INDEX) {
+            return opts.expire_after.map(|d| d.as_secs() as i64);
+        }
+    }
+    None
+}
+
+#[tokio::test]
+async fn alerts_ttl_index_is_idempotent_and_follows_retention() {
+    let Some(db) = scratch_db().await else { return };
+
+    db_init::ensure_alerts_ttl(&db, 7).await.unwrap();
+    db_init::ensure_alerts_ttl(&db, 7).await.unwrap();
+    assert_eq!(ttl_seconds(&db).await, Some(7 * 86_400));
+
+    db_init::ensure_alerts_ttl(&db, 3).await.unwrap();
+    assert_eq!(ttl_seconds(&db).await, Some(3 * 86_400));
+
+    db.drop(None).await.unwrap();
+}
+
+async fn winning_stage(db: &Database, collection: &str, filter: mongodb::bson::Document) -> String {
+    let plan = db
+        .run_command(
+            doc! {
+                "explain": { "find": collection, "filter": filter },
+                "verbosity": "queryPlanner",
+            },
+            None,
+        )
+        .await
+        .unwrap();
+    plan.get_document("queryPlanner")
+        .and_then(|q| q.get_document("winningPlan"))
+        .map(|w| w.to_string())
+        .unwrap_or_default()
+}
+
+#[tokio::test]
+async fn alert_and_account_lookups_use_an_index() {
+    let Some(db) = scratch_db().await else { return };
+    db_init::ensure_indexes(&db, &config::load()).await.unwrap();
+
+    let user_id = ObjectId::new();
+    let alerts: Vec<_> = (0..200)
+        .map(|i| {
+            let owner = if i % 4 == 0 { user_id } else { ObjectId::new() };
+            let symbol = ["AAPL", "MSFT", "TSLA"][i % 3];
+            doc! {
+                "user_id": owner,
+                "symbol": symbol,
+                "condition": "above",
+                "target_price": 100.0,
+                "triggered": false,
+            }
+        })
+        .collect();
+    db.collection("alerts").insert_many(alerts, None).await.unwrap();
+    db.collection("accounts")
+        .insert_one(doc! { "_id": user_id, "cash": 100.0, "updated_at": 0_i64 }, None)
+        .await
+        .unwrap();
+
+    let by_symbol = winning_stage(&db, "alerts", doc! { "user_id": user_id, "symbol": "AAPL" }).await;
+    assert!(by_symbol.contains("IXSCAN"), "{by_symbol}");
+    assert!(!by_symbol.contains("COLLSCAN"), "{by_symbol}");
+
+    let by_user = winning_stage(&db, "alerts", doc! { "user_id": user_id }).await;
+    assert!(by_user.contains("IXSCAN"), "{by_user}");
+
+    let account = winning_stage(&db, "accounts", doc! { "_id": user_id }).await;
+    assert!(!account.contains("COLLSCAN"), "{account}");
+
+    db.drop(None).await.unwrap();
+}
+
+#[tokio::test]
+async fn backfill_sets_missing_position_created_at_from_updated_at() {
+    let Some(db) = scratch_db().await else { return };
+    let positions = db.collection::<mongodb::bson::Document>("positions");
+    positions
+        .insert_many(
+            vec![
+                doc! { "symbol": "AAPL", "updated_at": 1_700_000_000_i64 },
+                doc! { "symbol": "MSFT", "created_at": 1_600_000_000_i64, "updated_at": 1_700_000_000_i64 },
+            ],
+            None,
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(db_init::backfill_position_created_at(&db).await.unwrap(), 1);
+    assert_eq!(db_init::backfill_position_created_at(&db).await.unwrap(), 0);
+
+    let aapl = positions.find_one(doc! { "symbol": "AAPL" }, None).await.unwrap().unwrap();
+    assert_eq!(aapl.get_i64("created_at").unwrap(), 1_700_000_000);
+    let msft = positions.find_one(doc! { "symbol": "MSFT" }, None).await.unwrap().unwrap();
+    assert_eq!(msft.get_i64("created_at").unwrap(), 1_600_000_000);
+
+    db.drop(None).await.unwrap();
+}
+
+#[tokio::test]
+async fn positions_index_allows_closed_rows_beside_the_open_one() {
+    let Some(db) = scratch_db().await else { return };
+    let positions = db.collection::<mongodb::bson::Document>("positions");
+    positions
+        .create_index(
+            mongodb::IndexModel::builder()
+                .keys(doc! { "user_id": 1, "symbol": 1 })
+                .options(mongodb::options::IndexOptions::builder().unique(true).build())
+                .build(),
+            None,
+        )
+        .await
+        .unwrap();
+
+    db_init::ensure_indexes(&db, &config::load()).await.unwrap();
+    db_init::ensure_indexes(&db, &config::load()).await.unwrap();
+
+    let names = positions.list_index_names().await.unwrap();
+    assert!(names.iter().any(|n| n == db_init::POSITIONS_OPEN_INDEX), "{names:?}");
+    assert!(!names.iter().any(|n| n == "user_id_1_symbol_1"), "{names:?}");
+
+    let user_id = ObjectId::new();
+    positions
+        .insert_many(
+            vec![
+                doc! { "user_id": user_id, "symbol": "AAPL", "qty": 5_i64, "updated_at": 2_i64 },
+                doc! { "user_id": user_id, "symbol": "AAPL", "qty": 0_i64, "updated_at": 1_i64 },
+            ],
+            None,
+        )
+        .await
+        .unwrap();
+
+    let dup = positions
+        .insert_one(doc! { "user_id": user_id, "symbol": "AAPL", "qty": 1_i64, "updated_at": 3_i64 }, None)
+        .await;
+    assert!(dup.unwrap_err().to_string().contains("E11000"));
+
+    db.drop(None).await.unwrap();
+}
@@ -0,0 +1,64 @@
+mod common;
+
+use mongodb::bson::oid::ObjectId;
+use rustmarket::models::{Account, Position};
+use rustmarket::services::dividend_monitor::{self, dividend_amount};
+use rustmarket::{services, AppState};
+
+async fn scratch_state() -> Option<AppState> {
+    let mut state = common::scratch_state().await?;
+    state.settings.dividend_yield_pct = Some(3.65);
+    state.settings.dividend_interval_secs = 86_400;
+    Some(state)
+}
+
+#[test]
+fn daily_dividend_is_a_365th_of_the_yearly_yield() {
+    // 100 shares at $100 with a 3.65% yield pay $365 a year, $1 a day
+    assert_eq!(dividend_amount(100, 100.0, 3.65, 86_400), 1.0);
+}
+
+#[test]
+fn dividend_is_rounded_to_cents() {
+    assert_eq!(dividend_amount(1, 10.0, 1.0, 86_400), 0.0);
+    assert_eq!(dividend_amount(3, 333.33, 5.0, 86_400 * 30), 4.11);
+}
+
+#[tokio::test]
+async fn a_period_is_paid_only_once() {
+    let Some(state) = scratch_state().await else { return };
+    let user_id = ObjectId::new();
+
+    state
+        .db
+        .collection::<Account>("accounts")
+        .insert_one(Account { id: user_id, cash: 0.0, updated_at: 0, deposit_day: None, deposited_today: 0.0 }, None)
+        .await
+        .unwrap();
+    // no Finnhub key: priced at avg_price
+    state
+        .db
+        .collection::<Position>("positions")
+        .insert_one(
+            Position {
+                id: ObjectId::new(),
+                user_id,
+                symbol: "AAPL".to_string(),
+                qty: 100,
+                avg_price: 100.0,
+                created_at: 1,
+                updated_at: 1,
+                lots: Vec::new(),
+            },
+            None,
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(dividend_monitor::run_tick(&state).await.unwrap(), 1);
+    assert_eq!(dividend_monitor::run_tick(&state).await.unwrap(), 0);
+
+    let acc = services::account_service::get_or_create_account(&state, user_id).await.unwrap();
+    assert_eq!(acc.cash, 1.0);
+    assert_eq!(services::portfolio_service::dividend_income(&state, user_id).await.unwrap(), 1.0);
+}
@@ -0,0 +1,31 @@
+use axum::{http::StatusCode, response::IntoResponse};
+use http_body_util::BodyExt;
+use rustmarket::error::AppError;
+use rustmarket::services::finnhub::FinnhubError;
+
+#[test]
+fn app_error_maps_to_status() {
+    assert_eq!(AppError::Db("boom".into()).status(), StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(AppError::NotFound.status(), StatusCode::NOT_FOUND);
+    assert_eq!(AppError::Unauthorized.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(AppError::invalid("qty", "Enter a valid quantity.").status(), StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(AppError::Finnhub(FinnhubError::NotFound).status(), StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(AppError::Finnhub(FinnhubError::RateLimited).status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(AppError::Finnhub(FinnhubError::MissingKey).status(), StatusCode::BAD_GATEWAY);
+}
+
+#[test]
+fn validation_message_is_the_field_message() {
+    assert_eq!(AppError::invalid("balance", "Not enough cash.").message(), "Not enough cash.");
+}
+
+#[tokio::test]
+async fn db_error_response_hides_the_cause() {
+    let res = AppError::Db("connection refused at 10.0.0.5:27017".into()).into_response();
+    assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+
+    let bytes = res.into_body().collect().await.unwrap().to_bytes();
+    let body = String::from_utf8_lossy(&bytes);
+    assert!(body.contains("Something went wrong"));
+    assert!(!body.contains("10.0.0.5"));
+}
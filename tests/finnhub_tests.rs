@@ -0,0 +1,249 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use reqwest::StatusCode;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use axum::{routing::get, Json, Router};
+use rustmarket::services::finnhub::{
+    CompanyProfile, FinnhubClient, FinnhubError, KnownSymbols, QuoteCache, QuoteResponse, SearchCache, SearchResponse,
+    MAX_CONCURRENT_QUOTES,
+};
+
+#[test]
+fn from_status_maps_known_codes() {
+    assert_eq!(
+        FinnhubError::from_status(StatusCode::TOO_MANY_REQUESTS),
+        FinnhubError::RateLimited
+    );
+    assert_eq!(FinnhubError::from_status(StatusCode::NOT_FOUND), FinnhubError::NotFound);
+    assert_eq!(
+        FinnhubError::from_status(StatusCode::BAD_GATEWAY),
+        FinnhubError::Http(StatusCode::BAD_GATEWAY)
+    );
+}
+
+#[test]
+fn transient_errors_are_retryable() {
+    assert!(FinnhubError::RateLimited.is_transient());
+    assert!(FinnhubError::Network("timeout".into()).is_transient());
+    assert!(!FinnhubError::NotFound.is_transient());
+    assert!(!FinnhubError::MissingKey.is_transient());
+}
+
+#[tokio::test]
+async fn quote_without_key_is_missing_key() {
+    let client = FinnhubClient::new(String::new());
+
+    let err = client.quote("AAPL").await.unwrap_err();
+    assert_eq!(err, FinnhubError::MissingKey);
+    assert_eq!(err.to_string(), "FINNHUB_API_KEY is missing in .env");
+}
+
+#[test]
+fn search_cache_keys_on_normalized_query() {
+    let cache = SearchCache::new(Duration::from_secs(60));
+    let resp = Arc::new(SearchResponse { count: 0, result: Vec::new() });
+
+    assert!(cache.get("apple").is_none());
+    cache.insert("  Apple   Inc ", resp);
+
+    assert!(cache.get("apple inc").is_some());
+    assert!(cache.get("APPLE INC").is_some());
+    assert!(cache.get("apple").is_none());
+}
+
+#[test]
+fn search_cache_entries_expire() {
+    let cache = SearchCache::new(Duration::ZERO);
+    cache.insert("aapl", Arc::new(SearchResponse { count: 0, result: Vec::new() }));
+
+    assert!(cache.get("aapl").is_none());
+}
+
+fn quote(c: f64) -> QuoteResponse {
+    QuoteResponse { c, d: 0.0, dp: 0.0, h: c, l: c, o: c, pc: c, t: 1 }
+}
+
+#[test]
+fn quote_cache_keys_on_uppercased_symbol() {
+    let cache = QuoteCache::new(Duration::from_secs(60));
+    assert!(cache.get("AAPL").is_none());
+
+    cache.insert("aapl", quote(190.0));
+    assert_eq!(cache.get("AAPL").map(|q| q.c), Some(190.0));
+    assert!(cache.get("MSFT").is_none());
+}
+
+#[test]
+fn quote_cache_entries_expire() {
+    let cache = QuoteCache::new(Duration::ZERO);
+    cache.insert("AAPL", quote(190.0));
+
+    assert!(cache.get("AAPL").is_none());
+}
+
+// A /quote stub that answers after `delay` and records the most requests it saw at once.
+async fn slow_quote_server(delay: Duration, peak: Arc<AtomicUsize>) -> String {
+    let in_flight = Arc::new(AtomicUsize::new(0));
+    let app = Router::new().route(
+        "/quote",
+        get(move || {
+            let (in_flight, peak) = (in_flight.clone(), peak.clone());
+            async move {
+                let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now, Ordering::SeqCst);
+                tokio::time::sleep(delay).await;
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+                Json(quote(10.0))
+            }
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    format!("http://{addr}")
+}
+
+#[tokio::test]
+async fn quotes_batch_runs_concurrently_but_capped() {
+    let delay = Duration::from_millis(100);
+    let peak = Arc::new(AtomicUsize::new(0));
+    let client = FinnhubClient::with_base_url("test-key".to_string(), &slow_quote_server(delay, peak.clone()).await);
+    let symbols: Vec<String> = (0..40).map(|i| format!("SYM{i}")).collect();
+
+    let started = std::time::Instant::now();
+    let quotes = client.quotes(&symbols).await;
+    let elapsed = started.elapsed();
+
+    assert_eq!(quotes.len(), symbols.len());
+    // serially this would take 40 x 100ms
+    assert!(elapsed < delay * 20, "took {elapsed:?}");
+    assert!(peak.load(Ordering::SeqCst) > 1);
+    assert!(peak.load(Ordering::SeqCst) <= MAX_CONCURRENT_QUOTES);
+}
+
+// Finnhub's answer, verbatim, for a symbol it doesn't know.
+const UNKNOWN_SYMBOL_QUOTE: &str = r#"{"c":0,"d":null,"dp":null,"h":0,"l":0,"o":0,"pc":0,"t":0}"#;
+
+#[test]
+fn unknown_symbol_quote_decodes_with_null_change() {
+    let q: QuoteResponse = serde_json::from_str(UNKNOWN_SYMBOL_QUOTE).expect("decodes");
+    assert_eq!((q.c, q.d, q.dp, q.t), (0.0, 0.0, 0.0, 0));
+}
+
+#[tokio::test]
+async fn quote_for_an_unknown_symbol_is_not_found() {
+    let app = Router::new().route(
+        "/quote",
+        get(|| async { ([(axum::http::header::CONTENT_TYPE, "application/json")], UNKNOWN_SYMBOL_QUOTE) }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    let client = FinnhubClient::with_base_url("test-key".to_string(), &format!("http://{addr}"));
+    assert_eq!(client.quote("APPL").await.unwrap_err(), FinnhubError::NotFound);
+}
+
+#[test]
+fn known_symbols_remember_both_answers_until_expiry() {
+    let known = KnownSymbols::new(Duration::from_secs(60));
+    known.insert("aapl", true);
+    known.insert("NOPE", false);
+
+    assert_eq!(known.get("AAPL"), Some(true));
+    assert_eq!(known.get("nope"), Some(false));
+    assert_eq!(known.get("MSFT"), None);
+
+    let expired = KnownSymbols::new(Duration::ZERO);
+    expired.insert("AAPL", true);
+    assert_eq!(expired.get("AAPL"), None);
+}
+
+// A /quote stub that knows only AAPL (Finnhub's real unknown-symbol body otherwise) and counts calls.
+async fn aapl_only_server(calls: Arc<AtomicUsize>) -> String {
+    let app = Router::new().route(
+        "/quote",
+        get(move |axum::extract::Query(q): axum::extract::Query<std::collections::HashMap<String, String>>| {
+            let calls = calls.clone();
+            async move {
+                calls.fetch_add(1, Ordering::SeqCst);
+                let body = if q.get("symbol").map(String::as_str) == Some("AAPL") {
+                    serde_json::to_string(&quote(190.0)).unwrap()
+                } else {
+                    UNKNOWN_SYMBOL_QUOTE.to_string()
+                };
+                ([(axum::http::header::CONTENT_TYPE, "application/json")], body)
+            }
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    format!("http://{addr}")
+}
+
+#[tokio::test]
+async fn symbol_exists_caches_the_answer() {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let client = FinnhubClient::with_base_url("test-key".to_string(), &aapl_only_server(calls.clone()).await);
+
+    assert_eq!(client.symbol_exists("AAPL").await, Ok(true));
+    assert_eq!(client.symbol_exists("APPL").await, Ok(false));
+    assert_eq!(client.symbol_exists("APPL").await, Ok(false));
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn upstream_check_reuses_its_answer() {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let client = FinnhubClient::with_base_url("test-key".to_string(), &aapl_only_server(calls.clone()).await);
+
+    for _ in 0..3 {
+        assert_eq!(client.check_upstream().await, Ok(()));
+    }
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn symbol_exists_without_key_is_an_error() {
+    let client = FinnhubClient::new(String::new());
+    assert_eq!(client.symbol_exists("AAPL").await, Err(FinnhubError::MissingKey));
+}
+
+// A /stock/profile2 stub: AAPL has a profile, anything else gets Finnhub's empty object.
+async fn profile_server(calls: Arc<AtomicUsize>) -> String {
+    let app = Router::new().route(
+        "/stock/profile2",
+        get(move |axum::extract::Query(q): axum::extract::Query<std::collections::HashMap<String, String>>| {
+            let calls = calls.clone();
+            async move {
+                calls.fetch_add(1, Ordering::SeqCst);
+                if q.get("symbol").map(String::as_str) == Some("AAPL") {
+                    Json(serde_json::json!({ "ticker": "AAPL", "name": "Apple Inc", "finnhubIndustry": "Technology" }))
+                } else {
+                    Json(serde_json::json!({}))
+                }
+            }
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    format!("http://{addr}")
+}
+
+#[tokio::test]
+async fn company_profile_maps_the_empty_answer_to_none() {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let client = FinnhubClient::with_base_url("test-key".to_string(), &profile_server(calls.clone()).await);
+
+    let aapl: CompanyProfile = client.company_profile("AAPL").await.unwrap().expect("profile");
+    assert_eq!(aapl.finnhub_industry, "Technology");
+    assert!(client.company_profile("SPY").await.unwrap().is_none());
+
+    // no in-memory cache: stocks_service::get_profile keeps them in Mongo
+    client.company_profile("AAPL").await.unwrap();
+    assert_eq!(calls.load(Ordering::SeqCst), 3);
+}
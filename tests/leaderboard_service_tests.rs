@@ -0,0 +1,31 @@
+use rustmarket::services::leaderboard_service::rank_traders;
+
+#[test]
+fn rank_traders_orders_by_equity_and_computes_return() {
+    let ranked = rank_traders(
+        vec![
+            ("bob".to_string(), 9_000.0),
+            ("alice".to_string(), 12_500.0),
+            ("carol".to_string(), 10_000.0),
+        ],
+        10_000.0,
+        10,
+    );
+
+    let names: Vec<_> = ranked.iter().map(|e| e.username.as_str()).collect();
+    assert_eq!(names, ["alice", "carol", "bob"]);
+    assert_eq!(ranked[0].rank, 1);
+    assert_eq!(ranked[0].return_pct, 25.0);
+    assert_eq!(ranked[2].return_pct, -10.0);
+    assert_eq!(ranked[2].return_class, "text-danger");
+}
+
+#[test]
+fn rank_traders_respects_limit() {
+    let equities = (0..5).map(|i| (format!("u{i}"), 10_000.0 + i as f64)).collect();
+
+    let ranked = rank_traders(equities, 10_000.0, 3);
+
+    assert_eq!(ranked.len(), 3);
+    assert_eq!(ranked[0].username, "u4");
+}
@@ -0,0 +1,45 @@
+use std::io;
+use std::sync::{Arc, Mutex};
+
+use rustmarket::logging::{JsonFields, JsonFormat};
+
+#[derive(Clone, Default)]
+struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+impl io::Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn json_lines_carry_target_fields_and_request_id() {
+    let buf = Buffer::default();
+    let writer = buf.clone();
+    let subscriber = tracing_subscriber::fmt()
+        .fmt_fields(JsonFields)
+        .event_format(JsonFormat)
+        .with_writer(move || writer.clone())
+        .finish();
+
+    tracing::subscriber::with_default(subscriber, || {
+        let span = tracing::info_span!("request", id = %"abc123");
+        let _enter = span.enter();
+        tracing::info!(target: "access", status = 200u16, "handled");
+    });
+
+    let out = String::from_utf8(buf.0.lock().unwrap().clone()).unwrap();
+    let line: serde_json::Value = serde_json::from_str(out.trim()).unwrap();
+
+    assert_eq!(line["target"], "access");
+    assert_eq!(line["level"], "INFO");
+    assert_eq!(line["request_id"], "abc123");
+    assert_eq!(line["status"], 200);
+    assert_eq!(line["message"], "handled");
+    assert!(line["timestamp"].is_string());
+}
@@ -0,0 +1,83 @@
+mod common;
+
+use axum::{
+    body::Body,
+    http::{header, Request, StatusCode},
+    routing::get,
+    Router,
+};
+use mongodb::bson::oid::ObjectId;
+use rustmarket::controllers::home_controller;
+use rustmarket::models::CurrentUser;
+use rustmarket::services::metrics;
+use tower::ServiceExt;
+use common::test_state;
+
+async fn scrape(token: Option<&str>, authorization: Option<&str>, user: Option<CurrentUser>) -> StatusCode {
+    metrics::install();
+    let mut state = test_state().await;
+    state.settings.metrics_token = token.map(str::to_string);
+    let app = Router::new()
+        .route("/metrics", get(home_controller::metrics))
+        .with_state(state);
+
+    let mut req = Request::builder().uri("/metrics");
+    if let Some(v) = authorization {
+        req = req.header(header::AUTHORIZATION, v);
+    }
+    let mut req = req.body(Body::empty()).unwrap();
+    if let Some(u) = user {
+        req.extensions_mut().insert(u);
+    }
+    app.oneshot(req).await.unwrap().status()
+}
+
+fn user(is_admin: bool) -> CurrentUser {
+    CurrentUser {
+        id: ObjectId::new(),
+        email: "test@example.com".to_string(),
+        username: "test".to_string(),
+        is_admin,
+    }
+}
+
+#[test]
+fn ws_guard_tracks_active_connections() {
+    metrics::install();
+
+    let a = metrics::WsConnectionGuard::new();
+    let b = metrics::WsConnectionGuard::new();
+    let out = metrics::render().expect("recorder installed");
+    assert!(out.contains(&format!("{} 2", metrics::WS_ACTIVE_CONNECTIONS)));
+
+    drop(a);
+    drop(b);
+    let out = metrics::render().expect("recorder installed");
+    assert!(out.contains(&format!("{} 0", metrics::WS_ACTIVE_CONNECTIONS)));
+}
+
+#[tokio::test]
+async fn metrics_are_closed_to_anonymous_and_non_admin_requests() {
+    assert_eq!(scrape(None, None, None).await, StatusCode::UNAUTHORIZED);
+    assert_eq!(scrape(Some("s3cret"), None, Some(user(false))).await, StatusCode::UNAUTHORIZED);
+    assert_eq!(scrape(Some("s3cret"), Some("Bearer wrong!"), None).await, StatusCode::UNAUTHORIZED);
+    // no configured token means no bearer token can match
+    assert_eq!(scrape(None, Some("Bearer "), None).await, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn metrics_open_to_the_scrape_token_or_an_admin() {
+    assert_eq!(scrape(Some("s3cret"), Some("Bearer s3cret"), None).await, StatusCode::OK);
+    assert_eq!(scrape(None, None, Some(user(true))).await, StatusCode::OK);
+}
+
+#[test]
+fn finnhub_latency_is_a_bucketed_histogram() {
+    metrics::install();
+    ::metrics::histogram!(metrics::FINNHUB_REQUEST_SECONDS, "endpoint" => "quote").record(0.2);
+
+    let out = metrics::render().expect("recorder installed");
+    let bucket = format!("{}_bucket{{endpoint=\"quote\",le=\"0.25\"}}", metrics::FINNHUB_REQUEST_SECONDS);
+    assert!(out.contains(&bucket), "missing {bucket} in:\n{out}");
+    assert!(!out.contains(&format!("{}{{endpoint=\"quote\",quantile=", metrics::FINNHUB_REQUEST_SECONDS)));
+}
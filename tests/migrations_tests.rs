@@ -0,0 +1,42 @@
+mod common;
+
+use mongodb::bson::doc;
+use rustmarket::services::migrations;
+use common::scratch_db;
+
+#[tokio::test]
+async fn migrations_run_once_and_record_the_version() {
+    let Some(db) = scratch_db().await else { return };
+    db.collection("alerts")
+        .insert_one(doc! { "triggered": true, "triggered_at": 1_700_000_000_i64 }, None)
+        .await
+        .unwrap();
+    db.collection("orders")
+        .insert_one(doc! { "symbol": "AAPL", "side": "buy", "qty": 1_i64 }, None)
+        .await
+        .unwrap();
+
+    assert_eq!(migrations::current_version(&db).await.unwrap(), 0);
+    assert_eq!(migrations::run(&db).await.unwrap(), migrations::latest_version());
+    assert_eq!(migrations::run(&db).await.unwrap(), migrations::latest_version());
+    assert_eq!(migrations::current_version(&db).await.unwrap(), migrations::latest_version());
+
+    let alert = db
+        .collection::<mongodb::bson::Document>("alerts")
+        .find_one(doc! {}, None)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(alert.get_datetime("triggered_on").unwrap().timestamp_millis(), 1_700_000_000_000);
+    assert!(alert.is_null("triggered_price"));
+
+    let order = db
+        .collection::<mongodb::bson::Document>("orders")
+        .find_one(doc! {}, None)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(order.get_str("status").unwrap(), "filled");
+
+    db.drop(None).await.unwrap();
+}
@@ -0,0 +1,38 @@
+mod common;
+
+use std::time::Duration;
+
+use rustmarket::services::monitor_lease;
+use common::scratch_db;
+
+#[test]
+fn instance_id_is_stable_within_a_process() {
+    assert_eq!(monitor_lease::instance_id(), monitor_lease::instance_id());
+    assert!(!monitor_lease::instance_id().is_empty());
+}
+
+#[tokio::test]
+async fn only_one_holder_at_a_time() {
+    let Some(db) = scratch_db().await else { return };
+    let ttl = Duration::from_secs(60);
+
+    assert!(monitor_lease::try_acquire(&db, "alert_monitor", "a", ttl).await.unwrap());
+    assert!(!monitor_lease::try_acquire(&db, "alert_monitor", "b", ttl).await.unwrap());
+    // the holder renews
+    assert!(monitor_lease::try_acquire(&db, "alert_monitor", "a", ttl).await.unwrap());
+    // other tasks have their own lease
+    assert!(monitor_lease::try_acquire(&db, "other", "b", ttl).await.unwrap());
+
+    let _ = db.drop(None).await;
+}
+
+#[tokio::test]
+async fn lapsed_lease_can_be_taken_over() {
+    let Some(db) = scratch_db().await else { return };
+
+    assert!(monitor_lease::try_acquire(&db, "alert_monitor", "a", Duration::ZERO).await.unwrap());
+    assert!(monitor_lease::try_acquire(&db, "alert_monitor", "b", Duration::from_secs(60)).await.unwrap());
+    assert!(!monitor_lease::try_acquire(&db, "alert_monitor", "a", Duration::from_secs(60)).await.unwrap());
+
+    let _ = db.drop(None).await;
+}
@@ -0,0 +1,55 @@
+mod common;
+
+use axum::{
+    http::{Request, StatusCode},
+    routing::get,
+    Router,
+};
+use mongodb::bson::oid::ObjectId;
+use rustmarket::controllers::portfolio_controller;
+use rustmarket::models::CurrentUser;
+use tower::ServiceExt;
+use common::{test_state, scratch_state, response_body_string};
+
+#[tokio::test]
+async fn position_card_unauthorized_returns_401() {
+    let state = test_state().await;
+    let app = Router::new()
+        .route("/portfolio/position/:symbol", get(portfolio_controller::get_portfolio_position_card))
+        .with_state(state);
+
+    let req = Request::builder()
+        .uri("/portfolio/position/AAPL")
+        .body(axum::body::Body::empty())
+        .unwrap();
+
+    let res = app.oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+}
+
+// The card refresh swaps outerHTML; an empty 200 is what removes a sold-out card.
+#[tokio::test]
+async fn position_card_for_missing_position_is_empty_200() {
+    let Some(state) = scratch_state().await else { return };
+    let db = state.db.clone();
+    let app = Router::new()
+        .route("/portfolio/position/:symbol", get(portfolio_controller::get_portfolio_position_card))
+        .with_state(state);
+
+    let mut req = Request::builder()
+        .uri("/portfolio/position/AAPL")
+        .body(axum::body::Body::empty())
+        .unwrap();
+    req.extensions_mut().insert(CurrentUser {
+        id: ObjectId::new(),
+        email: "test@example.com".to_string(),
+        username: "test".to_string(),
+        is_admin: false,
+    });
+
+    let res = app.oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(response_body_string(res).await, "");
+
+    db.drop(None).await.unwrap();
+}
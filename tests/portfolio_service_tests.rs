@@ -0,0 +1,348 @@
+mod common;
+
+use mongodb::bson::{doc, oid::ObjectId};
+use rustmarket::models::{Order, Position};
+use rustmarket::services::finnhub::QuoteResponse;
+use rustmarket::services::portfolio_service::{self, PositionView};
+
+fn view(symbol: &str, qty: i64, avg_price: f64, last_price: f64) -> PositionView {
+    PositionView {
+        symbol: symbol.to_string(),
+        qty,
+        avg_price,
+        last_price,
+        quote_unavailable: false,
+        pnl: (last_price - avg_price) * (qty as f64),
+        pnl_pct: 0.0,
+        pnl_class: "text-muted",
+        day_change: 0.0,
+        day_change_pct: 0.0,
+        day_change_class: "text-muted",
+        held_since: None,
+        days_held: None,
+        annualized_return_pct: None,
+        day_range: None,
+        lots: Vec::new(),
+    }
+}
+
+#[test]
+fn summarize_without_positions_is_cash_only() {
+    let s = portfolio_service::summarize(10_000.0, &[]);
+
+    assert_eq!(s.positions, 0);
+    assert_eq!(s.market_value, 0.0);
+    assert_eq!(s.cost_basis, 0.0);
+    assert_eq!(s.total_value, 10_000.0);
+    assert_eq!(s.unrealized_pnl, 0.0);
+    assert_eq!(s.unrealized_pnl_pct, 0.0);
+    assert_eq!(s.pnl_class, "text-muted");
+}
+
+#[test]
+fn summarize_adds_market_value_and_pnl() {
+    let views = vec![view("AAPL", 10, 100.0, 110.0), view("MSFT", 5, 200.0, 180.0)];
+    let s = portfolio_service::summarize(500.0, &views);
+
+    assert_eq!(s.positions, 2);
+    assert_eq!(s.market_value, 1_100.0 + 900.0);
+    assert_eq!(s.cost_basis, 1_000.0 + 1_000.0);
+    assert_eq!(s.total_value, 2_500.0);
+    assert_eq!(s.unrealized_pnl, 0.0);
+
+    let views = vec![view("AAPL", 10, 100.0, 120.0)];
+    let s = portfolio_service::summarize(0.0, &views);
+    assert_eq!(s.unrealized_pnl, 200.0);
+    assert!((s.unrealized_pnl_pct - 20.0).abs() < 1e-9);
+    assert_eq!(s.pnl_class, "text-success");
+}
+
+#[test]
+fn days_held_is_at_least_one_day() {
+    let now = 1_700_000_000;
+    assert_eq!(portfolio_service::days_held(0, now), None);
+    assert_eq!(portfolio_service::days_held(now - 60, now), Some(1));
+    assert_eq!(portfolio_service::days_held(now, now), Some(1));
+    assert_eq!(portfolio_service::days_held(now - 10 * 86_400, now), Some(10));
+}
+
+#[test]
+fn annualized_return_compounds_over_a_year() {
+    assert!((portfolio_service::annualized_return_pct(10.0, 365).unwrap() - 10.0).abs() < 1e-9);
+    assert!((portfolio_service::annualized_return_pct(10.0, 730).unwrap() - (1.1f64.sqrt() - 1.0) * 100.0).abs() < 1e-9);
+    assert_eq!(portfolio_service::annualized_return_pct(0.0, 30), Some(0.0));
+    assert_eq!(portfolio_service::annualized_return_pct(-100.0, 30), Some(-100.0));
+}
+
+#[test]
+fn short_holds_have_no_annualized_return() {
+    // +2% in a day would compound to ~137,000% a year
+    assert_eq!(portfolio_service::annualized_return_pct(2.0, 1), None);
+    assert_eq!(portfolio_service::annualized_return_pct(50.0, 0), None);
+    assert_eq!(portfolio_service::annualized_return_pct(2.0, portfolio_service::MIN_ANNUALIZED_DAYS - 1), None);
+    assert!(portfolio_service::annualized_return_pct(2.0, portfolio_service::MIN_ANNUALIZED_DAYS).is_some());
+    // too large for an f64
+    assert_eq!(portfolio_service::annualized_return_pct(1e300, 30), None);
+}
+
+#[test]
+fn parse_range_days_accepts_day_ranges() {
+    assert_eq!(portfolio_service::parse_range_days("30d"), Some(30));
+    assert_eq!(portfolio_service::parse_range_days(" 7d "), Some(7));
+    assert_eq!(portfolio_service::parse_range_days("9999d"), Some(730));
+    assert_eq!(portfolio_service::parse_range_days("0d"), None);
+    assert_eq!(portfolio_service::parse_range_days("30"), None);
+    assert_eq!(portfolio_service::parse_range_days("abc"), None);
+}
+
+#[test]
+fn order_filter_doc_uppercases_symbol_and_builds_range() {
+    use mongodb::bson::{doc, oid::ObjectId};
+    use rustmarket::services::portfolio_service::OrderFilter;
+
+    let user_id = ObjectId::new();
+
+    let q = portfolio_service::order_filter_doc(user_id, &OrderFilter::default());
+    assert_eq!(q, doc! { "user_id": user_id });
+
+    let filter = OrderFilter {
+        symbol: Some(" aapl ".to_string()),
+        from: Some(100),
+        to: Some(200),
+    };
+    let q = portfolio_service::order_filter_doc(user_id, &filter);
+    assert_eq!(
+        q,
+        doc! {
+            "user_id": user_id,
+            "symbol": "AAPL",
+            "created_at": { "$gte": 100_i64, "$lte": 200_i64 },
+        }
+    );
+}
+
+#[test]
+fn held_since_formats_first_buy_date() {
+    assert_eq!(portfolio_service::held_since(1_700_000_000), Some("2023-11-14".to_string()));
+    assert_eq!(portfolio_service::held_since(0), None);
+}
+
+#[test]
+fn fresh_fill_price_only_within_max_age() {
+    let now = 1_700_000_000;
+
+    assert_eq!(portfolio_service::fresh_fill_price(Some((101.5, now - 10)), now, 60), Some(101.5));
+    assert_eq!(portfolio_service::fresh_fill_price(Some((101.5, now - 60)), now, 60), Some(101.5));
+    assert_eq!(portfolio_service::fresh_fill_price(Some((101.5, now - 61)), now, 60), None);
+    assert_eq!(portfolio_service::fresh_fill_price(Some((101.5, now + 5)), now, 60), None);
+    assert_eq!(portfolio_service::fresh_fill_price(Some((0.0, now)), now, 60), None);
+    assert_eq!(portfolio_service::fresh_fill_price(None, now, 60), None);
+}
+
+fn order(symbol: &str, side: &str, qty: i64, price: f64, realized_pnl: Option<f64>) -> Order {
+    Order {
+        id: ObjectId::new(),
+        user_id: ObjectId::new(),
+        symbol: symbol.to_string(),
+        side: side.to_string(),
+        qty,
+        price,
+        total: price * (qty as f64),
+        created_at: 0,
+        realized_pnl,
+        quoted_price: None,
+        status: "filled".to_string(),
+        filled_qty: None,
+    }
+}
+
+#[test]
+fn realized_pnl_replays_sells_without_a_stored_figure() {
+    let orders = vec![
+        order("AAPL", "buy", 10, 100.0, None),
+        order("AAPL", "buy", 10, 120.0, None),
+        // average cost is 110
+        order("AAPL", "sell", 5, 130.0, None),
+        order("MSFT", "buy", 2, 300.0, None),
+        order("MSFT", "sell", 2, 250.0, None),
+    ];
+
+    assert_eq!(portfolio_service::realized_pnl(&orders), 5.0 * 20.0 - 2.0 * 50.0);
+}
+
+#[test]
+fn realized_pnl_prefers_the_stored_figure() {
+    // a FIFO sell: the oldest lot at 100 went out, not the 110 average
+    let orders = vec![
+        order("AAPL", "buy", 10, 100.0, None),
+        order("AAPL", "buy", 10, 120.0, None),
+        order("AAPL", "sell", 10, 130.0, Some(300.0)),
+        order("AAPL", "sell", 10, 130.0, None),
+    ];
+
+    // the second sell is priced against what the first one left: 10 shares at 120
+    assert_eq!(portfolio_service::realized_pnl(&orders), 300.0 + 100.0);
+}
+
+#[test]
+fn sold_everything_is_all_realized() {
+    let orders = vec![
+        order("AAPL", "buy", 4, 50.0, None),
+        order("AAPL", "sell", 4, 75.0, Some(100.0)),
+    ];
+
+    let b = portfolio_service::breakdown(portfolio_service::realized_pnl(&orders), 0.0);
+    assert_eq!(b.realized, 100.0);
+    assert_eq!(b.unrealized, 0.0);
+    assert_eq!(b.total, 100.0);
+    assert_eq!(b.total_class, "text-success");
+    assert_eq!(b.unrealized_class, "text-muted");
+}
+
+#[test]
+fn order_slippage_is_the_cost_against_the_quote() {
+    let buy = Order { quoted_price: Some(99.0), ..order("AAPL", "buy", 10, 100.0, None) };
+    assert_eq!(portfolio_service::order_slippage(&buy), Some(10.0));
+
+    let sell = Order { quoted_price: Some(101.0), ..order("AAPL", "sell", 10, 100.0, None) };
+    assert_eq!(portfolio_service::order_slippage(&sell), Some(10.0));
+
+    let at_quote = Order { quoted_price: Some(100.0), ..order("AAPL", "buy", 10, 100.0, None) };
+    assert_eq!(portfolio_service::order_slippage(&at_quote), None);
+    assert_eq!(portfolio_service::order_slippage(&order("AAPL", "buy", 10, 100.0, None)), None);
+}
+
+#[test]
+fn group_by_sector_sums_and_orders_by_value() {
+    let holdings = vec![
+        ("Technology".to_string(), 300.0),
+        (portfolio_service::UNKNOWN_SECTOR.to_string(), 100.0),
+        ("Technology".to_string(), 300.0),
+        ("Banking".to_string(), 200.0),
+    ];
+
+    let out = portfolio_service::group_by_sector(&holdings);
+    let names: Vec<&str> = out.iter().map(|s| s.sector.as_str()).collect();
+    assert_eq!(names, ["Technology", "Banking", "Unknown"]);
+    assert_eq!(out[0].value, 600.0);
+    assert!((out[0].pct - 66.666).abs() < 0.01);
+    assert!((out.iter().map(|s| s.pct).sum::<f64>() - 100.0).abs() < 1e-9);
+
+    assert!(portfolio_service::group_by_sector(&[]).is_empty());
+}
+
+#[test]
+fn orders_stored_before_statuses_read_as_fully_filled() {
+    let o = order("AAPL", "buy", 10, 100.0, None);
+    let mut stored = mongodb::bson::to_document(&o).unwrap();
+    stored.remove("status");
+
+    let read: Order = mongodb::bson::from_document(stored).unwrap();
+    assert_eq!(read.status, "filled");
+    assert_eq!(read.filled_qty(), 10);
+
+    let partial = Order { status: "open".to_string(), filled_qty: Some(4), ..o };
+    assert_eq!(partial.filled_qty(), 4);
+}
+
+#[test]
+fn order_status_badges() {
+    assert_eq!(portfolio_service::order_status_class("filled"), "text-bg-success");
+    assert_eq!(portfolio_service::order_status_class("open"), "text-bg-primary");
+    assert_eq!(portfolio_service::order_status_class("cancelled"), "text-bg-secondary");
+    assert_eq!(portfolio_service::order_status_class("weird"), "text-bg-light");
+}
+
+#[tokio::test]
+async fn position_reads_skip_closed_rows() {
+    let Some(state) = common::scratch_state().await else { return };
+    let user_id = ObjectId::new();
+    let row = |qty: i64, updated_at: i64| Position {
+        id: ObjectId::new(),
+        user_id,
+        symbol: "AAPL".to_string(),
+        qty,
+        avg_price: 100.0,
+        created_at: 1,
+        updated_at,
+        lots: Vec::new(),
+    };
+    let open = row(3, 1);
+    // closed rows, touched after the open one and so first in updated_at order
+    state
+        .db
+        .collection::<Position>("positions")
+        .insert_many(vec![row(0, 5), open.clone(), row(0, 9)], None)
+        .await
+        .unwrap();
+
+    let found = portfolio_service::get_user_position(&state, user_id, "aapl").await.unwrap().unwrap();
+    assert_eq!(found.id, open.id);
+
+    let listed = portfolio_service::list_user_positions(&state, user_id).await.unwrap();
+    assert_eq!(listed.iter().map(|p| p.id).collect::<Vec<_>>(), vec![open.id]);
+    assert_eq!(portfolio_service::count_positions(&state, user_id).await.unwrap(), 1);
+
+    let closed = state
+        .db
+        .collection::<Position>("positions")
+        .count_documents(doc! { "user_id": user_id, "qty": 0 }, None)
+        .await
+        .unwrap();
+    assert_eq!(closed, 2);
+
+    state.db.drop(None).await.unwrap();
+}
+
+#[test]
+fn failed_quote_values_the_position_at_cost() {
+    let pos = Position {
+        id: ObjectId::new(),
+        user_id: ObjectId::new(),
+        symbol: "aapl".to_string(),
+        qty: 4,
+        avg_price: 50.0,
+        created_at: 0,
+        updated_at: 0,
+        lots: Vec::new(),
+    };
+    let delisted = QuoteResponse { c: 0.0, d: 0.0, dp: 0.0, h: 0.0, l: 0.0, o: 0.0, pc: 0.0, t: 0 };
+
+    for quote in [None, Some(&delisted)] {
+        let v = portfolio_service::position_view(&pos, quote, false, 0);
+        assert!(v.quote_unavailable);
+        assert_eq!(v.last_price, 50.0);
+        assert_eq!(v.pnl, 0.0);
+
+        let summary = portfolio_service::summarize(100.0, &[v]);
+        assert_eq!(summary.total_value, 300.0);
+    }
+
+    let live = QuoteResponse { c: 60.0, ..delisted };
+    let v = portfolio_service::position_view(&pos, Some(&live), false, 0);
+    assert!(!v.quote_unavailable);
+    assert_eq!(v.pnl, 40.0);
+}
+
+#[test]
+fn position_view_counts_days_up_to_the_given_now() {
+    let now = 1_700_000_000;
+    let pos = Position {
+        id: ObjectId::new(),
+        user_id: ObjectId::new(),
+        symbol: "aapl".to_string(),
+        qty: 1,
+        avg_price: 100.0,
+        created_at: now - 365 * 86_400,
+        updated_at: now,
+        lots: Vec::new(),
+    };
+    let quote = QuoteResponse { c: 110.0, d: 0.0, dp: 0.0, h: 110.0, l: 110.0, o: 110.0, pc: 110.0, t: 0 };
+
+    let v = portfolio_service::position_view(&pos, Some(&quote), false, now);
+    assert_eq!(v.days_held, Some(365));
+    assert!((v.annualized_return_pct.unwrap() - 10.0).abs() < 1e-9);
+
+    let later = portfolio_service::position_view(&pos, Some(&quote), false, now + 365 * 86_400);
+    assert_eq!(later.days_held, Some(730));
+}
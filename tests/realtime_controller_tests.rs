@@ -0,0 +1,194 @@
+mod common;
+
+use std::time::Duration;
+
+use axum::response::{sse::Sse, IntoResponse};
+use futures_util::StreamExt;
+use http_body_util::BodyExt;
+use mongodb::bson::oid::ObjectId;
+use rustmarket::controllers::realtime_controller::{apply_client_command, heartbeat_expired, price_update_data, sse_stream, SSE_RESYNC_EVENT, unsupported_frame, FrameFormat, SymbolChange, MAX_WS_SYMBOLS, WS_PING_EVERY, WS_PONG_TIMEOUT};
+use rustmarket::services::portfolio_service;
+use rustmarket::services::trade_relay::{TradeBatcher, TradeTick};
+use common::test_state;
+
+fn tick(symbol: &str, price: f64, timestamp: i64) -> TradeTick {
+    TradeTick { symbol: symbol.to_string(), price, volume: 1.0, timestamp }
+}
+
+#[test]
+fn subscribe_adds_symbol_() {
+    let mut symbols = vec!["AAPL".to_string()];
+
+    let out = apply_client_command(&mut symbols, r#"{"type":"subscribe","symbol":" tsla "}"#).unwrap();
+
+    assert_eq!(symbols, vec!["AAPL", "TSLA"]);
+    assert_eq!(out, Some(SymbolChange::Subscribe("TSLA".to_string())));
+}
+
+#[test]
+fn unsubscribe_removes_symbol_and_ignores_unknown() {
+    let mut symbols = vec!["AAPL".to_string(), "MSFT".to_string()];
+
+    let out = apply_client_command(&mut symbols, r#"{"type":"unsubscribe","symbol":"AAPL"}"#).unwrap();
+    assert_eq!(symbols, vec!["MSFT"]);
+    assert_eq!(out, Some(SymbolChange::Unsubscribe("AAPL".to_string())));
+
+    let out = apply_client_command(&mut symbols, r#"{"type":"unsubscribe","symbol":"NVDA"}"#).unwrap();
+    assert!(out.is_none());
+}
+
+#[test]
+fn duplicate_and_malformed_frames_are_ignored() {
+    let mut symbols = vec!["AAPL".to_string()];
+
+    assert!(apply_client_command(&mut symbols, r#"{"type":"subscribe","symbol":"aapl"}"#).unwrap().is_none());
+    assert!(apply_client_command(&mut symbols, "not json").unwrap().is_none());
+    assert!(apply_client_command(&mut symbols, r#"{"type":"ping"}"#).unwrap().is_none());
+    assert_eq!(symbols, vec!["AAPL"]);
+}
+
+#[test]
+fn subscribe_enforces_symbol_cap() {
+    let mut symbols: Vec<String> = (0..MAX_WS_SYMBOLS).map(|i| format!("S{:02}", i)).collect();
+
+    let res = apply_client_command(&mut symbols, r#"{"type":"subscribe","symbol":"ZZZ"}"#);
+
+    assert!(res.is_err());
+    assert_eq!(symbols.len(), MAX_WS_SYMBOLS);
+}
+
+#[test]
+fn normalized_format_sends_one_message_per_symbol() {
+    let mut batcher = TradeBatcher::default();
+    batcher.push(&tick("MSFT", 400.0, 1_000));
+    batcher.push(&tick("AAPL", 190.0, 1_000));
+    batcher.push(&tick("AAPL", 191.0, 2_000));
+
+    let frames: Vec<serde_json::Value> = FrameFormat::Normalized
+        .trade_frames(&mut batcher)
+        .iter()
+        .map(|f| serde_json::from_str(f).unwrap())
+        .collect();
+
+    assert_eq!(
+        frames,
+        vec![
+            serde_json::json!({ "symbol": "AAPL", "price": 191.0, "ts": 2_000 }),
+            serde_json::json!({ "symbol": "MSFT", "price": 400.0, "ts": 1_000 }),
+        ]
+    );
+    assert!(FrameFormat::Normalized.trade_frames(&mut batcher).is_empty());
+}
+
+#[test]
+fn raw_format_keeps_the_finnhub_frame() {
+    let mut batcher = TradeBatcher::default();
+    batcher.push(&tick("AAPL", 190.0, 1_000));
+
+    let frames = FrameFormat::Raw.trade_frames(&mut batcher);
+    assert_eq!(frames.len(), 1);
+
+    let frame: serde_json::Value = serde_json::from_str(&frames[0]).unwrap();
+    assert_eq!(frame["type"], "trade");
+    assert_eq!(frame["data"][0]["s"], "AAPL");
+}
+
+#[test]
+fn normalized_format_only_surfaces_errors() {
+    assert!(FrameFormat::Normalized.status_frame("status", "reconnected").is_none());
+
+    let err: serde_json::Value =
+        serde_json::from_str(&FrameFormat::Normalized.status_frame("error", "upstream lost").unwrap()).unwrap();
+    assert_eq!(err, serde_json::json!({ "error": "upstream lost" }));
+
+    let status: serde_json::Value =
+        serde_json::from_str(&FrameFormat::Raw.status_frame("status", "reconnected").unwrap()).unwrap();
+    assert_eq!(status["type"], "status");
+    assert_eq!(FrameFormat::from_raw(true), FrameFormat::Raw);
+    assert_eq!(FrameFormat::from_raw(false), FrameFormat::Normalized);
+}
+
+#[test]
+fn unsupported_frame_is_typed() {
+    let frame: serde_json::Value = serde_json::from_str(&unsupported_frame("XYZ")).unwrap();
+
+    assert_eq!(frame, serde_json::json!({ "type": "error", "code": "unsupported_symbol", "symbol": "XYZ" }));
+}
+
+#[test]
+fn price_update_carries_symbol_and_price() {
+    let data: serde_json::Value = serde_json::from_str(&price_update_data(&tick("AAPL", 150.2, 1_000))).unwrap();
+
+    assert_eq!(data, serde_json::json!({ "symbol": "AAPL", "price": 150.2 }));
+}
+
+#[test]
+fn heartbeat_expires_only_after_the_pong_timeout() {
+    let seen = tokio::time::Instant::now();
+
+    // a missed ping or two is fine
+    assert!(!heartbeat_expired(seen, seen + WS_PING_EVERY * 2, WS_PONG_TIMEOUT));
+    assert!(!heartbeat_expired(seen, seen + WS_PONG_TIMEOUT, WS_PONG_TIMEOUT));
+    assert!(heartbeat_expired(seen, seen + WS_PONG_TIMEOUT + std::time::Duration::from_secs(1), WS_PONG_TIMEOUT));
+    // a clock that looks backwards never expires
+    assert!(!heartbeat_expired(seen + WS_PONG_TIMEOUT, seen, WS_PONG_TIMEOUT));
+}
+
+#[tokio::test]
+async fn sse_stream_ends_when_the_event_channel_closes() {
+    let state = test_state().await;
+    let (tx, rx) = tokio::sync::broadcast::channel::<String>(16);
+    tx.send("cashUpdated".to_string()).unwrap();
+    drop(tx);
+
+    let stream = sse_stream(state, ObjectId::new(), rx, None);
+    let events: Vec<_> = tokio::time::timeout(Duration::from_secs(2), stream.collect())
+        .await
+        .expect("stream should end instead of spinning on Closed");
+    assert_eq!(events.len(), 1);
+}
+
+#[tokio::test]
+async fn lagging_sse_subscriber_is_told_to_resync() {
+    let state = test_state().await;
+    let (tx, rx) = tokio::sync::broadcast::channel::<String>(2);
+    // five events into a channel of two: the subscriber misses the first three
+    for name in ["a", "b", "c", "alertsUpdated", "cashUpdated"] {
+        tx.send(name.to_string()).unwrap();
+    }
+    drop(tx);
+
+    let res = Sse::new(sse_stream(state, ObjectId::new(), rx, None)).into_response();
+    let bytes = tokio::time::timeout(Duration::from_secs(2), res.into_body().collect())
+        .await
+        .unwrap()
+        .unwrap()
+        .to_bytes();
+    let body = String::from_utf8_lossy(&bytes);
+
+    let events: Vec<&str> = body.lines().filter_map(|l| l.strip_prefix("event: ")).collect();
+    assert_eq!(events, vec![SSE_RESYNC_EVENT, "alertsUpdated", "cashUpdated"]);
+    assert!(body.contains("data: 3"));
+}
+
+#[tokio::test]
+async fn holdings_changes_stay_off_the_sse_wire() {
+    let state = test_state().await;
+    let (tx, rx) = tokio::sync::broadcast::channel::<String>(16);
+    let user_id = ObjectId::new();
+    tx.send(portfolio_service::holdings_changed_event(ObjectId::new())).unwrap();
+    tx.send(portfolio_service::holdings_changed_event(user_id)).unwrap();
+    tx.send("positionUpdated".to_string()).unwrap();
+    drop(tx);
+
+    let res = Sse::new(sse_stream(state, user_id, rx, None)).into_response();
+    let bytes = tokio::time::timeout(Duration::from_secs(2), res.into_body().collect())
+        .await
+        .unwrap()
+        .unwrap()
+        .to_bytes();
+    let body = String::from_utf8_lossy(&bytes);
+
+    let events: Vec<&str> = body.lines().filter_map(|l| l.strip_prefix("event: ")).collect();
+    assert_eq!(events, vec!["positionUpdated"]);
+}
@@ -0,0 +1,64 @@
+mod common;
+
+use axum::{http::StatusCode, response::IntoResponse};
+use http_body_util::BodyExt;
+use rustmarket::render::{escape_html, render_or_500, toast_trigger, undo_toast_trigger, ToastKind};
+use common::test_state;
+
+#[test]
+fn toast_trigger_carries_message_and_extra_events() {
+    let value = toast_trigger(ToastKind::Success, "Alert created.", &["alertsUpdated"]);
+
+    let parsed: serde_json::Value = serde_json::from_str(value.to_str().unwrap()).unwrap();
+    assert_eq!(parsed["showToast"]["kind"], "success");
+    assert_eq!(parsed["showToast"]["message"], "Alert created.");
+    assert_eq!(parsed["alertsUpdated"], true);
+}
+
+#[test]
+fn toast_trigger_escapes_non_ascii_for_the_header() {
+    let value = toast_trigger(ToastKind::Warning, "⚠️ Alert triggered!", &[]);
+
+    let raw = value.to_str().unwrap();
+    assert!(raw.is_ascii());
+    let parsed: serde_json::Value = serde_json::from_str(raw).unwrap();
+    assert_eq!(parsed["showToast"]["message"], "⚠️ Alert triggered!");
+}
+
+#[test]
+fn undo_toast_trigger_carries_the_undo_url() {
+    let value = undo_toast_trigger(ToastKind::Success, "Alert deleted.", "/alerts/by-id/abc/restore", &["alertsUpdated"]);
+
+    let parsed: serde_json::Value = serde_json::from_str(value.to_str().unwrap()).unwrap();
+    assert_eq!(parsed["showToast"]["undo"], "/alerts/by-id/abc/restore");
+    assert_eq!(parsed["showToast"]["message"], "Alert deleted.");
+    assert_eq!(parsed["alertsUpdated"], true);
+}
+
+#[test]
+fn escape_html_neutralizes_markup() {
+    assert_eq!(
+        escape_html(r#"<script>alert("x")</script> & 'y'"#),
+        "&lt;script&gt;alert(&quot;x&quot;)&lt;/script&gt; &amp; &#x27;y&#x27;"
+    );
+    assert_eq!(escape_html("AAPL"), "AAPL");
+}
+
+#[tokio::test]
+async fn render_or_500_hides_template_errors() {
+    let mut state = test_state().await;
+    state.settings.htmx_error_status = true;
+
+    let toast = render_or_500(&state, "partials/toast", &serde_json::json!({ "kind": "success", "message": "ok" }));
+    assert!(toast.unwrap().contains("ok"));
+
+    let res = render_or_500(&state, "partials/no_such_template", &serde_json::json!({}))
+        .unwrap_err()
+        .into_response();
+    assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+
+    let bytes = res.into_body().collect().await.unwrap().to_bytes();
+    let body = String::from_utf8_lossy(&bytes);
+    assert!(body.contains("Something went wrong"));
+    assert!(!body.contains("no_such_template"));
+}
@@ -0,0 +1,56 @@
+mod common;
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use axum::http::{HeaderMap, HeaderValue};
+use mongodb::bson::{doc, oid::ObjectId};
+use rustmarket::services::{clock::FixedClock, session_service};
+use common::scratch_state;
+
+#[test]
+fn client_ip_prefers_the_first_forwarded_hop() {
+    let peer: SocketAddr = "10.0.0.5:41000".parse().unwrap();
+    let mut headers = HeaderMap::new();
+    assert_eq!(session_service::client_ip(&headers, Some(peer)), "10.0.0.5");
+    assert_eq!(session_service::client_ip(&headers, None), "unknown");
+
+    headers.insert("x-forwarded-for", HeaderValue::from_static("203.0.113.7, 10.0.0.1"));
+    assert_eq!(session_service::client_ip(&headers, Some(peer)), "203.0.113.7");
+}
+
+#[tokio::test]
+async fn login_history_is_capped_and_revoked_by_logout_all() {
+    let Some(mut state) = scratch_state().await else { return };
+    let clock = Arc::new(FixedClock::new(chrono::Utc::now()));
+    state.clock = clock.clone();
+    let user_id = ObjectId::new();
+
+    let total = session_service::MAX_SESSIONS_PER_USER + 3;
+    for i in 0..total {
+        session_service::record_login(&state, user_id, &format!("198.51.100.{i}"), "test-agent")
+            .await
+            .unwrap();
+        clock.advance(chrono::Duration::seconds(1));
+    }
+
+    let rows = session_service::list_sessions(&state, user_id).await.unwrap();
+    assert_eq!(rows.len() as u64, session_service::MAX_SESSIONS_PER_USER);
+    // newest first, and the oldest three were pruned
+    assert_eq!(rows[0].ip, format!("198.51.100.{}", total - 1));
+    assert_eq!(rows.last().unwrap().ip, "198.51.100.3");
+    let stored = state
+        .db
+        .collection::<mongodb::bson::Document>("sessions")
+        .count_documents(doc! { "user_id": user_id }, None)
+        .await
+        .unwrap();
+    assert_eq!(stored, session_service::MAX_SESSIONS_PER_USER);
+
+    let revoked = session_service::revoke_all(&state, user_id).await.unwrap();
+    assert_eq!(revoked, session_service::MAX_SESSIONS_PER_USER);
+    let rows = session_service::list_sessions(&state, user_id).await.unwrap();
+    assert!(rows.iter().all(|s| s.revoked_at.is_some()));
+
+    state.db.drop(None).await.unwrap();
+}
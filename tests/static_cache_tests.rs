@@ -0,0 +1,45 @@
+mod common;
+
+use axum::http::{header, Request, StatusCode};
+use rustmarket::{routes, static_cache};
+use tower::ServiceExt;
+use common::test_state;
+
+#[test]
+fn detects_fingerprinted_asset_names() {
+    assert!(static_cache::is_fingerprinted("/static/js/app.3f9a1c2b.js"));
+    assert!(!static_cache::is_fingerprinted("/static/js/app.js"));
+    assert!(!static_cache::is_fingerprinted("/static/js/chart.min.js"));
+    assert!(!static_cache::is_fingerprinted("/static/3f9a1c2b.js"));
+}
+
+#[tokio::test]
+async fn static_assets_get_cache_control() {
+    let app = routes::app(test_state().await);
+
+    let req = Request::builder()
+        .uri("/static/css/app.css")
+        .body(axum::body::Body::empty())
+        .unwrap();
+
+    let res = app.oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(
+        res.headers().get(header::CACHE_CONTROL).unwrap(),
+        static_cache::DEFAULT_CACHE_CONTROL
+    );
+}
+
+#[tokio::test]
+async fn favicon_is_served() {
+    let app = routes::app(test_state().await);
+
+    let req = Request::builder()
+        .uri("/favicon.ico")
+        .body(axum::body::Body::empty())
+        .unwrap();
+
+    let res = app.oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.headers().get(header::CONTENT_TYPE).unwrap(), "image/svg+xml");
+}
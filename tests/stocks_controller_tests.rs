@@ -0,0 +1,103 @@
+mod common;
+
+use axum::{
+    http::{Request, StatusCode},
+    routing::get,
+    Router,
+};
+use mongodb::bson::{doc, oid::ObjectId};
+use rustmarket::models::CurrentUser;
+use rustmarket::controllers::stocks_controller;
+use tower::ServiceExt;
+use common::{test_state, scratch_state, response_body_string};
+
+#[tokio::test]
+async fn details_rejects_script_in_symbol() {
+    let state = test_state().await;
+    let app = Router::new()
+        .route("/details/:symbol", get(stocks_controller::get_details))
+        .with_state(state);
+
+    let req = Request::builder()
+        .method("GET")
+        .uri("/details/%3Cscript%3Ealert(1)%3C%2Fscript%3E")
+        .header("HX-Request", "true")
+        .body(axum::body::Body::empty())
+        .unwrap();
+
+    let res = app.oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    let body = response_body_string(res).await;
+    assert!(!body.contains("<script>"));
+    assert!(body.contains("Invalid symbol."));
+}
+
+#[tokio::test]
+async fn details_uppercases_the_path_symbol() {
+    let state = test_state().await;
+    let app = Router::new()
+        .route("/details/:symbol", get(stocks_controller::get_details))
+        .with_state(state);
+
+    let req = Request::builder()
+        .method("GET")
+        .uri("/details/%20binance:btcusdt")
+        .header("HX-Request", "true")
+        .body(axum::body::Body::empty())
+        .unwrap();
+
+    let res = app.oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let body = response_body_string(res).await;
+    assert!(body.contains("BINANCE:BTCUSDT"));
+    assert!(!body.contains("binance:btcusdt"));
+}
+
+#[tokio::test]
+async fn viewed_details_show_up_in_recent_deduped_and_capped() {
+    let Some(state) = scratch_state().await else { return };
+    let db = state.db.clone();
+    let user = CurrentUser {
+        id: ObjectId::new(),
+        email: "test@example.com".to_string(),
+        username: "test".to_string(),
+        is_admin: false,
+    };
+    db.collection("users")
+        .insert_one(doc! { "_id": user.id, "email": &user.email, "username": &user.username, "password_hash": "" }, None)
+        .await
+        .unwrap();
+
+    let app = Router::new()
+        .route("/details/:symbol", get(stocks_controller::get_details))
+        .route("/recent", get(stocks_controller::get_recent))
+        .with_state(state);
+
+    let get_as_user = |uri: String| {
+        let mut req = Request::builder()
+            .uri(uri)
+            .header("HX-Request", "true")
+            .body(axum::body::Body::empty())
+            .unwrap();
+        req.extensions_mut().insert(user.clone());
+        req
+    };
+
+    for i in 0..12 {
+        app.clone().oneshot(get_as_user(format!("/details/S{i}"))).await.unwrap();
+    }
+    app.clone().oneshot(get_as_user("/details/s5".to_string())).await.unwrap();
+
+    let res = app.oneshot(get_as_user("/recent".to_string())).await.unwrap();
+    let body = response_body_string(res).await;
+    let shown: Vec<&str> = body
+        .split("href=\"/details/")
+        .skip(1)
+        .map(|rest| &rest[..rest.find('"').unwrap()])
+        .collect();
+    assert_eq!(shown, vec!["S5", "S11", "S10", "S9", "S8", "S7", "S6", "S4", "S3", "S2"]);
+
+    db.drop(None).await.unwrap();
+}
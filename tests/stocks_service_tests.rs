@@ -0,0 +1,221 @@
+mod common;
+
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use axum::{routing::get, Json, Router};
+use rustmarket::services::clock::FixedClock;
+use rustmarket::services::finnhub::{FinnhubClient, SearchItem};
+use rustmarket::services::stocks_service::{self, filter_results, page_results, MAX_SEARCH_LIMIT};
+use rustmarket::AppState;
+
+fn item(symbol: &str, kind: &str) -> SearchItem {
+    SearchItem {
+        description: format!("{symbol} desc"),
+        display_symbol: symbol.to_string(),
+        symbol: symbol.to_string(),
+        kind: kind.to_string(),
+    }
+}
+
+#[test]
+fn filter_results_drops_empty_symbols_and_caps_at_ten() {
+    let mut items = vec![item("", "Common Stock"), item("  ", "Common Stock")];
+    items.extend((0..15).map(|i| item(&format!("S{i}"), "Common Stock")));
+
+    let out = filter_results(&items, None, None);
+
+    assert_eq!(out.len(), 10);
+    assert_eq!(out[0].symbol, "S0");
+}
+
+#[test]
+fn filter_results_keeps_only_requested_type() {
+    let items = vec![
+        item("AAPL", "Common Stock"),
+        item("SPY", "ETP"),
+        item("AAPL.MX", "Common Stock"),
+        item("BABA", "ADR"),
+    ];
+
+    let stocks: Vec<_> = filter_results(&items, Some("common stock"), None).iter().map(|i| i.symbol.as_str()).collect();
+    assert_eq!(stocks, ["AAPL", "AAPL.MX"]);
+
+    // Finnhub labels ETFs "ETP"
+    let etfs: Vec<_> = filter_results(&items, Some("ETF"), None).iter().map(|i| i.symbol.as_str()).collect();
+    assert_eq!(etfs, ["SPY"]);
+
+    assert_eq!(filter_results(&items, Some(""), None).len(), 4);
+}
+
+#[test]
+fn page_results_pages_through_matches() {
+    let mut items = vec![item("", "Common Stock")];
+    items.extend((0..25).map(|i| item(&format!("S{i}"), "Common Stock")));
+
+    let first = page_results(&items, None, None, 0, 10);
+    assert_eq!(first.items.len(), 10);
+    assert_eq!(first.total, 25);
+    assert_eq!(first.next_offset, Some(10));
+
+    let last = page_results(&items, None, None, 20, 10);
+    assert_eq!(last.items.iter().map(|i| i.symbol.as_str()).collect::<Vec<_>>(), ["S20", "S21", "S22", "S23", "S24"]);
+    assert_eq!(last.next_offset, None);
+
+    assert!(page_results(&items, None, None, 100, 10).items.is_empty());
+    assert_eq!(page_results(&items, None, None, 0, 0).items.len(), 1);
+    assert_eq!(page_results(&items, None, None, 0, 1000).items.len(), 25.min(MAX_SEARCH_LIMIT));
+}
+
+#[test]
+fn quote_freshness_flags_old_and_missing_timestamps() {
+    use rustmarket::services::stocks_service::{quote_freshness, QUOTE_STALE_SECS};
+
+    let now = 1_700_000_000;
+    assert_eq!(quote_freshness(now - 60, now), (false, Some("2023-11-14 22:12 UTC".to_string())));
+    assert!(quote_freshness(now - QUOTE_STALE_SECS - 1, now).0);
+    assert_eq!(quote_freshness(0, now), (true, None));
+}
+
+#[test]
+fn normalize_symbol_keeps_exchange_prefix() {
+    assert_eq!(stocks_service::normalize_symbol(" aapl "), "AAPL");
+    assert_eq!(stocks_service::normalize_symbol("binance:btcusdt"), "BINANCE:BTCUSDT");
+    assert_eq!(stocks_service::normalize_symbol(" Binance : btcUSDT"), "BINANCE:BTCUSDT");
+}
+
+#[test]
+fn valid_symbols_are_tickers_or_one_prefix() {
+    for ok in ["AAPL", "BRK.B", "^GSPC", "BINANCE:BTCUSDT", "OANDA:EUR_USD"] {
+        assert!(stocks_service::is_valid_symbol(ok), "{ok}");
+    }
+    for bad in ["", "BINANCE:", ":BTCUSDT", "A:B:C", "AAPL;DROP", "AA PL"] {
+        assert!(!stocks_service::is_valid_symbol(bad), "{bad}");
+    }
+}
+
+#[test]
+fn symbol_list_is_normalized_deduped_and_capped() {
+    assert_eq!(
+        stocks_service::parse_symbol_list(" msft,aapl,,AAPL, bad sym ,binance:btcusdt", 50),
+        vec!["AAPL", "BINANCE:BTCUSDT", "MSFT"]
+    );
+    assert_eq!(stocks_service::parse_symbol_list("c,b,a", 2), vec!["A", "B"]);
+    assert!(stocks_service::parse_symbol_list("", 50).is_empty());
+}
+
+#[test]
+fn day_range_pct_places_price_between_low_and_high() {
+    assert_eq!(stocks_service::day_range_pct(150.0, 100.0, 200.0), Some(50.0));
+    assert_eq!(stocks_service::day_range_pct(100.0, 100.0, 200.0), Some(0.0));
+    // a stale low/high can leave the price just outside the range
+    assert_eq!(stocks_service::day_range_pct(210.0, 100.0, 200.0), Some(100.0));
+    assert_eq!(stocks_service::day_range_pct(150.0, 150.0, 150.0), None);
+    assert_eq!(stocks_service::day_range_pct(150.0, 0.0, 0.0), None);
+}
+
+#[test]
+fn day_range_needs_session_figures() {
+    use rustmarket::services::finnhub::QuoteResponse;
+
+    let q = QuoteResponse { c: 190.0, d: 1.0, dp: 0.5, h: 200.0, l: 180.0, o: 185.0, pc: 189.0, t: 1 };
+    let r = stocks_service::day_range(&q).unwrap();
+    assert_eq!((r.open, r.high, r.low, r.prev_close), (185.0, 200.0, 180.0, 189.0));
+    assert_eq!(r.pct, Some(50.0));
+
+    let empty = QuoteResponse { h: 0.0, l: 0.0, ..q };
+    assert!(stocks_service::day_range(&empty).is_none());
+}
+
+#[test]
+fn symbol_exchange_reads_the_suffix() {
+    assert_eq!(stocks_service::symbol_exchange("AAPL"), "US");
+    assert_eq!(stocks_service::symbol_exchange("BRK.B"), "US");
+    assert_eq!(stocks_service::symbol_exchange("VOD.L"), "L");
+    assert_eq!(stocks_service::symbol_exchange("sap.de"), "DE");
+    assert_eq!(stocks_service::symbol_exchange("BINANCE:BTCUSDT"), "BINANCE");
+}
+
+// Roughly what Finnhub's /search returns for "apple".
+fn canned_apple_search() -> Vec<SearchItem> {
+    vec![
+        item("AAPL", "Common Stock"),
+        item("APC.DE", "Common Stock"),
+        item("AAPL.MX", "Common Stock"),
+        item("", "Common Stock"),
+        item("APLE", "REIT"),
+        item("0R2V.L", "Common Stock"),
+    ]
+}
+
+#[test]
+fn exchange_filter_keeps_only_that_exchange() {
+    let items = canned_apple_search();
+    let symbols = |v: Vec<&SearchItem>| v.iter().map(|i| i.symbol.clone()).collect::<Vec<_>>();
+
+    assert_eq!(symbols(filter_results(&items, None, Some("us"))), vec!["AAPL", "APLE"]);
+    assert_eq!(symbols(filter_results(&items, Some("Common Stock"), Some("US"))), vec!["AAPL"]);
+    assert_eq!(symbols(filter_results(&items, None, Some("DE"))), vec!["APC.DE"]);
+    // empty means every exchange, still without the blank symbol
+    assert_eq!(filter_results(&items, None, Some("")).len(), 5);
+    assert_eq!(page_results(&items, None, Some("US"), 0, 10).total, 2);
+}
+
+#[test]
+fn profiles_go_stale_after_max_age() {
+    let hour = 60 * 60;
+    assert!(stocks_service::profile_is_fresh(1_000, 1_000 + 23 * hour, 24));
+    assert!(!stocks_service::profile_is_fresh(1_000, 1_000 + 24 * hour, 24));
+}
+
+// A /stock/profile2 stub that counts its calls; every symbol gets the same profile.
+async fn profile_server(calls: Arc<AtomicUsize>) -> String {
+    let app = Router::new().route(
+        "/stock/profile2",
+        get(move || {
+            let calls = calls.clone();
+            async move {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Json(serde_json::json!({ "ticker": "AAPL", "name": "Apple Inc", "finnhubIndustry": "Technology", "logo": "https://example.com/aapl.png" }))
+            }
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    format!("http://{addr}")
+}
+
+async fn scratch_state(finnhub: FinnhubClient) -> Option<AppState> {
+    let mut state = common::scratch_state().await?;
+    state.finnhub = finnhub;
+    Some(state)
+}
+
+#[tokio::test]
+async fn stored_profile_is_reused_until_it_goes_stale() {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let finnhub = FinnhubClient::with_base_url("test-key".to_string(), &profile_server(calls.clone()).await);
+    let Some(mut state) = scratch_state(finnhub).await else { return };
+    let clock = Arc::new(FixedClock::new(chrono::Utc::now()));
+    state.clock = clock.clone();
+    state.settings.profile_max_age_hours = 24;
+
+    let p = stocks_service::get_profile(&state, "aapl").await.unwrap().unwrap();
+    assert_eq!(p.logo, "https://example.com/aapl.png");
+    stocks_service::get_profile(&state, "AAPL").await.unwrap();
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+    // past the max age the next view refetches, even though the client's own cache is warm
+    clock.advance(chrono::Duration::hours(25));
+    stocks_service::get_profile(&state, "AAPL").await.unwrap();
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+    // and an admin refresh always does
+    stocks_service::refresh_profile(&state, "AAPL").await.unwrap();
+    assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+    state.db.drop(None).await.unwrap();
+}
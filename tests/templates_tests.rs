@@ -0,0 +1,181 @@
+use rustmarket::templates;
+
+#[test]
+fn default_build_keeps_templates_cached() {
+    let hb = templates::build_handlebars();
+
+    assert!(!hb.dev_mode());
+    assert!(hb.get_template("navbar").is_some());
+}
+
+#[test]
+fn hot_reload_build_enables_dev_mode() {
+    let hb = templates::build_handlebars_with_reload(true);
+
+    assert!(hb.dev_mode());
+    assert!(hb.get_template("layouts/base").is_some());
+}
+
+#[test]
+fn format_currency_groups_thousands() {
+    assert_eq!(templates::format_currency(10_000.0), "$10,000.00");
+    assert_eq!(templates::format_currency(1_234_567.891), "$1,234,567.89");
+    assert_eq!(templates::format_currency(999.999), "$1,000.00");
+    assert_eq!(templates::format_currency(12.5), "$12.50");
+}
+
+#[test]
+fn format_currency_handles_negatives_and_zero() {
+    assert_eq!(templates::format_currency(-3.2), "-$3.20");
+    assert_eq!(templates::format_currency(-1_500.0), "-$1,500.00");
+    assert_eq!(templates::format_currency(0.0), "$0.00");
+    assert_eq!(templates::format_currency(-0.001), "$0.00");
+}
+
+#[test]
+fn format_pct_signs_the_value() {
+    assert_eq!(templates::format_pct(3.214), "+3.21%");
+    assert_eq!(templates::format_pct(-1.5), "-1.50%");
+    assert_eq!(templates::format_pct(0.0), "0.00%");
+    assert_eq!(templates::format_pct(-0.001), "0.00%");
+}
+
+#[test]
+fn currency_and_pct_helpers_render_raw_numbers() {
+    let hb = templates::build_handlebars();
+
+    let out = hb
+        .render_template(
+            "{{currency cash}} {{pct pnl_pct}}",
+            &serde_json::json!({ "cash": 10000.0, "pnl_pct": -2.5 }),
+        )
+        .unwrap();
+
+    assert_eq!(out, "$10,000.00 -2.50%");
+}
+
+#[test]
+fn format_money_uses_the_currency_symbol_and_decimals() {
+    assert_eq!(templates::format_money(1_234.5, "EUR"), "€1,234.50");
+    assert_eq!(templates::format_money(-3.2, "GBP"), "-£3.20");
+    assert_eq!(templates::format_money(1_234.5, "JPY"), "¥1,235");
+    assert_eq!(templates::format_money(12.0, "XYZ"), "$12.00");
+}
+
+#[test]
+fn currency_helper_follows_root_display_currency() {
+    let hb = templates::build_handlebars();
+
+    let out = hb
+        .render_template(
+            "{{currency cash}}{{#each items}} {{currency this}}{{/each}}",
+            &serde_json::json!({ "cash": 10.0, "items": [2000.0], "display_currency": "EUR" }),
+        )
+        .unwrap();
+
+    assert_eq!(out, "€10.00 €2,000.00");
+}
+
+#[test]
+fn search_results_offers_more_and_appends_bare_rows() {
+    let hb = templates::build_handlebars();
+    let row = serde_json::json!({ "symbol": "AAPL", "display_symbol": "AAPL", "description": "Apple", "type": "Common Stock" });
+
+    let first = hb
+        .render(
+            "partials/search_results",
+            &serde_json::json!({ "query": "a", "results": [row], "count": 1, "total": 3, "next_offset": 1, "limit": 1, "append": false }),
+        )
+        .unwrap();
+    assert!(first.contains("list-group\""));
+    assert!(first.contains(r#""offset": 1"#));
+    assert!(first.contains("More results (1 of 3)"));
+
+    let more = hb
+        .render(
+            "partials/search_results",
+            &serde_json::json!({ "query": "a", "results": [row], "count": 3, "total": 3, "next_offset": null, "limit": 1, "append": true }),
+        )
+        .unwrap();
+    assert!(more.contains("/details/AAPL"));
+    assert!(!more.contains("Results for"));
+    assert!(!more.contains("More results"));
+}
+
+#[cfg(feature = "embed-templates")]
+#[test]
+fn embedded_build_registers_every_template() {
+    assert!(templates::templates_embedded());
+
+    let hb = templates::build_handlebars();
+    assert!(hb.get_template("layouts/base").is_some());
+    assert!(hb.get_template("footer").is_some());
+}
+
+#[test]
+fn watchlist_alerts_show_the_triggered_price_when_known() {
+    let hb = templates::build_handlebars();
+    let alert = |price: serde_json::Value| {
+        serde_json::json!({
+            "groups": [{
+                "symbol": "AAPL",
+                "has_price": false,
+                "alerts": [{
+                    "id": "a1",
+                    "condition": "above",
+                    "target_price": 180.0,
+                    "triggered": true,
+                    "triggered_date": "2023-11-14",
+                    "triggered_price": price,
+                }],
+            }],
+        })
+    };
+
+    let html = hb.render("partials/watchlist_alerts", &alert(serde_json::json!(187.5))).unwrap();
+    assert!(html.contains("Triggered at $187.50 on 2023-11-14"));
+
+    let html = hb.render("partials/watchlist_alerts", &alert(serde_json::Value::Null)).unwrap();
+    assert!(html.contains("Triggered on 2023-11-14"));
+}
+
+#[test]
+fn quote_shows_the_day_range_bar_only_when_known() {
+    let hb = templates::build_handlebars();
+    let quote = serde_json::json!({ "c": 190.0, "d": 1.0, "dp": 0.5, "h": 200.0, "l": 180.0, "o": 185.0, "pc": 189.0, "t": 1 });
+
+    let html = hb
+        .render(
+            "partials/quote",
+            &serde_json::json!({ "quote": quote, "day_range": { "low": 180.0, "high": 200.0, "pct": 0.0 } }),
+        )
+        .unwrap();
+    assert!(html.contains("data-role=\"day-range\""));
+    assert!(html.contains("width: 0.0%"));
+
+    let html = hb.render("partials/quote", &serde_json::json!({ "quote": quote, "day_range": null })).unwrap();
+    assert!(!html.contains("data-role=\"day-range\""));
+}
+
+#[test]
+fn position_card_hides_annualized_return_when_unknown() {
+    let hb = templates::build_handlebars();
+    let card = |annualized: serde_json::Value| {
+        hb.render(
+            "partials/portfolio_position_card",
+            &serde_json::json!({
+                "symbol": "AAPL", "qty": 1, "avg": 100.0, "current_price": 102.0, "pnl": 2.0, "pnl_pct": 2.0,
+                "day_change": 1.0, "day_change_pct": 1.0, "days_held": 1, "annualized_pct": annualized,
+            }),
+        )
+        .unwrap()
+    };
+
+    let html = card(serde_json::Value::Null);
+    assert!(html.contains("Held:"));
+    assert!(!html.contains("Annualized:"));
+
+    let html = card(serde_json::json!(0.0));
+    assert!(html.contains("Annualized:"));
+    assert!(html.contains("0.00%"));
+}
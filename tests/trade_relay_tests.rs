@@ -0,0 +1,113 @@
+use rustmarket::services::trade_relay::{erroring_symbol, split_trades, upstream_error, SymbolRefs, TradeBatcher, TradeTick};
+
+fn tick(symbol: &str, price: f64, volume: f64, timestamp: i64) -> TradeTick {
+    TradeTick {
+        symbol: symbol.to_string(),
+        price,
+        volume,
+        timestamp,
+    }
+}
+
+#[test]
+fn refs_subscribe_on_first_and_unsubscribe_on_last() {
+    let mut refs = SymbolRefs::default();
+
+    assert!(refs.acquire("AAPL"));
+    assert!(!refs.acquire("AAPL"));
+    assert_eq!(refs.count("AAPL"), 2);
+
+    assert!(!refs.release("AAPL"));
+    assert!(refs.release("AAPL"));
+    assert!(refs.is_empty());
+}
+
+#[test]
+fn refs_ignore_release_of_unknown_symbol() {
+    let mut refs = SymbolRefs::default();
+
+    assert!(!refs.release("MSFT"));
+    assert!(refs.is_empty());
+}
+
+#[test]
+fn split_trades_groups_by_symbol() {
+    let text = r#"{"type":"trade","data":[
+        {"p":190.1,"s":"AAPL","t":1,"v":10},
+        {"p":410.5,"s":"MSFT","t":2,"v":5},
+        {"p":190.2,"s":"AAPL","t":3,"v":1}
+    ]}"#;
+
+    let out = split_trades(text);
+
+    assert_eq!(out.len(), 2);
+    assert_eq!(out[0].0, "AAPL");
+    assert_eq!(out[0].1, vec![tick("AAPL", 190.1, 10.0, 1), tick("AAPL", 190.2, 1.0, 3)]);
+    assert_eq!(out[1].0, "MSFT");
+    assert_eq!(out[1].1, vec![tick("MSFT", 410.5, 5.0, 2)]);
+}
+
+#[test]
+fn batcher_keeps_last_price_and_sums_volume() {
+    let mut batcher = TradeBatcher::default();
+    batcher.push(&tick("AAPL", 190.0, 10.0, 1));
+    batcher.push(&tick("AAPL", 190.5, 2.0, 3));
+    batcher.push(&tick("AAPL", 189.0, 1.0, 2));
+    batcher.push(&tick("MSFT", 410.0, 4.0, 2));
+
+    let frame: serde_json::Value = serde_json::from_str(&batcher.flush().unwrap()).unwrap();
+
+    assert_eq!(frame["type"], "trade");
+    let data = frame["data"].as_array().unwrap();
+    assert_eq!(data.len(), 2);
+    assert_eq!(data[0]["s"], "AAPL");
+    assert_eq!(data[0]["p"], 190.5);
+    assert_eq!(data[0]["v"], 13.0);
+    assert_eq!(data[0]["t"], 3);
+    assert_eq!(data[1]["s"], "MSFT");
+}
+
+#[test]
+fn batcher_flush_drains_window() {
+    let mut batcher = TradeBatcher::default();
+    assert!(batcher.flush().is_none());
+
+    batcher.push(&tick("AAPL", 190.0, 1.0, 1));
+    assert!(batcher.flush().is_some());
+    assert!(batcher.is_empty());
+    assert!(batcher.flush().is_none());
+}
+
+#[test]
+fn split_trades_skips_non_trade_frames() {
+    assert!(split_trades(r#"{"type":"ping"}"#).is_empty());
+    assert!(split_trades("garbage").is_empty());
+}
+
+#[test]
+fn upstream_error_reads_only_error_frames() {
+    assert_eq!(
+        upstream_error(r#"{"type":"error","msg":"Symbol not supported: XYZ"}"#),
+        Some("Symbol not supported: XYZ".to_string())
+    );
+    assert!(upstream_error(r#"{"type":"ping"}"#).is_none());
+    assert!(upstream_error(r#"{"type":"trade","data":[]}"#).is_none());
+    assert!(upstream_error("not json").is_none());
+}
+
+#[test]
+fn erroring_symbol_prefers_the_named_symbol() {
+    let watched = vec!["AAPL".to_string(), "BINANCE:BTCUSDT".to_string()];
+
+    assert_eq!(
+        erroring_symbol("Invalid symbol binance:btcusdt", &watched, Some("AAPL")),
+        Some("BINANCE:BTCUSDT".to_string())
+    );
+    assert_eq!(
+        erroring_symbol("Symbol not supported", &watched, Some("AAPL")),
+        Some("AAPL".to_string())
+    );
+    // the latest subscribe only counts while something still watches it
+    assert_eq!(erroring_symbol("Symbol not supported", &watched, Some("TSLA")), None);
+    assert_eq!(erroring_symbol("Symbol not supported", &watched, None), None);
+}
@@ -1,39 +1,15 @@
+mod common;
+
 use axum::{
     http::{header, Request, StatusCode},
-    routing::post,
+    routing::{get, post},
     Router,
 };
-use http_body_util::BodyExt;
-use mongodb::{bson::oid::ObjectId, Client};
-use rustmarket::{controllers::trading_controller, config, services, templates, AppState};
+use mongodb::bson::oid::ObjectId;
+use rustmarket::controllers::trading_controller;
 use rustmarket::models::CurrentUser;
 use tower::ServiceExt;
-
-async fn test_state() -> AppState {
-    let mut settings = config::load();
-    settings.finnhub_api_key = String::new();
-
-    let client = Client::with_uri_str(&settings.mongodb_uri)
-        .await
-        .expect("mongodb client");
-    let db = client.database(&settings.mongodb_db);
-
-    let finnhub = services::finnhub::FinnhubClient::new(settings.finnhub_api_key.clone());
-    let (events_tx, _events_rx) = tokio::sync::broadcast::channel::<String>(16);
-
-    AppState {
-        hbs: templates::build_handlebars(),
-        db,
-        settings,
-        finnhub,
-        events_tx,
-    }
-}
-
-async fn response_body_string(res: axum::response::Response) -> String {
-    let bytes = res.into_body().collect().await.unwrap().to_bytes();
-    String::from_utf8_lossy(&bytes).to_string()
-}
+use common::{test_state, response_body_string};
 
 #[tokio::test]
 async fn post_trade_buy_unauthorized_returns_401() {
@@ -75,10 +51,11 @@ async fn post_trade_buy_invalid_qty_renders_error() {
         id: ObjectId::new(),
         email: "test@example.com".to_string(),
         username: "test".to_string(),
+        is_admin: false,
     });
 
     let res = app.oneshot(req).await.unwrap();
-    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
 
     let body = response_body_string(res).await;
     assert!(body.contains("Enter a valid quantity"));
@@ -124,10 +101,11 @@ async fn post_trade_sell_invalid_qty_renders_error() {
         id: ObjectId::new(),
         email: "test@example.com".to_string(),
         username: "test".to_string(),
+        is_admin: false,
     });
 
     let res = app.oneshot(req).await.unwrap();
-    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
 
     let body = response_body_string(res).await;
     assert!(body.contains("Enter a valid quantity"));
@@ -151,10 +129,11 @@ async fn post_trade_buy_zero_qty_renders_error() {
         id: ObjectId::new(),
         email: "test@example.com".to_string(),
         username: "test".to_string(),
+        is_admin: false,
     });
 
     let res = app.oneshot(req).await.unwrap();
-    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
 
     let body = response_body_string(res).await;
     assert!(body.contains("Enter a valid quantity"));
@@ -178,10 +157,11 @@ async fn post_trade_buy_negative_qty_renders_error() {
         id: ObjectId::new(),
         email: "test@example.com".to_string(),
         username: "test".to_string(),
+        is_admin: false,
     });
 
     let res = app.oneshot(req).await.unwrap();
-    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
 
     let body = response_body_string(res).await;
     assert!(body.contains("Enter a valid quantity"));
@@ -205,10 +185,11 @@ async fn post_trade_sell_zero_qty_renders_error() {
         id: ObjectId::new(),
         email: "test@example.com".to_string(),
         username: "test".to_string(),
+        is_admin: false,
     });
 
     let res = app.oneshot(req).await.unwrap();
-    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
 
     let body = response_body_string(res).await;
     assert!(body.contains("Enter a valid quantity"));
@@ -232,23 +213,24 @@ async fn post_trade_sell_negative_qty_renders_error() {
         id: ObjectId::new(),
         email: "test@example.com".to_string(),
         username: "test".to_string(),
+        is_admin: false,
     });
 
     let res = app.oneshot(req).await.unwrap();
-    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
 
     let body = response_body_string(res).await;
     assert!(body.contains("Enter a valid quantity"));
 }
 
 #[tokio::test]
-async fn post_trade_buy_missing_symbol_returns_generic_error() {
+async fn post_trade_buy_blank_symbol_is_rejected_at_the_router() {
     let state = test_state().await;
     let app = Router::new()
         .route("/trade/:symbol/buy", post(trading_controller::post_trade_buy))
         .with_state(state);
 
-    // Symbol is whitespace ("%20"), which the service treats as missing.
+    // Symbol is whitespace ("%20"); the Symbol extractor refuses it before the handler runs.
     let mut req = Request::builder()
         .method("POST")
         .uri("/trade/%20/buy")
@@ -260,11 +242,189 @@ async fn post_trade_buy_missing_symbol_returns_generic_error() {
         id: ObjectId::new(),
         email: "test@example.com".to_string(),
         username: "test".to_string(),
+        is_admin: false,
+    });
+
+    let res = app.oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    let body = response_body_string(res).await;
+    assert!(body.contains("Invalid symbol."));
+}
+
+#[tokio::test]
+async fn post_trade_sell_all_unauthorized_returns_401() {
+    let state = test_state().await;
+    let app = Router::new()
+        .route("/trade/:symbol/sell_all", post(trading_controller::post_trade_sell_all))
+        .with_state(state);
+
+    let req = Request::builder()
+        .method("POST")
+        .uri("/trade/AAPL/sell_all")
+        .body(axum::body::Body::empty())
+        .unwrap();
+
+    let res = app.oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+    let body = response_body_string(res).await;
+    assert!(body.to_lowercase().contains("unauthorized"));
+}
+
+#[tokio::test]
+async fn post_trade_buy_invalid_qty_triggers_danger_toast() {
+    let state = test_state().await;
+    let app = Router::new()
+        .route("/trade/:symbol/buy", post(trading_controller::post_trade_buy))
+        .with_state(state);
+
+    let mut req = Request::builder()
+        .method("POST")
+        .uri("/trade/AAPL/buy")
+        .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+        .body(axum::body::Body::from("qty=notanumber"))
+        .unwrap();
+
+    req.extensions_mut().insert(CurrentUser {
+        id: ObjectId::new(),
+        email: "test@example.com".to_string(),
+        username: "test".to_string(),
+        is_admin: false,
+    });
+
+    let res = app.oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let trigger = res.headers().get("HX-Trigger").unwrap().to_str().unwrap().to_string();
+    assert!(trigger.contains(r#""showToast""#));
+    assert!(trigger.contains(r#""kind":"danger""#));
+
+    let body = response_body_string(res).await;
+    assert!(body.contains("alert-danger"));
+}
+
+#[tokio::test]
+async fn post_trade_buy_error_keeps_200_when_error_status_disabled() {
+    let mut state = test_state().await;
+    state.settings.htmx_error_status = false;
+    let app = Router::new()
+        .route("/trade/:symbol/buy", post(trading_controller::post_trade_buy))
+        .with_state(state);
+
+    let mut req = Request::builder()
+        .method("POST")
+        .uri("/trade/AAPL/buy")
+        .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+        .body(axum::body::Body::from("qty=notanumber"))
+        .unwrap();
+
+    req.extensions_mut().insert(CurrentUser {
+        id: ObjectId::new(),
+        email: "test@example.com".to_string(),
+        username: "test".to_string(),
+        is_admin: false,
     });
 
     let res = app.oneshot(req).await.unwrap();
     assert_eq!(res.status(), StatusCode::OK);
 
     let body = response_body_string(res).await;
-    assert!(body.contains("Could not buy"));
+    assert!(body.contains("Enter a valid quantity"));
+}
+
+#[tokio::test]
+async fn get_position_panel_without_user_renders_no_position() {
+    let state = test_state().await;
+    let app = Router::new()
+        .route("/positions/:symbol", get(trading_controller::get_position_panel))
+        .with_state(state);
+
+    let req = Request::builder()
+        .uri("/positions/aapl")
+        .body(axum::body::Body::empty())
+        .unwrap();
+
+    let res = app.oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let body = response_body_string(res).await;
+    assert!(body.contains("No position"));
+    assert!(!body.contains("data-position-panel"));
+}
+
+#[tokio::test]
+async fn post_trade_buy_error_from_details_has_no_oob_panel() {
+    let state = test_state().await;
+    let app = Router::new()
+        .route("/trade/:symbol/buy", post(trading_controller::post_trade_buy))
+        .with_state(state);
+
+    let mut req = Request::builder()
+        .method("POST")
+        .uri("/trade/AAPL/buy")
+        .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+        .header("HX-Request", "true")
+        .header("HX-Target", "tradeMsg")
+        .body(axum::body::Body::from("qty=0"))
+        .unwrap();
+
+    req.extensions_mut().insert(CurrentUser {
+        id: ObjectId::new(),
+        email: "test@example.com".to_string(),
+        username: "test".to_string(),
+        is_admin: false,
+    });
+
+    let res = app.oneshot(req).await.unwrap();
+    let body = response_body_string(res).await;
+    assert!(!body.contains("hx-swap-oob"));
+}
+
+#[tokio::test]
+async fn post_trade_sell_fraction_unauthorized_returns_401() {
+    let state = test_state().await;
+    let app = Router::new()
+        .route("/trade/:symbol/sell_fraction", post(trading_controller::post_trade_sell_fraction))
+        .with_state(state);
+
+    let req = Request::builder()
+        .method("POST")
+        .uri("/trade/AAPL/sell_fraction")
+        .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+        .body(axum::body::Body::from("fraction=0.5"))
+        .unwrap();
+
+    let res = app.oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn post_trade_sell_fraction_rejects_out_of_range() {
+    let state = test_state().await;
+    let app = Router::new()
+        .route("/trade/:symbol/sell_fraction", post(trading_controller::post_trade_sell_fraction))
+        .with_state(state);
+
+    for fraction in ["0", "-0.5", "1.5", "half"] {
+        let mut req = Request::builder()
+            .method("POST")
+            .uri("/trade/AAPL/sell_fraction")
+            .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .body(axum::body::Body::from(format!("fraction={fraction}")))
+            .unwrap();
+
+        req.extensions_mut().insert(CurrentUser {
+            id: ObjectId::new(),
+            email: "test@example.com".to_string(),
+            username: "test".to_string(),
+            is_admin: false,
+        });
+
+        let res = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY, "{fraction}");
+
+        let body = response_body_string(res).await;
+        assert!(body.contains("Choose a fraction between 0 and 1."), "{fraction}");
+    }
 }
@@ -0,0 +1,304 @@
+mod common;
+
+use axum::{routing::get, Json, Router};
+use mongodb::bson::{doc, oid::ObjectId};
+use rustmarket::error::AppError;
+use rustmarket::config::CostBasisMethod;
+use rustmarket::models::{Lot, Position};
+use rustmarket::services::trading_service::{self, exceeds_position_limit};
+use rustmarket::{services, AppState};
+use serde_json::json;
+use common::scratch_state;
+
+// Serves a fixed /quote answer, standing in for Finnhub.
+async fn stub_finnhub(quote: serde_json::Value) -> String {
+    let app = Router::new().route("/quote", get(move || async move { Json(quote) }));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    format!("http://{addr}")
+}
+
+async fn test_state(finnhub_url: &str) -> AppState {
+    let mut state = common::test_state().await;
+    state.settings.finnhub_api_key = "test-key".to_string();
+    state.finnhub = services::finnhub::FinnhubClient::with_base_url(state.settings.finnhub_api_key.clone(), finnhub_url);
+    state
+}
+
+fn zero_quote() -> serde_json::Value {
+    // delisted symbol: a timestamp but no price
+    json!({ "c": 0.0, "d": 0.0, "dp": 0.0, "h": 0.0, "l": 0.0, "o": 0.0, "pc": 0.0, "t": 1_700_000_000 })
+}
+
+fn price_error(err: AppError) -> String {
+    match err {
+        AppError::Validation(errs) => errs.get("price").cloned().unwrap_or_default(),
+        other => panic!("expected a validation error, got {other}"),
+    }
+}
+
+#[test]
+fn position_limit_is_unlimited_by_default() {
+    assert!(!exceeds_position_limit(1_000_000, 500.0, None));
+}
+
+#[test]
+fn position_limit_rejects_only_above_the_cap() {
+    let cap = Some(10_000.0);
+    assert!(!exceeds_position_limit(50, 200.0, cap));
+    assert!(exceeds_position_limit(51, 200.0, cap));
+}
+
+#[tokio::test]
+async fn market_buy_rejects_zero_price_quote() {
+    let state = test_state(&stub_finnhub(zero_quote()).await).await;
+
+    let err = trading_service::market_buy(&state, ObjectId::new(), "dead", 10)
+        .await
+        .expect_err("buy at $0 must fail");
+
+    assert_eq!(price_error(err), "No valid market price for DEAD");
+}
+
+#[tokio::test]
+async fn market_sell_rejects_zero_price_quote() {
+    let state = test_state(&stub_finnhub(zero_quote()).await).await;
+
+    let err = trading_service::market_sell(&state, ObjectId::new(), "DEAD", 1)
+        .await
+        .expect_err("sell at $0 must fail");
+
+    assert_eq!(price_error(err), "No valid market price for DEAD");
+}
+
+#[tokio::test]
+async fn market_buy_keeps_the_exchange_prefix() {
+    let state = test_state(&stub_finnhub(zero_quote()).await).await;
+
+    let err = trading_service::market_buy(&state, ObjectId::new(), " binance:btcusdt", 1)
+        .await
+        .expect_err("buy at $0 must fail");
+    assert_eq!(price_error(err), "No valid market price for BINANCE:BTCUSDT");
+
+    match trading_service::market_buy(&state, ObjectId::new(), "BINANCE:BTC:USDT", 1).await {
+        Err(AppError::Validation(errs)) => assert_eq!(errs["symbol"], "Invalid symbol."),
+        _ => panic!("expected a symbol error"),
+    }
+}
+
+#[tokio::test]
+async fn crypto_buy_opens_a_prefixed_position() {
+    let Some(mut state) = scratch_state().await else { return };
+    let quote = json!({ "c": 65_000.0, "d": 0.0, "dp": 0.0, "h": 0.0, "l": 0.0, "o": 0.0, "pc": 0.0, "t": 1_700_000_000 });
+    state.finnhub = services::finnhub::FinnhubClient::with_base_url("test-key".to_string(), &stub_finnhub(quote).await);
+    state.settings.starting_balance = 100_000.0;
+    let user_id = ObjectId::new();
+
+    let res = trading_service::market_buy(&state, user_id, "binance:btcusdt", 1).await.unwrap();
+    assert_eq!(res.symbol, "BINANCE:BTCUSDT");
+
+    let pos = trading_service::get_user_position(&state, user_id, "Binance:BTCUSDT").await.unwrap().unwrap();
+    assert_eq!(pos.qty, 1);
+
+    state.db.drop(None).await.unwrap();
+}
+
+fn quote_at(price: f64) -> serde_json::Value {
+    json!({ "c": price, "d": 0.0, "dp": 0.0, "h": 0.0, "l": 0.0, "o": 0.0, "pc": 0.0, "t": 1_700_000_000 })
+}
+
+#[tokio::test]
+async fn emptied_position_survives_a_buy_landing_before_the_delete() {
+    let Some(mut state) = scratch_state().await else { return };
+    state.finnhub = services::finnhub::FinnhubClient::with_base_url("test-key".to_string(), &stub_finnhub(quote_at(120.0)).await);
+    state.settings.starting_balance = 100_000.0;
+    state.settings.slippage_bps = 0.0;
+    let positions = state.db.collection::<Position>("positions");
+
+    let pos = Position {
+        id: ObjectId::new(),
+        user_id: ObjectId::new(),
+        symbol: "AAPL".to_string(),
+        qty: 5,
+        avg_price: 100.0,
+        created_at: 1,
+        updated_at: 1,
+        lots: Vec::new(),
+    };
+    positions.insert_one(&pos, None).await.unwrap();
+
+    // sell everything...
+    let after_sell = trading_service::take_shares(&state, pos.id, 5, 2).await.unwrap().unwrap();
+    assert_eq!(after_sell.qty, 0);
+
+    // ...a buy of 3 lands before the sell gets to delete the row
+    let bought = trading_service::market_buy(&state, pos.user_id, "AAPL", 3).await.unwrap();
+    assert_eq!(bought.position.id, pos.id);
+    assert_eq!(bought.position.qty, 3);
+    // the sold shares' cost doesn't leak into the new average
+    assert_eq!(bought.position.avg_price, 120.0);
+
+    assert!(!trading_service::delete_empty_position(&state, pos.id).await.unwrap());
+    let kept = positions.find_one(doc! { "_id": pos.id }, None).await.unwrap().unwrap();
+    assert_eq!(kept.qty, 3);
+
+    // a second sell can't take more than is left
+    assert!(trading_service::take_shares(&state, pos.id, 4, 3).await.unwrap().is_none());
+
+    trading_service::take_shares(&state, pos.id, 3, 3).await.unwrap().unwrap();
+    assert!(trading_service::delete_empty_position(&state, pos.id).await.unwrap());
+    assert!(!trading_service::delete_empty_position(&state, pos.id).await.unwrap());
+
+    // once the row is gone the next buy opens a fresh one
+    let reopened = trading_service::market_buy(&state, pos.user_id, "AAPL", 2).await.unwrap();
+    assert_ne!(reopened.position.id, pos.id);
+    assert_eq!(reopened.position.qty, 2);
+
+    state.db.drop(None).await.unwrap();
+}
+
+#[tokio::test]
+async fn concurrent_buys_all_land_on_one_position() {
+    let Some(mut state) = scratch_state().await else { return };
+    state.finnhub = services::finnhub::FinnhubClient::with_base_url("test-key".to_string(), &stub_finnhub(quote_at(10.0)).await);
+    state.settings.starting_balance = 100_000.0;
+    state.settings.slippage_bps = 0.0;
+    let user_id = ObjectId::new();
+
+    let buys = (0..8).map(|_| trading_service::market_buy(&state, user_id, "AAPL", 1));
+    for res in futures_util::future::join_all(buys).await {
+        res.unwrap();
+    }
+
+    let pos = trading_service::get_user_position(&state, user_id, "AAPL").await.unwrap().unwrap();
+    assert_eq!(pos.qty, 8);
+    assert_eq!(pos.avg_price, 10.0);
+    let rows = state
+        .db
+        .collection::<Position>("positions")
+        .count_documents(doc! { "user_id": user_id }, None)
+        .await
+        .unwrap();
+    assert_eq!(rows, 1);
+
+    state.db.drop(None).await.unwrap();
+}
+
+fn lot(qty: i64, price: f64, bought_at: i64) -> Lot {
+    Lot { qty, price, bought_at }
+}
+
+fn position(qty: i64, avg_price: f64, lots: Vec<Lot>) -> Position {
+    Position {
+        id: ObjectId::new(),
+        user_id: ObjectId::new(),
+        symbol: "AAPL".to_string(),
+        qty,
+        avg_price,
+        created_at: 1,
+        updated_at: 1,
+        lots,
+    }
+}
+
+#[test]
+fn cost_basis_method_defaults_to_average() {
+    assert_eq!(CostBasisMethod::parse("FIFO"), CostBasisMethod::Fifo);
+    assert_eq!(CostBasisMethod::parse("average"), CostBasisMethod::Average);
+    assert_eq!(CostBasisMethod::parse("lifo"), CostBasisMethod::Average);
+}
+
+#[test]
+fn fifo_consumes_oldest_lots_first() {
+    let lots = vec![lot(5, 100.0, 10), lot(5, 120.0, 20)];
+
+    let (left, cost) = trading_service::consume_fifo(&lots, 7);
+
+    assert_eq!(cost, 5.0 * 100.0 + 2.0 * 120.0);
+    assert_eq!(left, vec![lot(3, 120.0, 20)]);
+    assert_eq!(trading_service::lots_avg_price(&left), Some(120.0));
+}
+
+#[test]
+fn fifo_selling_everything_leaves_no_lots() {
+    let lots = vec![lot(2, 50.0, 10), lot(3, 60.0, 20)];
+
+    let (left, cost) = trading_service::consume_fifo(&lots, 5);
+
+    assert!(left.is_empty());
+    assert_eq!(cost, 280.0);
+    assert_eq!(trading_service::lots_avg_price(&left), None);
+}
+
+#[test]
+fn open_lots_covers_shares_bought_before_lots_were_kept() {
+    // 4 shares from before FIFO was switched on, then a tracked buy of 2
+    let pos = position(6, 90.0, vec![lot(2, 110.0, 30)]);
+
+    assert_eq!(trading_service::open_lots(&pos), vec![lot(4, 90.0, 1), lot(2, 110.0, 30)]);
+}
+
+#[test]
+fn open_lots_drops_lots_sold_under_the_average_method() {
+    let pos = position(3, 105.0, vec![lot(2, 100.0, 10), lot(2, 110.0, 20)]);
+
+    assert_eq!(trading_service::open_lots(&pos), vec![lot(1, 100.0, 10), lot(2, 110.0, 20)]);
+}
+
+#[tokio::test]
+async fn take_lots_refuses_a_position_that_changed_since_the_read() {
+    let Some(state) = scratch_state().await else { return };
+    let positions = state.db.collection::<Position>("positions");
+
+    let pos = position(4, 100.0, vec![lot(4, 100.0, 1)]);
+    positions.insert_one(&pos, None).await.unwrap();
+
+    let left = vec![lot(1, 100.0, 1)];
+    assert!(trading_service::take_lots(&state, pos.id, 5, &left, 2).await.unwrap().is_none());
+
+    let after = trading_service::take_lots(&state, pos.id, 4, &left, 2).await.unwrap().unwrap();
+    assert_eq!(after.qty, 1);
+    assert_eq!(after.lots, left);
+}
+
+#[test]
+fn fraction_qty_rounds_down_to_whole_shares() {
+    assert_eq!(trading_service::fraction_qty(10, 0.25), 2);
+    assert_eq!(trading_service::fraction_qty(10, 0.5), 5);
+    assert_eq!(trading_service::fraction_qty(10, 1.0), 10);
+    assert_eq!(trading_service::fraction_qty(3, 0.25), 0);
+}
+
+#[test]
+fn slippage_moves_fills_against_the_trader() {
+    assert_eq!(trading_service::slipped_price(100.0, 0.0, true), 100.0);
+    assert_eq!(trading_service::slipped_price(100.0, 0.0, false), 100.0);
+    assert!((trading_service::slipped_price(100.0, 25.0, true) - 100.25).abs() < 1e-9);
+    assert!((trading_service::slipped_price(100.0, 25.0, false) - 99.75).abs() < 1e-9);
+}
+
+#[tokio::test]
+async fn withdrawals_racing_buys_and_sells_keep_the_balance_exact() {
+    let Some(mut state) = scratch_state().await else { return };
+    state.finnhub = services::finnhub::FinnhubClient::with_base_url("test-key".to_string(), &stub_finnhub(quote_at(10.0)).await);
+    state.settings.starting_balance = 1_000.0;
+    state.settings.slippage_bps = 0.0;
+    let user_id = ObjectId::new();
+
+    trading_service::market_buy(&state, user_id, "AAPL", 20).await.unwrap();
+
+    let buys = futures_util::future::join_all((0..5).map(|_| trading_service::market_buy(&state, user_id, "MSFT", 5)));
+    let sells = futures_util::future::join_all((0..5).map(|_| trading_service::market_sell(&state, user_id, "AAPL", 2)));
+    let withdrawals = futures_util::future::join_all((0..5).map(|_| services::user_service::withdraw_funds(&state, user_id, 100.0)));
+    let (buys, sells, withdrawals) = tokio::join!(buys, sells, withdrawals);
+    assert!(buys.iter().all(Result::is_ok));
+    assert!(sells.iter().all(Result::is_ok));
+    assert!(withdrawals.iter().all(Result::is_ok));
+
+    // 1000 - 200 (AAPL) - 250 (MSFT) + 100 (sold AAPL) - 500 (withdrawn)
+    let acc = services::account_service::find_account(&state, user_id).await.unwrap().unwrap();
+    assert_eq!(acc.cash, 150.0);
+
+    state.db.drop(None).await.unwrap();
+}
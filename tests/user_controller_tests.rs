@@ -1,39 +1,15 @@
+mod common;
+
 use axum::{
     http::{header, Request, StatusCode},
-    routing::post,
+    routing::{get, post},
     Router,
 };
-use http_body_util::BodyExt;
-use mongodb::{bson::oid::ObjectId, Client};
-use rustmarket::{controllers::user_controller, config, services, templates, AppState};
-use rustmarket::models::CurrentUser;
+use mongodb::bson::{doc, oid::ObjectId};
+use rustmarket::{controllers::user_controller, services};
+use rustmarket::models::{CurrentUser, Order, PortfolioSnapshot, Position, ORDER_FILLED};
 use tower::ServiceExt;
-
-async fn test_state() -> AppState {
-    let mut settings = config::load();
-    settings.finnhub_api_key = String::new();
-
-    let client = Client::with_uri_str(&settings.mongodb_uri)
-        .await
-        .expect("mongodb client");
-    let db = client.database(&settings.mongodb_db);
-
-    let finnhub = services::finnhub::FinnhubClient::new(settings.finnhub_api_key.clone());
-    let (events_tx, _events_rx) = tokio::sync::broadcast::channel::<String>(16);
-
-    AppState {
-        hbs: templates::build_handlebars(),
-        db,
-        settings,
-        finnhub,
-        events_tx,
-    }
-}
-
-async fn response_body_string(res: axum::response::Response) -> String {
-    let bytes = res.into_body().collect().await.unwrap().to_bytes();
-    String::from_utf8_lossy(&bytes).to_string()
-}
+use common::{test_state, scratch_state, response_body_string};
 
 #[tokio::test]
 async fn post_funds_unauthorized_renders_error_banner() {
@@ -50,7 +26,7 @@ async fn post_funds_unauthorized_renders_error_banner() {
         .unwrap();
 
     let res = app.oneshot(req).await.unwrap();
-    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
 
     let body = response_body_string(res).await;
     assert!(body.to_lowercase().contains("error getting user"));
@@ -74,10 +50,11 @@ async fn post_funds_invalid_amount_renders_error() {
         id: ObjectId::new(),
         email: "test@example.com".to_string(),
         username: "test".to_string(),
+        is_admin: false,
     });
 
     let res = app.oneshot(req).await.unwrap();
-    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
 
     let body = response_body_string(res).await;
     assert!(body.to_lowercase().contains("error with the amount"));
@@ -101,6 +78,7 @@ async fn post_settings_email_same_as_current_renders_error() {
         id: ObjectId::new(),
         email: "test@example.com".to_string(),
         username: "test".to_string(),
+        is_admin: false,
     });
 
     let res = app.oneshot(req).await.unwrap();
@@ -128,10 +106,11 @@ async fn post_funds_zero_amount_renders_error() {
         id: ObjectId::new(),
         email: "test@example.com".to_string(),
         username: "test".to_string(),
+        is_admin: false,
     });
 
     let res = app.oneshot(req).await.unwrap();
-    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
 
     let body = response_body_string(res).await.to_lowercase();
     assert!(body.contains("bigger than zero"));
@@ -155,10 +134,11 @@ async fn post_funds_negative_amount_renders_error() {
         id: ObjectId::new(),
         email: "test@example.com".to_string(),
         username: "test".to_string(),
+        is_admin: false,
     });
 
     let res = app.oneshot(req).await.unwrap();
-    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
 
     let body = response_body_string(res).await.to_lowercase();
     assert!(body.contains("bigger than zero"));
@@ -203,6 +183,7 @@ async fn post_settings_email_invalid_format_renders_error() {
         id: ObjectId::new(),
         email: "old@example.com".to_string(),
         username: "test".to_string(),
+        is_admin: false,
     });
 
     let res = app.oneshot(req).await.unwrap();
@@ -230,6 +211,7 @@ async fn post_settings_email_empty_renders_error() {
         id: ObjectId::new(),
         email: "old@example.com".to_string(),
         username: "test".to_string(),
+        is_admin: false,
     });
 
     let res = app.oneshot(req).await.unwrap();
@@ -257,6 +239,7 @@ async fn post_settings_password_mismatch_renders_error() {
         id: ObjectId::new(),
         email: "test@example.com".to_string(),
         username: "test".to_string(),
+        is_admin: false,
     });
 
     let res = app.oneshot(req).await.unwrap();
@@ -265,3 +248,520 @@ async fn post_settings_password_mismatch_renders_error() {
     let body = response_body_string(res).await;
     assert!(body.contains("Passwords do not match."));
 }
+
+#[tokio::test]
+async fn post_delete_account_email_mismatch_renders_error() {
+    let state = test_state().await;
+    let app = Router::new()
+        .route(
+            "/settings/delete-account",
+            post(user_controller::post_settings_delete_account),
+        )
+        .with_state(state);
+
+    let mut req = Request::builder()
+        .method("POST")
+        .uri("/settings/delete-account")
+        .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+        .body(axum::body::Body::from(
+            "confirmEmail=other%40example.com&password=123456",
+        ))
+        .unwrap();
+
+    req.extensions_mut().insert(CurrentUser {
+        id: ObjectId::new(),
+        email: "test@example.com".to_string(),
+        username: "test".to_string(),
+        is_admin: false,
+    });
+
+    let res = app.oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert!(res.headers().get("HX-Redirect").is_none());
+
+    let body = response_body_string(res).await;
+    assert!(body.contains("Type your current email to confirm."));
+}
+
+#[tokio::test]
+async fn post_funds_same_idempotency_key_deposits_once() {
+    let Some(state) = scratch_state().await else { return };
+    let user_id = ObjectId::new();
+    let before = services::account_service::get_or_create_account(&state, user_id)
+        .await
+        .unwrap()
+        .cash;
+
+    let app = Router::new()
+        .route("/funds", post(user_controller::post_funds))
+        .with_state(state.clone());
+
+    for _ in 0..2 {
+        let mut req = Request::builder()
+            .method("POST")
+            .uri("/funds")
+            .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .body(axum::body::Body::from("amount=250&idempotency_key=abc123"))
+            .unwrap();
+        req.extensions_mut().insert(CurrentUser {
+            id: user_id,
+            email: "test@example.com".to_string(),
+            username: "test".to_string(),
+            is_admin: false,
+        });
+
+        let res = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    let after = services::account_service::get_or_create_account(&state, user_id)
+        .await
+        .unwrap()
+        .cash;
+    assert_eq!(after - before, 250.0);
+
+    state.db.drop(None).await.unwrap();
+}
+
+#[tokio::test]
+async fn post_withdraw_zero_amount_renders_error() {
+    let state = test_state().await;
+    let app = Router::new()
+        .route("/funds/withdraw", post(user_controller::post_withdraw))
+        .with_state(state);
+
+    let mut req = Request::builder()
+        .method("POST")
+        .uri("/funds/withdraw")
+        .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+        .body(axum::body::Body::from("amount=0"))
+        .unwrap();
+
+    req.extensions_mut().insert(CurrentUser {
+        id: ObjectId::new(),
+        email: "test@example.com".to_string(),
+        username: "test".to_string(),
+        is_admin: false,
+    });
+
+    let res = app.oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let body = response_body_string(res).await.to_lowercase();
+    assert!(body.contains("bigger than zero"));
+}
+
+#[tokio::test]
+async fn post_withdraw_more_than_cash_is_rejected() {
+    let Some(state) = scratch_state().await else { return };
+    let user_id = ObjectId::new();
+    let before = services::account_service::get_or_create_account(&state, user_id)
+        .await
+        .unwrap()
+        .cash;
+
+    let app = Router::new()
+        .route("/funds/withdraw", post(user_controller::post_withdraw))
+        .with_state(state.clone());
+
+    let withdraw = |amount: f64| {
+        let mut req = Request::builder()
+            .method("POST")
+            .uri("/funds/withdraw")
+            .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .body(axum::body::Body::from(format!("amount={amount}")))
+            .unwrap();
+        req.extensions_mut().insert(CurrentUser {
+            id: user_id,
+            email: "test@example.com".to_string(),
+            username: "test".to_string(),
+            is_admin: false,
+        });
+        app.clone().oneshot(req)
+    };
+
+    let res = withdraw(before + 1.0).await.unwrap();
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let body = response_body_string(res).await;
+    assert!(body.contains("Insufficient funds"));
+
+    let body = response_body_string(withdraw(100.0).await.unwrap()).await;
+    assert!(body.contains("withdrawal was successful"));
+
+    let after = services::account_service::get_or_create_account(&state, user_id)
+        .await
+        .unwrap()
+        .cash;
+    assert_eq!(before - after, 100.0);
+
+    state.db.drop(None).await.unwrap();
+}
+
+#[tokio::test]
+async fn post_settings_preferences_rejects_unknown_currency() {
+    let state = test_state().await;
+    let app = Router::new()
+        .route("/settings/preferences", post(user_controller::post_settings_preferences))
+        .with_state(state);
+
+    let mut req = Request::builder()
+        .method("POST")
+        .uri("/settings/preferences")
+        .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+        .body(axum::body::Body::from("default_qty=5&display_currency=DOGE"))
+        .unwrap();
+
+    req.extensions_mut().insert(CurrentUser {
+        id: ObjectId::new(),
+        email: "test@example.com".to_string(),
+        username: "test".to_string(),
+        is_admin: false,
+    });
+
+    let res = app.oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let body = response_body_string(res).await;
+    assert!(body.contains("Unsupported currency."));
+    assert!(body.contains(r#"value="5""#));
+}
+
+#[tokio::test]
+async fn get_profile_unauthorized_renders_error() {
+    let state = test_state().await;
+    let app = Router::new()
+        .route("/profile", axum::routing::get(user_controller::get_profile))
+        .with_state(state);
+
+    let req = Request::builder()
+        .uri("/profile")
+        .header("HX-Request", "true")
+        .body(axum::body::Body::empty())
+        .unwrap();
+
+    let res = app.oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+    let body = response_body_string(res).await;
+    assert!(body.contains("Profile"));
+    assert!(body.to_lowercase().contains("error getting user"));
+}
+
+#[tokio::test]
+async fn get_cash_badge_does_not_open_an_account() {
+    let Some(state) = scratch_state().await else { return };
+    let user_id = ObjectId::new();
+
+    let app = Router::new()
+        .route("/cash", get(user_controller::get_cash_badge))
+        .with_state(state.clone());
+
+    let mut req = Request::builder().uri("/cash").body(axum::body::Body::empty()).unwrap();
+    req.extensions_mut().insert(CurrentUser {
+        id: user_id,
+        email: "test@example.com".to_string(),
+        username: "test".to_string(),
+        is_admin: false,
+    });
+
+    let res = app.oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    assert!(response_body_string(res).await.contains("0.00"));
+    assert!(!services::account_service::account_exists(&state, user_id).await.unwrap());
+
+    state.db.drop(None).await.unwrap();
+}
+
+#[tokio::test]
+async fn post_funds_success_sends_a_parseable_hx_trigger() {
+    let Some(state) = scratch_state().await else { return };
+
+    let app = Router::new()
+        .route("/funds", post(user_controller::post_funds))
+        .with_state(state.clone());
+
+    let mut req = Request::builder()
+        .method("POST")
+        .uri("/funds")
+        .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+        .body(axum::body::Body::from("amount=25"))
+        .unwrap();
+    req.extensions_mut().insert(CurrentUser {
+        id: ObjectId::new(),
+        email: "test@example.com".to_string(),
+        username: "test".to_string(),
+        is_admin: false,
+    });
+
+    let res = app.oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let trigger = res.headers().get("HX-Trigger").expect("HX-Trigger").to_str().unwrap();
+    let parsed: serde_json::Value = serde_json::from_str(trigger).unwrap();
+    assert_eq!(parsed["cashUpdated"], true);
+    assert_eq!(parsed["showToast"]["kind"], "success");
+
+    state.db.drop(None).await.unwrap();
+}
+
+#[tokio::test]
+async fn post_reset_account_without_password_is_rejected() {
+    let state = test_state().await;
+    let app = Router::new()
+        .route("/settings/reset-account", post(user_controller::post_settings_reset_account))
+        .with_state(state);
+
+    let mut req = Request::builder()
+        .method("POST")
+        .uri("/settings/reset-account")
+        .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+        .body(axum::body::Body::from("password="))
+        .unwrap();
+    req.extensions_mut().insert(CurrentUser {
+        id: ObjectId::new(),
+        email: "test@example.com".to_string(),
+        username: "test".to_string(),
+        is_admin: false,
+    });
+
+    let res = app.oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let body = response_body_string(res).await;
+    assert!(body.contains("Password is required."));
+}
+
+#[tokio::test]
+async fn post_reset_account_wipes_trading_state_and_restores_cash() {
+    let Some(state) = scratch_state().await else { return };
+    let user_id = services::auth_service::register_user(&state, "resetter", "resetter@example.com", "secret123")
+        .await
+        .unwrap();
+    services::account_service::set_cash(&state, user_id, 1.0, 0).await.unwrap();
+    state
+        .db
+        .collection::<Position>("positions")
+        .insert_one(
+            Position {
+                id: ObjectId::new(),
+                user_id,
+                symbol: "AAPL".to_string(),
+                qty: 2,
+                avg_price: 100.0,
+                created_at: 1,
+                updated_at: 1,
+                lots: Vec::new(),
+            },
+            None,
+        )
+        .await
+        .unwrap();
+    state
+        .db
+        .collection::<Order>("orders")
+        .insert_one(
+            Order {
+                id: ObjectId::new(),
+                user_id,
+                symbol: "AAPL".to_string(),
+                side: "buy".to_string(),
+                qty: 2,
+                price: 100.0,
+                total: 200.0,
+                created_at: 1,
+                realized_pnl: None,
+                quoted_price: None,
+                status: ORDER_FILLED.to_string(),
+                filled_qty: None,
+            },
+            None,
+        )
+        .await
+        .unwrap();
+    services::alerts_service::create_alert(&state, user_id, "AAPL", "above", 150.0, None)
+        .await
+        .unwrap();
+    state
+        .db
+        .collection::<PortfolioSnapshot>("portfolio_snapshots")
+        .insert_one(
+            PortfolioSnapshot {
+                id: ObjectId::new(),
+                user_id,
+                date: "2026-01-02".to_string(),
+                total_value: 201.0,
+                cash: 1.0,
+                created_at: 1,
+            },
+            None,
+        )
+        .await
+        .unwrap();
+    let mut events = state.events_tx.subscribe();
+
+    let app = Router::new()
+        .route("/settings/reset-account", post(user_controller::post_settings_reset_account))
+        .with_state(state.clone());
+
+    let reset = |password: &str| {
+        let mut req = Request::builder()
+            .method("POST")
+            .uri("/settings/reset-account")
+            .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .body(axum::body::Body::from(format!("password={password}")))
+            .unwrap();
+        req.extensions_mut().insert(CurrentUser {
+            id: user_id,
+            email: "resetter@example.com".to_string(),
+            username: "resetter".to_string(),
+            is_admin: false,
+        });
+        app.clone().oneshot(req)
+    };
+
+    let res = reset("wrong").await.unwrap();
+    assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(response_body_string(res).await.contains("Password is incorrect."));
+
+    let res = reset("secret123").await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let trigger = res.headers()["HX-Trigger"].to_str().unwrap().to_string();
+    for event in services::account_service::RESET_EVENTS {
+        assert!(trigger.contains(event), "{trigger}");
+    }
+
+    let mut sent = vec![];
+    while let Ok(e) = events.try_recv() {
+        sent.push(e);
+    }
+    let mut expected = services::account_service::RESET_EVENTS.map(String::from).to_vec();
+    expected.push(services::portfolio_service::holdings_changed_event(user_id));
+    assert_eq!(sent, expected);
+
+    for name in ["positions", "orders", "alerts", "portfolio_snapshots"] {
+        let left = state
+            .db
+            .collection::<mongodb::bson::Document>(name)
+            .count_documents(doc! { "user_id": user_id }, None)
+            .await
+            .unwrap();
+        assert_eq!(left, 0, "{name}");
+    }
+    let acc = services::account_service::find_account(&state, user_id).await.unwrap().unwrap();
+    assert_eq!(acc.cash, state.settings.starting_balance);
+
+    state.db.drop(None).await.unwrap();
+}
+
+#[tokio::test]
+async fn post_delete_account_removes_every_trace_of_the_user() {
+    let Some(state) = scratch_state().await else { return };
+    let email = "leaver@example.com";
+    let user_id = services::auth_service::register_user(&state, "leaver", email, "secret123")
+        .await
+        .unwrap();
+
+    services::user_service::deposit_funds(&state, user_id, 50.0, Some("key-1")).await.unwrap();
+    services::alerts_service::create_alert(&state, user_id, "AAPL", "above", 150.0, None)
+        .await
+        .unwrap();
+    services::watchlist_service::add(&state, user_id, "AAPL").await.unwrap();
+    services::user_service::record_recent_symbol(&state, user_id, "AAPL").await.unwrap();
+    services::session_service::record_login(&state, user_id, "127.0.0.1", "test").await.unwrap();
+    let owned = doc! { "user_id": user_id, "symbol": "AAPL", "qty": 1_i64, "created_at": 1_i64 };
+    for name in ["positions", "orders", "portfolio_snapshots", "cash_events"] {
+        state
+            .db
+            .collection::<mongodb::bson::Document>(name)
+            .insert_one(owned.clone(), None)
+            .await
+            .unwrap();
+    }
+
+    let app = Router::new()
+        .route("/settings/delete-account", post(user_controller::post_settings_delete_account))
+        .with_state(state.clone());
+    let mut req = Request::builder()
+        .method("POST")
+        .uri("/settings/delete-account")
+        .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+        .header("HX-Request", "true")
+        .body(axum::body::Body::from("confirmEmail=leaver%40example.com&password=secret123"))
+        .unwrap();
+    req.extensions_mut().insert(CurrentUser {
+        id: user_id,
+        email: email.to_string(),
+        username: "leaver".to_string(),
+        is_admin: false,
+    });
+    let res = app.oneshot(req).await.unwrap();
+    assert_eq!(res.headers()["HX-Redirect"], "/");
+
+    for name in [
+        "positions",
+        "orders",
+        "alerts",
+        "watchlists",
+        "portfolio_snapshots",
+        "deposit_keys",
+        "cash_events",
+        "sessions",
+    ] {
+        let left = state
+            .db
+            .collection::<mongodb::bson::Document>(name)
+            .count_documents(doc! { "user_id": user_id }, None)
+            .await
+            .unwrap();
+        assert_eq!(left, 0, "{name}");
+    }
+    for name in ["accounts", "users"] {
+        let left = state
+            .db
+            .collection::<mongodb::bson::Document>(name)
+            .count_documents(doc! { "_id": user_id }, None)
+            .await
+            .unwrap();
+        assert_eq!(left, 0, "{name}");
+    }
+
+    state.db.drop(None).await.unwrap();
+}
+
+#[tokio::test]
+async fn full_page_delete_account_autoloads_the_form() {
+    let state = test_state().await;
+    let app = Router::new()
+        .route("/settings/delete-account", get(user_controller::get_settings_delete_account))
+        .with_state(state);
+
+    let req = Request::builder()
+        .uri("/settings/delete-account")
+        .body(axum::body::Body::empty())
+        .unwrap();
+
+    let res = app.oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let body = response_body_string(res).await;
+    assert!(body.contains(r##"<div hx-get="/settings/delete-account" hx-trigger="load" hx-target="#rightPane""##));
+    assert!(!body.contains(r#"\""#));
+}
+
+#[tokio::test]
+async fn full_page_reset_account_autoloads_the_form() {
+    let state = test_state().await;
+    let app = Router::new()
+        .route("/settings/reset-account", get(user_controller::get_settings_reset_account))
+        .with_state(state);
+
+    let req = Request::builder()
+        .uri("/settings/reset-account")
+        .body(axum::body::Body::empty())
+        .unwrap();
+
+    let res = app.oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let body = response_body_string(res).await;
+    assert!(body.contains(r##"<div hx-get="/settings/reset-account" hx-trigger="load" hx-target="#rightPane""##));
+}
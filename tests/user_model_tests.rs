@@ -0,0 +1,37 @@
+use rustmarket::models::user::normalize_and_validate_email;
+
+#[test]
+fn email_is_trimmed_and_lowercased() {
+    assert_eq!(normalize_and_validate_email("  Jane.Doe@Example.COM ").unwrap(), "jane.doe@example.com");
+    assert_eq!(normalize_and_validate_email("USER@GMAIL.COM").unwrap(), "user@gmail.com");
+}
+
+#[test]
+fn plus_addressing_and_subdomains_are_valid() {
+    assert_eq!(normalize_and_validate_email("me+stocks@mail.example.co.uk").unwrap(), "me+stocks@mail.example.co.uk");
+    assert!(normalize_and_validate_email("first_last-1@my-domain.io").is_ok());
+}
+
+#[test]
+fn empty_email_is_required() {
+    assert_eq!(normalize_and_validate_email("   "), Err("Email is required."));
+}
+
+#[test]
+fn malformed_emails_are_rejected() {
+    for bad in [
+        "not-an-email",
+        "a@b",
+        "a@@b.com",
+        "a b@c.com",
+        "a@b .com",
+        "@example.com",
+        "user@",
+        ".user@example.com",
+        "us..er@example.com",
+        "user@-example.com",
+        "user@example..com",
+    ] {
+        assert_eq!(normalize_and_validate_email(bad), Err("Invalid email."), "{bad}");
+    }
+}
@@ -0,0 +1,43 @@
+mod common;
+
+use axum::{
+    http::{Request, StatusCode},
+    routing::{get, post},
+    Router,
+};
+use rustmarket::controllers::watchlist_controller;
+use tower::ServiceExt;
+use common::test_state;
+
+#[tokio::test]
+async fn post_watch_unauthorized_returns_401() {
+    let state = test_state().await;
+    let app = Router::new()
+        .route("/watchlist/:symbol", post(watchlist_controller::post_watch))
+        .with_state(state);
+
+    let req = Request::builder()
+        .method("POST")
+        .uri("/watchlist/AAPL")
+        .body(axum::body::Body::empty())
+        .unwrap();
+
+    let res = app.oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn get_watchlist_unauthorized_returns_401() {
+    let state = test_state().await;
+    let app = Router::new()
+        .route("/watchlist", get(watchlist_controller::get_watchlist))
+        .with_state(state);
+
+    let req = Request::builder()
+        .uri("/watchlist")
+        .body(axum::body::Body::empty())
+        .unwrap();
+
+    let res = app.oneshot(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+}
@@ -0,0 +1,50 @@
+use mongodb::bson::oid::ObjectId;
+use rustmarket::services::ws_limiter::WsLimiter;
+
+#[test]
+fn rejects_connections_over_the_per_user_cap() {
+    let limiter = WsLimiter::new(2);
+    let user = ObjectId::new();
+
+    let a = limiter.try_acquire(user);
+    let b = limiter.try_acquire(user);
+
+    assert!(a.is_some());
+    assert!(b.is_some());
+    assert!(limiter.try_acquire(user).is_none());
+    assert_eq!(limiter.open_for(&user), 2);
+}
+
+#[test]
+fn dropping_a_slot_frees_it() {
+    let limiter = WsLimiter::new(1);
+    let user = ObjectId::new();
+
+    let slot = limiter.try_acquire(user);
+    assert!(limiter.try_acquire(user).is_none());
+
+    drop(slot);
+    assert_eq!(limiter.open_for(&user), 0);
+    assert!(limiter.try_acquire(user).is_some());
+}
+
+#[test]
+fn cap_is_per_user() {
+    let limiter = WsLimiter::new(1);
+
+    let _a = limiter.try_acquire(ObjectId::new()).unwrap();
+    assert!(limiter.try_acquire(ObjectId::new()).is_some());
+}
+
+#[test]
+fn total_open_counts_every_user() {
+    let limiter = WsLimiter::new(2);
+
+    let _a = limiter.try_acquire(ObjectId::new()).unwrap();
+    let _b = limiter.try_acquire(ObjectId::new()).unwrap();
+    let c = limiter.try_acquire(ObjectId::new()).unwrap();
+    assert_eq!(limiter.total_open(), 3);
+
+    drop(c);
+    assert_eq!(limiter.total_open(), 2);
+}